@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::utils::{ANALYSIS_VERSION, CachedMetrics};
+use crate::warnings::{RULE_METRICS, metric_value};
+
+/// Fewer tracks than this and the distributions would give away individual files.
+pub const MIN_TRACKS: usize = 5;
+
+const PRECISION: f32 = 100.0; // Two decimal places, enough for comparison
+
+/// Quantiles of one metric across the collection.
+#[derive(Serialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub mean: f32,
+    pub std_dev: f32,
+    pub min: f32,
+    pub p10: f32,
+    pub p25: f32,
+    pub median: f32,
+    pub p75: f32,
+    pub p90: f32,
+    pub max: f32,
+}
+
+/// An anonymized statistical summary of a collection: metric distributions and category
+/// counts only, with no filenames, paths, notes, ratings, hashes, or timestamps.
+#[derive(Serialize)]
+pub struct Aggregate {
+    pub analysis_version: u32,
+    pub track_count: usize,
+    pub metrics: BTreeMap<&'static str, Distribution>,
+    /// Per-band share of energy, for the band count most tracks were analyzed with
+    pub bands: Vec<Distribution>,
+    pub keys: BTreeMap<String, usize>,
+    pub codecs: BTreeMap<String, usize>,
+    pub sample_rates: BTreeMap<u32, usize>,
+}
+
+fn round(value: f32) -> f32 {
+    (value * PRECISION).round() / PRECISION
+}
+
+fn distribution(mut values: Vec<f32>) -> Option<Distribution> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f32::total_cmp);
+    let count = values.len();
+    let mean = values.iter().sum::<f32>() / count as f32;
+    let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / count as f32;
+    let quantile = |q: f32| round(values[((count - 1) as f32 * q).round() as usize]);
+
+    Some(Distribution {
+        count,
+        mean: round(mean),
+        std_dev: round(variance.sqrt()),
+        min: quantile(0.0),
+        p10: quantile(0.1),
+        p25: quantile(0.25),
+        median: quantile(0.5),
+        p75: quantile(0.75),
+        p90: quantile(0.9),
+        max: quantile(1.0),
+    })
+}
+
+fn count<K: Ord>(keys: impl Iterator<Item = K>) -> BTreeMap<K, usize> {
+    let mut counts = BTreeMap::new();
+    for key in keys {
+        *counts.entry(key).or_insert(0) += 1;
+    }
+    counts
+}
+
+pub fn aggregate(tracks: &[&CachedMetrics]) -> Aggregate {
+    let all_metrics: Vec<_> = tracks.iter().map(|t| t.to_metrics()).collect();
+    let metrics = RULE_METRICS
+        .iter()
+        .filter_map(|&key| {
+            let values = all_metrics
+                .iter()
+                .filter_map(|m| metric_value(m, key))
+                .collect();
+            distribution(values).map(|d| (key, d))
+        })
+        .collect();
+
+    // Only tracks sharing the most common band layout are comparable band by band
+    let band_count = tracks
+        .iter()
+        .map(|t| t.band_percentages.len())
+        .max_by_key(|&len| {
+            tracks
+                .iter()
+                .filter(|t| t.band_percentages.len() == len)
+                .count()
+        })
+        .unwrap_or(0);
+    let bands = (0..band_count)
+        .filter_map(|band| {
+            distribution(
+                tracks
+                    .iter()
+                    .filter(|t| t.band_percentages.len() == band_count)
+                    .map(|t| t.band_percentages[band])
+                    .collect(),
+            )
+        })
+        .collect();
+
+    Aggregate {
+        analysis_version: ANALYSIS_VERSION,
+        track_count: tracks.len(),
+        metrics,
+        bands,
+        keys: count(
+            tracks
+                .iter()
+                .filter_map(|t| t.key.as_ref().map(|k| k.to_string())),
+        ),
+        codecs: count(
+            tracks
+                .iter()
+                .filter_map(|t| t.stream.as_ref().map(|s| s.codec.clone()))
+                .filter(|codec| !codec.is_empty()),
+        ),
+        sample_rates: count(
+            tracks
+                .iter()
+                .filter_map(|t| t.stream.as_ref().map(|s| s.sample_rate)),
+        ),
+    }
+}
@@ -0,0 +1,750 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{fmt, io};
+
+use rayon::prelude::*;
+
+use crate::frequency_bands::{
+    FRAME_SIZE, FrequencyBand, SpectrumMetrics, Transform, band_energies_with_frame,
+    calculate_band_positions, calculate_loudness, calculate_zero_crossing_rate,
+    count_zero_crossings, get_bands, is_reproducible, normalize_zcr, validate_bands,
+};
+use crate::units::{Db, Hz, Lufs, Percent};
+use crate::utils::{DecodedAudio, decode_audio_limited, decode_audio_window, downmix};
+use crate::{
+    beats, clipping, cqt, dynamics, frequency_bands, key, loudness, psychoacoustics, ratios,
+    silence, stereo, structure, surround, tempo, transcode,
+};
+
+/// Decode a file and compute every metric, using `band_layout` in place of the built-in
+/// bands when given.
+pub fn analyze_frequency_distribution(
+    path: &Path,
+    band_layout: Option<&[FrequencyBand]>,
+    transform: Transform,
+) -> Result<SpectrumMetrics, AnalysisErrorKind> {
+    analyze_within_memory(path, band_layout, transform, None)
+}
+
+/// `analyze_frequency_distribution` holding at most about `max_memory` bytes of decoded
+/// audio. Longer tracks are read in windows that fit: the level, band, and crossing
+/// readings cover the whole track, the rest the first window, with `windowed` set on the
+/// result.
+pub fn analyze_within_memory(
+    path: &Path,
+    band_layout: Option<&[FrequencyBand]>,
+    transform: Transform,
+    max_memory: Option<u64>,
+) -> Result<SpectrumMetrics, AnalysisErrorKind> {
+    analyze_with_progress(
+        path,
+        band_layout,
+        transform,
+        max_memory,
+        &CancelToken::new(),
+        |_| {},
+    )
+}
+
+// Shorter frames can't place a bass note in a band; longer ones smear whole beats together
+const MIN_FRAME_SIZE: usize = 256;
+const MAX_FRAME_SIZE: usize = 32768;
+
+/// Optional groups of measurements. The band distribution, centroid, spread, RMS level,
+/// and zero-crossing rate are always computed; a group left out of
+/// [`AnalyzerBuilder::metrics`] comes back as `None` (or empty) and its work is skipped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetricGroup {
+    /// R128 loudness, loudness range, true peak, DR score, RMS and crest factor, clipping,
+    /// and silent stretches
+    Loudness,
+    /// L/R balance, side level, and mono-compatible bass
+    Stereo,
+    /// Per-channel energy and LFE usage of multichannel files
+    Surround,
+    /// Tempo, onset strength, groove, and the rhythm fingerprint
+    Rhythm,
+    Key,
+    /// Chorus location for previews, and intro and outro profiles for transitions
+    Structure,
+    /// Exact centroid, rolloff, tilt, and lowpass cutoff from the full FFT spectrum
+    Spectral,
+    /// Sharpness and roughness
+    Timbre,
+}
+
+impl MetricGroup {
+    pub const ALL: [MetricGroup; 8] = [
+        MetricGroup::Loudness,
+        MetricGroup::Stereo,
+        MetricGroup::Surround,
+        MetricGroup::Rhythm,
+        MetricGroup::Key,
+        MetricGroup::Structure,
+        MetricGroup::Spectral,
+        MetricGroup::Timbre,
+    ];
+}
+
+/// Analysis settings shared by every file: build one with [`Analyzer::builder`] and reuse
+/// it across a library.
+#[derive(Clone)]
+pub struct Analyzer {
+    bands: Option<Vec<FrequencyBand>>,
+    transform: Transform,
+    frame_size: usize,
+    max_memory: Option<u64>,
+    metrics: Vec<MetricGroup>,
+    jobs: Option<usize>,
+}
+
+/// Collects `Analyzer` settings; anything not set keeps the command's defaults.
+#[derive(Clone)]
+pub struct AnalyzerBuilder {
+    analyzer: Analyzer,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Analyzer {
+            bands: None,
+            transform: Transform::default(),
+            frame_size: FRAME_SIZE,
+            max_memory: None,
+            metrics: MetricGroup::ALL.to_vec(),
+            jobs: None,
+        }
+    }
+}
+
+impl AnalyzerBuilder {
+    /// Custom band layout in place of the built-in bands; `None` restores them.
+    pub fn bands(mut self, bands: impl Into<Option<Vec<FrequencyBand>>>) -> Self {
+        self.analyzer.bands = bands.into();
+        self
+    }
+
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.analyzer.transform = transform;
+        self
+    }
+
+    /// STFT frame length in samples for the band energies, exact centroid, and rolloff.
+    /// Must be a power of two from 256 to 32768; the hop stays a quarter frame.
+    pub fn frame_size(mut self, frame_size: usize) -> Self {
+        self.analyzer.frame_size = frame_size;
+        self
+    }
+
+    /// Decoded audio held per file, as with `--max-memory`.
+    pub fn max_memory(mut self, bytes: impl Into<Option<u64>>) -> Self {
+        self.analyzer.max_memory = bytes.into();
+        self
+    }
+
+    /// Only compute these optional groups.
+    pub fn metrics(mut self, groups: impl IntoIterator<Item = MetricGroup>) -> Self {
+        self.analyzer.metrics = groups.into_iter().collect();
+        self
+    }
+
+    /// Files analyzed at once by `analyze_paths`, as with `--jobs`; `None` uses every core.
+    pub fn jobs(mut self, jobs: impl Into<Option<usize>>) -> Self {
+        self.analyzer.jobs = jobs.into();
+        self
+    }
+
+    pub fn build(self) -> Result<Analyzer, AnalysisErrorKind> {
+        let frame_size = self.analyzer.frame_size;
+        if !frame_size.is_power_of_two() || !(MIN_FRAME_SIZE..=MAX_FRAME_SIZE).contains(&frame_size)
+        {
+            return Err(AnalysisErrorKind::InvalidSettings(format!(
+                "frame size {} must be a power of two from {} to {}",
+                frame_size, MIN_FRAME_SIZE, MAX_FRAME_SIZE
+            )));
+        }
+        if let Some(bands) = &self.analyzer.bands {
+            validate_bands(bands)?;
+        }
+        Ok(self.analyzer)
+    }
+}
+
+/// Stops an analysis from another thread. Clones share the same flag.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// One file's metrics from `Analyzer::analyze_paths`.
+pub struct FileAnalysis {
+    pub path: PathBuf,
+    pub metrics: SpectrumMetrics,
+}
+
+/// Why a file couldn't be analyzed. Decoder errors are kept as their message, since
+/// they can't cross threads.
+#[derive(Debug)]
+pub enum AnalysisErrorKind {
+    /// The file couldn't be opened or read
+    Io(io::Error),
+    /// The file was read but isn't audio this build can decode
+    Decode(String),
+    /// Decoding produced no samples
+    EmptyAudio,
+    /// A sample rate the analysis can't work with, such as 0
+    UnsupportedSampleRate(usize),
+    /// The analyzer's own settings are invalid, as with an empty band layout
+    InvalidSettings(String),
+    /// The `CancelToken` passed to `analyze_with_progress` was cancelled
+    Cancelled,
+}
+
+impl fmt::Display for AnalysisErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnalysisErrorKind::Io(e) => write!(f, "can't read file: {}", e),
+            AnalysisErrorKind::Decode(message) | AnalysisErrorKind::InvalidSettings(message) => {
+                write!(f, "{}", message)
+            }
+            AnalysisErrorKind::EmptyAudio => write!(f, "no audio data found"),
+            AnalysisErrorKind::UnsupportedSampleRate(rate) => {
+                write!(f, "unsupported sample rate: {} Hz", rate)
+            }
+            AnalysisErrorKind::Cancelled => write!(f, "analysis cancelled"),
+        }
+    }
+}
+
+impl Error for AnalysisErrorKind {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AnalysisErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// Errors from the decoders and the rest of the analysis arrive boxed; ours come back out
+// as they were, I/O errors keep their kind, and anything else is a decoding failure
+impl From<Box<dyn Error>> for AnalysisErrorKind {
+    fn from(error: Box<dyn Error>) -> Self {
+        let error = match error.downcast::<AnalysisErrorKind>() {
+            Ok(error) => return *error,
+            Err(error) => error,
+        };
+        match error.downcast::<io::Error>() {
+            Ok(error) => AnalysisErrorKind::Io(*error),
+            Err(error) => AnalysisErrorKind::Decode(error.to_string()),
+        }
+    }
+}
+
+/// Why one file of a batch failed.
+#[derive(Debug)]
+pub struct AnalysisError {
+    pub path: PathBuf,
+    pub kind: AnalysisErrorKind,
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.kind)
+    }
+}
+
+impl Error for AnalysisError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.kind)
+    }
+}
+
+/// The part of the analysis about to run, in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Decoding,
+    Loudness,
+    Spectrum,
+    Rhythm,
+    Tonality,
+    Timbre,
+    Done,
+}
+
+impl Stage {
+    /// Rough share of the work finished when this stage starts, 0.0 to 1.0.
+    pub fn fraction(self) -> f32 {
+        self as usize as f32 / Stage::Done as usize as f32
+    }
+}
+
+/// `analyze_within_memory` for host applications: `progress` is called as each stage
+/// starts, and the analysis stops with `AnalysisErrorKind::Cancelled` at the next stage once
+/// `cancel` is set.
+/// Stages aren't interrupted part-way, so decoding a long file still runs to the end.
+pub fn analyze_with_progress(
+    path: &Path,
+    band_layout: Option<&[FrequencyBand]>,
+    transform: Transform,
+    max_memory: Option<u64>,
+    cancel: &CancelToken,
+    progress: impl FnMut(Stage),
+) -> Result<SpectrumMetrics, AnalysisErrorKind> {
+    Analyzer::builder()
+        .bands(band_layout.map(<[FrequencyBand]>::to_vec))
+        .transform(transform)
+        .max_memory(max_memory)
+        .build()?
+        .analyze_with_progress(path, cancel, progress)
+}
+
+impl Analyzer {
+    pub fn builder() -> AnalyzerBuilder {
+        AnalyzerBuilder {
+            analyzer: Analyzer::default(),
+        }
+    }
+
+    pub fn bands(&self) -> Option<&[FrequencyBand]> {
+        self.bands.as_deref()
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    pub fn max_memory(&self) -> Option<u64> {
+        self.max_memory
+    }
+
+    pub fn measures(&self, group: MetricGroup) -> bool {
+        self.metrics.contains(&group)
+    }
+
+    pub fn jobs(&self) -> Option<usize> {
+        self.jobs
+    }
+
+    /// Decode a file and compute the configured metrics.
+    pub fn analyze(&self, path: &Path) -> Result<SpectrumMetrics, AnalysisErrorKind> {
+        self.analyze_with_progress(path, &CancelToken::new(), |_| {})
+    }
+
+    /// `analyze` with stage callbacks and cancellation, as `analyze_with_progress`.
+    pub fn analyze_with_progress(
+        &self,
+        path: &Path,
+        cancel: &CancelToken,
+        mut progress: impl FnMut(Stage),
+    ) -> Result<SpectrumMetrics, AnalysisErrorKind> {
+        let mut checkpoint = |stage: Stage| {
+            if cancel.is_cancelled() {
+                return Err(AnalysisErrorKind::Cancelled);
+            }
+            progress(stage);
+            Ok(())
+        };
+
+        checkpoint(Stage::Decoding)?;
+        let audio = decode_audio_limited(path, self.max_memory)?;
+        self.measure(audio, Some(path), checkpoint)
+    }
+
+    /// `analyze` over consecutive `seconds`-long windows instead of the whole track, so
+    /// intros, drops, and outros each get their own reading. The last window takes
+    /// whatever is left.
+    pub fn analyze_segments(
+        &self,
+        path: &Path,
+        seconds: f32,
+    ) -> Result<Vec<SpectrumMetrics>, AnalysisErrorKind> {
+        let audio = decode_audio_limited(path, self.max_memory)?;
+        let window = ((seconds * audio.sample_rate as f32) as usize).max(1);
+        let length = audio.channels.iter().map(Vec::len).max().unwrap_or(0);
+        (0..length)
+            .step_by(window)
+            .map(|start| {
+                let segment = DecodedAudio {
+                    channels: audio
+                        .channels
+                        .iter()
+                        .map(|c| c[start.min(c.len())..(start + window).min(c.len())].to_vec())
+                        .collect(),
+                    sample_rate: audio.sample_rate,
+                    stream: audio.stream.clone(),
+                    truncated: audio.truncated,
+                };
+                self.measure(segment, None, |_| Ok(()))
+            })
+            .collect()
+    }
+
+    // Everything after decoding, calling `checkpoint` as each stage starts. When `audio` is
+    // truncated, the rest of the track is read from `source` one window at a time.
+    fn measure(
+        &self,
+        audio: DecodedAudio,
+        source: Option<&Path>,
+        mut checkpoint: impl FnMut(Stage) -> Result<(), AnalysisErrorKind>,
+    ) -> Result<SpectrumMetrics, AnalysisErrorKind> {
+        let transform = self.transform;
+        let measures = |group| self.measures(group);
+        let DecodedAudio {
+            channels,
+            sample_rate,
+            stream,
+            truncated,
+        } = audio;
+
+        let bands = get_bands(sample_rate, self.bands());
+
+        checkpoint(Stage::Loudness)?;
+        // Surround mixes are measured through their stereo fold-down, except R128, which
+        // weights the surround channels itself
+        let surround = if channels.len() > 2 && measures(MetricGroup::Surround) {
+            Some(surround::channel_energies(&channels, sample_rate, &bands)?)
+        } else {
+            None
+        };
+        let lfe = surround
+            .as_ref()
+            .and_then(|energies| surround::lfe_usage(&channels, sample_rate, energies));
+        let r128 =
+            measures(MetricGroup::Loudness).then(|| loudness::measure(&channels, sample_rate));
+        let clipping =
+            measures(MetricGroup::Loudness).then(|| clipping::detect_clipping(&channels));
+        let dynamics = measures(MetricGroup::Loudness)
+            .then(|| dynamics::measure_dynamics(&channels))
+            .flatten();
+        let channels = surround::stereo_downmix(&channels).unwrap_or(channels);
+
+        // Compare the channels before folding them to mono
+        let stereo = measures(MetricGroup::Stereo)
+            .then(|| stereo::measure_stereo(&channels, sample_rate))
+            .flatten();
+        let dr_score = measures(MetricGroup::Loudness)
+            .then(|| loudness::dr_score(&channels, sample_rate))
+            .flatten();
+        let all_samples = downmix(&channels);
+        drop(channels);
+
+        if all_samples.is_empty() {
+            return Err(AnalysisErrorKind::EmptyAudio);
+        };
+
+        // Calculate duration in seconds
+        let mut duration_seconds = all_samples.len() as f32 / sample_rate as f32;
+
+        // Calculate loudness (RMS in dB)
+        let mut loudness = calculate_loudness(&all_samples);
+
+        // Untrimmed lead-ins and tails, and dropouts inside the track
+        let silence = measures(MetricGroup::Loudness)
+            .then(|| silence::detect_silence(&all_samples, sample_rate));
+
+        checkpoint(Stage::Spectrum)?;
+        // Calculate energy distribution
+        let mut band_energies = self.band_energies(&all_samples, sample_rate, &bands)?;
+
+        // Calculate zero-crossing rate
+        let mut zcr = calculate_zero_crossing_rate(&all_samples);
+
+        checkpoint(Stage::Rhythm)?;
+        let rhythm = measures(MetricGroup::Rhythm);
+        // Estimate tempo with half/double-time resolution
+        let tempo = rhythm
+            .then(|| tempo::estimate_tempo(&all_samples, sample_rate))
+            .flatten();
+
+        // Transient-heaviness from frame-to-frame spectral flux
+        let onset_strength = rhythm
+            .then(|| tempo::onset_strength(&all_samples))
+            .flatten();
+
+        checkpoint(Stage::Tonality)?;
+        // Best-matching major or minor key from the track's chroma
+        let key = measures(MetricGroup::Key)
+            .then(|| key::estimate_key(&all_samples, sample_rate, transform))
+            .flatten();
+
+        // Measure swing and timing against the beat grid
+        let groove = tempo
+            .as_ref()
+            .and_then(|t| beats::measure_groove(&all_samples, sample_rate, t.bpm));
+
+        // Fingerprint the bar-level onset pattern for rhythm-aware similarity
+        let rhythm_pattern = tempo
+            .as_ref()
+            .map(|t| beats::rhythm_pattern(&all_samples, sample_rate, t.bpm))
+            .unwrap_or_default();
+
+        // Locate the chorus to suggest a preview offset
+        let chorus = measures(MetricGroup::Structure)
+            .then(|| {
+                structure::find_chorus(
+                    &all_samples,
+                    sample_rate,
+                    tempo.as_ref().map(|t| t.bpm),
+                    transform,
+                )
+            })
+            .flatten();
+
+        // Opening and closing tone, overlapping on tracks under a minute
+        let edge = ((structure::EDGE_SECONDS * sample_rate as f32) as usize).min(all_samples.len());
+        let profile = |excerpt: &[f32]| {
+            measures(MetricGroup::Structure)
+                .then(|| structure::edge_profile(excerpt, sample_rate, &bands, self.frame_size))
+                .transpose()
+        };
+        let intro = profile(&all_samples[..edge])?;
+        let outro = profile(&all_samples[all_samples.len() - edge..])?;
+
+        // One-number brightness: slope of the long-term spectrum
+        let spectral = measures(MetricGroup::Spectral);
+        let tilt = spectral
+            .then(|| frequency_bands::spectral_tilt(&all_samples, sample_rate))
+            .flatten();
+        let rolloff = spectral
+            .then(|| frequency_bands::spectral_rolloff(&all_samples, sample_rate, self.frame_size))
+            .flatten();
+        // Brick-wall shelf left by a lossy encoder's lowpass
+        let bandwidth = spectral
+            .then(|| transcode::measure_bandwidth(&all_samples, sample_rate, self.frame_size))
+            .flatten();
+        let exact = spectral
+            .then(|| frequency_bands::spectral_centroid(&all_samples, sample_rate, self.frame_size))
+            .flatten();
+
+        checkpoint(Stage::Timbre)?;
+        // Perceptual harshness from Bark-band loudness and envelope modulation
+        let timbre = measures(MetricGroup::Timbre);
+        let sharpness = timbre
+            .then(|| psychoacoustics::sharpness(&all_samples, sample_rate))
+            .flatten();
+        let roughness = timbre
+            .then(|| psychoacoustics::roughness(&all_samples, sample_rate))
+            .flatten();
+
+        // The rest of a windowed track adds to the level, band, and crossing readings,
+        // each window weighted by its length; the other readings stay with the first
+        if let Some(path) = source.filter(|_| truncated) {
+            let mut measured = all_samples.len();
+            let mut crossings = count_zero_crossings(&all_samples);
+            drop(all_samples);
+            let mut mean_square = 10f64.powf(loudness as f64 / 10.0);
+            let mut more = true;
+            while more {
+                let window = decode_audio_window(path, measured, self.max_memory)?;
+                more = window.truncated;
+                let channels =
+                    surround::stereo_downmix(&window.channels).unwrap_or(window.channels);
+                let samples = downmix(&channels);
+                drop(channels);
+                if samples.is_empty() {
+                    break;
+                }
+
+                let weight = samples.len() as f64 / (measured + samples.len()) as f64;
+                // A tail too short for one transform frame only adds to the length
+                if let Some(energies) = self
+                    .band_energies(&samples, sample_rate, &bands)
+                    .ok()
+                    .filter(|energies| energies.iter().all(|e| e.is_finite()))
+                {
+                    for (total, energy) in band_energies.iter_mut().zip(energies) {
+                        *total += (energy - *total) * weight;
+                    }
+                }
+                let level = 10f64.powf(calculate_loudness(&samples) as f64 / 10.0);
+                mean_square += (level - mean_square) * weight;
+                crossings += count_zero_crossings(&samples);
+                measured += samples.len();
+            }
+            zcr = normalize_zcr(crossings as f32 / measured as f32);
+            duration_seconds = measured as f32 / sample_rate as f32;
+            loudness = (10.0 * mean_square.log10()).clamp(-60.0, 0.0) as f32;
+        }
+        checkpoint(Stage::Done)?;
+
+        // Calculate total energy
+        let total_energy: f64 = band_energies.iter().sum();
+
+        // Convert to percentages
+        let band_percentages: Vec<f32> = band_energies
+            .iter()
+            .map(|&energy| {
+                if total_energy > 0.0 {
+                    (energy / total_energy * 100.0) as f32
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        // Low, mid, and high thirds and the ratios between them
+        let ratios = ratios::energy_ratios(&band_percentages, &bands);
+
+        // Calculate spectral centroid (weighted average position)
+        // Map each band to a position: 0 (sub-bass) to 100 (highs)
+        let band_positions = calculate_band_positions(&bands, sample_rate);
+
+        let centroid = band_percentages
+            .iter()
+            .zip(band_positions.iter())
+            .map(|(pct, pos)| pct * pos)
+            .sum::<f32>()
+            / 100.0;
+
+        // Calculate spectral spread (standard deviation from centroid)
+        let variance = band_percentages
+            .iter()
+            .zip(band_positions.iter())
+            .map(|(pct, pos)| {
+                let diff = pos - centroid;
+                pct * diff * diff
+            })
+            .sum::<f32>()
+            / 100.0;
+
+        let spread = variance.sqrt();
+
+        // Normalize spread to 0-100 scale (typical spread ranges from 0-35)
+        let normalized_spread = (spread / 35.0 * 100.0).min(100.0);
+
+        Ok(SpectrumMetrics {
+            centroid: Percent(centroid),
+            centroid_hz: exact.map(|(hz, _)| Hz(hz)),
+            exact_centroid: exact.map(|(_, position)| Percent(position)),
+            spread: Percent(normalized_spread),
+            zero_crossing_rate: zcr,
+            loudness: Db(loudness),
+            integrated_lufs: r128.as_ref().and_then(|r| r.integrated_lufs).map(Lufs),
+            loudness_range: r128.as_ref().and_then(|r| r.loudness_range).map(Db),
+            true_peak_db: r128.as_ref().and_then(|r| r.true_peak_db).map(Db),
+            dr_score: dr_score.map(Db),
+            dynamics,
+            clipping,
+            silence,
+            duration_seconds,
+            band_percentages,
+            ratios,
+            tempo,
+            key,
+            groove,
+            rhythm_pattern,
+            chorus,
+            intro,
+            outro,
+            tilt,
+            rolloff: rolloff.map(Hz),
+            bandwidth,
+            onset_strength: onset_strength.map(Percent),
+            sharpness,
+            roughness,
+            stereo,
+            surround,
+            lfe,
+            stream: Some(stream),
+            windowed: truncated,
+        })
+    }
+
+    // Per-frame average energy in each band, by the configured transform
+    fn band_energies(
+        &self,
+        samples: &[f32],
+        sample_rate: usize,
+        bands: &[FrequencyBand],
+    ) -> Result<Vec<f64>, AnalysisErrorKind> {
+        Ok(match self.transform {
+            Transform::Stft => {
+                band_energies_with_frame(samples, sample_rate, bands, self.frame_size)?
+            }
+            Transform::Cqt => cqt::cqt_band_energies(samples, sample_rate, bands)?,
+        })
+    }
+
+    /// Analyze many files in parallel, returning a result per path in the same order. A
+    /// file that fails doesn't stop the others.
+    pub fn analyze_paths(&self, paths: &[PathBuf]) -> Vec<Result<FileAnalysis, AnalysisError>> {
+        self.analyze_paths_with_progress(paths, &CancelToken::new(), |_, _| {})
+    }
+
+    /// `analyze_paths` with cancellation and stage callbacks. `progress` is called from the
+    /// worker threads with the file each stage belongs to.
+    pub fn analyze_paths_with_progress(
+        &self,
+        paths: &[PathBuf],
+        cancel: &CancelToken,
+        progress: impl Fn(&Path, Stage) + Sync,
+    ) -> Vec<Result<FileAnalysis, AnalysisError>> {
+        self.map_paths(paths, |analyzer, path| {
+            analyzer
+                .analyze_with_progress(path, cancel, |stage| progress(path, stage))
+                .map(|metrics| FileAnalysis {
+                    path: path.to_path_buf(),
+                    metrics,
+                })
+                .map_err(|e| AnalysisError {
+                    path: path.to_path_buf(),
+                    kind: e,
+                })
+        })
+    }
+
+    /// Run `analyze` over `paths` on `jobs` threads, each with an even share of
+    /// `max_memory`, for callers that wrap every file's analysis in work of their own.
+    pub fn map_paths<P, T>(
+        &self,
+        paths: &[P],
+        analyze: impl Fn(&Analyzer, &Path) -> T + Sync,
+    ) -> Vec<T>
+    where
+        P: AsRef<Path> + Sync,
+        T: Send,
+    {
+        let run = || {
+            let mut analyzer = self.clone();
+            analyzer.max_memory = self
+                .max_memory
+                .map(|bytes| bytes / rayon::current_num_threads() as u64);
+            paths
+                .par_iter()
+                .map(|path| analyze(&analyzer, path.as_ref()))
+                .collect()
+        };
+
+        // Exact runs keep to one thread when memory is capped, so the analyzed windows
+        // don't depend on the machine's core count
+        let jobs = match self.max_memory {
+            Some(_) if is_reproducible() => Some(1),
+            _ => self.jobs,
+        };
+        match rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs.unwrap_or(0))
+            .build()
+        {
+            Ok(pool) => pool.install(run),
+            Err(_) => run(),
+        }
+    }
+}
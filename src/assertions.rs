@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::CachedMetrics;
+use crate::warnings::{WarningRule, evaluate_rule};
+
+/// Limits a mastering pipeline can enforce. Unset limits aren't checked.
+#[derive(clap::Args, Serialize, Deserialize, Default, Clone)]
+pub struct Constraints {
+    /// Fail when true peak exceeds this level (dBTP)
+    #[arg(long, value_name = "DB", allow_negative_numbers = true)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_true_peak: Option<f32>,
+
+    /// Fail when integrated loudness is below this level (LUFS)
+    #[arg(long, value_name = "LUFS", allow_negative_numbers = true)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_lufs: Option<f32>,
+
+    /// Fail when integrated loudness is above this level (LUFS)
+    #[arg(long, value_name = "LUFS", allow_negative_numbers = true)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_lufs: Option<f32>,
+}
+
+impl Constraints {
+    /// These limits, falling back to `base` for any left unset.
+    pub fn or(&self, base: &Constraints) -> Constraints {
+        Constraints {
+            max_true_peak: self.max_true_peak.or(base.max_true_peak),
+            min_lufs: self.min_lufs.or(base.min_lufs),
+            max_lufs: self.max_lufs.or(base.max_lufs),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Failure {
+    pub file: String,
+    pub check: String,
+    pub limit: f32,
+    /// `None` when the file has no measurement for this check
+    pub value: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl Failure {
+    /// Short human-readable form for the warnings line.
+    pub fn describe(&self) -> String {
+        if let Some(message) = &self.message {
+            return message.clone();
+        }
+        let (name, is_maximum) = match self.check.as_str() {
+            "max-true-peak" => ("true peak", true),
+            "min-lufs" => ("loudness", false),
+            "max-lufs" => ("loudness", true),
+            other => (other, true),
+        };
+        match self.value {
+            Some(value) => format!(
+                "{} {:.1} {} {}",
+                name,
+                value,
+                if is_maximum { ">" } else { "<" },
+                self.limit
+            ),
+            None => format!("{} not measured", name),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct AssertReport {
+    pub passed: bool,
+    pub files_checked: usize,
+    pub failures: Vec<Failure>,
+}
+
+/// Check one file against every set limit. A missing measurement fails the check rather
+/// than passing it silently.
+pub fn check_constraints(entry: &CachedMetrics, constraints: &Constraints) -> Vec<Failure> {
+    let checks: [(&str, Option<f32>, Option<f32>, bool); 3] = [
+        (
+            "max-true-peak",
+            constraints.max_true_peak,
+            entry.true_peak_db,
+            true,
+        ),
+        (
+            "min-lufs",
+            constraints.min_lufs,
+            entry.integrated_lufs,
+            false,
+        ),
+        (
+            "max-lufs",
+            constraints.max_lufs,
+            entry.integrated_lufs,
+            true,
+        ),
+    ];
+
+    checks
+        .into_iter()
+        .filter_map(|(check, limit, value, is_maximum)| {
+            let limit = limit?;
+            let violated = match value {
+                Some(value) if is_maximum => value > limit,
+                Some(value) => value < limit,
+                None => true,
+            };
+            violated.then(|| Failure {
+                file: entry.filename.clone(),
+                check: check.to_string(),
+                limit,
+                value,
+                message: None,
+            })
+        })
+        .collect()
+}
+
+/// Limits plus metric rules, e.g. from a `--target` preset.
+pub fn check_file(
+    entry: &CachedMetrics,
+    constraints: &Constraints,
+    rules: &[WarningRule],
+) -> Vec<Failure> {
+    let mut failures = check_constraints(entry, constraints);
+
+    let metrics = entry.to_metrics();
+    for rule in rules {
+        if let Some(breach) = evaluate_rule(rule, &metrics) {
+            failures.push(Failure {
+                file: entry.filename.clone(),
+                check: rule.metric.clone(),
+                limit: breach.limit,
+                value: Some(breach.value),
+                message: rule.message.clone(),
+            });
+        }
+    }
+
+    failures
+}
+
+pub fn check_all(
+    entries: &[&CachedMetrics],
+    constraints: &Constraints,
+    rules: &[WarningRule],
+) -> AssertReport {
+    let failures: Vec<Failure> = entries
+        .iter()
+        .flat_map(|entry| check_file(entry, constraints, rules))
+        .collect();
+
+    AssertReport {
+        passed: failures.is_empty(),
+        files_checked: entries.len(),
+        failures,
+    }
+}
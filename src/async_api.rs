@@ -0,0 +1,60 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use tokio::task;
+
+use crate::analysis::{CancelToken, Stage, analyze_with_progress};
+use crate::frequency_bands::{FrequencyBand, SpectrumMetrics, Transform};
+use crate::utils::audio_files_in;
+
+/// Errors from the async entry points, which have to cross threads. Analysis failures
+/// downcast to `AnalysisErrorKind`, including cancellation.
+pub type AsyncError = Box<dyn Error + Send + Sync>;
+
+/// `audio_files_in` on tokio's blocking pool.
+pub async fn scan_directory(dir: PathBuf) -> Result<Vec<PathBuf>, AsyncError> {
+    Ok(task::spawn_blocking(move || audio_files_in(&dir)).await??)
+}
+
+/// `analyze_within_memory` on tokio's blocking pool, so decoding and FFTs never hold up
+/// the runtime's worker threads.
+pub async fn analyze_file(
+    path: PathBuf,
+    band_layout: Option<Vec<FrequencyBand>>,
+    transform: Transform,
+    max_memory: Option<u64>,
+) -> Result<SpectrumMetrics, AsyncError> {
+    analyze_file_with_progress(
+        path,
+        band_layout,
+        transform,
+        max_memory,
+        CancelToken::new(),
+        |_| {},
+    )
+    .await
+}
+
+/// `analyze_with_progress` on tokio's blocking pool. `progress` runs on the blocking
+/// thread. Dropping the future doesn't stop the work; cancel the token for that.
+pub async fn analyze_file_with_progress(
+    path: PathBuf,
+    band_layout: Option<Vec<FrequencyBand>>,
+    transform: Transform,
+    max_memory: Option<u64>,
+    cancel: CancelToken,
+    progress: impl FnMut(Stage) + Send + 'static,
+) -> Result<SpectrumMetrics, AsyncError> {
+    task::spawn_blocking(move || {
+        analyze_with_progress(
+            &path,
+            band_layout.as_deref(),
+            transform,
+            max_memory,
+            &cancel,
+            progress,
+        )
+    })
+    .await?
+    .map_err(AsyncError::from)
+}
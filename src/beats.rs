@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frequency_bands::{FRAME_SIZE, FrequencyBand, HOP_SIZE, calculate_band_energies};
+use crate::tempo::{low_band_bins, onset_envelopes};
+
+pub const BEATS_PER_BAR: usize = 4;
+
+// Off-beat eighths are looked for between straight (0.5) and heavy swing (~0.75) positions
+const SWING_PHASE_MIN: f32 = 0.35;
+const SWING_PHASE_MAX: f32 = 0.85;
+// Onsets within this fraction of a beat from the grid count as on-beat hits
+const ON_BEAT_WINDOW: f32 = 0.15;
+const MIN_GROOVE_ONSETS: usize = 8;
+// Rhythm fingerprints have one step per 16th note of a 4/4 bar
+pub const PATTERN_STEPS: usize = 16;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Groove {
+    pub swing_percent: f32,       // 50 = straight, ~67 = triplet swing
+    pub timing_deviation_ms: f32, // Mean distance of on-beat onsets from the grid
+}
+
+pub struct BarEnergies {
+    pub start_seconds: f32,
+    pub end_seconds: f32,
+    pub band_db: Vec<f32>, // Mean energy per band over the bar, in dB
+}
+
+/// Sample positions of each beat, phase-aligned to the strongest onsets. The first
+/// returned beat is the estimated downbeat.
+pub fn beat_positions(samples: &[f32], sample_rate: usize, bpm: f32) -> Vec<usize> {
+    let (full, low) = onset_envelopes(samples, low_band_bins(sample_rate));
+    let frame_rate = sample_rate as f32 / HOP_SIZE as f32;
+
+    // Envelope value i measures the change into the frame starting at (i + 1) hops;
+    // place the beat at that frame's center
+    beat_frames(&full, &low, frame_rate, bpm)
+        .into_iter()
+        .map(|position| ((position + 1.0) * HOP_SIZE as f32) as usize + FRAME_SIZE / 2)
+        .collect()
+}
+
+/// Beat positions in envelope frames, starting from the estimated downbeat.
+fn beat_frames(full: &[f32], low: &[f32], frame_rate: f32, bpm: f32) -> Vec<f32> {
+    if full.is_empty() || bpm <= 0.0 {
+        return Vec::new();
+    }
+
+    let period = 60.0 / bpm * frame_rate;
+
+    // Pick the grid offset whose beats land on the most onset energy
+    let beat_score = |envelope: &[f32], offset: f32, step: f32| -> f32 {
+        let mut position = offset;
+        let mut score = 0.0;
+        while (position as usize) < envelope.len() {
+            score += envelope[position as usize];
+            position += step;
+        }
+        score
+    };
+
+    let phase = (0..period.ceil() as usize)
+        .map(|offset| offset as f32)
+        .max_by(|&a, &b| beat_score(full, a, period).total_cmp(&beat_score(full, b, period)))
+        .unwrap_or(0.0);
+
+    // Downbeats usually carry the strongest kick, so choose the bar phase on the low band
+    let bar_period = period * BEATS_PER_BAR as f32;
+    let downbeat = (0..BEATS_PER_BAR)
+        .map(|beat| phase + beat as f32 * period)
+        .max_by(|&a, &b| beat_score(low, a, bar_period).total_cmp(&beat_score(low, b, bar_period)))
+        .unwrap_or(phase);
+
+    let mut beats = Vec::new();
+    let mut position = downbeat;
+    while (position as usize) < full.len() {
+        beats.push(position);
+        position += period;
+    }
+    beats
+}
+
+/// Onset strength folded onto one bar of 16th-note steps and normalized to sum to 1.
+/// Tempo-independent, so a four-on-the-floor pattern matches at 120 or 128 BPM.
+pub fn rhythm_pattern(samples: &[f32], sample_rate: usize, bpm: f32) -> Vec<f32> {
+    let (full, low) = onset_envelopes(samples, low_band_bins(sample_rate));
+    let frame_rate = sample_rate as f32 / HOP_SIZE as f32;
+
+    let beats = beat_frames(&full, &low, frame_rate, bpm);
+    let Some(&downbeat) = beats.first() else {
+        return Vec::new();
+    };
+    let bar_period = 60.0 / bpm * frame_rate * BEATS_PER_BAR as f32;
+
+    let mut pattern = [0.0f32; PATTERN_STEPS];
+    for (frame, &strength) in full.iter().enumerate().skip(downbeat.ceil() as usize) {
+        let position = ((frame as f32 - downbeat) / bar_period).fract();
+        // Round to the nearest step so onsets just before a step still count for it
+        let step = (position * PATTERN_STEPS as f32).round() as usize % PATTERN_STEPS;
+        pattern[step] += strength;
+    }
+
+    let total: f32 = pattern.iter().sum();
+    if total <= 0.0 {
+        return Vec::new();
+    }
+    pattern.iter().map(|&value| value / total).collect()
+}
+
+/// Onset peaks as (frame, strength): local maxima standing out from the envelope's spread.
+fn detect_onsets(envelope: &[f32]) -> Vec<(f32, f32)> {
+    if envelope.len() < 3 {
+        return Vec::new();
+    }
+
+    let mean = envelope.iter().sum::<f32>() / envelope.len() as f32;
+    let variance = envelope
+        .iter()
+        .map(|&x| (x - mean) * (x - mean))
+        .sum::<f32>()
+        / envelope.len() as f32;
+    let threshold = mean + variance.sqrt();
+
+    (1..envelope.len() - 1)
+        .filter(|&i| {
+            envelope[i] > threshold
+                && envelope[i] > envelope[i - 1]
+                && envelope[i] >= envelope[i + 1]
+        })
+        .map(|i| (i as f32, envelope[i]))
+        .collect()
+}
+
+/// Measure how off-beat onsets sit between beats and how tightly on-beat onsets hit the grid.
+pub fn measure_groove(samples: &[f32], sample_rate: usize, bpm: f32) -> Option<Groove> {
+    let (full, low) = onset_envelopes(samples, low_band_bins(sample_rate));
+    let frame_rate = sample_rate as f32 / HOP_SIZE as f32;
+
+    let beats = beat_frames(&full, &low, frame_rate, bpm);
+    let first_beat = *beats.first()?;
+    let period = 60.0 / bpm * frame_rate;
+
+    let mut offbeat_weight = 0.0;
+    let mut offbeat_phase = 0.0;
+    let mut offbeat_count = 0;
+    let mut deviation_sum = 0.0;
+    let mut onbeat_count = 0;
+
+    for (frame, strength) in detect_onsets(&full) {
+        if frame < first_beat {
+            continue;
+        }
+        let phase = ((frame - first_beat) / period).fract();
+
+        if (SWING_PHASE_MIN..=SWING_PHASE_MAX).contains(&phase) {
+            // The second eighth of the beat: 0.5 is straight, ~0.67 is triplet swing
+            offbeat_phase += phase * strength;
+            offbeat_weight += strength;
+            offbeat_count += 1;
+        } else if !(ON_BEAT_WINDOW..1.0 - ON_BEAT_WINDOW).contains(&phase) {
+            let distance = phase.min(1.0 - phase) * period;
+            deviation_sum += distance / frame_rate * 1000.0;
+            onbeat_count += 1;
+        }
+    }
+
+    if offbeat_count < MIN_GROOVE_ONSETS || offbeat_weight <= 0.0 {
+        return None;
+    }
+
+    Some(Groove {
+        swing_percent: offbeat_phase / offbeat_weight * 100.0,
+        timing_deviation_ms: if onbeat_count > 0 {
+            deviation_sum / onbeat_count as f32
+        } else {
+            0.0
+        },
+    })
+}
+
+/// Band energies for each bar of `beats_per_bar` beats, so sections line up with the music.
+pub fn bar_energy_grid(
+    samples: &[f32],
+    sample_rate: usize,
+    bands: &[FrequencyBand],
+    beats: &[usize],
+    beats_per_bar: usize,
+) -> Vec<BarEnergies> {
+    let mut grid = Vec::new();
+
+    for (index, bar) in beats.chunks(beats_per_bar).enumerate() {
+        // Skip a trailing partial bar, it has no stable reading
+        if bar.len() < beats_per_bar {
+            break;
+        }
+
+        let start = bar[0];
+        let end = beats
+            .get((index + 1) * beats_per_bar)
+            .copied()
+            .unwrap_or(samples.len())
+            .min(samples.len());
+        if end <= start + FRAME_SIZE {
+            continue;
+        }
+
+        let Ok(energies) = calculate_band_energies(&samples[start..end], sample_rate, bands) else {
+            continue;
+        };
+        grid.push(BarEnergies {
+            start_seconds: start as f32 / sample_rate as f32,
+            end_seconds: end as f32 / sample_rate as f32,
+            band_db: energies
+                .iter()
+                .map(|&e| (10.0 * (e + 1e-10).log10()) as f32)
+                .collect(),
+        });
+    }
+
+    grid
+}
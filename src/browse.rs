@@ -0,0 +1,476 @@
+use std::cmp::Ordering;
+use std::io::{self, Stderr};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use ratatui::Terminal;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::cursor::Show;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState};
+
+use crate::frequency_bands::{FrequencyBand, band_labels};
+use crate::i18n::{Msg, tr};
+use crate::utils::CachedMetrics;
+
+const HISTOGRAM_WIDTH: usize = 30; // Characters for the loudest band in the detail pane
+const PAGE_ROWS: usize = 10;
+
+struct Column {
+    key: &'static str, // Used in `key>value` filters
+    title: &'static str,
+    width: u16,
+    value: fn(&CachedMetrics) -> Option<f32>,
+    format: fn(f32) -> String,
+}
+
+fn one_decimal(value: f32) -> String {
+    format!("{:.1}", value)
+}
+
+const COLUMNS: &[Column] = &[
+    Column {
+        key: "dur",
+        title: "Time",
+        width: 6,
+        value: |e| Some(e.duration_seconds),
+        format: |v| format!("{}:{:02}", v as u32 / 60, v as u32 % 60),
+    },
+    Column {
+        key: "centroid",
+        title: "Centroid",
+        width: 8,
+        value: |e| Some(e.centroid),
+        format: one_decimal,
+    },
+    Column {
+        key: "spread",
+        title: "Spread",
+        width: 6,
+        value: |e| Some(e.spread),
+        format: one_decimal,
+    },
+    Column {
+        key: "rms",
+        title: "RMS dB",
+        width: 6,
+        value: |e| Some(e.loudness),
+        format: one_decimal,
+    },
+    Column {
+        key: "lufs",
+        title: "LUFS",
+        width: 6,
+        value: |e| e.integrated_lufs,
+        format: one_decimal,
+    },
+    Column {
+        key: "peak",
+        title: "dBTP",
+        width: 5,
+        value: |e| e.true_peak_db,
+        format: one_decimal,
+    },
+    Column {
+        key: "dr",
+        title: "DR",
+        width: 3,
+        value: |e| e.dr_score,
+        format: |v| format!("{:.0}", v),
+    },
+    Column {
+        key: "crest",
+        title: "Crest",
+        width: 5,
+        value: |e| e.dynamics.as_ref().map(|d| d.crest_factor_db),
+        format: one_decimal,
+    },
+    Column {
+        key: "bpm",
+        title: "BPM",
+        width: 6,
+        value: |e| e.tempo.as_ref().map(|t| t.bpm),
+        format: one_decimal,
+    },
+];
+
+/// A `key>value` or `key<value` filter, e.g. `lufs>-9` or `bpm<100`.
+fn parse_condition(filter: &str) -> Option<(&'static Column, bool, f32)> {
+    let at = filter.find(['<', '>'])?;
+    let key = filter[..at].trim().to_lowercase();
+    let limit = filter[at + 1..].trim().parse().ok()?;
+    let column = COLUMNS.iter().find(|c| c.key == key)?;
+    Some((column, filter[at..].starts_with('>'), limit))
+}
+
+fn matches(entry: &CachedMetrics, filter: &str) -> bool {
+    if let Some((column, above, limit)) = parse_condition(filter) {
+        return (column.value)(entry).is_some_and(|v| if above { v > limit } else { v < limit });
+    }
+    let needle = filter.to_lowercase();
+    entry.filename.to_lowercase().contains(&needle)
+        || entry
+            .tags
+            .as_ref()
+            .and_then(|t| t.display_name())
+            .is_some_and(|name| name.to_lowercase().contains(&needle))
+}
+
+struct Browser<'a> {
+    entries: &'a [CachedMetrics],
+    dir: &'a Path,
+    labels: Vec<String>,
+    visible: Vec<usize>, // Indices into `entries`, filtered and sorted
+    sort: usize,         // 0 sorts by filename, then one per column
+    descending: bool,
+    filter: String,
+    editing: bool,
+    queued: Vec<usize>,
+    table: TableState,
+    status: String,
+}
+
+impl Browser<'_> {
+    fn selected(&self) -> Option<usize> {
+        self.table
+            .selected()
+            .and_then(|row| self.visible.get(row))
+            .copied()
+    }
+
+    /// Re-filter and re-sort, keeping the same file selected while it's still listed.
+    fn refresh(&mut self) {
+        let previous = self.selected();
+        let entries = self.entries;
+        self.visible = (0..entries.len())
+            .filter(|&i| matches(&entries[i], &self.filter))
+            .collect();
+
+        let (sort, descending) = (self.sort, self.descending);
+        self.visible.sort_by(|&a, &b| {
+            let order = match sort {
+                0 => entries[a]
+                    .filename
+                    .to_lowercase()
+                    .cmp(&entries[b].filename.to_lowercase()),
+                n => {
+                    let value = COLUMNS[n - 1].value;
+                    match (value(&entries[a]), value(&entries[b])) {
+                        (Some(x), Some(y)) => x.total_cmp(&y),
+                        // Unmeasured tracks stay at the bottom either way
+                        (Some(_), None) => return Ordering::Less,
+                        (None, Some(_)) => return Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    }
+                }
+            };
+            if descending { order.reverse() } else { order }
+        });
+
+        let row = previous
+            .and_then(|index| self.visible.iter().position(|&i| i == index))
+            .unwrap_or(0);
+        self.table.select((!self.visible.is_empty()).then_some(row));
+    }
+
+    fn move_by(&mut self, rows: isize) {
+        if self.visible.is_empty() {
+            return;
+        }
+        let current = self.table.selected().unwrap_or(0) as isize;
+        let last = self.visible.len() as isize - 1;
+        self.table
+            .select(Some((current + rows).clamp(0, last) as usize));
+    }
+
+    fn path(&self, index: usize) -> PathBuf {
+        self.dir.join(&self.entries[index].filename)
+    }
+
+    /// Handles a key, returning false once the user quits.
+    fn handle(&mut self, key: KeyCode) -> bool {
+        if self.editing {
+            match key {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.editing = false,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.editing = false;
+                }
+                _ => return true,
+            }
+            self.refresh();
+            return true;
+        }
+
+        self.status.clear();
+        match key {
+            KeyCode::Char('q') => return false,
+            KeyCode::Esc if self.filter.is_empty() => return false,
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.refresh();
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::PageDown => self.move_by(PAGE_ROWS as isize),
+            KeyCode::PageUp => self.move_by(-(PAGE_ROWS as isize)),
+            KeyCode::Home | KeyCode::Char('g') => self.move_by(isize::MIN / 2),
+            KeyCode::End | KeyCode::Char('G') => self.move_by(isize::MAX / 2),
+            KeyCode::Right | KeyCode::Char('s') => {
+                self.sort = (self.sort + 1) % (COLUMNS.len() + 1);
+                self.refresh();
+            }
+            KeyCode::Left | KeyCode::Char('S') => {
+                self.sort = (self.sort + COLUMNS.len()) % (COLUMNS.len() + 1);
+                self.refresh();
+            }
+            KeyCode::Char('r') => {
+                self.descending = !self.descending;
+                self.refresh();
+            }
+            KeyCode::Char('/') => self.editing = true,
+            KeyCode::Char(' ') => {
+                if let Some(index) = self.selected() {
+                    match self.queued.iter().position(|&i| i == index) {
+                        Some(at) => {
+                            self.queued.remove(at);
+                        }
+                        None => self.queued.push(index),
+                    }
+                    self.move_by(1);
+                }
+            }
+            KeyCode::Char('o') | KeyCode::Enter => {
+                if let Some(index) = self.selected() {
+                    let name = &self.entries[index].filename;
+                    self.status = match open_file(&self.path(index)) {
+                        Ok(()) => tr(Msg::Opened, &[name]),
+                        Err(e) => tr(Msg::OpenFailed, &[name, &e]),
+                    };
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn table(&self) -> Table<'_> {
+        let arrow = if self.descending { " ▼" } else { " ▲" };
+        let title = |index: usize, title: &str| {
+            if index == self.sort {
+                format!("{}{}", title, arrow)
+            } else {
+                title.to_string()
+            }
+        };
+        let header = Row::new(
+            std::iter::once(title(0, "File")).chain(
+                COLUMNS
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| format!("{:>w$}", title(i + 1, c.title), w = c.width as usize)),
+            ),
+        )
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let rows = self.visible.iter().map(|&index| {
+            let entry = &self.entries[index];
+            let mark = if self.queued.contains(&index) {
+                "+ "
+            } else {
+                "  "
+            };
+            Row::new(
+                std::iter::once(Cell::from(format!("{}{}", mark, entry.filename))).chain(
+                    COLUMNS.iter().map(|c| {
+                        let text = (c.value)(entry).map(c.format).unwrap_or_default();
+                        Cell::from(format!("{:>w$}", text, w = c.width as usize))
+                    }),
+                ),
+            )
+        });
+
+        let widths = std::iter::once(Constraint::Min(16))
+            .chain(COLUMNS.iter().map(|c| Constraint::Length(c.width + 2)));
+        Table::new(rows, widths)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                " {} — {} of {} files, {} queued ",
+                self.dir.display(),
+                self.visible.len(),
+                self.entries.len(),
+                self.queued.len()
+            )))
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+    }
+
+    fn detail(&self) -> Paragraph<'_> {
+        let block = Block::default().borders(Borders::ALL).title(" Details ");
+        let Some(entry) = self.selected().map(|i| &self.entries[i]) else {
+            return Paragraph::new("No files match the filter").block(block);
+        };
+
+        let mut lines = vec![Line::from(entry.filename.clone())];
+        if let Some(tags) = &entry.tags {
+            lines.extend(tags.display_name().map(Line::from));
+            let extra: Vec<&str> = [&tags.album, &tags.genre]
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .collect();
+            if !extra.is_empty() {
+                lines.push(Line::from(extra.join(" │ ")));
+            }
+        }
+        lines.push(Line::from(""));
+        let mut field = |label: &str, value: Option<String>| {
+            if let Some(value) = value {
+                lines.push(Line::from(format!("{:<10} {}", label, value)));
+            }
+        };
+        field("Key", entry.key.as_ref().map(|k| k.to_string()));
+        field(
+            "LRA",
+            entry.loudness_range.map(|lra| format!("{:.1} LU", lra)),
+        );
+        field("Tilt", entry.tilt.map(|t| format!("{:.1} dB/oct", t)));
+        field("Rolloff", entry.rolloff.map(|hz| format!("{:.0} Hz", hz)));
+        field("ZCR", Some(format!("{:.4}", entry.zero_crossing_rate)));
+
+        lines.push(Line::from(""));
+        let loudest = entry
+            .band_percentages
+            .iter()
+            .copied()
+            .fold(0.0f32, f32::max);
+        for (label, &pct) in self.labels.iter().zip(&entry.band_percentages) {
+            let width = if loudest > 0.0 {
+                (pct / loudest * HISTOGRAM_WIDTH as f32).round() as usize
+            } else {
+                0
+            };
+            lines.push(Line::from(format!(
+                "{:<10} {:>5.1}% {}",
+                label,
+                pct,
+                "█".repeat(width)
+            )));
+        }
+        Paragraph::new(lines).block(block)
+    }
+
+    fn help(&self) -> Line<'_> {
+        if self.editing {
+            Line::from(format!(
+                "Filter: {}_  (text, or e.g. lufs>-9; Enter keeps it, Esc clears)",
+                self.filter
+            ))
+        } else if !self.status.is_empty() {
+            Line::from(self.status.as_str())
+        } else {
+            let filter = if self.filter.is_empty() {
+                String::new()
+            } else {
+                format!("[{}]  ", self.filter)
+            };
+            Line::from(format!(
+                "{}↑↓ move  ←→ sort  r reverse  / filter  o open  space queue  q quit",
+                filter
+            ))
+        }
+    }
+}
+
+/// Hand a file to the desktop's default application.
+fn open_file(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+// Puts the terminal back however the browser exits
+struct RestoreTerminal;
+
+impl Drop for RestoreTerminal {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stderr(), LeaveAlternateScreen, Show);
+    }
+}
+
+/// Browse analyzed tracks in a full-screen table. Drawn on stderr so the queued files,
+/// returned in the order they were queued, can be piped from stdout.
+pub fn run(
+    entries: &[CachedMetrics],
+    dir: &Path,
+    bands: &[FrequencyBand],
+) -> io::Result<Vec<PathBuf>> {
+    enable_raw_mode()?;
+    let _restore = RestoreTerminal;
+    execute!(io::stderr(), EnterAlternateScreen)?;
+    let mut terminal: Terminal<CrosstermBackend<Stderr>> =
+        Terminal::new(CrosstermBackend::new(io::stderr()))?;
+
+    let mut browser = Browser {
+        entries,
+        dir,
+        labels: band_labels(bands),
+        visible: Vec::new(),
+        sort: 0,
+        descending: false,
+        filter: String::new(),
+        editing: false,
+        queued: Vec::new(),
+        table: TableState::default(),
+        status: String::new(),
+    };
+    browser.refresh();
+
+    loop {
+        terminal.draw(|frame| {
+            let [main, footer] =
+                Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+            let [list, detail] =
+                Layout::horizontal([Constraint::Percentage(64), Constraint::Percentage(36)])
+                    .areas(main);
+            let mut table = browser.table.clone();
+            frame.render_stateful_widget(browser.table(), list, &mut table);
+            browser.table = table;
+            frame.render_widget(browser.detail(), detail);
+            frame.render_widget(browser.help(), footer);
+        })?;
+
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && !browser.handle(key.code)
+        {
+            break;
+        }
+    }
+
+    Ok(browser.queued.iter().map(|&i| browser.path(i)).collect())
+}
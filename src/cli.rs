@@ -0,0 +1,587 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+use rust_audio_analysis::assertions::Constraints;
+use rust_audio_analysis::clips::Segment;
+use rust_audio_analysis::config::{Config, parse_profile_name};
+use rust_audio_analysis::envelope::DEFAULT_POINTS;
+use rust_audio_analysis::filter::FileFilter;
+use rust_audio_analysis::frequency_bands::Transform;
+use rust_audio_analysis::i18n::Lang;
+use rust_audio_analysis::metrics::metric_keys;
+use rust_audio_analysis::normalization::LoudnormTargets;
+use rust_audio_analysis::output::{DisplayFormat, FileFormat, OutputTarget, parse_output_target};
+use rust_audio_analysis::release::ReleaseTolerances;
+use rust_audio_analysis::sorting::{SortKey, parse_sort_key};
+use rust_audio_analysis::utils::{CacheOptions, MAX_RATING};
+use rust_audio_analysis::warnings::RULE_METRICS;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Spectral analysis for folders of MP3, WAV, AIFF, FLAC, DSD, and AAC files, and the audio of MP4/MKV videos"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Directory to analyze (defaults to the current directory)
+    pub directory: Option<PathBuf>,
+
+    /// Use a named profile with its own config, cache location, and band layout
+    #[arg(long, global = true, value_parser = parse_profile_name)]
+    pub profile_name: Option<String>,
+
+    /// Bit-identical metrics on every machine and thread count, at some cost in speed
+    #[arg(long, global = true)]
+    pub reproducible: bool,
+
+    /// Plain terminal output without colors (also set by the NO_COLOR environment variable)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Describe bars and sparklines in words, without box-drawing characters, for screen readers and plain-text logs
+    #[arg(long, global = true)]
+    pub screen_reader: bool,
+
+    /// Decode formats the built-in decoders can't read through ffmpeg, optionally at PATH
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        num_args = 0..=1,
+        default_missing_value = "ffmpeg"
+    )]
+    pub ffmpeg: Option<PathBuf>,
+
+    /// Analyze the Nth audio stream (from 0) in files with several; see `streams`
+    #[arg(long, global = true, value_name = "N")]
+    pub stream: Option<usize>,
+
+    /// Language for status messages and summaries (default: from LC_ALL, LC_MESSAGES, or LANG)
+    #[arg(long, global = true, value_enum)]
+    pub lang: Option<Lang>,
+
+    /// Decode past damaged MP3 frames instead of failing the file; the share lost is reported as a warning
+    #[arg(long, global = true)]
+    pub skip_bad_frames: bool,
+
+    /// Print each intermediate step of analyzing FILE (window, spectrum, band mapping, raw energies)
+    #[arg(long, value_name = "FILE")]
+    pub explain_run: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub cache: CacheArgs,
+
+    #[command(flatten)]
+    pub analyze: AnalyzeArgs,
+}
+
+/// Options for the default directory analysis.
+#[derive(Args, Default)]
+pub struct AnalyzeArgs {
+    /// Only analyze files whose name matches this glob, e.g. "*.mp3" (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Skip files whose name matches this glob, e.g. "demo_*" (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Only analyze files with these extensions, e.g. mp3,flac,wav
+    #[arg(long, value_name = "EXT", value_delimiter = ',', value_parser = parse_extension)]
+    pub ext: Vec<String>,
+
+    /// Number of files to analyze at once (defaults to one per CPU core)
+    #[arg(short, long, value_name = "N")]
+    pub jobs: Option<usize>,
+
+    /// Export per-bar band energies aligned to detected beats as CSV
+    #[arg(long, value_name = "CSV")]
+    pub bar_grid: Option<PathBuf>,
+
+    /// Round metrics in JSON and CSV output to this many decimal places
+    #[arg(long, value_name = "DIGITS")]
+    pub precision: Option<usize>,
+
+    /// Keep cached values when re-analysis changes them by less than this fraction (e.g. 0.001)
+    #[arg(long, value_name = "FRACTION")]
+    pub tolerance: Option<f32>,
+
+    /// Bound decoded audio across all threads (e.g. 512M, 2G); longer tracks are read in
+    /// windows, with tempo, key, structure, and R128 taken from the first, and peak memory is
+    /// shown per file
+    #[arg(long, value_parser = parse_size)]
+    pub max_memory: Option<u64>,
+
+    /// Cut detected segments (drops, chorus, or preview) into clips in DIR: stream copies in
+    /// the source format with --ffmpeg, WAV re-encodes without
+    #[arg(long, num_args = 2, value_names = ["SEGMENT", "DIR"])]
+    pub extract: Vec<String>,
+
+    /// Export downsampled min/max/RMS waveform envelopes as JSON, e.g. for wavesurfer.js
+    #[arg(long, value_name = "JSON")]
+    pub envelope: Option<PathBuf>,
+
+    /// Points per track in the --envelope export
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_POINTS, requires = "envelope")]
+    pub envelope_points: usize,
+
+    /// Spectral transform for band energies and chroma
+    #[arg(long, value_enum, default_value_t = Transform::Stft)]
+    pub transform: Transform,
+
+    /// Measure the level at specific frequencies, e.g. 50,60 for mains hum or 15734 for CRT whine
+    #[arg(long, value_name = "HZ", value_delimiter = ',')]
+    pub probe: Vec<f32>,
+
+    /// Also measure each track over consecutive windows of this many seconds and show how it changes
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub segments: Option<f32>,
+
+    /// Check channel order against same-named files in another directory (e.g. an earlier master)
+    #[arg(long, value_name = "DIR")]
+    pub swap_reference: Option<PathBuf>,
+
+    /// Warn about files that miss a mastering target: streaming, club, vinyl, or a custom preset
+    #[arg(long, value_name = "PRESET")]
+    pub target: Option<String>,
+
+    /// Record the directory's average metrics in its history (see `history`)
+    #[arg(long)]
+    pub snapshot: bool,
+
+    /// Exit with status 1 if any file triggers a warning
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Skip per-file output; only summaries and errors are printed
+    #[arg(long)]
+    pub quiet: bool,
+
+    /// Don't show the progress bar on stderr (it's hidden anyway when stderr isn't a terminal)
+    #[arg(long)]
+    pub no_progress: bool,
+
+    /// How per-file results are shown in the terminal
+    #[arg(long, value_enum, default_value_t = DisplayFormat::Table)]
+    pub format: DisplayFormat,
+
+    /// Explain the table's columns and band names above the results
+    #[arg(long)]
+    pub legend: bool,
+
+    /// Head each file with its tagged artist and title (ID3) instead of the filename
+    #[arg(long)]
+    pub show_tags: bool,
+
+    /// Store centroid, spread, ZCR, and BPM in TXXX frames of each MP3's ID3 tag
+    #[arg(long)]
+    pub write_tags: bool,
+
+    /// Write an M3U playlist of the analyzed files, ordered by --playlist-by
+    #[arg(long, value_name = "M3U")]
+    pub playlist: Option<PathBuf>,
+
+    /// Metric that orders the --playlist, lowest first (e.g. centroid for an energy-building set)
+    #[arg(
+        long,
+        value_name = "METRIC",
+        default_value = "centroid",
+        requires = "playlist",
+        value_parser = clap::builder::PossibleValuesParser::new(RULE_METRICS)
+    )]
+    pub playlist_by: String,
+
+    /// Order the display and output files by metrics, e.g. centroid,-spread (a leading - sorts highest first; ties fall back to filename)
+    #[arg(
+        long,
+        value_name = "METRICS",
+        value_delimiter = ',',
+        allow_hyphen_values = true,
+        value_parser = parse_sort_key
+    )]
+    pub sort_by: Vec<SortKey>,
+
+    /// Draw each band as its difference from the folder's average profile, to spot unusually bassy or bright tracks
+    #[arg(long)]
+    pub relative: bool,
+
+    /// Report every metric as a z-score against the library database (or this folder's cache without one), in the table and in exports
+    #[arg(long, conflicts_with = "relative")]
+    pub standardize: bool,
+
+    /// Print cached results at once and check their files in the background, then analyze and append any that changed
+    #[arg(long, conflicts_with_all = ["relative", "standardize", "limit", "offset"])]
+    pub lazy: bool,
+
+    /// After the first pass, keep watching the folder and analyze audio files as they are added or changed, until interrupted
+    #[arg(long)]
+    pub watch: bool,
+
+    /// With --watch, also append each newly analyzed track to FILE as a line of JSON
+    #[arg(long, value_name = "FILE", requires = "watch")]
+    pub watch_log: Option<PathBuf>,
+
+    /// Report at most N tracks (after --sort-by); the rest are still analyzed and cached
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Skip the first N tracks of the report, e.g. --offset 50 --limit 50 for the second page
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub offset: usize,
+
+    /// Page the output through $PAGER (less by default) when it goes to a terminal
+    #[arg(long)]
+    pub pager: bool,
+
+    /// Order the --playlist highest first
+    #[arg(long, requires = "playlist")]
+    pub descending: bool,
+
+    /// Also write results to a file, e.g. json=metrics.json or csv=metrics.csv (repeatable)
+    #[arg(long, value_name = "FORMAT=PATH", value_parser = parse_output_target)]
+    pub out: Vec<OutputTarget>,
+
+    /// Write every metric to a CSV file for spreadsheets or pandas (same as --out csv=PATH)
+    #[arg(long, value_name = "PATH")]
+    pub csv: Option<PathBuf>,
+}
+
+impl AnalyzeArgs {
+    /// The segment kind and output directory from --extract.
+    pub fn extract_target(&self) -> Result<Option<(Segment, PathBuf)>, String> {
+        let [segment, dir] = self.extract.as_slice() else {
+            return Ok(None);
+        };
+        let segment = Segment::from_str(segment, true).map_err(|_| {
+            format!(
+                "unknown segment '{}' for --extract (expected drops, chorus, or preview)",
+                segment
+            )
+        })?;
+        Ok(Some((segment, PathBuf::from(dir))))
+    }
+
+    /// Which files to analyze, from --include, --exclude, and --ext.
+    pub fn file_filter(&self) -> FileFilter {
+        FileFilter {
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            extensions: self.ext.clone(),
+        }
+    }
+
+    /// Files to write besides the terminal output, from --out and its shorthands.
+    pub fn output_targets(&self) -> Vec<OutputTarget> {
+        let csv = self.csv.iter().map(|path| OutputTarget {
+            format: FileFormat::Csv,
+            path: path.clone(),
+        });
+        self.out.iter().cloned().chain(csv).collect()
+    }
+}
+
+#[derive(Args)]
+pub struct CacheArgs {
+    /// Write the cache as compact JSON instead of pretty-printed
+    #[arg(long, global = true)]
+    pub compact_cache: bool,
+
+    /// Gzip the cache file (file_calc_cache.json.gz)
+    #[arg(long, global = true)]
+    pub gzip_cache: bool,
+
+    /// Evict entries for deleted files, oldest first, once the cache exceeds this size (e.g. 500K, 20M)
+    #[arg(long, global = true, value_parser = parse_size)]
+    pub max_cache_size: Option<u64>,
+}
+
+impl CacheArgs {
+    pub fn options(&self, config: &Config) -> CacheOptions {
+        CacheOptions {
+            compact: self.compact_cache,
+            gzip: self.gzip_cache,
+            max_size: self.max_cache_size,
+            cache_dir: config.cache_dir.clone(),
+            profile: config.profile.clone(),
+        }
+    }
+}
+
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (digits, multiplier) = match value.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&value[..value.len() - 1], 1024),
+        Some('M') => (&value[..value.len() - 1], 1024 * 1024),
+        Some('G') => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+
+    let n = digits
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| format!("invalid size '{}' (expected e.g. 500K, 20M, 1G)", value))?;
+    n.checked_mul(multiplier)
+        .ok_or_else(|| format!("size too large: {}", value))
+}
+
+fn parse_extension(value: &str) -> Result<String, String> {
+    let extension = value.trim().trim_start_matches('.').to_ascii_lowercase();
+    if extension.is_empty() || !extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("invalid extension '{}' (e.g. mp3 or flac)", value));
+    }
+    Ok(extension)
+}
+
+fn parse_seconds(value: &str) -> Result<f32, String> {
+    match value.trim().parse::<f32>() {
+        Ok(seconds) if seconds >= 1.0 && seconds.is_finite() => Ok(seconds),
+        _ => Err(format!(
+            "invalid length '{}' (expected at least 1 second)",
+            value
+        )),
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Import metrics from sidecar JSONs, Essentia output, or bs1770gain logs into the cache
+    Import {
+        /// Files or folders to import from
+        #[arg(required = true)]
+        sources: Vec<PathBuf>,
+
+        /// Directory whose cache receives the imported metrics
+        #[arg(short, long)]
+        directory: Option<PathBuf>,
+    },
+
+    /// Manage the multi-directory library registry
+    Library {
+        #[command(subcommand)]
+        action: LibraryAction,
+    },
+
+    /// Manage files that scans always skip, such as ringtones or voice memos
+    Ignore {
+        #[command(subcommand)]
+        action: IgnoreAction,
+    },
+
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to generate completions for
+        shell: Shell,
+    },
+
+    /// Explain what a metric means and how it is computed
+    Explain {
+        /// Metric to explain (all metrics if omitted)
+        #[arg(value_parser = clap::builder::PossibleValuesParser::new(metric_keys()))]
+        metric: Option<String>,
+    },
+
+    /// Print a man page (roff) to stdout
+    Man,
+
+    /// Find the tracks that sound most like a reference, by timbre and rhythm
+    Similar {
+        /// Reference track; its directory is searched
+        reference: PathBuf,
+
+        /// How many matches to list
+        #[arg(short = 'n', long, default_value_t = 10)]
+        count: usize,
+
+        /// Search every registered library root instead of the reference's directory
+        #[arg(long)]
+        library: bool,
+    },
+
+    /// Print a one-page mastering QC report for a track
+    Report {
+        /// Track to report on; analyzed first if it isn't cached
+        track: PathBuf,
+
+        /// Write the report as an HTML page instead of printing it
+        #[arg(long, value_name = "FILE")]
+        html: Option<PathBuf>,
+
+        /// Include issues against a mastering target preset
+        #[arg(long, value_name = "PRESET")]
+        target: Option<String>,
+
+        /// Reference curve for tonal balance (pink, white, brown, pop, rock, hiphop, edm,
+        /// classical, or a custom curve)
+        #[arg(long, value_name = "CURVE", default_value = "pink")]
+        curve: String,
+    },
+
+    /// Compare each track's long-term spectrum against a reference tonal-balance curve
+    Tonal {
+        /// Directory of tracks to compare
+        directory: Option<PathBuf>,
+
+        /// Reference curve (pink, white, brown, pop, rock, hiphop, edm, classical, or a custom
+        /// curve)
+        #[arg(long, value_name = "CURVE", default_value = "pink")]
+        curve: String,
+    },
+
+    /// Check loudness constraints for CI; prints a JSON report and exits 1 on any failure
+    Assert {
+        /// Directory of tracks to check
+        directory: PathBuf,
+
+        #[command(flatten)]
+        constraints: Constraints,
+
+        /// Start from a preset's limits and rules (streaming, club, vinyl, or a custom preset);
+        /// explicit limits override it
+        #[arg(long, value_name = "PRESET")]
+        target: Option<String>,
+    },
+
+    /// Summarize a folder: track count, total time, loudness range, spectrum, and outliers
+    Summary {
+        /// Directory to summarize
+        directory: Option<PathBuf>,
+
+        /// Summarize every registered library root instead of a directory
+        #[arg(long, conflicts_with = "directory")]
+        library: bool,
+
+        /// Print a compact Markdown card for pasting into forums or release notes
+        #[arg(long)]
+        share: bool,
+    },
+
+    /// Browse a folder's metrics in an interactive table; queued files are printed on exit
+    Browse {
+        /// Directory to browse
+        directory: Option<PathBuf>,
+
+        /// Browse every registered library root instead of a directory
+        #[arg(long, conflicts_with = "directory")]
+        library: bool,
+    },
+
+    /// Write an anonymized summary of metric distributions (no filenames) for sharing
+    ExportAggregate {
+        /// Directory to summarize
+        directory: Option<PathBuf>,
+
+        /// Summarize every registered library root instead of a directory
+        #[arg(long, conflicts_with = "directory")]
+        library: bool,
+
+        /// Write the JSON here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Synthesize a small labeled corpus (bass-heavy, bright, noisy, silent, short) to check the analyzer against
+    GenTestset {
+        /// Directory to write the files into
+        directory: PathBuf,
+    },
+
+    /// List a file's audio streams, with the one `--stream` selects marked
+    Streams {
+        /// File to inspect
+        file: PathBuf,
+    },
+
+    /// Print a time-vs-frequency heatmap of a track in the terminal
+    Spectrogram {
+        /// Track to draw
+        file: PathBuf,
+
+        /// Frequency rows, from 20 Hz at the bottom to Nyquist at the top
+        #[arg(long, default_value_t = 24)]
+        height: usize,
+
+        /// Time columns (the terminal's width when omitted)
+        #[arg(long)]
+        width: Option<usize>,
+    },
+
+    /// Show how a directory's average loudness and brightness changed across snapshots
+    History {
+        /// Directory whose snapshots to show
+        directory: Option<PathBuf>,
+
+        /// Show the library's snapshots, recorded by `library scan --snapshot`
+        #[arg(long, conflicts_with = "directory")]
+        library: bool,
+    },
+
+    /// Print ffmpeg loudnorm parameters from each track's measured loudness, one
+    /// tab-separated `filename<TAB>filter` line per track
+    Loudnorm {
+        /// Directory of tracks
+        directory: Option<PathBuf>,
+
+        #[command(flatten)]
+        targets: LoudnormTargets,
+    },
+
+    /// Attach a note to a track, shown and exported with its metrics
+    Note {
+        /// Track to annotate
+        track: PathBuf,
+
+        /// Note text; an empty string removes the note
+        text: String,
+    },
+
+    /// Rate a track from 1 to 5 stars, shown and exported with its metrics
+    Rate {
+        /// Track to rate
+        track: PathBuf,
+
+        /// Number of stars; 0 removes the rating
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=MAX_RATING as i64))]
+        stars: u8,
+    },
+
+    /// Check that an album or EP's tracks are consistent; exits 1 if any rule fails
+    CheckRelease {
+        /// Directory holding the release
+        directory: PathBuf,
+
+        #[command(flatten)]
+        tolerances: ReleaseTolerances,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum IgnoreAction {
+    /// Skip files whose name matches a glob (e.g. '*.m4r'), or one file given by its path
+    Add { pattern: String },
+    /// Stop skipping a pattern or file
+    Remove { pattern: String },
+    /// List ignored patterns and files
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum LibraryAction {
+    /// Register a directory as a library root
+    Add { path: PathBuf },
+    /// Unregister a library root
+    Remove { path: PathBuf },
+    /// List registered library roots
+    List,
+    /// Analyze every root and refresh the unified library database
+    Scan {
+        /// Record the library's average metrics in its history (see `history --library`)
+        #[arg(long)]
+        snapshot: bool,
+    },
+    /// Show metrics for every file in the library database
+    Show,
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+// Decoders clamp overs to the largest sample value, which for 16 bits is 32767/32768
+const FULL_SCALE: f32 = 0.999;
+// Fewer consecutive full-scale samples happen on clean peaks
+pub const MIN_RUN: usize = 3;
+// Share of samples in clipped runs above which a file is flagged
+pub const CLIPPED_PERCENT_LIMIT: f32 = 0.01;
+
+/// Runs of consecutive full-scale samples, the flat tops a clipped waveform leaves.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Clipping {
+    pub clipped_percent: f32, // Share of all samples that sit in a run
+    pub runs: usize,
+    pub longest_run: usize, // In samples
+}
+
+impl Clipping {
+    pub fn is_distorted(&self) -> bool {
+        self.clipped_percent > CLIPPED_PERCENT_LIMIT
+    }
+}
+
+/// Count clipped runs in each channel separately, since a downmix averages a clipped
+/// channel with a clean one and hides it.
+pub fn detect_clipping(channels: &[Vec<f32>]) -> Clipping {
+    let mut clipping = Clipping::default();
+    let mut clipped_samples = 0;
+    for channel in channels {
+        let mut run = 0;
+        // A trailing sentinel closes a run that reaches the end
+        for &x in channel.iter().chain([0.0].iter()) {
+            if x.abs() >= FULL_SCALE {
+                run += 1;
+                continue;
+            }
+            if run >= MIN_RUN {
+                clipping.runs += 1;
+                clipping.longest_run = clipping.longest_run.max(run);
+                clipped_samples += run;
+            }
+            run = 0;
+        }
+    }
+
+    let total: usize = channels.iter().map(Vec::len).sum();
+    if total > 0 {
+        clipping.clipped_percent = clipped_samples as f32 / total as f32 * 100.0;
+    }
+    clipping
+}
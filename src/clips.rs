@@ -0,0 +1,155 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+
+use crate::structure::{Section, find_drops};
+use crate::utils::{CachedMetrics, DecodedAudio, downmix, ffmpeg_binary, selected_audio_stream};
+
+const PREVIEW_SECONDS: f32 = 30.0;
+// Where previews start, as a share of the track, when no chorus was found
+const PREVIEW_FALLBACK_POSITION: f32 = 0.33;
+// Short fades so clips don't start or stop with a click
+const FADE_SECONDS: f32 = 0.01;
+
+/// Segments `--extract` can cut out of each track.
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum Segment {
+    /// Every detected drop, with a bar of lead-in
+    Drops,
+    /// The most repeated high-energy phrase
+    Chorus,
+    /// 30 seconds from the chorus, for social or store previews
+    Preview,
+}
+
+impl Segment {
+    fn label(self) -> &'static str {
+        match self {
+            Segment::Drops => "drop",
+            Segment::Chorus => "chorus",
+            Segment::Preview => "preview",
+        }
+    }
+}
+
+/// The sections of a decoded track to extract, using cached results where possible.
+pub fn find_segments(
+    segment: Segment,
+    audio: &DecodedAudio,
+    entry: &CachedMetrics,
+) -> Vec<Section> {
+    let duration = audio.channels.first().map_or(0, |c| c.len()) as f32 / audio.sample_rate as f32;
+    match segment {
+        Segment::Drops => find_drops(
+            &downmix(&audio.channels),
+            audio.sample_rate,
+            entry.tempo.as_ref().map(|t| t.bpm),
+        ),
+        Segment::Chorus => entry.chorus.iter().cloned().collect(),
+        Segment::Preview => {
+            let start = entry
+                .chorus
+                .as_ref()
+                .map_or(duration * PREVIEW_FALLBACK_POSITION, |chorus| {
+                    chorus.start_seconds
+                });
+            // Short tracks preview from wherever a full clip still fits
+            let start = start.min(duration - PREVIEW_SECONDS).max(0.0);
+            vec![Section {
+                start_seconds: start,
+                end_seconds: (start + PREVIEW_SECONDS).min(duration),
+            }]
+        }
+    }
+}
+
+/// Write each section as a clip named `<stem>.<segment>[-n].<ext>`, returning the files
+/// written. With the ffmpeg fallback enabled, clips are stream copies in the source's own
+/// format, cut at the nearest packet so MP3 frames come through bit for bit. Otherwise,
+/// or when ffmpeg can't copy a format, they're 16-bit WAV re-encodes with short fades.
+pub fn write_clips(
+    dir: &Path,
+    file_path: &Path,
+    segment: Segment,
+    audio: &DecodedAudio,
+    sections: &[Section],
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    fs::create_dir_all(dir)?;
+    let stem = file_path.file_stem().unwrap_or_default().to_string_lossy();
+    let clip_name = |index: usize, extension: &str| {
+        if sections.len() > 1 {
+            format!("{}.{}-{}.{}", stem, segment.label(), index + 1, extension)
+        } else {
+            format!("{}.{}.{}", stem, segment.label(), extension)
+        }
+    };
+    let spec = WavSpec {
+        channels: audio.channels.len().max(1) as u16,
+        sample_rate: audio.sample_rate as u32,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let fade = (FADE_SECONDS * audio.sample_rate as f32) as usize;
+
+    let mut written = Vec::new();
+    for (index, section) in sections.iter().enumerate() {
+        if let (Some(ffmpeg), Some(extension)) = (ffmpeg_binary(), file_path.extension()) {
+            let path = dir.join(clip_name(index, &extension.to_string_lossy()));
+            if copy_clip(ffmpeg, file_path, section, &path).is_ok() {
+                written.push(path);
+                continue;
+            }
+            let _ = fs::remove_file(&path);
+        }
+        let path = dir.join(clip_name(index, "wav"));
+
+        let length = audio.channels.first().map_or(0, |c| c.len());
+        let start = ((section.start_seconds * audio.sample_rate as f32) as usize).min(length);
+        let end = ((section.end_seconds * audio.sample_rate as f32) as usize).clamp(start, length);
+
+        let mut writer = WavWriter::create(&path, spec)?;
+        for i in start..end {
+            let gain = ((i - start).min(end - 1 - i) as f32 / fade.max(1) as f32).min(1.0);
+            for channel in &audio.channels {
+                let sample = (channel[i] * gain).clamp(-1.0, 1.0);
+                writer.write_sample((sample * i16::MAX as f32) as i16)?;
+            }
+        }
+        writer.finalize()?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Cut `section` out of the selected audio stream without re-encoding.
+fn copy_clip(
+    ffmpeg: &Path,
+    file_path: &Path,
+    section: &Section,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let index = selected_audio_stream().unwrap_or(0);
+    let output = Command::new(ffmpeg)
+        .args(["-v", "error", "-nostdin", "-y", "-ss"])
+        .arg(format!("{:.3}", section.start_seconds))
+        .arg("-i")
+        .arg(file_path)
+        .arg("-t")
+        .arg(format!(
+            "{:.3}",
+            section.end_seconds - section.start_seconds
+        ))
+        .args(["-map", &format!("0:a:{}", index), "-c", "copy"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .to_string()
+            .into());
+    }
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::AnalysisErrorKind;
+use crate::derived::DerivedMetric;
+use crate::frequency_bands::{FrequencyBand, get_bands, validate_bands};
+use crate::warnings::WarningRule;
+
+const APP_DIR: &str = "rust-audio-analysis";
+const CONFIG_FILE_NAME: &str = "config.json";
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Config {
+    #[serde(default)]
+    pub library_roots: Vec<PathBuf>,
+    /// Store per-directory caches here instead of next to the audio files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
+    /// Custom band layout replacing the built-in seven bands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bands: Option<Vec<FrequencyBand>>,
+    /// Thresholds that print a warning when a file crosses them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warning_rules: Vec<WarningRule>,
+    /// Extra columns computed from other metrics, shown and exported after the built-ins
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derived_metrics: Vec<DerivedMetric>,
+    /// Relative change below which re-analyzed metrics keep their cached values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_tolerance: Option<f32>,
+    /// Decimal places for metrics in JSON and CSV output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub export_precision: Option<usize>,
+    /// ffmpeg binary for formats the built-in decoders can't read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ffmpeg: Option<PathBuf>,
+    /// File-name globs and absolute file paths that scans skip, managed with `ignore`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+
+    #[serde(skip)]
+    pub dir: PathBuf,
+    #[serde(skip)]
+    pub profile: Option<String>,
+}
+
+/// Named profiles live in their own subdirectory with a separate config and library.
+pub fn config_dir(profile: Option<&str>) -> PathBuf {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let dir = base.join(APP_DIR);
+    match profile {
+        Some(name) => dir.join("profiles").join(name),
+        None => dir,
+    }
+}
+
+pub fn parse_profile_name(name: &str) -> Result<String, String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err("profile names may only contain letters, digits, '-' and '_'".to_string())
+    }
+}
+
+impl Config {
+    /// The profile's config, or the defaults if it has none. Fails on a band layout the
+    /// analysis can't use.
+    pub fn load(profile: Option<&str>) -> Result<Config, AnalysisErrorKind> {
+        let dir = config_dir(profile);
+        let mut config: Config = if let Ok(file) = File::open(dir.join(CONFIG_FILE_NAME)) {
+            let reader = BufReader::new(file);
+            serde_json::from_reader(reader).unwrap_or_default()
+        } else {
+            Config::default()
+        };
+
+        if let Some(bands) = &config.bands {
+            validate_bands(bands)?;
+        }
+        config.dir = dir;
+        config.profile = profile.map(str::to_string);
+        Ok(config)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        fs::create_dir_all(&self.dir)?;
+        let writer = BufWriter::new(File::create(self.dir.join(CONFIG_FILE_NAME))?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    pub fn band_count(&self) -> usize {
+        match &self.bands {
+            Some(bands) => bands.len(),
+            None => get_bands(44100, None).len(),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use rustfft::num_complex::Complex;
+
+use crate::frequency_bands::{FrequencyBand, plan_fft};
+
+pub const BINS_PER_OCTAVE: usize = 12;
+// Lowest bin is A0; bin k has pitch class (k + 9) % 12 counting from C
+const MIN_HZ: f32 = 27.5;
+pub const CQT_HOP_SIZE: usize = 4096;
+// Kernel entries below this share of a kernel's peak are dropped
+const SPARSITY: f32 = 0.01;
+
+/// Constant-Q analyzer using precomputed sparse spectral kernels (Brown & Puckette), so each
+/// frame costs one FFT plus a short dot product per bin.
+pub struct Cqt {
+    frequencies: Vec<f32>,
+    kernels: Vec<Vec<(usize, Complex<f32>)>>,
+    frame_size: usize,
+}
+
+impl Cqt {
+    pub fn new(sample_rate: usize) -> Self {
+        let q = 1.0 / (2.0f32.powf(1.0 / BINS_PER_OCTAVE as f32) - 1.0);
+        let nyquist = sample_rate as f32 / 2.0;
+
+        // Stop before the top bin's bandwidth would cross Nyquist
+        let frequencies: Vec<f32> = (0..)
+            .map(|k| MIN_HZ * 2.0f32.powf(k as f32 / BINS_PER_OCTAVE as f32))
+            .take_while(|&f| f * (1.0 + 0.5 / q) < nyquist)
+            .collect();
+
+        let longest = (q * sample_rate as f32 / MIN_HZ).ceil() as usize;
+        let frame_size = longest.next_power_of_two();
+
+        let fft = plan_fft(frame_size);
+
+        let kernels = frequencies
+            .iter()
+            .map(|&frequency| {
+                // Hann-windowed complex sinusoid of q periods, centered in the frame
+                let length = (q * sample_rate as f32 / frequency).ceil() as usize;
+                let offset = (frame_size - length) / 2;
+                let mut atom = vec![Complex::new(0.0f32, 0.0); frame_size];
+                for n in 0..length {
+                    let window =
+                        0.5 * (1.0 - (2.0 * std::f32::consts::PI * n as f32 / length as f32).cos());
+                    let phase =
+                        2.0 * std::f32::consts::PI * frequency * n as f32 / sample_rate as f32;
+                    atom[offset + n] = Complex::from_polar(window / length as f32, phase);
+                }
+                fft.process(&mut atom);
+
+                let peak = atom.iter().map(|c| c.norm()).fold(0.0f32, f32::max);
+                atom.iter()
+                    .enumerate()
+                    .filter(|(_, c)| c.norm() > peak * SPARSITY)
+                    .map(|(bin, c)| (bin, c.conj() / frame_size as f32))
+                    .collect()
+            })
+            .collect();
+
+        Cqt {
+            frequencies,
+            kernels,
+            frame_size,
+        }
+    }
+
+    /// Center frequency of each bin in Hz.
+    pub fn frequencies(&self) -> &[f32] {
+        &self.frequencies
+    }
+
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    /// Pass the magnitude of every bin, frame by frame, to `visit`.
+    pub fn for_each_frame(&self, samples: &[f32], mut visit: impl FnMut(&[f32])) {
+        let fft = plan_fft(self.frame_size);
+
+        let mut buffer = vec![Complex::new(0.0f32, 0.0); self.frame_size];
+        let mut magnitude = vec![0.0f32; self.frequencies.len()];
+
+        for i in (0..samples.len().saturating_sub(self.frame_size)).step_by(CQT_HOP_SIZE) {
+            // The kernels carry their own windows, so the frame goes in as-is
+            for (slot, &s) in buffer.iter_mut().zip(&samples[i..i + self.frame_size]) {
+                *slot = Complex::new(s, 0.0);
+            }
+            fft.process(&mut buffer);
+
+            for (m, kernel) in magnitude.iter_mut().zip(&self.kernels) {
+                *m = kernel
+                    .iter()
+                    .map(|&(bin, k)| buffer[bin] * k)
+                    .sum::<Complex<f32>>()
+                    .norm();
+            }
+
+            visit(&magnitude);
+        }
+    }
+}
+
+/// Average energy per band, summing the CQT bins whose center falls inside each band.
+pub fn cqt_band_energies(
+    samples: &[f32],
+    sample_rate: usize,
+    bands: &[FrequencyBand],
+) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    let cqt = Cqt::new(sample_rate);
+    if samples.len() <= cqt.frame_size() {
+        return Err("Track too short for constant-Q analysis".into());
+    }
+
+    let band_of_bin: Vec<Option<usize>> = cqt
+        .frequencies()
+        .iter()
+        .map(|&f| {
+            bands
+                .iter()
+                .position(|band| (band.low_hz as f32..band.high_hz as f32).contains(&f))
+        })
+        .collect();
+
+    let mut band_energies = vec![0.0f64; bands.len()];
+    let mut frame_count = 0;
+
+    cqt.for_each_frame(samples, |magnitude| {
+        for (&m, band) in magnitude.iter().zip(&band_of_bin) {
+            if let Some(band) = band {
+                band_energies[*band] += (m * m) as f64;
+            }
+        }
+        frame_count += 1;
+    });
+
+    for energy in &mut band_energies {
+        *energy /= frame_count as f64;
+    }
+
+    Ok(band_energies)
+}
+
+/// Pitch class (0 = C) of a CQT bin.
+pub fn pitch_class(bin: usize) -> usize {
+    (bin + 9) % 12
+}
@@ -0,0 +1,236 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::frequency_bands::SpectrumMetrics;
+use crate::warnings::{RULE_METRICS, metric_value};
+
+// Set from the config before any output is written
+static DERIVED: OnceLock<Vec<Derived>> = OnceLock::new();
+
+/// A column defined in the config as arithmetic over other metrics, e.g.
+/// `{"name": "warmth", "expression": "band[1] + band[2] - band[6]"}`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DerivedMetric {
+    pub name: String,
+    pub expression: String,
+}
+
+/// A parsed and validated derived metric.
+pub struct Derived {
+    pub name: String,
+    expression: Expr,
+}
+
+enum Expr {
+    Number(f32),
+    Metric(&'static str),
+    Band(usize),
+    Negate(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn evaluate(&self, metrics: &SpectrumMetrics) -> Option<f32> {
+        match self {
+            Expr::Number(value) => Some(*value),
+            Expr::Metric(key) => metric_value(metrics, key),
+            Expr::Band(index) => metrics.band_percentages.get(*index).copied(),
+            Expr::Negate(inner) => Some(-inner.evaluate(metrics)?),
+            Expr::Binary(op, left, right) => {
+                let (a, b) = (left.evaluate(metrics)?, right.evaluate(metrics)?);
+                let value = match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    _ => a / b,
+                };
+                // Division by zero has no meaningful reading
+                value.is_finite().then_some(value)
+            }
+        }
+    }
+}
+
+// Recursive descent over `+ - * /`, unary minus, parentheses, numbers, `band[N]`, and
+// metric keys, which are written with underscores for their dashes (`true_peak`)
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+    band_count: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        format!(
+            "{} at column {}\n  {}\n  {}^",
+            message,
+            self.position + 1,
+            self.text,
+            " ".repeat(self.position)
+        )
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        let rest = &self.text[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+        self.text[self.position..].chars().next()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(expected);
+        if found {
+            self.position += expected.len_utf8();
+        }
+        found
+    }
+
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> &str {
+        let start = self.position;
+        let rest = &self.text[start..];
+        let len = rest.find(|c| !accept(c)).unwrap_or(rest.len());
+        self.position += len;
+        &self.text[start..start + len]
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        let mut left = self.term()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.position += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut left = self.factor()?;
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.position += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.factor()?));
+        }
+        Ok(left)
+    }
+
+    fn factor(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some('-') => {
+                self.position += 1;
+                Ok(Expr::Negate(Box::new(self.factor()?)))
+            }
+            Some('(') => {
+                self.position += 1;
+                let inner = self.expression()?;
+                if !self.eat(')') {
+                    return Err(self.error("expected ')'"));
+                }
+                Ok(inner)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.position;
+                let digits = self
+                    .take_while(|c| c.is_ascii_digit() || c == '.')
+                    .to_string();
+                digits.parse().map(Expr::Number).map_err(|_| {
+                    self.position = start;
+                    self.error(&format!("'{}' is not a number", digits))
+                })
+            }
+            Some(c) if c.is_ascii_alphabetic() => self.name(),
+            Some(c) => Err(self.error(&format!("unexpected '{}'", c))),
+            None => Err(self.error("expression ends early")),
+        }
+    }
+
+    fn name(&mut self) -> Result<Expr, String> {
+        let start = self.position;
+        let name = self
+            .take_while(|c| c.is_ascii_alphanumeric() || c == '_')
+            .to_string();
+        if name == "band" {
+            if !self.eat('[') {
+                return Err(self.error("expected '[' after band"));
+            }
+            let index_start = self.position;
+            let index: usize = self
+                .take_while(|c| c.is_ascii_digit())
+                .parse()
+                .map_err(|_| self.error("expected a band number"))?;
+            if index >= self.band_count {
+                self.position = index_start;
+                return Err(self.error(&format!(
+                    "band[{}] doesn't exist; bands are numbered 0 to {}",
+                    index,
+                    self.band_count.saturating_sub(1)
+                )));
+            }
+            if !self.eat(']') {
+                return Err(self.error("expected ']'"));
+            }
+            return Ok(Expr::Band(index));
+        }
+
+        let key = name.replace('_', "-");
+        match RULE_METRICS.iter().find(|&&metric| metric == key) {
+            Some(metric) => Ok(Expr::Metric(metric)),
+            None => {
+                self.position = start;
+                Err(self.error(&format!(
+                    "unknown metric '{}' (expected band[N] or one of: {})",
+                    name,
+                    RULE_METRICS.join(", ").replace('-', "_")
+                )))
+            }
+        }
+    }
+}
+
+/// Parse every definition, checking names and band numbers against a layout of
+/// `band_count` bands.
+pub fn compile(definitions: &[DerivedMetric], band_count: usize) -> Result<Vec<Derived>, String> {
+    let mut compiled: Vec<Derived> = Vec::new();
+    for definition in definitions {
+        let name = definition.name.trim();
+        let context = |message: String| format!("derived metric '{}': {}", name, message);
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(context(
+                "names may only contain letters, digits, and '_'".to_string(),
+            ));
+        }
+        if compiled.iter().any(|d| d.name == name) {
+            return Err(context("defined twice".to_string()));
+        }
+
+        let mut parser = Parser {
+            text: &definition.expression,
+            position: 0,
+            band_count,
+        };
+        let expression = parser.expression().map_err(context)?;
+        if let Some(c) = parser.peek() {
+            return Err(context(parser.error(&format!("unexpected '{}'", c))));
+        }
+        compiled.push(Derived {
+            name: name.to_string(),
+            expression,
+        });
+    }
+    Ok(compiled)
+}
+
+/// Use these derived metrics for display and export.
+pub fn set_derived(derived: Vec<Derived>) {
+    let _ = DERIVED.set(derived);
+}
+
+pub fn derived() -> &'static [Derived] {
+    DERIVED.get().map_or(&[], Vec::as_slice)
+}
+
+/// Each derived metric's value for a track, in config order. `None` when a metric it
+/// uses wasn't measured or it divides by zero.
+pub fn evaluate(metrics: &SpectrumMetrics) -> Vec<(&'static str, Option<f32>)> {
+    derived()
+        .iter()
+        .map(|d| (d.name.as_str(), d.expression.evaluate(metrics)))
+        .collect()
+}
@@ -0,0 +1,321 @@
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::utils::bytes_per_frame;
+
+/// DSD rate over PCM rate: DSD64 comes out at 44.1 kHz, DSD128 at 88.2 kHz.
+pub const DECIMATION: usize = 64;
+const BYTES_PER_SAMPLE: usize = DECIMATION / 8; // Input bytes consumed per PCM sample
+
+// The low-pass runs over this many bytes of 1-bit input (4096 taps), which keeps the
+// noise-shaped hiss above 22 kHz from folding back into the audible band
+const FILTER_BYTES: usize = 512;
+const CUTOFF: f64 = 0.476; // Of the PCM rate, so 21 kHz for DSD64
+
+// Four ones and four zeros: DSD's idle pattern, which averages to zero
+const SILENCE: u8 = 0x69;
+
+// Limits on header fields, so a corrupt file fails on its own instead of asking for an
+// allocation that aborts the run. SACD carries at most six channels; DSF blocks are 4096
+// bytes in practice.
+const MAX_CHANNELS: usize = 8;
+const MAX_BLOCK_SIZE: usize = 1 << 20;
+// Interleaved DSDIFF audio is read this many frames (one byte per channel) at a time
+const READ_FRAMES: u64 = 1 << 16;
+
+/// 1-bit audio read from a DSF or DSDIFF file, one byte vector per channel with the
+/// earliest bit of each byte in the least significant position.
+pub struct DsdStream {
+    pub dsd_rate: u32,
+    pub channels: Vec<Vec<u8>>,
+    pub truncated: bool,
+}
+
+impl DsdStream {
+    pub fn pcm_rate(&self) -> u32 {
+        self.dsd_rate / DECIMATION as u32
+    }
+}
+
+// Input bytes per channel that fit in `max_bytes` once converted to PCM
+fn byte_limit(max_bytes: Option<u64>, channel_count: usize) -> usize {
+    max_bytes.map_or(usize::MAX, |bytes| {
+        (bytes / bytes_per_frame(channel_count)) as usize * BYTES_PER_SAMPLE
+    })
+}
+
+// Input bytes per channel before PCM frame `start`
+fn start_bytes(start: usize) -> usize {
+    start.saturating_mul(BYTES_PER_SAMPLE)
+}
+
+// Bytes between the reader's position and the end of the file
+fn remaining_bytes(reader: &mut BufReader<File>) -> std::io::Result<u64> {
+    let position = reader.stream_position()?;
+    Ok(reader.get_ref().metadata()?.len().saturating_sub(position))
+}
+
+fn u32_le(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+fn u64_le(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+/// Sony's DSF: little-endian chunks, with each channel's bits in blocks (usually 4096
+/// bytes) that alternate between channels. Reading begins at PCM frame `start`.
+pub fn read_dsf(
+    path: &Path,
+    start: usize,
+    max_bytes: Option<u64>,
+) -> Result<DsdStream, Box<dyn std::error::Error>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut header = [0u8; 28];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != b"DSD " {
+        return Err("not a DSF file".into());
+    }
+    reader.seek(SeekFrom::Start(u64_le(&header[4..])))?;
+
+    let mut fmt = [0u8; 52];
+    reader.read_exact(&mut fmt)?;
+    if &fmt[..4] != b"fmt " {
+        return Err("DSF file has no format chunk".into());
+    }
+    let channel_count = u32_le(&fmt[24..]) as usize;
+    let dsd_rate = u32_le(&fmt[28..]);
+    let bits_per_sample = u32_le(&fmt[32..]);
+    let sample_count = u64_le(&fmt[36..]);
+    let block_size = u32_le(&fmt[44..]) as usize;
+    if !(1..=MAX_CHANNELS).contains(&channel_count)
+        || !(1..=MAX_BLOCK_SIZE).contains(&block_size)
+        || dsd_rate < DECIMATION as u32
+    {
+        return Err("invalid DSF format chunk".into());
+    }
+    let data_start = u64_le(&header[4..])
+        .checked_add(u64_le(&fmt[4..]))
+        .ok_or("DSF chunk size out of range")?;
+    reader.seek(SeekFrom::Start(data_start))?;
+
+    let mut data = [0u8; 12];
+    reader.read_exact(&mut data)?;
+    if &data[..4] != b"data" {
+        return Err("DSF file has no data chunk".into());
+    }
+
+    // The last block is padded, so the sample count says where the audio really ends
+    let valid_bytes = sample_count.div_ceil(8);
+    if valid_bytes > remaining_bytes(&mut reader)? / channel_count as u64 {
+        return Err("DSF sample count out of range".into());
+    }
+    let valid_bytes = valid_bytes as usize;
+    let skip = start_bytes(start).min(valid_bytes);
+    let limit = byte_limit(max_bytes, channel_count);
+    let wanted = (valid_bytes - skip).min(limit);
+
+    // Whole block groups are seeked over; the rest of the skip is dropped after reading
+    let skip_blocks = skip / block_size;
+    let offset = skip % block_size;
+    reader.seek(SeekFrom::Current(
+        (skip_blocks * block_size * channel_count) as i64,
+    ))?;
+    let mut channels = vec![Vec::with_capacity(offset + wanted); channel_count];
+    let mut block = vec![0u8; block_size];
+    'blocks: while channels[0].len() < offset + wanted {
+        for channel in channels.iter_mut() {
+            match reader.read_exact(&mut block) {
+                Ok(()) => channel.extend_from_slice(&block),
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break 'blocks,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    for channel in channels.iter_mut() {
+        channel.drain(..offset.min(channel.len()));
+        channel.truncate(wanted);
+        // 8-bit DSF puts the earliest bit in the most significant position
+        if bits_per_sample == 8 {
+            channel.iter_mut().for_each(|b| *b = b.reverse_bits());
+        }
+    }
+
+    Ok(DsdStream {
+        dsd_rate,
+        channels,
+        truncated: valid_bytes - skip > limit,
+    })
+}
+
+/// Philips' DSDIFF (.dff): big-endian IFF chunks, with channels interleaved byte by byte
+/// and the earliest bit in each byte's most significant position. Reading begins at PCM
+/// frame `start`.
+pub fn read_dff(
+    path: &Path,
+    start: usize,
+    max_bytes: Option<u64>,
+) -> Result<DsdStream, Box<dyn std::error::Error>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut form = [0u8; 16];
+    reader.read_exact(&mut form)?;
+    if &form[..4] != b"FRM8" || &form[12..] != b"DSD " {
+        return Err("not a DSDIFF file".into());
+    }
+
+    let mut dsd_rate = 0;
+    let mut channel_count = 0;
+    loop {
+        let mut chunk = [0u8; 12];
+        reader.read_exact(&mut chunk)?;
+        let size = u64::from_be_bytes(chunk[4..].try_into().unwrap());
+        match &chunk[..4] {
+            // PROP holds the format as nested chunks after its own "SND " tag
+            b"PROP" => {
+                // Read through `take` so a bogus size can't force a huge allocation
+                let mut prop = Vec::new();
+                reader.by_ref().take(size).read_to_end(&mut prop)?;
+                if (prop.len() as u64) < size {
+                    return Err("DSDIFF chunk size out of range".into());
+                }
+                let mut at = 4;
+                while at + 12 <= prop.len() {
+                    let id = &prop[at..at + 4];
+                    let length = u64::from_be_bytes(prop[at + 4..at + 12].try_into().unwrap())
+                        .min(prop.len() as u64) as usize;
+                    let end = (at + 12)
+                        .checked_add(length)
+                        .ok_or("DSDIFF chunk size out of range")?;
+                    let body = &prop[at + 12..end.min(prop.len())];
+                    match id {
+                        b"FS  " if body.len() >= 4 => {
+                            dsd_rate = u32::from_be_bytes(body[..4].try_into().unwrap())
+                        }
+                        b"CHNL" if body.len() >= 2 => {
+                            channel_count = u16::from_be_bytes([body[0], body[1]]) as usize
+                        }
+                        b"CMPR" if body.starts_with(b"DST ") => {
+                            return Err("DST-compressed DSDIFF isn't supported; \
+                                        convert it to DSF or FLAC, or pass --ffmpeg"
+                                .into());
+                        }
+                        _ => {}
+                    }
+                    at = end
+                        .checked_add(length % 2)
+                        .ok_or("DSDIFF chunk size out of range")?;
+                }
+                if size % 2 == 1 {
+                    reader.seek(SeekFrom::Current(1))?;
+                }
+            }
+            b"DSD " => {
+                if !(1..=MAX_CHANNELS).contains(&channel_count) || dsd_rate < DECIMATION as u32 {
+                    return Err("DSDIFF file has no usable format chunk".into());
+                }
+                if size > remaining_bytes(&mut reader)? {
+                    return Err("DSDIFF chunk size out of range".into());
+                }
+                let valid_bytes = size as usize / channel_count;
+                let skip = start_bytes(start).min(valid_bytes);
+                let limit = byte_limit(max_bytes, channel_count);
+                let wanted = (valid_bytes - skip).min(limit);
+                reader.seek(SeekFrom::Current((skip * channel_count) as i64))?;
+
+                let mut channels = vec![Vec::with_capacity(wanted); channel_count];
+                let mut audio = reader.by_ref().take((wanted * channel_count) as u64);
+                let mut interleaved = Vec::new();
+                loop {
+                    interleaved.clear();
+                    audio
+                        .by_ref()
+                        .take(READ_FRAMES * channel_count as u64)
+                        .read_to_end(&mut interleaved)?;
+                    if interleaved.is_empty() {
+                        break;
+                    }
+                    for frame in interleaved.chunks_exact(channel_count) {
+                        for (channel, &byte) in channels.iter_mut().zip(frame) {
+                            channel.push(byte.reverse_bits());
+                        }
+                    }
+                }
+                return Ok(DsdStream {
+                    dsd_rate,
+                    channels,
+                    truncated: valid_bytes - skip > limit,
+                });
+            }
+            _ => {
+                let skip = size
+                    .checked_add(size % 2)
+                    .and_then(|skip| i64::try_from(skip).ok())
+                    .ok_or("DSDIFF chunk size out of range")?;
+                reader.seek(SeekFrom::Current(skip))?;
+            }
+        }
+    }
+}
+
+/// Windowed-sinc low-pass folded into one lookup table per input byte: entry `[k][b]` is
+/// the filter's response to byte `b` at position `k`, with set bits as +1 and clear as -1.
+fn byte_tables() -> &'static [[f32; 256]] {
+    static TABLES: OnceLock<Vec<[f32; 256]>> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let taps = FILTER_BYTES * 8;
+        let cutoff = CUTOFF / DECIMATION as f64; // Cycles per DSD sample
+        let center = (taps - 1) as f64 / 2.0;
+        let mut kernel: Vec<f64> = (0..taps)
+            .map(|n| {
+                let x = n as f64 - center;
+                let sinc = if x == 0.0 {
+                    2.0 * cutoff
+                } else {
+                    (2.0 * PI * cutoff * x).sin() / (PI * x)
+                };
+                let phase = 2.0 * PI * n as f64 / (taps - 1) as f64;
+                sinc * (0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos())
+            })
+            .collect();
+        let gain: f64 = kernel.iter().sum();
+        kernel.iter_mut().for_each(|k| *k /= gain);
+
+        kernel
+            .chunks_exact(8)
+            .map(|bits| {
+                let mut table = [0.0f32; 256];
+                for (byte, entry) in table.iter_mut().enumerate() {
+                    *entry = bits
+                        .iter()
+                        .enumerate()
+                        .map(|(bit, k)| if byte >> bit & 1 == 1 { *k } else { -*k })
+                        .sum::<f64>() as f32;
+                }
+                table
+            })
+            .collect()
+    })
+}
+
+/// Low-pass and decimate one channel of 1-bit audio to PCM at `DECIMATION` times lower
+/// rate. Full modulation maps to ±1.0, so SACD's 50% reference level reads -6 dBFS, as
+/// with ffmpeg's decoder.
+pub fn to_pcm(bits: &[u8]) -> Vec<f32> {
+    let tables = byte_tables();
+    // Padded with idle bytes so the filter stays centered on each output sample
+    let pad = [SILENCE; FILTER_BYTES / 2];
+    let padded: Vec<u8> = pad.iter().chain(bits).chain(&pad).copied().collect();
+    (0..bits.len() / BYTES_PER_SAMPLE)
+        .map(|i| {
+            let start = i * BYTES_PER_SAMPLE;
+            padded[start..start + FILTER_BYTES]
+                .iter()
+                .zip(tables)
+                .map(|(&byte, table)| table[byte as usize])
+                .sum()
+        })
+        .collect()
+}
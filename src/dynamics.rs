@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frequency_bands::sum;
+
+// Ranges the display bars span; loudness-war masters sit at the bottom of the crest bar
+pub const RMS_FLOOR_DB: f32 = -60.0;
+pub const CREST_CEILING_DB: f32 = 20.0;
+
+/// Overall level and how far the peaks stand above it. Limiting pushes the level up and
+/// the crest factor down.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Dynamics {
+    pub rms_db: f32,          // dBFS; a full-scale sine reads -3
+    pub crest_factor_db: f32, // Sample peak over RMS; a sine reads 3
+}
+
+impl Dynamics {
+    /// Where the RMS level sits on its bar (0-100).
+    pub fn rms_position(&self) -> f32 {
+        ((self.rms_db - RMS_FLOOR_DB) / -RMS_FLOOR_DB * 100.0).clamp(0.0, 100.0)
+    }
+
+    /// Where the crest factor sits on its bar (0-100).
+    pub fn crest_position(&self) -> f32 {
+        (self.crest_factor_db / CREST_CEILING_DB * 100.0).clamp(0.0, 100.0)
+    }
+}
+
+/// RMS and crest factor over every sample of every channel, before the mono fold-down
+/// can cancel or average away peaks. `None` for digital silence.
+pub fn measure_dynamics(channels: &[Vec<f32>]) -> Option<Dynamics> {
+    let count: usize = channels.iter().map(Vec::len).sum();
+    let peak = channels
+        .iter()
+        .flatten()
+        .fold(0.0f32, |peak, &x| peak.max(x.abs()));
+    if count == 0 || peak <= 0.0 {
+        return None;
+    }
+
+    let sum_squares = sum(channels.iter().flatten().map(|&x| x * x));
+    let rms = (sum_squares / count as f32).sqrt();
+    Some(Dynamics {
+        rms_db: 20.0 * rms.log10(),
+        crest_factor_db: 20.0 * (peak / rms).log10(),
+    })
+}
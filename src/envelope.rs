@@ -0,0 +1,38 @@
+use serde::Serialize;
+
+pub const DEFAULT_POINTS: usize = 1000;
+
+/// A waveform overview: the min, max, and RMS of each of `points` equal slices of a track,
+/// the shape web waveform renderers such as wavesurfer.js draw from.
+#[derive(Serialize, Clone, Default)]
+pub struct Envelope {
+    pub duration_seconds: f32,
+    pub min: Vec<f32>,
+    pub max: Vec<f32>,
+    pub rms: Vec<f32>,
+}
+
+/// Downsample `samples` to at most `points` slices; shorter tracks get one point per sample.
+pub fn envelope(samples: &[f32], sample_rate: usize, points: usize) -> Envelope {
+    let points = points.clamp(1, samples.len().max(1));
+    let mut result = Envelope {
+        duration_seconds: samples.len() as f32 / sample_rate.max(1) as f32,
+        ..Envelope::default()
+    };
+    if samples.is_empty() {
+        return result;
+    }
+
+    for i in 0..points {
+        // Slice bounds are spread evenly so every sample lands in exactly one point
+        let slice = &samples[i * samples.len() / points..(i + 1) * samples.len() / points];
+        let (low, high, energy) = slice.iter().fold(
+            (f32::INFINITY, f32::NEG_INFINITY, 0.0),
+            |(low, high, energy), &x| (low.min(x), high.max(x), energy + x * x),
+        );
+        result.min.push(low);
+        result.max.push(high);
+        result.rms.push((energy / slice.len() as f32).sqrt());
+    }
+    result
+}
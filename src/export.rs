@@ -0,0 +1,108 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::beats::BarEnergies;
+use crate::envelope::Envelope;
+
+/// Quote a CSV field when it contains separators, quotes, or newlines.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One row per bar: filename, bar number, start/end time, then band energies in dB.
+pub fn write_bar_grid_csv(
+    path: &Path,
+    tracks: &[(String, f32, Vec<BarEnergies>)],
+    band_count: usize,
+) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    let band_columns: Vec<String> = (0..band_count).map(|i| format!("band_{}_db", i)).collect();
+    writeln!(
+        writer,
+        "filename,bpm,bar,start_seconds,end_seconds,{}",
+        band_columns.join(",")
+    )?;
+
+    for (filename, bpm, bars) in tracks {
+        for (index, bar) in bars.iter().enumerate() {
+            let bands: Vec<String> = bar.band_db.iter().map(|db| format!("{:.2}", db)).collect();
+            writeln!(
+                writer,
+                "{},{:.2},{},{:.3},{:.3},{}",
+                csv_field(filename),
+                bpm,
+                index + 1,
+                bar.start_seconds,
+                bar.end_seconds,
+                bands.join(",")
+            )?;
+        }
+    }
+
+    writer.flush()
+}
+
+/// One JSON object keyed by filename, each holding a track's `min`, `max`, and `rms` arrays.
+pub fn write_envelopes_json(path: &Path, tracks: &[(String, Envelope)]) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let object: Map<String, Value> = tracks
+        .iter()
+        .map(|(filename, envelope)| {
+            let value = serde_json::to_value(envelope).unwrap_or_default();
+            (filename.clone(), value)
+        })
+        .collect();
+    serde_json::to_writer(&mut writer, &object)?;
+    writer.flush()
+}
+
+// `target` as seen from `base`, both absolute, with / separators so any player can follow it
+fn relative_to(base: &Path, target: &Path) -> String {
+    let base: Vec<_> = base.components().collect();
+    let target: Vec<_> = target.components().collect();
+    let common = base.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    // Nothing shared, e.g. another drive on Windows: only the full path will do
+    if common == 0 {
+        return target
+            .iter()
+            .collect::<PathBuf>()
+            .to_string_lossy()
+            .to_string();
+    }
+    std::iter::repeat_n("..".to_string(), base.len() - common)
+        .chain(
+            target[common..]
+                .iter()
+                .map(|c| c.as_os_str().to_string_lossy().to_string()),
+        )
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Extended M3U of `(file, seconds)` in the given order, with paths relative to the playlist.
+pub fn write_m3u(path: &Path, tracks: &[(PathBuf, f32)]) -> std::io::Result<()> {
+    let parent = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let base = fs::canonicalize(parent)?;
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writeln!(writer, "#EXTM3U")?;
+    for (file, seconds) in tracks {
+        let file = fs::canonicalize(file).unwrap_or_else(|_| file.clone());
+        let title = file.file_stem().unwrap_or_default().to_string_lossy();
+        writeln!(writer, "#EXTINF:{},{}", seconds.round() as u32, title)?;
+        writeln!(writer, "{}", relative_to(&base, &file))?;
+    }
+
+    writer.flush()
+}
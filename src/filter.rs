@@ -0,0 +1,130 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Which files of a directory to analyze, from `--include` and `--exclude` globs on the
+/// file name and an `--ext` list. Empty lists don't restrict anything.
+#[derive(Clone, Default)]
+pub struct FileFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub extensions: Vec<String>, // Lowercase, without the dot
+}
+
+impl FileFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty() && self.extensions.is_empty()
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+
+        (self.extensions.is_empty() || self.extensions.contains(&extension))
+            && (self.include.is_empty() || self.include.iter().any(|p| glob_match(p, &name)))
+            && !self.exclude.iter().any(|p| glob_match(p, &name))
+    }
+}
+
+/// How `ignore add` stores its argument: an existing file by its absolute path, anything
+/// else as a glob on file names.
+pub fn ignore_pattern(target: &str) -> String {
+    let path = Path::new(target);
+    match fs::canonicalize(path) {
+        Ok(absolute) if path.is_file() => absolute.to_string_lossy().to_string(),
+        _ => target.to_string(),
+    }
+}
+
+/// Whether the ignore list covers `path`. Absolute entries match the whole path, with
+/// globs allowed, and the rest match the file name.
+pub fn is_ignored(patterns: &[String], path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    // Only resolved when an absolute entry needs it
+    let absolute = OnceLock::new();
+    patterns.iter().any(|pattern| {
+        if Path::new(pattern).is_absolute() {
+            let absolute = absolute.get_or_init(|| {
+                fs::canonicalize(path)
+                    .unwrap_or_else(|_| path.to_path_buf())
+                    .to_string_lossy()
+                    .to_string()
+            });
+            glob_match(pattern, absolute)
+        } else {
+            glob_match(pattern, &name)
+        }
+    })
+}
+
+// Whether `c` is in a `[...]` class body such as `abc`, `a-z`, or `!0-9`
+fn class_contains(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() {
+        Some('!' | '^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            found |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
+
+/// Shell-style matching of a whole file name: `*` for any run of characters, `?` for one,
+/// and `[...]` for one from a set. Case is ignored, as music folders mix `.mp3` and `.MP3`.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Where to resume after the last `*` when a later part fails to match
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => pattern[p + 1..]
+                .iter()
+                .skip(1) // A `]` right after `[` is part of the set
+                .position(|&c| c == ']')
+                .map(|end| p + 2 + end)
+                .filter(|&end| class_contains(&pattern[p + 1..end], name[n]))
+                .map(|end| end + 1 - p),
+            Some(&c) if c == name[n] => Some(1),
+            _ => None,
+        };
+        match (step, star) {
+            (Some(step), _) => {
+                p += step;
+                n += 1;
+            }
+            (None, Some((star_p, star_n))) => {
+                p = star_p + 1;
+                n = star_n + 1;
+                star = Some((star_p, star_n + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,131 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use rustfft::{Fft, num_complex::Complex};
+
+use crate::analysis::Analyzer;
+use crate::frequency_bands::{
+    calculate_loudness, calculate_zero_crossing_rate, get_bands, hann_window, hop_size, plan_fft,
+    sum,
+};
+use crate::units::{Db, Hz, Percent};
+use crate::utils::{SampleStream, open_sample_stream};
+
+/// What one STFT frame of the mono downmix holds. Frames overlap, starting a hop (a
+/// quarter frame) apart.
+pub struct FrameFeatures {
+    pub index: usize,
+    pub time_seconds: f32,       // Start of the frame
+    pub spectrum: Vec<f32>,      // Magnitudes of the `frame_size / 2` bins below Nyquist
+    pub band_energies: Vec<f64>, // Power per analyzer band, in band order
+    pub loudness: Db,            // RMS level of the frame's samples
+    pub centroid_hz: Option<Hz>, // Magnitude-weighted mean frequency; `None` for silence
+    pub zero_crossing_rate: Percent,
+}
+
+/// Per-frame features of a file, decoded lazily so only about one frame of audio is held
+/// at a time. Ends after the first decode error.
+pub struct Frames {
+    samples: SampleStream,
+    sample_rate: usize,
+    frame_size: usize,
+    hop_size: usize,
+    band_bins: Vec<(usize, usize)>,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    pending: Vec<f32>, // Decoded audio not yet past the current frame's start
+    index: usize,
+    failed: bool,
+}
+
+impl Analyzer {
+    /// Walk a file frame by frame with this analyzer's frame size and bands, for
+    /// aggregations the crate doesn't compute itself.
+    pub fn frames(&self, path: &Path) -> Result<Frames, Box<dyn std::error::Error>> {
+        let samples = open_sample_stream(path)?;
+        let sample_rate = samples.sample_rate;
+        let frame_size = self.frame_size();
+        let band_bins = get_bands(sample_rate, self.bands())
+            .iter()
+            .map(|band| {
+                let low_bin = band.low_hz * frame_size / sample_rate;
+                let high_bin = (band.high_hz * frame_size / sample_rate).min(frame_size / 2);
+                (low_bin, high_bin.max(low_bin))
+            })
+            .collect();
+        Ok(Frames {
+            samples,
+            sample_rate,
+            frame_size,
+            hop_size: hop_size(frame_size),
+            band_bins,
+            fft: plan_fft(frame_size),
+            window: hann_window(frame_size),
+            pending: Vec::with_capacity(frame_size * 2),
+            index: 0,
+            failed: false,
+        })
+    }
+}
+
+impl Frames {
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    fn features(&self, frame: &[f32]) -> FrameFeatures {
+        let mut buffer: Vec<Complex<f32>> = frame
+            .iter()
+            .zip(&self.window)
+            .map(|(&s, &w)| Complex::new(s * w, 0.0))
+            .collect();
+        self.fft.process(&mut buffer);
+        let spectrum: Vec<f32> = buffer[..self.frame_size / 2]
+            .iter()
+            .map(|c| (c.re * c.re + c.im * c.im).sqrt())
+            .collect();
+
+        let band_energies = self
+            .band_bins
+            .iter()
+            .map(|&(low, high)| sum(spectrum[low..high].iter().map(|&m| m * m)) as f64)
+            .collect();
+        let bin_hz = self.sample_rate as f32 / self.frame_size as f32;
+        let total = sum(spectrum.iter().copied());
+        let weighted = sum(spectrum.iter().enumerate().map(|(bin, &m)| bin as f32 * m));
+
+        FrameFeatures {
+            index: self.index,
+            time_seconds: (self.index * self.hop_size) as f32 / self.sample_rate as f32,
+            band_energies,
+            loudness: Db(calculate_loudness(frame)),
+            centroid_hz: (total > 0.0).then(|| Hz(weighted / total * bin_hz)),
+            zero_crossing_rate: Percent(calculate_zero_crossing_rate(frame)),
+            spectrum,
+        }
+    }
+}
+
+impl Iterator for Frames {
+    type Item = Result<FrameFeatures, Box<dyn std::error::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        while self.pending.len() < self.frame_size {
+            match self.samples.next()? {
+                Ok(block) => self.pending.extend_from_slice(&block),
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        let features = self.features(&self.pending[..self.frame_size]);
+        self.pending.drain(..self.hop_size);
+        self.index += 1;
+        Some(Ok(features))
+    }
+}
@@ -1,23 +1,189 @@
-use rustfft::{FftPlanner, num_complex::Complex};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rustfft::{Fft, FftPlanner, FftPlannerScalar, num_complex::Complex};
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::AnalysisErrorKind;
+use crate::beats::Groove;
+use crate::clipping::Clipping;
+use crate::dynamics::Dynamics;
+use crate::key::KeyEstimate;
+use crate::ratios::EnergyRatios;
+use crate::silence::Silence;
+use crate::stereo::Stereo;
+use crate::structure::{EdgeProfile, Section};
+use crate::surround::{ChannelEnergy, LfeUsage};
+use crate::tempo::TempoEstimate;
+use crate::transcode::Bandwidth;
+use crate::units::{Db, Hz, Lufs, Percent};
+use crate::utils::StreamInfo;
+
+pub const FRAME_SIZE: usize = 2048;
+pub const HOP_SIZE: usize = 512;
+
+/// Hop for a given STFT frame, keeping the default 75% overlap.
+pub fn hop_size(frame_size: usize) -> usize {
+    frame_size * HOP_SIZE / FRAME_SIZE
+}
+
+// Bands entirely below this get measured again with a longer window, since 2048-sample
+// bins are ~21 Hz wide and can't separate sub-bass from bass
+pub const LOW_ZOOM_MAX_HZ: usize = 250;
+pub const ZOOM_FRAME_SIZE: usize = 8192;
+const ZOOM_HOP_SIZE: usize = 2048;
+
+// Spectral tilt is fitted where masters carry meaningful energy, over third-octave averages
+// so the many bins at high frequencies don't dominate the fit
+const TILT_MIN_HZ: f32 = 50.0;
+const TILT_MAX_HZ: f32 = 16000.0;
+const TILT_BINS_PER_OCTAVE: f32 = 3.0;
+
+const ROLLOFF_SHARE: f64 = 0.85;
+
+// Set from the command line before any analysis runs
+static REPRODUCIBLE: AtomicBool = AtomicBool::new(false);
+static COLOR: AtomicBool = AtomicBool::new(false);
+static SCREEN_READER: AtomicBool = AtomicBool::new(false);
+
+// ANSI foreground colors from the low end of the spectrum to the top: blue through red
+const SPECTRUM_COLORS: [u8; 6] = [34, 36, 32, 33, 35, 31];
+
+/// Spectral transform used for band energies and chroma.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Transform {
+    /// Short-time Fourier transform with linear bins
+    #[default]
+    Stft,
+    /// Constant-Q transform with 12 log-spaced bins per octave
+    Cqt,
+}
 
-const FRAME_SIZE: usize = 2048;
-const HOP_SIZE: usize = 512;
+impl Transform {
+    pub fn is_stft(&self) -> bool {
+        *self == Transform::Stft
+    }
+}
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct FrequencyBand {
-    pub(crate) low_hz: usize,
-    pub(crate) high_hz: usize,
+    pub low_hz: usize,
+    pub high_hz: usize,
 }
 
+// Far past any sample rate's Nyquist; higher edges only overflow the bin arithmetic
+const MAX_BAND_HZ: usize = 1_000_000;
+
+/// Check a custom band layout: at least one band, each with its low edge below its high
+/// edge and both within `MAX_BAND_HZ`. Edges past a file's Nyquist are fine, since
+/// `get_bands` clamps them.
+pub fn validate_bands(bands: &[FrequencyBand]) -> Result<(), AnalysisErrorKind> {
+    if bands.is_empty() {
+        return Err(AnalysisErrorKind::InvalidSettings(
+            "band layout has no bands".to_string(),
+        ));
+    }
+    for band in bands {
+        if band.low_hz >= band.high_hz || band.high_hz > MAX_BAND_HZ {
+            return Err(AnalysisErrorKind::InvalidSettings(format!(
+                "band {}-{} Hz must have its low edge below its high edge, up to {} Hz",
+                band.low_hz, band.high_hz, MAX_BAND_HZ
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Everything measured for one file. Serializes under the same keys as the cache, with
+/// missing keys defaulted so output from other versions still reads.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct SpectrumMetrics {
-    pub(crate) centroid: f32, // Where on the spectrum (0-100, low to high)
-    pub(crate) spread: f32,   // How distributed (0-100, focused to broad)
-    pub(crate) zero_crossing_rate: f32, // Sharpness/noisiness (0-100)
-    pub(crate) loudness: f32, // Overall loudness in dB (typically -60 to 0)
-    pub(crate) duration_seconds: f32, // Track length in seconds
-    pub(crate) band_percentages: Vec<f32>,
+    pub centroid: Percent,       // Where on the spectrum (0-100, low to high)
+    pub centroid_hz: Option<Hz>, // Exact centroid from the full FFT spectrum
+    pub exact_centroid: Option<Percent>, // `centroid_hz` on the 0-100 band-centroid scale
+    pub spread: Percent,         // How distributed (0-100, focused to broad)
+    pub zero_crossing_rate: f32, // Sharpness/noisiness (0-100)
+    pub loudness: Db,            // Overall loudness in dB (typically -60 to 0)
+    pub integrated_lufs: Option<Lufs>, // EBU R128 integrated loudness
+    pub loudness_range: Option<Db>, // EBU R128 loudness range in LU
+    pub true_peak_db: Option<Db>, // 4x oversampled peak in dBTP
+    pub dr_score: Option<Db>,    // DR-meter dynamic range in dB
+    pub dynamics: Option<Dynamics>, // RMS level and crest factor
+    pub clipping: Option<Clipping>, // Runs of full-scale samples
+    pub silence: Option<Silence>, // Silent lead-in, tail, and gaps
+    pub duration_seconds: f32,   // Track length in seconds
+    pub band_percentages: Vec<f32>,
+    pub ratios: Option<EnergyRatios>, // Bands folded into low, mid, and high thirds
+    pub tempo: Option<TempoEstimate>, // None for tracks without a detectable pulse
+    pub key: Option<KeyEstimate>,     // None for unpitched material
+    pub groove: Option<Groove>,
+    pub rhythm_pattern: Vec<f32>, // Onset share per 16th-note step of a bar
+    pub chorus: Option<Section>,  // Most repeated high-energy phrase
+    pub intro: Option<EdgeProfile>, // First 30 seconds
+    pub outro: Option<EdgeProfile>, // Last 30 seconds
+    pub tilt: Option<f32>,        // Long-term spectral slope in dB/octave
+    pub rolloff: Option<Hz>,      // Below this 85% of the energy lies
+    pub bandwidth: Option<Bandwidth>, // Encoder lowpass shelf, if any
+    pub onset_strength: Option<Percent>, // Share of spectral flux arriving as onsets
+    pub sharpness: Option<f32>,   // Zwicker sharpness in acum
+    pub roughness: Option<f32>,   // Roughness in asper
+    pub stereo: Option<Stereo>,   // None for mono files
+    pub surround: Option<Vec<ChannelEnergy>>, // Per-channel energy for more than two channels
+    pub lfe: Option<LfeUsage>,    // Surround layouts with an LFE channel
+    pub stream: Option<StreamInfo>,
+    pub windowed: bool, // Read in windows under --max-memory; tempo, key, etc. from the first
+}
+
+/// Trade speed for results that are bit-identical on every machine: FFTs skip the SIMD
+/// code paths rustfft picks per CPU, and long sums are compensated so their rounding
+/// doesn't depend on how the compiler vectorized them. Analysis of each file already runs
+/// on a single thread, so the thread count never changes results.
+pub fn set_reproducible(enabled: bool) {
+    REPRODUCIBLE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_reproducible() -> bool {
+    REPRODUCIBLE.load(Ordering::Relaxed)
+}
+
+/// A forward FFT of `len` points, using only scalar code in reproducible mode.
+pub fn plan_fft(len: usize) -> Arc<dyn Fft<f32>> {
+    if is_reproducible() {
+        FftPlannerScalar::new().plan_fft_forward(len)
+    } else {
+        FftPlanner::new().plan_fft_forward(len)
+    }
 }
 
-pub fn get_bands(sample_rate: usize) -> Vec<FrequencyBand> {
+/// Sum in input order, with Kahan compensation in reproducible mode.
+pub fn sum(values: impl Iterator<Item = f32>) -> f32 {
+    if !is_reproducible() {
+        return values.sum();
+    }
+    let (mut total, mut compensation) = (0.0f32, 0.0f32);
+    for value in values {
+        let adjusted = value - compensation;
+        let next = total + adjusted;
+        compensation = (next - total) - adjusted;
+        total = next;
+    }
+    total
+}
+
+pub fn get_bands(sample_rate: usize, custom: Option<&[FrequencyBand]>) -> Vec<FrequencyBand> {
+    // Profile-defined layouts are clamped so no band reaches past Nyquist
+    if let Some(custom) = custom {
+        return custom
+            .iter()
+            .map(|band| FrequencyBand {
+                low_hz: band.low_hz.min(sample_rate / 2),
+                high_hz: band.high_hz.min(sample_rate / 2),
+            })
+            .collect();
+    }
+
     vec![
         FrequencyBand {
             low_hz: 20,
@@ -50,6 +216,33 @@ pub fn get_bands(sample_rate: usize) -> Vec<FrequencyBand> {
     ]
 }
 
+/// Names for the default seven-band layout, low to high, with what each band carries.
+pub const BAND_NAMES: [(&str, &str); 7] = [
+    ("Sub", "felt more than heard: kick and bass fundamentals"),
+    ("Bass", "body and punch of the low end"),
+    ("Low-Mid", "warmth, or mud when overdone"),
+    ("Mid", "vocals and most instruments"),
+    ("Hi-Mid", "attack and bite"),
+    ("Presence", "clarity, consonants, and sibilance"),
+    ("Air", "cymbal shimmer and sparkle"),
+];
+
+/// A short label per band: the names above for the default layout, otherwise numbered.
+pub fn band_labels(bands: &[FrequencyBand]) -> Vec<String> {
+    let default_edges = get_bands(usize::MAX, None)
+        .iter()
+        .map(|band| band.low_hz)
+        .eq(bands.iter().map(|band| band.low_hz));
+    bands
+        .iter()
+        .enumerate()
+        .map(|(i, _)| match BAND_NAMES.get(i) {
+            Some((name, _)) if default_edges => name.to_string(),
+            _ => format!("Band {}", i + 1),
+        })
+        .collect()
+}
+
 pub fn calculate_band_positions(bands: &[FrequencyBand], sample_rate: usize) -> Vec<f32> {
     let nyquist = sample_rate as f32 / 2.0;
 
@@ -61,32 +254,54 @@ pub fn calculate_band_positions(bands: &[FrequencyBand], sample_rate: usize) ->
             let high = band.high_hz.min(sample_rate / 2) as f32;
             let center = (low * high).sqrt();
 
-            // Map to 0-100 scale using logarithmic scaling
-            // Human hearing is logarithmic (octaves, not linear Hz)
-            let log_min = 20.0_f32.ln(); // 20 Hz
-            let log_max = nyquist.ln();
-            let log_center = center.ln();
-
-            // Normalize to 0-100
-            ((log_center - log_min) / (log_max - log_min) * 100.0).clamp(0.0, 100.0)
+            hz_to_position(center, nyquist)
         })
         .collect()
 }
 
+pub fn hz_to_position(hz: f32, nyquist: f32) -> f32 {
+    // Map to 0-100 scale using logarithmic scaling
+    // Human hearing is logarithmic (octaves, not linear Hz)
+    let log_min = 20.0_f32.ln(); // 20 Hz
+    let log_max = nyquist.ln();
+    let log_center = hz.max(1.0).ln();
+
+    // Normalize to 0-100
+    ((log_center - log_min) / (log_max - log_min) * 100.0).clamp(0.0, 100.0)
+}
+
+/// Mean STFT energy in each band. Fails on empty input or a zero sample rate rather
+/// than returning NaN.
 pub fn calculate_band_energies(
     samples: &[f32],
     sample_rate: usize,
     bands: &[FrequencyBand],
-) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
-    let mut planner = FftPlanner::new();
-    let fft = planner.plan_fft_forward(FRAME_SIZE);
+) -> Result<Vec<f64>, AnalysisErrorKind> {
+    band_energies_with_frame(samples, sample_rate, bands, FRAME_SIZE)
+}
+
+/// `calculate_band_energies` with a different STFT frame: longer frames resolve the low
+/// bands better, shorter ones follow transients more closely.
+pub fn band_energies_with_frame(
+    samples: &[f32],
+    sample_rate: usize,
+    bands: &[FrequencyBand],
+    frame_size: usize,
+) -> Result<Vec<f64>, AnalysisErrorKind> {
+    if samples.is_empty() {
+        return Err(AnalysisErrorKind::EmptyAudio);
+    }
+    if sample_rate == 0 {
+        return Err(AnalysisErrorKind::UnsupportedSampleRate(sample_rate));
+    }
 
     // Convert Hz ranges to bin indices
     let band_bins: Vec<(usize, usize)> = bands
         .iter()
         .map(|band| {
-            let low_bin = (band.low_hz * FRAME_SIZE / sample_rate).max(0);
-            let high_bin = (band.high_hz * FRAME_SIZE / sample_rate).min(FRAME_SIZE / 2);
+            // Clamped to the spectrum, so edges past Nyquist give an empty band
+            let high_bin = (band.high_hz * frame_size / sample_rate).min(frame_size / 2);
+            let low_bin = (band.low_hz * frame_size / sample_rate).min(high_bin);
             (low_bin, high_bin)
         })
         .collect();
@@ -94,54 +309,229 @@ pub fn calculate_band_energies(
     let mut band_energies = vec![0.0f64; bands.len()];
     let mut frame_count = 0;
 
-    // Process audio in overlapping frames
-    for i in (0..samples.len().saturating_sub(FRAME_SIZE)).step_by(HOP_SIZE) {
-        let frame = &samples[i..i + FRAME_SIZE];
-
-        // Apply Hann window
-        let mut windowed: Vec<Complex<f32>> = frame
-            .iter()
-            .enumerate()
-            .map(|(j, &s)| {
-                let window = 0.5
-                    * (1.0
-                        - ((2.0 * std::f32::consts::PI * j as f32) / (FRAME_SIZE as f32 - 1.0))
-                            .cos());
-                Complex::new(s * window, 0.0)
-            })
-            .collect();
-
-        // Perform FFT
-        fft.process(&mut windowed);
-
-        // Calculate magnitude spectrum
-        let magnitude: Vec<f32> = windowed[..FRAME_SIZE / 2]
-            .iter()
-            .map(|c| (c.re * c.re + c.im * c.im).sqrt())
-            .collect();
-
+    for_each_spectrum(samples, frame_size, hop_size(frame_size), |magnitude| {
         // Accumulate energy per band
         for (band_idx, &(low_bin, high_bin)) in band_bins.iter().enumerate() {
-            let band_energy: f32 = magnitude[low_bin..high_bin].iter().map(|&m| m * m).sum();
+            let band_energy = sum(magnitude[low_bin..high_bin].iter().map(|&m| m * m));
             band_energies[band_idx] += band_energy as f64;
         }
 
         frame_count += 1;
-    }
+    });
 
     // Average over all frames
     for energy in &mut band_energies {
         *energy /= frame_count as f64;
     }
 
+    // Frames as long as the zoom window already resolve the low bands
+    if frame_size < ZOOM_FRAME_SIZE {
+        zoom_low_bands(samples, sample_rate, bands, frame_size, &mut band_energies);
+    }
+
     Ok(band_energies)
 }
 
+/// Replace the energies of low bands with readings from a longer FFT window.
+fn zoom_low_bands(
+    samples: &[f32],
+    sample_rate: usize,
+    bands: &[FrequencyBand],
+    frame_size: usize,
+    band_energies: &mut [f64],
+) {
+    let zoomed: Vec<(usize, usize, usize)> = bands
+        .iter()
+        .enumerate()
+        .filter(|(_, band)| band.high_hz <= LOW_ZOOM_MAX_HZ)
+        .map(|(index, band)| {
+            let high_bin = (band.high_hz * ZOOM_FRAME_SIZE / sample_rate).min(ZOOM_FRAME_SIZE / 2);
+            let low_bin = (band.low_hz * ZOOM_FRAME_SIZE / sample_rate).min(high_bin);
+            (index, low_bin, high_bin)
+        })
+        .collect();
+    if zoomed.is_empty() {
+        return;
+    }
+
+    let mut energies = vec![0.0f64; zoomed.len()];
+    let mut frame_count = 0;
+    for_each_spectrum(samples, ZOOM_FRAME_SIZE, ZOOM_HOP_SIZE, |magnitude| {
+        for (energy, &(_, low_bin, high_bin)) in energies.iter_mut().zip(&zoomed) {
+            *energy += magnitude[low_bin..high_bin]
+                .iter()
+                .map(|&m| (m * m) as f64)
+                .sum::<f64>();
+        }
+        frame_count += 1;
+    });
+
+    // Too short for the long window; keep the standard reading
+    if frame_count == 0 {
+        return;
+    }
+
+    // Band energy grows with the square of the window length, so rescale to match the
+    // shorter-frame readings of the other bands
+    let scale = (ZOOM_FRAME_SIZE / frame_size).pow(2) as f64;
+    for (energy, &(index, _, _)) in energies.iter().zip(&zoomed) {
+        band_energies[index] = energy / frame_count as f64 / scale;
+    }
+}
+
+/// Power per FFT bin summed over the whole track.
+pub fn long_term_power(samples: &[f32], frame_size: usize, hop_size: usize) -> Vec<f64> {
+    let mut power = vec![0.0f64; frame_size / 2];
+    for_each_spectrum(samples, frame_size, hop_size, |magnitude| {
+        for (total, &m) in power.iter_mut().zip(magnitude) {
+            *total += (m as f64) * (m as f64);
+        }
+    });
+    power
+}
+
+/// Magnitude-weighted mean frequency of the full FFT spectrum in Hz, averaged over the
+/// track, and its position on the same 0-100 log axis as the band centroid. `None` for
+/// silence.
+pub fn spectral_centroid(
+    samples: &[f32],
+    sample_rate: usize,
+    frame_size: usize,
+) -> Option<(f32, f32)> {
+    let bin_hz = sample_rate as f64 / frame_size as f64;
+    let (mut weighted, mut total) = (0.0f64, 0.0f64);
+    for_each_spectrum(samples, frame_size, hop_size(frame_size), |magnitude| {
+        for (bin, &m) in magnitude.iter().enumerate() {
+            weighted += bin as f64 * bin_hz * m as f64;
+            total += m as f64;
+        }
+    });
+    if total <= 0.0 {
+        return None;
+    }
+
+    let hz = (weighted / total) as f32;
+    Some((hz, hz_to_position(hz, sample_rate as f32 / 2.0)))
+}
+
+/// The frequency in Hz below which `ROLLOFF_SHARE` of the track's energy lies. Unlike the
+/// band centroid it follows the actual top end, so dull and airy masters separate clearly.
+/// `None` for silence.
+pub fn spectral_rolloff(samples: &[f32], sample_rate: usize, frame_size: usize) -> Option<f32> {
+    let power = long_term_power(samples, frame_size, hop_size(frame_size));
+    let total: f64 = power.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    let mut cumulative = 0.0;
+    let bin = power.iter().position(|&p| {
+        cumulative += p;
+        cumulative >= total * ROLLOFF_SHARE
+    })?;
+    Some(bin as f32 * sample_rate as f32 / frame_size as f32)
+}
+
+/// Slope of the long-term spectrum in dB per octave from a least-squares fit on log-log
+/// axes. Pink noise reads -3, white noise 0; lower is darker. `None` for silence.
+pub fn spectral_tilt(samples: &[f32], sample_rate: usize) -> Option<f32> {
+    // The long window resolves the lowest third-octaves, which span only a few Hz
+    let power = long_term_power(samples, ZOOM_FRAME_SIZE, ZOOM_HOP_SIZE);
+
+    let bin_hz = sample_rate as f32 / ZOOM_FRAME_SIZE as f32;
+    let max_hz = TILT_MAX_HZ.min(sample_rate as f32 / 2.0);
+    let group_count = ((max_hz / TILT_MIN_HZ).log2() * TILT_BINS_PER_OCTAVE).ceil() as usize;
+    let mut groups = vec![(0.0f64, 0usize); group_count];
+    for (bin, &p) in power.iter().enumerate() {
+        let hz = bin as f32 * bin_hz;
+        if hz < TILT_MIN_HZ || hz >= max_hz {
+            continue;
+        }
+        let group = ((hz / TILT_MIN_HZ).log2() * TILT_BINS_PER_OCTAVE) as usize;
+        if let Some((sum, count)) = groups.get_mut(group) {
+            *sum += p;
+            *count += 1;
+        }
+    }
+
+    // (octaves above TILT_MIN_HZ, mean power in dB) for each non-empty group
+    let points: Vec<(f64, f64)> = groups
+        .iter()
+        .enumerate()
+        .filter(|(_, (sum, count))| *count > 0 && *sum > 0.0)
+        .map(|(i, (sum, count))| {
+            let octave = (i as f64 + 0.5) / TILT_BINS_PER_OCTAVE as f64;
+            (octave, 10.0 * (sum / *count as f64).log10())
+        })
+        .collect();
+    if points.len() < 3 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let covariance: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let variance: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    Some((covariance / variance) as f32)
+}
+
+/// Symmetric Hann window, zero at both ends.
+pub fn hann_window(frame_size: usize) -> Vec<f32> {
+    (0..frame_size)
+        .map(|j| {
+            0.5 * (1.0
+                - ((2.0 * std::f32::consts::PI * j as f32) / (frame_size as f32 - 1.0)).cos())
+        })
+        .collect()
+}
+
+/// Process audio in overlapping Hann-windowed frames, passing each magnitude spectrum
+/// (`frame_size / 2` bins) to `visit`.
+pub fn for_each_spectrum(
+    samples: &[f32],
+    frame_size: usize,
+    hop_size: usize,
+    mut visit: impl FnMut(&[f32]),
+) {
+    let fft = plan_fft(frame_size);
+    let window = hann_window(frame_size);
+
+    let mut buffer = vec![Complex::new(0.0f32, 0.0); frame_size];
+    let mut magnitude = vec![0.0f32; frame_size / 2];
+
+    for i in (0..samples.len().saturating_sub(frame_size)).step_by(hop_size) {
+        let frame = &samples[i..i + frame_size];
+
+        // Apply Hann window
+        for ((slot, &s), &w) in buffer.iter_mut().zip(frame).zip(&window) {
+            *slot = Complex::new(s * w, 0.0);
+        }
+
+        // Perform FFT
+        fft.process(&mut buffer);
+
+        // Calculate magnitude spectrum
+        for (m, c) in magnitude.iter_mut().zip(&buffer[..frame_size / 2]) {
+            *m = (c.re * c.re + c.im * c.im).sqrt();
+        }
+
+        visit(&magnitude);
+    }
+}
+
 pub fn calculate_zero_crossing_rate(samples: &[f32]) -> f32 {
     if samples.len() < 2 {
         return 0.0;
     }
 
+    // Calculate rate as crossings per sample
+    let zcr = count_zero_crossings(samples) as f32 / samples.len() as f32;
+
+    normalize_zcr(zcr)
+}
+
+pub fn count_zero_crossings(samples: &[f32]) -> usize {
     let mut zero_crossings = 0;
 
     for i in 1..samples.len() {
@@ -153,15 +543,14 @@ pub fn calculate_zero_crossing_rate(samples: &[f32]) -> f32 {
         }
     }
 
-    // Calculate rate as crossings per sample
-    let zcr = zero_crossings as f32 / samples.len() as f32;
+    zero_crossings
+}
 
+pub fn normalize_zcr(zcr: f32) -> f32 {
     // Normalize to 0-100 scale
     // Typical ZCR ranges from ~0.01 (bass-heavy) to ~0.15 (very sharp/noisy)
     // We'll map 0.15 to 100 for normalization
-    let normalized_zcr = (zcr / 0.15 * 100.0).min(100.0);
-
-    normalized_zcr
+    (zcr / 0.15 * 100.0).min(100.0)
 }
 
 pub fn calculate_loudness(samples: &[f32]) -> f32 {
@@ -170,7 +559,7 @@ pub fn calculate_loudness(samples: &[f32]) -> f32 {
     }
 
     // Calculate RMS (Root Mean Square) - standard measure of loudness
-    let sum_squares: f32 = samples.iter().map(|&x| x * x).sum();
+    let sum_squares = sum(samples.iter().map(|&x| x * x));
     let rms = (sum_squares / samples.len() as f32).sqrt();
 
     // Convert to dB, with reference level of 1.0
@@ -182,14 +571,78 @@ pub fn calculate_loudness(samples: &[f32]) -> f32 {
     db.max(-60.0).min(0.0)
 }
 
+/// Color the bars and markers of terminal output. Off unless turned on from the command
+/// line, which leaves it off for `--no-color`, `NO_COLOR`, and output that isn't a terminal.
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+/// `text` in the spectrum color for `position` (0-100, low to high), or unchanged when
+/// color is off.
+pub fn paint(text: &str, position: f32) -> String {
+    if text.is_empty() || !COLOR.load(Ordering::Relaxed) {
+        return text.to_string();
+    }
+    let step = (position.clamp(0.0, 100.0) / 100.0 * (SPECTRUM_COLORS.len() - 1) as f32).round();
+    format!("\x1b[{}m{}\x1b[0m", SPECTRUM_COLORS[step as usize], text)
+}
+
+/// Describe bars and sparklines in words and leave out box-drawing characters, for screen
+/// readers and plain-text logs.
+pub fn set_screen_reader(enabled: bool) {
+    SCREEN_READER.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_screen_reader() -> bool {
+    SCREEN_READER.load(Ordering::Relaxed)
+}
+
+/// What goes between the fields of one output line.
+pub fn separator() -> &'static str {
+    if is_screen_reader() { "; " } else { "  │  " }
+}
+
+/// A horizontal rule under a heading, left out for screen readers.
+pub fn print_rule(width: usize) {
+    if !is_screen_reader() {
+        println!("{}", "=".repeat(width));
+    }
+}
+
+/// Where `position` (0-100, low to high) sits on the spectrum, in words.
+pub fn spectrum_region(position: f32) -> &'static str {
+    match position {
+        p if p < 20.0 => "low",
+        p if p < 40.0 => "low-mid",
+        p if p < 60.0 => "mid",
+        p if p < 80.0 => "high-mid",
+        _ => "high",
+    }
+}
+
+// A 0-100 reading in words, standing in for its bar
+fn level_word(value: f32) -> &'static str {
+    match value {
+        v if v < 20.0 => "very low",
+        v if v < 40.0 => "low",
+        v if v < 60.0 => "moderate",
+        v if v < 80.0 => "high",
+        _ => "very high",
+    }
+}
+
 pub fn print_spectrum_position(centroid: f32) {
+    if is_screen_reader() {
+        print!("{}", spectrum_region(centroid));
+        return;
+    }
     let bar_width = 20;
     let position = ((centroid / 100.0) * bar_width as f32) as usize;
 
     print!("[");
     for i in 0..bar_width {
         if i == position {
-            print!("●");
+            print!("{}", paint("●", centroid));
         } else {
             print!("─");
         }
@@ -198,13 +651,18 @@ pub fn print_spectrum_position(centroid: f32) {
 }
 
 pub fn print_spread_bar(spread: f32) {
+    if is_screen_reader() {
+        print!("{}", level_word(spread));
+        return;
+    }
     let bar_width = 10;
     let filled = ((spread / 100.0) * bar_width as f32) as usize;
 
     print!("[");
     for i in 0..bar_width {
         if i < filled {
-            print!("█");
+            // Each block takes the color of its place along the bar
+            print!("{}", paint("█", i as f32 / (bar_width - 1) as f32 * 100.0));
         } else {
             print!("░");
         }
@@ -212,22 +670,95 @@ pub fn print_spread_bar(spread: f32) {
     print!("]");
 }
 
-pub fn print_histogram_bar(percentage: f32) {
+/// A band's share as a bar, colored by the band's `position` from lowest (0) to highest
+/// (100).
+pub fn print_histogram_bar(percentage: f32, position: f32) {
     // Each character represents 5% (max 20 chars for 100%)
     let max_width = 10;
     let blocks = (percentage / 10.0 * max_width as f32) * 8.0;
     let full_blocks = blocks as usize / 8;
     let remainder = blocks as usize % 8;
 
+    if is_screen_reader() {
+        println!("{:.1} percent", percentage);
+        return;
+    }
     print!("{:>5.1}% | ", percentage);
     let block_chars: [char; 9] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
-    for _ in 0..full_blocks {
-        print!("█");
-    }
+    let mut bar = "█".repeat(full_blocks);
     if remainder > 0 {
-        print!("{}", block_chars[remainder]);
+        bar.push(block_chars[remainder]);
+    }
+    println!("{}", paint(&bar, position));
+}
+
+/// A band's difference from a reference share in percentage points, as a bar growing
+/// left of the center line when below it and right when above.
+pub fn print_deviation_bar(difference: f32, position: f32) {
+    // Each side holds 10 characters, one per 2 points
+    let half_width = 10;
+    let eighths = ((difference.abs() / 2.0 * 8.0).round() as usize).min(half_width * 8);
+    let (full_blocks, remainder) = (eighths / 8, eighths % 8);
+
+    if is_screen_reader() {
+        match difference {
+            d if d.abs() < 0.05 => println!("at the average"),
+            d if d > 0.0 => println!("{:.1} points above average", d),
+            d => println!("{:.1} points below average", -d),
+        }
+        return;
     }
-    println!();
+    print!("{:>+6.1} | ", difference);
+    if difference < 0.0 {
+        // There are no left-aligned eighth blocks, so a half block stands in for the rest
+        let mut bar = if remainder >= 4 { "▐" } else { "" }.to_string();
+        bar.push_str(&"█".repeat(full_blocks));
+        let padding = " ".repeat(half_width - bar.chars().count());
+        println!("{}{}│", padding, paint(&bar, position));
+    } else {
+        let block_chars: [char; 9] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+        let mut bar = "█".repeat(full_blocks);
+        if remainder > 0 {
+            bar.push(block_chars[remainder]);
+        }
+        println!("{}│{}", " ".repeat(half_width), paint(&bar, position));
+    }
+}
+
+pub fn print_rhythm_pattern(pattern: &[f32]) {
+    let levels: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = pattern.iter().cloned().fold(0.0f32, f32::max);
+
+    if is_screen_reader() {
+        // Steps holding at least half the strongest onset, counted from 1
+        let accents: Vec<String> = pattern
+            .iter()
+            .enumerate()
+            .filter(|&(_, &value)| max > 0.0 && value >= max / 2.0)
+            .map(|(i, _)| (i + 1).to_string())
+            .collect();
+        if accents.is_empty() {
+            print!("no accents");
+        } else {
+            print!(
+                "accents on steps {} of {}",
+                accents.join(", "),
+                pattern.len()
+            );
+        }
+        return;
+    }
+
+    print!("[");
+    for &value in pattern {
+        let level = if max > 0.0 {
+            ((value / max) * (levels.len() - 1) as f32).round() as usize
+        } else {
+            0
+        };
+        print!("{}", levels[level]);
+    }
+    print!("]");
 }
 
 pub fn print_duration(seconds: f32) {
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::utils::{CachedMetrics, unix_now};
+
+const HISTORY_FILE_NAME: &str = "history.json";
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Directory-wide averages at one point in time, recorded by `--snapshot`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Snapshot {
+    pub timestamp: u64,
+    pub track_count: usize,
+    pub loudness: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrated_lufs: Option<f32>,
+    pub centroid: f32,
+    pub spread: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tilt: Option<f32>,
+}
+
+/// Snapshots for every directory, keyed by absolute path, oldest first.
+pub type History = HashMap<String, Vec<Snapshot>>;
+
+pub fn history_path(config: &Config) -> PathBuf {
+    config.dir.join(HISTORY_FILE_NAME)
+}
+
+pub fn load_history(path: &Path) -> History {
+    File::open(path)
+        .ok()
+        .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_history(path: &Path, history: &History) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(writer, history)?;
+    Ok(())
+}
+
+/// The history key for a directory, so relative and absolute paths share one trend.
+pub fn history_key(dir_path: &Path) -> String {
+    fs::canonicalize(dir_path)
+        .unwrap_or_else(|_| dir_path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+fn mean(values: impl Iterator<Item = f32>) -> Option<f32> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count as f32)
+}
+
+pub fn take_snapshot(tracks: &[&CachedMetrics]) -> Snapshot {
+    Snapshot {
+        timestamp: unix_now(),
+        track_count: tracks.len(),
+        loudness: mean(tracks.iter().map(|t| t.loudness)).unwrap_or(0.0),
+        integrated_lufs: mean(tracks.iter().filter_map(|t| t.integrated_lufs)),
+        centroid: mean(tracks.iter().map(|t| t.centroid)).unwrap_or(0.0),
+        spread: mean(tracks.iter().map(|t| t.spread)).unwrap_or(0.0),
+        tilt: mean(tracks.iter().filter_map(|t| t.tilt)),
+    }
+}
+
+/// YYYY-MM-DD (UTC) for a Unix timestamp.
+fn date(timestamp: u64) -> String {
+    // Civil-from-days conversion for the proleptic Gregorian calendar
+    let days = (timestamp / SECONDS_PER_DAY) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn optional(value: Option<f32>, precision: usize) -> String {
+    value.map_or("--".to_string(), |v| format!("{:.*}", precision, v))
+}
+
+/// `name` is the directory, or the library, the snapshots belong to.
+pub fn print_history(name: &str, snapshots: &[Snapshot]) {
+    println!("\nHistory: {}\n", name);
+    println!(
+        "{:<12} {:>7} {:>10} {:>8} {:>9} {:>7} {:>8}",
+        "Date", "Tracks", "Loudness", "LUFS", "Centroid", "Spread", "Tilt"
+    );
+    println!("{}", "-".repeat(67));
+    for snapshot in snapshots {
+        println!(
+            "{:<12} {:>7} {:>10.1} {:>8} {:>9.1} {:>7.1} {:>8}",
+            date(snapshot.timestamp),
+            snapshot.track_count,
+            snapshot.loudness,
+            optional(snapshot.integrated_lufs, 1),
+            snapshot.centroid,
+            snapshot.spread,
+            optional(snapshot.tilt, 2)
+        );
+    }
+
+    if let [first, .., last] = snapshots {
+        println!(
+            "\nSince {}: loudness {:+.1} dB, centroid {:+.1}, {:+} tracks",
+            date(first.timestamp),
+            last.loudness - first.loudness,
+            last.centroid - first.centroid,
+            last.track_count as i64 - first.track_count as i64
+        );
+    }
+}
@@ -0,0 +1,457 @@
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+// Set from --lang or the environment before any output
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Languages with a message catalog. Messages a catalog doesn't have yet come out in
+/// English.
+#[derive(clap::ValueEnum, Clone, Copy, Default, PartialEq, Debug)]
+pub enum Lang {
+    #[default]
+    En,
+    De,
+}
+
+/// Print messages in `lang` from now on.
+pub fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+pub fn lang() -> Lang {
+    LANG.get().copied().unwrap_or_default()
+}
+
+/// The catalog language of the locale in `LC_ALL`, `LC_MESSAGES`, or `LANG` (the first
+/// one set, as POSIX has it), e.g. `de_DE.UTF-8`. `None` for other languages.
+pub fn detect_lang() -> Option<Lang> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())?;
+    let language = locale.split(['_', '.', '@']).next().unwrap_or_default();
+    match language.to_ascii_lowercase().as_str() {
+        "en" | "c" | "posix" => Some(Lang::En),
+        "de" => Some(Lang::De),
+        _ => None,
+    }
+}
+
+/// Status lines, errors, and summary text shown to the user. Metric names, warnings, and
+/// exported columns stay in English so files and scripts read the same everywhere.
+#[derive(Clone, Copy)]
+pub enum Msg {
+    Error,
+    ConfigError,
+    ConfigSaveError,
+    NotADirectory,
+    DirectoryUsage,
+    ReadDirError,
+    FoundFiles,
+    NoAudioFiles,
+    NoMatchingFiles,
+    AllIgnored,
+    UnknownRuleMetric,
+    Renamed,
+    ZScores,
+    ThisFolder,
+    TheLibrary,
+    PeakMemory,
+    AnalyzeFailed,
+    AnalyzeFailedRow,
+    StillBeingWritten,
+    ChangedSinceCached,
+    OutputError,
+    ShowingTracks,
+    NoTracksPastOffset,
+    FilesWithWarnings,
+    NoAnalyzedTracks,
+    WatchError,
+    Watching,
+    WatchEventError,
+    RootAdded,
+    AlreadyRegistered,
+    RootRemoved,
+    NotARoot,
+    NoRoots,
+    RootMissing,
+    SkippingMissingRoot,
+    LibraryUpdated,
+    AlreadyIgnored,
+    Ignoring,
+    NotIgnored,
+    Unignored,
+    NothingIgnored,
+    LibrarySummary,
+    Tracks,
+    TotalTime,
+    Loudness,
+    AverageSpectrum,
+    LowToHigh,
+    FurthestFromAverage,
+    Points,
+    Wrote,
+    CompareTestset,
+    NoSnapshots,
+    Skipping,
+    Imported,
+    Entry,
+    Entries,
+    SkippingCacheEntry,
+    WaitingForLock,
+    CacheOverLimit,
+    ReportError,
+    ReportWritten,
+    FilesChecked,
+    ReleaseCheck,
+    RulesFailed,
+    RulesPassed,
+    SnapshotRecorded,
+    HistorySaveError,
+    MissingLoudness,
+    LoudnessSkipped,
+    DecodeFailed,
+    AnalyzeTrackFailed,
+    BrowseNeedsTerminal,
+    Opened,
+    OpenFailed,
+    TonalBalance,
+    NoStreams,
+    AggregateTooSmall,
+    AggregateEncodeError,
+    AggregateWritten,
+    WriteError,
+    NotAFile,
+    RunLibraryScan,
+    NoTracksToCompare,
+    MostSimilar,
+    Distance,
+    ExtractError,
+    ClipsExtracted,
+    EnvelopesWritten,
+    EnvelopesError,
+    TagWriteError,
+    TagsWritten,
+    PlaylistWritten,
+    PlaylistError,
+    BarGridWritten,
+    BarGridError,
+    FrequencyProbes,
+    Segments,
+    SegmentError,
+    ChannelOrder,
+    Legend,
+    BandShares,
+    HzAndUp,
+    ExplainHint,
+    FrequencyBands,
+    Band,
+    RangeHz,
+    VsAverage,
+    Share,
+    Library,
+    NoLibrarySnapshots,
+}
+
+fn english(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Error => "Error: {0}",
+        Msg::ConfigError => "Error in config: {0}",
+        Msg::ConfigSaveError => "Error saving config: {0}",
+        Msg::NotADirectory => "Not a directory: {0}",
+        Msg::DirectoryUsage => {
+            "If no path is provided, analyzes all audio files in the current directory"
+        }
+        Msg::ReadDirError => "Error reading directory: {0}",
+        Msg::FoundFiles => "Found {0} audio file(s) in {1}",
+        Msg::NoAudioFiles => "No audio files ({0}) found in directory: {1}",
+        Msg::NoMatchingFiles => {
+            "No audio files matching --include, --exclude, or --ext in directory: {0}"
+        }
+        Msg::AllIgnored => {
+            "No audio files to analyze in directory: {0} ({1} ignored; see `ignore list`)"
+        }
+        Msg::UnknownRuleMetric => {
+            "Ignoring warning rule for unknown metric '{0}' (expected one of: {1})"
+        }
+        Msg::Renamed => "Renamed: {0} -> {1} (cached metrics kept)",
+        Msg::ZScores => "Values are z-scores against {0} track(s) in {1}",
+        Msg::ThisFolder => "this folder",
+        Msg::TheLibrary => "the library",
+        Msg::PeakMemory => "Peak memory: {0} MB",
+        Msg::AnalyzeFailed => "{0}: failed to analyze: {1}",
+        Msg::AnalyzeFailedRow => "ERROR: Failed to analyze: {0}",
+        Msg::StillBeingWritten => "still being written (it changed during analysis)",
+        Msg::ChangedSinceCached => "{0} file(s) new or changed since they were cached:",
+        Msg::OutputError => "Error writing output: {0}",
+        Msg::ShowingTracks => "Showing tracks {0}-{1} of {2}",
+        Msg::NoTracksPastOffset => "No tracks past --offset {0} ({1} found)",
+        Msg::FilesWithWarnings => "{0} file(s) with warnings",
+        Msg::NoAnalyzedTracks => "No analyzed tracks in {0}",
+        Msg::WatchError => "Error watching {0}: {1}",
+        Msg::Watching => "Watching {0} for new or changed audio files (Ctrl+C to stop)",
+        Msg::WatchEventError => "Watch error: {0}",
+        Msg::RootAdded => "Registered library root: {0}",
+        Msg::AlreadyRegistered => "Already registered: {0}",
+        Msg::RootRemoved => "Removed library root: {0}",
+        Msg::NotARoot => "Not a registered library root: {0}",
+        Msg::NoRoots => "No library roots registered. Use `library add <path>` to add one.",
+        Msg::RootMissing => "(missing)",
+        Msg::SkippingMissingRoot => "Skipping missing library root: {0}",
+        Msg::LibraryUpdated => "Library database updated: {0} file(s)",
+        Msg::AlreadyIgnored => "Already ignored: {0}",
+        Msg::Ignoring => "Ignoring: {0}",
+        Msg::NotIgnored => "Not on the ignore list: {0}",
+        Msg::Unignored => "No longer ignoring: {0}",
+        Msg::NothingIgnored => "Nothing ignored. Use `ignore add <pattern|file>` to skip files.",
+        Msg::LibrarySummary => "Library summary: {0}",
+        Msg::Tracks => "Tracks",
+        Msg::TotalTime => "Total time",
+        Msg::Loudness => "Loudness",
+        Msg::AverageSpectrum => "Average spectrum",
+        Msg::LowToHigh => "(low to high)",
+        Msg::FurthestFromAverage => "Furthest from the average spectrum:",
+        Msg::Points => "points",
+        Msg::Wrote => "Wrote {0}",
+        Msg::CompareTestset => "Run `{0} {1}` and compare with testset.json",
+        Msg::NoSnapshots => "No snapshots for {0}. Run an analysis with --snapshot to record one.",
+        Msg::Skipping => "Skipping {0}: {1}",
+        Msg::Imported => "Imported {0} {1} from {2} file(s) into {3}",
+        Msg::Entry => "entry",
+        Msg::Entries => "entries",
+        Msg::SkippingCacheEntry => "Skipping unreadable cache entry for {0}: {1}",
+        Msg::WaitingForLock => "Waiting for another run to release {0}...",
+        Msg::CacheOverLimit => {
+            "Warning: cache is {0} bytes after evicting missing files (limit {1})"
+        }
+        Msg::ReportError => "Error writing report: {0}",
+        Msg::ReportWritten => "Report written to {0}",
+        Msg::FilesChecked => "{0} file(s) checked, {1} failure(s)",
+        Msg::ReleaseCheck => "Release check: {0} ({1} tracks)",
+        Msg::RulesFailed => "{0} of {1} rule(s) failed",
+        Msg::RulesPassed => "All {0} rules passed",
+        Msg::SnapshotRecorded => "Snapshot recorded ({0} in history)",
+        Msg::HistorySaveError => "Error saving history: {0}",
+        Msg::MissingLoudness => "{0}: missing integrated loudness, loudness range, or true peak",
+        Msg::LoudnessSkipped => {
+            "{0} file(s) skipped; import loudness measurements with `import` first"
+        }
+        Msg::DecodeFailed => "Failed to decode {0}: {1}",
+        Msg::AnalyzeTrackFailed => "Failed to analyze {0}: {1}",
+        Msg::BrowseNeedsTerminal => "browse needs a terminal",
+        Msg::Opened => "Opened {0}",
+        Msg::OpenFailed => "Couldn't open {0}: {1}",
+        Msg::TonalBalance => "Tonal balance vs {0}: {1}",
+        Msg::NoStreams => "No audio streams in {0}",
+        Msg::AggregateTooSmall => {
+            "Need at least {0} analyzed tracks to keep the aggregate anonymous ({1} in {2})"
+        }
+        Msg::AggregateEncodeError => "Error encoding aggregate: {0}",
+        Msg::AggregateWritten => "Aggregate of {0} tracks written to {1}",
+        Msg::WriteError => "Error writing {0}: {1}",
+        Msg::NotAFile => "Not a file: {0}",
+        Msg::RunLibraryScan => "run `library scan` first",
+        Msg::NoTracksToCompare => "No analyzed tracks to compare against in {0}",
+        Msg::MostSimilar => "Tracks most similar to {0}",
+        Msg::Distance => "distance",
+        Msg::ExtractError => "Error extracting from {0}: {1}",
+        Msg::ClipsExtracted => "Extracted {0} clip(s) to {1}",
+        Msg::EnvelopesWritten => "Waveform envelopes written to {0}",
+        Msg::EnvelopesError => "Error writing envelopes: {0}",
+        Msg::TagWriteError => "{0}: can't write tags: {1}",
+        Msg::TagsWritten => "Metric tags written to {0} MP3 file(s)",
+        Msg::PlaylistWritten => "Playlist of {0} track(s) by {1} written to {2}",
+        Msg::PlaylistError => "Error writing playlist: {0}",
+        Msg::BarGridWritten => "Bar energy grid written to {0}",
+        Msg::BarGridError => "Error writing bar grid: {0}",
+        Msg::FrequencyProbes => "Frequency probes (dBFS)",
+        Msg::Segments => "Segments ({0} s each)",
+        Msg::SegmentError => "Error segmenting {0}: {1}",
+        Msg::ChannelOrder => "Channel order against {0}",
+        Msg::Legend => "Legend:",
+        Msg::BandShares => "Frequency bands (share of total energy):",
+        Msg::HzAndUp => "{0} Hz and up",
+        Msg::ExplainHint => "Run `explain METRIC` for how each reading is computed.",
+        Msg::FrequencyBands => "Frequency Bands:",
+        Msg::Band => "Band",
+        Msg::RangeHz => "Range (Hz)",
+        Msg::VsAverage => "vs avg",
+        Msg::Share => "Share",
+        Msg::Library => "all library roots",
+        Msg::NoLibrarySnapshots => {
+            "No snapshots for the library. Run `library scan --snapshot` to record one."
+        }
+    }
+}
+
+fn german(msg: Msg) -> Option<&'static str> {
+    Some(match msg {
+        Msg::Error => "Fehler: {0}",
+        Msg::ConfigError => "Fehler in der Konfiguration: {0}",
+        Msg::ConfigSaveError => "Fehler beim Speichern der Konfiguration: {0}",
+        Msg::NotADirectory => "Kein Verzeichnis: {0}",
+        Msg::DirectoryUsage => {
+            "Ohne Pfadangabe werden alle Audiodateien im aktuellen Verzeichnis analysiert"
+        }
+        Msg::ReadDirError => "Fehler beim Lesen des Verzeichnisses: {0}",
+        Msg::FoundFiles => "{0} Audiodatei(en) in {1} gefunden",
+        Msg::NoAudioFiles => "Keine Audiodateien ({0}) im Verzeichnis gefunden: {1}",
+        Msg::NoMatchingFiles => {
+            "Keine Audiodateien passend zu --include, --exclude oder --ext im Verzeichnis: {0}"
+        }
+        Msg::AllIgnored => {
+            "Keine Audiodateien zu analysieren im Verzeichnis: {0} ({1} ignoriert; siehe `ignore list`)"
+        }
+        Msg::UnknownRuleMetric => {
+            "Warnregel für unbekannte Metrik '{0}' wird übergangen (erwartet wird eine von: {1})"
+        }
+        Msg::Renamed => "Umbenannt: {0} -> {1} (zwischengespeicherte Werte übernommen)",
+        Msg::ZScores => "Die Werte sind z-Werte gegenüber {0} Titel(n) in {1}",
+        Msg::ThisFolder => "diesem Ordner",
+        Msg::TheLibrary => "der Bibliothek",
+        Msg::PeakMemory => "Spitzenspeicher: {0} MB",
+        Msg::AnalyzeFailed => "{0}: Analyse fehlgeschlagen: {1}",
+        Msg::AnalyzeFailedRow => "FEHLER: Analyse fehlgeschlagen: {0}",
+        Msg::StillBeingWritten => "wird noch geschrieben (hat sich während der Analyse geändert)",
+        Msg::ChangedSinceCached => "{0} Datei(en) neu oder seit dem Zwischenspeichern geändert:",
+        Msg::OutputError => "Fehler beim Schreiben der Ausgabe: {0}",
+        Msg::ShowingTracks => "Titel {0}-{1} von {2}",
+        Msg::NoTracksPastOffset => "Keine Titel nach --offset {0} ({1} gefunden)",
+        Msg::FilesWithWarnings => "{0} Datei(en) mit Warnungen",
+        Msg::NoAnalyzedTracks => "Keine analysierten Titel in {0}",
+        Msg::WatchError => "Fehler beim Überwachen von {0}: {1}",
+        Msg::Watching => "Überwache {0} auf neue oder geänderte Audiodateien (Strg+C zum Beenden)",
+        Msg::WatchEventError => "Überwachungsfehler: {0}",
+        Msg::RootAdded => "Bibliotheksordner registriert: {0}",
+        Msg::AlreadyRegistered => "Bereits registriert: {0}",
+        Msg::RootRemoved => "Bibliotheksordner entfernt: {0}",
+        Msg::NotARoot => "Kein registrierter Bibliotheksordner: {0}",
+        Msg::NoRoots => {
+            "Keine Bibliotheksordner registriert. Mit `library add <Pfad>` einen hinzufügen."
+        }
+        Msg::RootMissing => "(fehlt)",
+        Msg::SkippingMissingRoot => "Fehlender Bibliotheksordner wird übersprungen: {0}",
+        Msg::LibraryUpdated => "Bibliotheksdatenbank aktualisiert: {0} Datei(en)",
+        Msg::AlreadyIgnored => "Bereits ignoriert: {0}",
+        Msg::Ignoring => "Wird ignoriert: {0}",
+        Msg::NotIgnored => "Nicht auf der Ignorierliste: {0}",
+        Msg::Unignored => "Nicht mehr ignoriert: {0}",
+        Msg::NothingIgnored => {
+            "Nichts ignoriert. Mit `ignore add <Muster|Datei>` Dateien überspringen."
+        }
+        Msg::LibrarySummary => "Bibliotheksübersicht: {0}",
+        Msg::Tracks => "Titel",
+        Msg::TotalTime => "Gesamtdauer",
+        Msg::Loudness => "Lautheit",
+        Msg::AverageSpectrum => "Mittleres Spektrum",
+        Msg::LowToHigh => "(tief bis hoch)",
+        Msg::FurthestFromAverage => "Am weitesten vom mittleren Spektrum entfernt:",
+        Msg::Points => "Punkte",
+        Msg::Wrote => "Geschrieben: {0}",
+        Msg::CompareTestset => "`{0} {1}` ausführen und mit testset.json vergleichen",
+        Msg::NoSnapshots => {
+            "Keine Momentaufnahmen für {0}. Eine Analyse mit --snapshot zeichnet eine auf."
+        }
+        Msg::Skipping => "{0} wird übersprungen: {1}",
+        Msg::Imported => "{0} {1} aus {2} Datei(en) nach {3} importiert",
+        Msg::Entry => "Eintrag",
+        Msg::Entries => "Einträge",
+        Msg::SkippingCacheEntry => "Unlesbarer Cache-Eintrag für {0} wird übersprungen: {1}",
+        Msg::WaitingForLock => "Warte, bis ein anderer Lauf {0} freigibt...",
+        Msg::CacheOverLimit => {
+            "Warnung: Cache ist nach dem Entfernen fehlender Dateien {0} Bytes groß (Limit {1})"
+        }
+        Msg::ReportError => "Fehler beim Schreiben des Berichts: {0}",
+        Msg::ReportWritten => "Bericht nach {0} geschrieben",
+        Msg::FilesChecked => "{0} Datei(en) geprüft, {1} Verstoß/Verstöße",
+        Msg::ReleaseCheck => "Release-Prüfung: {0} ({1} Titel)",
+        Msg::RulesFailed => "{0} von {1} Regel(n) nicht erfüllt",
+        Msg::RulesPassed => "Alle {0} Regeln erfüllt",
+        Msg::SnapshotRecorded => "Momentaufnahme gespeichert ({0} im Verlauf)",
+        Msg::HistorySaveError => "Fehler beim Speichern des Verlaufs: {0}",
+        Msg::MissingLoudness => "{0}: integrierte Lautheit, Lautheitsbereich oder True Peak fehlt",
+        Msg::LoudnessSkipped => {
+            "{0} Datei(en) übersprungen; zuerst Lautheitsmessungen mit `import` einlesen"
+        }
+        Msg::DecodeFailed => "Dekodieren von {0} fehlgeschlagen: {1}",
+        Msg::AnalyzeTrackFailed => "Analyse von {0} fehlgeschlagen: {1}",
+        Msg::BrowseNeedsTerminal => "browse braucht ein Terminal",
+        Msg::Opened => "{0} geöffnet",
+        Msg::OpenFailed => "{0} konnte nicht geöffnet werden: {1}",
+        Msg::TonalBalance => "Klangbalance gegenüber {0}: {1}",
+        Msg::NoStreams => "Keine Audiostreams in {0}",
+        Msg::AggregateTooSmall => {
+            "Mindestens {0} analysierte Titel nötig, damit das Aggregat anonym bleibt ({1} in {2})"
+        }
+        Msg::AggregateEncodeError => "Fehler beim Kodieren des Aggregats: {0}",
+        Msg::AggregateWritten => "Aggregat aus {0} Titeln nach {1} geschrieben",
+        Msg::WriteError => "Fehler beim Schreiben von {0}: {1}",
+        Msg::NotAFile => "Keine Datei: {0}",
+        Msg::RunLibraryScan => "zuerst `library scan` ausführen",
+        Msg::NoTracksToCompare => "Keine analysierten Vergleichstitel in {0}",
+        Msg::MostSimilar => "Die ähnlichsten Titel zu {0}",
+        Msg::Distance => "Abstand",
+        Msg::ExtractError => "Fehler beim Ausschneiden aus {0}: {1}",
+        Msg::ClipsExtracted => "{0} Ausschnitt(e) nach {1} extrahiert",
+        Msg::EnvelopesWritten => "Wellenform-Hüllkurven nach {0} geschrieben",
+        Msg::EnvelopesError => "Fehler beim Schreiben der Hüllkurven: {0}",
+        Msg::TagWriteError => "{0}: Tags können nicht geschrieben werden: {1}",
+        Msg::TagsWritten => "Metrik-Tags in {0} MP3-Datei(en) geschrieben",
+        Msg::PlaylistWritten => "Playlist mit {0} Titel(n) nach {1} in {2} geschrieben",
+        Msg::PlaylistError => "Fehler beim Schreiben der Playlist: {0}",
+        Msg::BarGridWritten => "Takt-Energieraster nach {0} geschrieben",
+        Msg::BarGridError => "Fehler beim Schreiben des Takt-Energierasters: {0}",
+        Msg::FrequencyProbes => "Frequenzproben (dBFS)",
+        Msg::Segments => "Abschnitte (je {0} s)",
+        Msg::SegmentError => "Fehler beim Unterteilen von {0}: {1}",
+        Msg::ChannelOrder => "Kanalreihenfolge gegenüber {0}",
+        Msg::Legend => "Legende:",
+        Msg::BandShares => "Frequenzbänder (Anteil an der Gesamtenergie):",
+        Msg::HzAndUp => "ab {0} Hz",
+        Msg::ExplainHint => "`explain METRIC` zeigt, wie jeder Wert berechnet wird.",
+        Msg::FrequencyBands => "Frequenzbänder:",
+        Msg::Band => "Band",
+        Msg::RangeHz => "Bereich (Hz)",
+        Msg::VsAverage => "ggü. Ø",
+        Msg::Share => "Anteil",
+        Msg::Library => "alle Bibliotheksordner",
+        Msg::NoLibrarySnapshots => {
+            "Keine Momentaufnahmen der Bibliothek. `library scan --snapshot` zeichnet eine auf."
+        }
+    })
+}
+
+/// `msg` in the current language with `{0}`, `{1}`, … filled from `args`. Placeholders
+/// are numbered so a translation can put them in a different order.
+pub fn tr(msg: Msg, args: &[&dyn Display]) -> String {
+    let template = match lang() {
+        Lang::En => None,
+        Lang::De => german(msg),
+    }
+    .unwrap_or_else(|| english(msg));
+
+    // One pass over the template, so braces inside an argument are left alone
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        text.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let placeholder = after
+            .find('}')
+            .and_then(|close| Some((after[..close].parse::<usize>().ok()?, close)))
+            .and_then(|(i, close)| Some((args.get(i)?, close)));
+        match placeholder {
+            Some((arg, close)) => {
+                text.push_str(&arg.to_string());
+                rest = &after[close + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
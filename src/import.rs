@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::frequency_bands::{hz_to_position, normalize_zcr};
+use crate::utils::CachedMetrics;
+
+/// Metrics recovered from a foreign tool. Anything the tool doesn't report stays `None`.
+#[derive(Default)]
+pub struct ImportedMetrics {
+    pub filename: String,
+    pub centroid: Option<f32>,
+    pub zero_crossing_rate: Option<f32>,
+    pub duration_seconds: Option<f32>,
+    pub integrated_lufs: Option<f32>,
+    pub loudness_range: Option<f32>,
+    pub true_peak_db: Option<f32>,
+}
+
+pub enum ImportSource {
+    Sidecar(Vec<CachedMetrics>),
+    Essentia(ImportedMetrics),
+    Bs1770gain(Vec<ImportedMetrics>),
+}
+
+impl ImportSource {
+    fn label(&self) -> &'static str {
+        match self {
+            ImportSource::Sidecar(_) => "sidecar",
+            ImportSource::Essentia(_) => "essentia",
+            ImportSource::Bs1770gain(_) => "bs1770gain",
+        }
+    }
+}
+
+pub fn parse_import_file(path: &Path) -> Result<ImportSource, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)?;
+
+    let Ok(json) = serde_json::from_str::<Value>(&contents) else {
+        let entries = parse_bs1770gain(&contents);
+        if entries.is_empty() {
+            return Err("Unrecognized import format".into());
+        }
+        return Ok(ImportSource::Bs1770gain(entries));
+    };
+
+    // Essentia extractor output always has a lowlevel section
+    if json.get("lowlevel").is_some() {
+        return Ok(ImportSource::Essentia(parse_essentia(&json, path)));
+    }
+
+    // A single sidecar entry names its file; otherwise it may be a whole cache file from
+    // another directory. Entries default missing keys, so the name is what tells them apart
+    if json.get("filename").is_some_and(Value::is_string) {
+        let entry = serde_json::from_value::<CachedMetrics>(json)?;
+        return Ok(ImportSource::Sidecar(vec![entry]));
+    }
+    if let Ok(entries) = serde_json::from_value::<HashMap<String, CachedMetrics>>(json) {
+        return Ok(ImportSource::Sidecar(entries.into_values().collect()));
+    }
+
+    Err("Unrecognized import format".into())
+}
+
+fn parse_essentia(json: &Value, path: &Path) -> ImportedMetrics {
+    let number = |pointer: &str| {
+        json.pointer(pointer)
+            .and_then(Value::as_f64)
+            .map(|v| v as f32)
+    };
+
+    // Essentia records the analyzed file name; fall back to the JSON's own stem
+    let filename = json
+        .pointer("/metadata/tags/file_name")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        });
+
+    let sample_rate = number("/metadata/audio_properties/sample_rate").unwrap_or(44100.0);
+
+    ImportedMetrics {
+        filename: base_name(&filename),
+        centroid: number("/lowlevel/spectral_centroid/mean")
+            .map(|hz| hz_to_position(hz, sample_rate / 2.0)),
+        zero_crossing_rate: number("/lowlevel/zerocrossingrate/mean").map(normalize_zcr),
+        duration_seconds: number("/metadata/audio_properties/length"),
+        integrated_lufs: number("/lowlevel/loudness_ebu128/integrated"),
+        loudness_range: number("/lowlevel/loudness_ebu128/loudness_range"),
+        true_peak_db: None,
+    }
+}
+
+fn parse_bs1770gain(contents: &str) -> Vec<ImportedMetrics> {
+    let mut entries: Vec<ImportedMetrics> = Vec::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+
+        // Track headers look like: [1/12] "01 Intro.mp3":
+        if line.starts_with('[') && !line.starts_with("[ALBUM]") {
+            if let Some(name) = line.split('"').nth(1) {
+                entries.push(ImportedMetrics {
+                    filename: base_name(name),
+                    ..Default::default()
+                });
+            }
+            continue;
+        }
+
+        // Album summaries follow the tracks and aren't tied to a file
+        if line.starts_with("[ALBUM]") {
+            break;
+        }
+
+        let Some(current) = entries.last_mut() else {
+            continue;
+        };
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value
+            .split_whitespace()
+            .next()
+            .and_then(|v| v.parse::<f32>().ok());
+
+        match key.trim() {
+            "integrated" => current.integrated_lufs = value,
+            "range" => current.loudness_range = value,
+            "true peak" => current.true_peak_db = value,
+            _ => {}
+        }
+    }
+
+    entries
+}
+
+fn base_name(name: &str) -> String {
+    Path::new(name)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Merge an import into the cache, returning how many entries were touched.
+pub fn merge_import(source: ImportSource, cache: &mut HashMap<String, CachedMetrics>) -> usize {
+    let label = source.label();
+
+    match source {
+        ImportSource::Sidecar(entries) => {
+            let count = entries.len();
+            for mut entry in entries {
+                entry.imported_from = Some(label.to_string());
+                cache.insert(entry.filename.clone(), entry);
+            }
+            count
+        }
+        ImportSource::Essentia(imported) => {
+            merge_partial(imported, label, cache);
+            1
+        }
+        ImportSource::Bs1770gain(entries) => {
+            let count = entries.len();
+            for imported in entries {
+                merge_partial(imported, label, cache);
+            }
+            count
+        }
+    }
+}
+
+fn merge_partial(
+    imported: ImportedMetrics,
+    label: &str,
+    cache: &mut HashMap<String, CachedMetrics>,
+) {
+    // Unknown files get an incomplete entry; the next scan fills in band data
+    let entry = cache
+        .entry(imported.filename.clone())
+        .or_insert_with(|| CachedMetrics {
+            filename: imported.filename.clone(),
+            loudness: -60.0,
+            ..Default::default()
+        });
+
+    if let Some(centroid) = imported.centroid {
+        entry.centroid = centroid;
+    }
+    if let Some(zcr) = imported.zero_crossing_rate {
+        entry.zero_crossing_rate = zcr;
+    }
+    if let Some(duration) = imported.duration_seconds {
+        entry.duration_seconds = duration;
+    }
+    entry.integrated_lufs = imported.integrated_lufs.or(entry.integrated_lufs);
+    entry.loudness_range = imported.loudness_range.or(entry.loudness_range);
+    entry.true_peak_db = imported.true_peak_db.or(entry.true_peak_db);
+    entry.imported_from = Some(label.to_string());
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::frequency_bands::Transform;
+use crate::structure::track_chroma;
+
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
+];
+
+// Krumhansl-Kessler probe-tone ratings for each scale degree, tonic first
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct KeyEstimate {
+    pub tonic: usize, // Pitch class, 0 = C
+    pub mode: Mode,
+    pub correlation: f32, // Pearson correlation with the key's profile (-1 to 1)
+}
+
+impl fmt::Display for KeyEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mode = match self.mode {
+            Mode::Major => "major",
+            Mode::Minor => "minor",
+        };
+        write!(f, "{} {}", NOTE_NAMES[self.tonic % 12], mode)
+    }
+}
+
+fn pearson(a: &[f32; 12], b: &[f32; 12]) -> f32 {
+    let mean_a = a.iter().sum::<f32>() / 12.0;
+    let mean_b = b.iter().sum::<f32>() / 12.0;
+    let (mut covariance, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (&x, &y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a) * (x - mean_a);
+        var_b += (y - mean_b) * (y - mean_b);
+    }
+    if var_a <= 0.0 || var_b <= 0.0 {
+        return 0.0;
+    }
+    covariance / (var_a * var_b).sqrt()
+}
+
+/// The major or minor key whose Krumhansl-Kessler profile best matches the track's
+/// overall chroma. `None` for silence and unpitched material.
+pub fn estimate_key(
+    samples: &[f32],
+    sample_rate: usize,
+    transform: Transform,
+) -> Option<KeyEstimate> {
+    let chroma = track_chroma(samples, sample_rate, transform);
+    if chroma.iter().all(|&x| x <= 0.0) {
+        return None;
+    }
+
+    let mut best: Option<KeyEstimate> = None;
+    for (mode, profile) in [(Mode::Major, MAJOR_PROFILE), (Mode::Minor, MINOR_PROFILE)] {
+        for tonic in 0..12 {
+            // Rotate the profile so its tonic lands on this pitch class
+            let rotated: [f32; 12] =
+                std::array::from_fn(|class| profile[(class + 12 - tonic) % 12]);
+            let correlation = pearson(&chroma, &rotated);
+            if best.as_ref().is_none_or(|b| correlation > b.correlation) {
+                best = Some(KeyEstimate {
+                    tonic,
+                    mode,
+                    correlation,
+                });
+            }
+        }
+    }
+    best.filter(|key| key.correlation > 0.0)
+}
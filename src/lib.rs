@@ -0,0 +1,74 @@
+//! Spectral, loudness, rhythm, and stereo analysis for audio files.
+//!
+//! [`analyze_frequency_distribution`] decodes a file and computes everything the
+//! `rust-audio-analysis` command shows; the modules expose the individual measurements for
+//! callers that only need some of them. [`Analyzer::builder`] collects the settings (band
+//! layout, transform, frame size, which metrics to compute) for analyzing many files the
+//! same way: [`Analyzer::analyze_paths`] runs a whole batch in parallel with a result per
+//! file, and [`Analyzer::frames`] streams per-frame spectra for custom aggregations
+//! without holding the whole track. [`analysis::analyze_with_progress`] adds progress reporting and cancellation
+//! for long analyses, and the `async` feature adds tokio versions in `async_api`.
+
+pub mod aggregate;
+pub mod analysis;
+pub mod assertions;
+#[cfg(feature = "async")]
+pub mod async_api;
+pub mod beats;
+pub mod browse;
+pub mod clipping;
+pub mod clips;
+pub mod config;
+pub mod cqt;
+pub mod derived;
+pub mod dsd;
+pub mod dynamics;
+pub mod envelope;
+pub mod export;
+pub mod filter;
+pub mod frames;
+pub mod frequency_bands;
+pub mod history;
+pub mod i18n;
+pub mod import;
+pub mod key;
+pub mod library;
+pub mod loudness;
+pub mod metrics;
+pub mod normalization;
+pub mod output;
+pub mod presets;
+pub mod probe;
+pub mod psychoacoustics;
+pub mod ratios;
+pub mod release;
+pub mod report;
+pub mod silence;
+pub mod similarity;
+pub mod sorting;
+pub mod spectrogram;
+pub mod standardize;
+pub mod stereo;
+pub mod structure;
+pub mod summary;
+pub mod surround;
+pub mod tags;
+pub mod tempo;
+pub mod testset;
+pub mod tonal;
+pub mod transcode;
+pub mod units;
+pub mod utils;
+pub mod walkthrough;
+pub mod warnings;
+
+pub use analysis::{
+    AnalysisError, AnalysisErrorKind, Analyzer, FileAnalysis, MetricGroup,
+    analyze_frequency_distribution,
+};
+pub use frames::{FrameFeatures, Frames};
+pub use frequency_bands::{
+    FrequencyBand, SpectrumMetrics, Transform, calculate_band_energies, get_bands,
+};
+pub use units::{Db, Hz, Lufs, Percent};
+pub use utils::{DecodedAudio, StreamInfo, decode_audio, get_samples};
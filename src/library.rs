@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::i18n::{Msg, tr};
+use crate::utils::CachedMetrics;
+
+const LIBRARY_DB_NAME: &str = "library.json";
+
+/// The library's entry in the history file, beside the per-directory ones.
+pub const LIBRARY_HISTORY_KEY: &str = "library";
+
+/// The unified database, keyed by absolute file path across all registered roots.
+pub fn library_db_path(config: &Config) -> PathBuf {
+    config.dir.join(LIBRARY_DB_NAME)
+}
+
+pub fn add_root(config: &mut Config, path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let root = fs::canonicalize(path)?;
+    if !root.is_dir() {
+        return Err(tr(Msg::NotADirectory, &[&root.display()]).into());
+    }
+    if config.library_roots.contains(&root) {
+        return Err(tr(Msg::AlreadyRegistered, &[&root.display()]).into());
+    }
+
+    config.library_roots.push(root.clone());
+    config.library_roots.sort();
+    Ok(root)
+}
+
+pub fn remove_root(config: &mut Config, path: &Path) -> Option<PathBuf> {
+    // The root may have been deleted since, so fall back to the literal path
+    let root = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let index = config.library_roots.iter().position(|r| *r == root)?;
+    Some(config.library_roots.remove(index))
+}
+
+/// Replace everything stored under `root` with the entries of its directory cache.
+pub fn merge_directory(
+    db: &mut HashMap<String, CachedMetrics>,
+    root: &Path,
+    cache: &HashMap<String, CachedMetrics>,
+) {
+    db.retain(|key, _| !Path::new(key).starts_with(root));
+
+    for (filename, entry) in cache {
+        let path = root.join(filename);
+        if path.exists() {
+            db.insert(path.to_string_lossy().to_string(), entry.clone());
+        }
+    }
+}
+
+/// Drop entries belonging to roots that are no longer registered.
+pub fn prune_unregistered(db: &mut HashMap<String, CachedMetrics>, roots: &[PathBuf]) {
+    db.retain(|key, _| roots.iter().any(|root| Path::new(key).starts_with(root)));
+}
+
+/// Library entries whose files still exist, each named by its path below the closest
+/// directory holding every root (so names start with their root's folder), sorted by
+/// name. Returns that directory along with them.
+pub fn present_tracks(
+    db: &HashMap<String, CachedMetrics>,
+    roots: &[PathBuf],
+) -> (PathBuf, Vec<CachedMetrics>) {
+    let base = common_parent(roots);
+    let mut tracks: Vec<CachedMetrics> = db
+        .iter()
+        .filter(|(key, _)| {
+            let path = Path::new(key);
+            roots.iter().any(|root| path.starts_with(root)) && path.is_file()
+        })
+        .filter_map(|(key, entry)| {
+            let name = Path::new(key).strip_prefix(&base).ok()?;
+            Some(CachedMetrics {
+                filename: name.to_string_lossy().to_string(),
+                ..entry.clone()
+            })
+        })
+        .collect();
+    tracks.sort_by(|a, b| a.filename.cmp(&b.filename));
+    (base, tracks)
+}
+
+// The deepest directory above every root
+fn common_parent(roots: &[PathBuf]) -> PathBuf {
+    let mut base = roots
+        .first()
+        .and_then(|root| root.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    for root in roots {
+        while !root.starts_with(&base) && base.pop() {}
+    }
+    base
+}
+
+/// Library entries grouped by root, in a stable order for display.
+pub fn entries_by_root<'a>(
+    db: &'a HashMap<String, CachedMetrics>,
+    roots: &[PathBuf],
+) -> Vec<(PathBuf, Vec<&'a CachedMetrics>)> {
+    roots
+        .iter()
+        .map(|root| {
+            let mut keys: Vec<&String> = db
+                .keys()
+                .filter(|key| Path::new(key).starts_with(root))
+                .collect();
+            keys.sort();
+            (root.clone(), keys.into_iter().map(|key| &db[key]).collect())
+        })
+        .collect()
+}
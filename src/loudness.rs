@@ -0,0 +1,232 @@
+use std::f64::consts::PI;
+
+// BS.1770 gating blocks are 400 ms, stepped by a quarter
+const SEGMENT_SECONDS: f64 = 0.1;
+const BLOCK_SEGMENTS: usize = 4;
+const SHORT_TERM_SEGMENTS: usize = 30; // 3 s windows for loudness range
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+const RANGE_RELATIVE_GATE_LU: f64 = -20.0;
+const RANGE_LOW_PERCENTILE: f64 = 0.10;
+const RANGE_HIGH_PERCENTILE: f64 = 0.95;
+
+// DR meter blocks, and the share of the loudest blocks whose RMS counts
+const DR_BLOCK_SECONDS: f64 = 3.0;
+const DR_TOP_SHARE: f64 = 0.2;
+
+// True peak is read from a 4x oversampled signal, interpolated with a windowed sinc
+const OVERSAMPLING: usize = 4;
+const INTERPOLATION_TAPS: isize = 8; // Either side of each interpolated point
+
+/// EBU R128 loudness measured from the decoded channels.
+pub struct Loudness {
+    pub integrated_lufs: Option<f32>, // None when every block falls below the gates
+    pub loudness_range: Option<f32>,  // LU between the 10th and 95th short-term percentiles
+    pub true_peak_db: Option<f32>,    // dBTP; None for digital silence
+}
+
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        // Transposed direct form II
+        let y = self.b[0] * x + self.state[0];
+        self.state[0] = self.b[1] * x - self.a[0] * y + self.state[1];
+        self.state[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// The two K-weighting stages (high shelf, then high pass) for any sample rate, using the
+/// analog prototypes behind the 48 kHz coefficients in BS.1770.
+fn k_weighting(sample_rate: usize) -> [Biquad; 2] {
+    let fs = sample_rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        state: [0.0; 2],
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / fs).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        state: [0.0; 2],
+    };
+
+    [shelf, high_pass]
+}
+
+// BS.1770 channel weights, assuming the usual L, R, C, LFE, Ls, Rs order
+fn channel_weight(channel: usize, channel_count: usize) -> f64 {
+    match (channel_count >= 5, channel) {
+        (true, 3) => 0.0,
+        (true, 4 | 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+fn lufs(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.max(f64::MIN_POSITIVE).log10()
+}
+
+/// Weighted mean square of each window of `length` segments, stepped one segment at a time.
+fn windows(segments: &[f64], segment_length: usize, length: usize) -> Vec<f64> {
+    segments
+        .windows(length)
+        .map(|w| w.iter().sum::<f64>() / (length * segment_length) as f64)
+        .collect()
+}
+
+fn integrated(blocks: &[f64]) -> Option<f64> {
+    let gated = |threshold: f64| {
+        let passing: Vec<f64> = blocks
+            .iter()
+            .copied()
+            .filter(|&z| lufs(z) > threshold)
+            .collect();
+        (!passing.is_empty()).then(|| passing.iter().sum::<f64>() / passing.len() as f64)
+    };
+    let ungated = gated(ABSOLUTE_GATE_LUFS)?;
+    gated(lufs(ungated) + RELATIVE_GATE_LU).map(lufs)
+}
+
+fn loudness_range(short_term: &[f64]) -> Option<f64> {
+    let above_absolute: Vec<f64> = short_term
+        .iter()
+        .copied()
+        .filter(|&z| lufs(z) > ABSOLUTE_GATE_LUFS)
+        .collect();
+    if above_absolute.is_empty() {
+        return None;
+    }
+    let mean = above_absolute.iter().sum::<f64>() / above_absolute.len() as f64;
+    let mut levels: Vec<f64> = above_absolute
+        .iter()
+        .map(|&z| lufs(z))
+        .filter(|&level| level > lufs(mean) + RANGE_RELATIVE_GATE_LU)
+        .collect();
+    levels.sort_by(f64::total_cmp);
+
+    let percentile = |p: f64| levels[((levels.len() - 1) as f64 * p).round() as usize];
+    Some(percentile(RANGE_HIGH_PERCENTILE) - percentile(RANGE_LOW_PERCENTILE))
+}
+
+/// Highest absolute sample value of a channel upsampled `OVERSAMPLING` times.
+fn true_peak(samples: &[f32]) -> f32 {
+    // One windowed-sinc kernel per fractional position between input samples
+    let kernels: Vec<Vec<f32>> = (1..OVERSAMPLING)
+        .map(|phase| {
+            let offset = phase as f64 / OVERSAMPLING as f64;
+            (-INTERPOLATION_TAPS + 1..=INTERPOLATION_TAPS)
+                .map(|tap| {
+                    let x = tap as f64 - offset;
+                    let sinc = (PI * x).sin() / (PI * x);
+                    let window = 0.5 + 0.5 * (PI * x / INTERPOLATION_TAPS as f64).cos();
+                    (sinc * window) as f32
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut peak = samples.iter().fold(0.0f32, |peak, &s| peak.max(s.abs()));
+    let reach = INTERPOLATION_TAPS as usize;
+    for i in reach..samples.len().saturating_sub(reach) {
+        let neighbours = &samples[i + 1 - reach..=i + reach];
+        for kernel in &kernels {
+            let value: f32 = neighbours.iter().zip(kernel).map(|(s, k)| s * k).sum();
+            peak = peak.max(value.abs());
+        }
+    }
+    peak
+}
+
+// DR of one channel: second-highest block peak over the RMS of the loudest 20% of blocks
+fn channel_dr(samples: &[f32], block_length: usize) -> Option<f64> {
+    let mut peaks = Vec::new();
+    let mut levels = Vec::new();
+    for block in samples.chunks(block_length) {
+        let mean_square =
+            block.iter().map(|&s| s as f64 * s as f64).sum::<f64>() / block.len() as f64;
+        // Scaled by 2 so a full-scale sine reads 0 dB, as the original meter does
+        levels.push(2.0 * mean_square);
+        peaks.push(
+            block
+                .iter()
+                .fold(0.0f64, |peak, &s| peak.max(s.abs() as f64)),
+        );
+    }
+    peaks.sort_by(|a, b| b.total_cmp(a));
+    levels.sort_by(|a, b| b.total_cmp(a));
+
+    let peak = *peaks.get(1).or(peaks.first())?;
+    let top = ((levels.len() as f64 * DR_TOP_SHARE).round() as usize).max(1);
+    let rms = (levels[..top].iter().sum::<f64>() / top as f64).sqrt();
+    // A pure sine is the floor; rounding can push it a hair below
+    (peak > 0.0 && rms > 0.0).then(|| (20.0 * (peak / rms).log10()).max(0.0))
+}
+
+/// DR-meter style dynamic range in dB, averaged over channels. Heavily limited masters
+/// land around 4-7, dynamic ones above 12.
+pub fn dr_score(channels: &[Vec<f32>], sample_rate: usize) -> Option<f32> {
+    let block_length = ((sample_rate as f64 * DR_BLOCK_SECONDS) as usize).max(1);
+    let scores: Vec<f64> = channels
+        .iter()
+        .filter_map(|c| channel_dr(c, block_length))
+        .collect();
+    (!scores.is_empty()).then(|| (scores.iter().sum::<f64>() / scores.len() as f64) as f32)
+}
+
+/// Integrated loudness, loudness range, and true peak per EBU R128 / ITU-R BS.1770-4.
+pub fn measure(channels: &[Vec<f32>], sample_rate: usize) -> Loudness {
+    let segment_length = ((sample_rate as f64 * SEGMENT_SECONDS) as usize).max(1);
+    let length = channels.iter().map(|c| c.len()).min().unwrap_or(0);
+    let mut segments = vec![0.0f64; length / segment_length];
+
+    // K-weighted energy per 100 ms segment, summed over channels with their weights
+    for (index, channel) in channels.iter().enumerate() {
+        let weight = channel_weight(index, channels.len());
+        if weight == 0.0 {
+            continue;
+        }
+        let [mut shelf, mut high_pass] = k_weighting(sample_rate);
+        for (segment, chunk) in segments
+            .iter_mut()
+            .zip(channel.chunks_exact(segment_length))
+        {
+            let energy: f64 = chunk
+                .iter()
+                .map(|&s| high_pass.process(shelf.process(s as f64)))
+                .map(|y| y * y)
+                .sum();
+            *segment += weight * energy;
+        }
+    }
+
+    let peak = channels.iter().map(|c| true_peak(c)).fold(0.0f32, f32::max);
+
+    Loudness {
+        integrated_lufs: integrated(&windows(&segments, segment_length, BLOCK_SEGMENTS))
+            .map(|l| l as f32),
+        loudness_range: loudness_range(&windows(&segments, segment_length, SHORT_TERM_SEGMENTS))
+            .map(|l| l as f32),
+        true_peak_db: (peak > 0.0).then(|| 20.0 * peak.log10()),
+    }
+}
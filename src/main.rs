@@ -1,252 +1,2225 @@
-mod frequency_bands;
-mod utils;
+mod cli;
+mod memory;
+mod progress;
 
 use std::{
+    collections::{HashMap, HashSet},
     env, fs,
+    io::IsTerminal,
     path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
 };
 
-use frequency_bands::{SpectrumMetrics, get_bands};
-use utils::get_samples;
-
-use crate::{
-    frequency_bands::{
-        calculate_band_energies, calculate_band_positions, calculate_loudness,
-        calculate_zero_crossing_rate, print_duration, print_histogram_bar, print_spectrum_position,
-        print_spread_bar,
+use clap::{CommandFactory, Parser};
+use notify::{EventKind, RecursiveMode, Watcher};
+use rust_audio_analysis::{
+    aggregate,
+    analysis::{Analyzer, CancelToken},
+    assertions::{self, Constraints, Failure},
+    beats, browse, clips,
+    config::Config,
+    derived, envelope, export,
+    filter::{self, is_ignored},
+    frequency_bands::{self, FrequencyBand, SpectrumMetrics, Transform, get_bands},
+    history,
+    i18n::{self, Msg, tr},
+    import, library, metrics, normalization,
+    output::{self, DisplayFormat, Sink},
+    presets::{self, Preset},
+    probe, release, report, similarity,
+    sorting::{self, SortKey},
+    spectrogram,
+    standardize::Distribution,
+    stereo, summary, tags, tempo, testset, tonal,
+    utils::{
+        self, ANALYSIS_VERSION, AUDIO_EXTENSIONS, CacheOptions, CachedMetrics, adopt_renamed_files,
+        audio_files_in, cache_exceeds_limit, cache_file_path, content_hash, decode_audio,
+        entry_is_stale, file_unchanged, get_samples, is_audio_file, load_cache, lock_cache,
+        modified_secs, save_cache, should_analyze, truncate_filename, unix_now,
     },
-    utils::{CachedMetrics, load_cache, save_cache, should_analyze, truncate_filename},
+    walkthrough,
+    warnings::{self, WarningRule},
 };
 
+use cli::{AnalyzeArgs, Cli, Command, IgnoreAction, LibraryAction};
+use progress::AnalysisProgress;
+
+// Keeps color on when stdout is a pipe, as for the child behind --pager
+const COLOR_FORCE: &str = "CLICOLOR_FORCE";
+// How long --watch waits after the last file event before analyzing
+const WATCH_SETTLE: Duration = Duration::from_secs(2);
+// Files that change while being analyzed are likely still downloading or exporting; they
+// get this many more tries, this far apart, before being reported instead of cached
+const WRITE_RETRIES: usize = 2;
+const WRITE_RETRY_WAIT: Duration = Duration::from_secs(3);
+
+#[global_allocator]
+static ALLOCATOR: memory::PeakTracker = memory::PeakTracker;
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let cli = Cli::parse();
+    let color = !cli.no_color
+        && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && (std::io::stdout().is_terminal()
+            || env::var_os(COLOR_FORCE).is_some_and(|value| !value.is_empty() && value != "0"));
+    // Paged runs happen in a child process whose output goes to the pager
+    if cli.analyze.pager
+        && cli.command.is_none()
+        && std::io::stdout().is_terminal()
+        && let Some(code) = run_paged(color)
+    {
+        std::process::exit(code);
+    }
+    i18n::set_lang(cli.lang.or_else(i18n::detect_lang).unwrap_or_default());
+    frequency_bands::set_reproducible(cli.reproducible);
+    frequency_bands::set_color(color);
+    frequency_bands::set_screen_reader(cli.screen_reader);
+    let mut config = Config::load(cli.profile_name.as_deref()).unwrap_or_else(|e| {
+        eprintln!("{}", tr(Msg::ConfigError, &[&e]));
+        std::process::exit(2);
+    });
+    let cache_options = cli.cache.options(&config);
+    if let Some(ffmpeg) = cli.ffmpeg.clone().or(config.ffmpeg.clone()) {
+        utils::set_ffmpeg(ffmpeg);
+    }
+    if let Some(index) = cli.stream {
+        utils::set_audio_stream(index);
+    }
+    utils::set_skip_bad_frames(cli.skip_bad_frames);
+    match derived::compile(&config.derived_metrics, config.band_count()) {
+        Ok(compiled) => derived::set_derived(compiled),
+        Err(e) => {
+            eprintln!("{}", tr(Msg::ConfigError, &[&e]));
+            std::process::exit(2);
+        }
+    }
 
-    let target_path = if args.len() == 2 {
-        PathBuf::from(&args[1])
-    } else {
-        env::current_dir().expect("Failed to get current directory")
-    };
+    if let Some(path) = &cli.explain_run {
+        if let Err(e) = walkthrough::explain_run(path, config.bands.as_deref()) {
+            eprintln!("{}", tr(Msg::Error, &[&e]));
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    match cli.command {
+        Some(Command::Import { sources, directory }) => {
+            let target_path = resolve_directory(directory);
+            import_metrics(&target_path, &sources, &cache_options);
+        }
+        Some(Command::Library { action }) => run_library(action, &mut config, &cache_options),
+        Some(Command::Ignore { action }) => run_ignore(action, &mut config),
+        Some(Command::Completions { shell }) => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+        }
+        Some(Command::Explain { metric }) => match metric.as_deref().and_then(metrics::find_metric)
+        {
+            Some(metric) => metrics::print_explanation(metric),
+            None => {
+                for (i, metric) in metrics::METRICS.iter().enumerate() {
+                    if i > 0 {
+                        println!();
+                    }
+                    metrics::print_explanation(metric);
+                }
+            }
+        },
+        Some(Command::Similar {
+            reference,
+            count,
+            library,
+        }) => find_similar(&reference, count, library, &config, &cache_options),
+        Some(Command::Report {
+            track,
+            html,
+            target,
+            curve,
+        }) => report_track(
+            &track,
+            html.as_deref(),
+            target.as_deref(),
+            &curve,
+            &config,
+            &cache_options,
+        ),
+        Some(Command::Tonal { directory, curve }) => {
+            let target_path = resolve_directory(directory);
+            compare_tonal_balance(&target_path, &curve, &config, &cache_options);
+        }
+        Some(Command::Assert {
+            directory,
+            constraints,
+            target,
+        }) => {
+            let target_path = resolve_directory(Some(directory));
+            let preset = load_target(target.as_deref(), &config);
+            let constraints = constraints.or(&preset.constraints);
+            run_assertions(
+                &target_path,
+                &constraints,
+                &preset.rules,
+                &config,
+                &cache_options,
+            );
+        }
+        Some(Command::Browse { directory, library }) => {
+            browse_directory(directory, library, &config, &cache_options);
+        }
+        Some(Command::Summary {
+            directory,
+            library,
+            share,
+        }) => summarize_directory(directory, library, share, &config, &cache_options),
+        Some(Command::ExportAggregate {
+            directory,
+            library,
+            output,
+        }) => export_aggregate(
+            directory,
+            library,
+            output.as_deref(),
+            &config,
+            &cache_options,
+        ),
+        Some(Command::GenTestset { directory }) => match testset::generate(&directory) {
+            Ok(written) => {
+                for path in &written {
+                    println!("{}", tr(Msg::Wrote, &[&path.display()]));
+                }
+                println!(
+                    "{}",
+                    tr(
+                        Msg::CompareTestset,
+                        &[&env!("CARGO_PKG_NAME"), &directory.display()]
+                    )
+                );
+            }
+            Err(e) => {
+                eprintln!("{}", tr(Msg::Error, &[&e]));
+                std::process::exit(1);
+            }
+        },
+        Some(Command::Streams { file }) => list_streams(&file),
+        Some(Command::Spectrogram {
+            file,
+            height,
+            width,
+        }) => print_spectrogram(&file, height, width),
+        Some(Command::History { directory, library }) => {
+            let history = history::load_history(&history::history_path(&config));
+            let (key, name) = if library {
+                (
+                    library::LIBRARY_HISTORY_KEY.to_string(),
+                    tr(Msg::Library, &[]),
+                )
+            } else {
+                let target_path = resolve_directory(directory);
+                (
+                    history::history_key(&target_path),
+                    target_path.display().to_string(),
+                )
+            };
+            match history.get(&key) {
+                Some(snapshots) if !snapshots.is_empty() => {
+                    history::print_history(&name, snapshots)
+                }
+                _ if library => println!("{}", tr(Msg::NoLibrarySnapshots, &[])),
+                _ => println!("{}", tr(Msg::NoSnapshots, &[&name])),
+            }
+        }
+        Some(Command::Loudnorm { directory, targets }) => {
+            let target_path = resolve_directory(directory);
+            suggest_loudnorm(&target_path, &targets, &config, &cache_options);
+        }
+        Some(Command::Note { track, text }) => {
+            let note = (!text.is_empty()).then_some(text);
+            annotate_track(&track, &cache_options, |entry| entry.note = note);
+        }
+        Some(Command::Rate { track, stars }) => {
+            let rating = (stars > 0).then_some(stars);
+            annotate_track(&track, &cache_options, |entry| entry.rating = rating);
+        }
+        Some(Command::CheckRelease {
+            directory,
+            tolerances,
+        }) => {
+            let target_path = resolve_directory(Some(directory));
+            check_release(&target_path, &tolerances, &config, &cache_options);
+        }
+        Some(Command::Man) => {
+            let mut page = Vec::new();
+            if clap_mangen::Man::new(Cli::command())
+                .render(&mut page)
+                .is_ok()
+            {
+                print!("{}", String::from_utf8_lossy(&page));
+                print!("{}", metrics::man_section());
+            }
+        }
+        None => {
+            let target_path = resolve_directory(cli.directory);
+            analyze_directory(&target_path, &config, &cache_options, &cli.analyze);
+            if cli.analyze.watch {
+                watch_directory(&target_path, &config, &cache_options, &cli.analyze);
+            }
+        }
+    }
+}
+
+fn run_library(action: LibraryAction, config: &mut Config, cache_options: &CacheOptions) {
+    match action {
+        LibraryAction::Add { path } => match library::add_root(config, &path) {
+            Ok(root) => {
+                save_config(config);
+                println!("{}", tr(Msg::RootAdded, &[&root.display()]));
+            }
+            Err(e) => {
+                eprintln!("{}", tr(Msg::Error, &[&e]));
+                std::process::exit(1);
+            }
+        },
+        LibraryAction::Remove { path } => match library::remove_root(config, &path) {
+            Some(root) => {
+                save_config(config);
+                println!("{}", tr(Msg::RootRemoved, &[&root.display()]));
+            }
+            None => {
+                eprintln!("{}", tr(Msg::NotARoot, &[&path.display()]));
+                std::process::exit(1);
+            }
+        },
+        LibraryAction::List => {
+            if config.library_roots.is_empty() {
+                println!("{}", tr(Msg::NoRoots, &[]));
+            }
+            for root in &config.library_roots {
+                if root.is_dir() {
+                    println!("{}", root.display());
+                } else {
+                    println!("{}  {}", root.display(), tr(Msg::RootMissing, &[]));
+                }
+            }
+        }
+        LibraryAction::Scan { snapshot } => {
+            let db_file = library::library_db_path(config);
+            let _lock = lock_cache(&db_file);
+            let mut db = load_cache(&db_file);
+
+            for root in &config.library_roots {
+                if !root.is_dir() {
+                    eprintln!("{}", tr(Msg::SkippingMissingRoot, &[&root.display()]));
+                    continue;
+                }
+                let cache = analyze_directory(root, config, cache_options, &AnalyzeArgs::default());
+                library::merge_directory(&mut db, root, &cache);
+            }
+            library::prune_unregistered(&mut db, &config.library_roots);
+
+            save_cache(&db_file, &config.dir, &mut db, &CacheOptions::default());
+            println!("\n{}", tr(Msg::LibraryUpdated, &[&db.len()]));
+
+            if snapshot {
+                let (_, tracks) = library::present_tracks(&db, &config.library_roots);
+                record_snapshot(
+                    library::LIBRARY_HISTORY_KEY.to_string(),
+                    &tracks.iter().collect::<Vec<_>>(),
+                    config,
+                    true,
+                );
+            }
+        }
+        LibraryAction::Show => {
+            let db = load_cache(&library::library_db_path(config));
+            for (root, entries) in library::entries_by_root(&db, &config.library_roots) {
+                println!("\n{}", root.display());
+                frequency_bands::print_rule(80);
+                for entry in entries {
+                    let metrics = entry.to_metrics();
+                    let warnings = warnings::file_warnings(&metrics, &config.warning_rules);
+                    output::display_metrics(
+                        entry,
+                        &metrics,
+                        &warnings,
+                        config.bands.as_deref(),
+                        false,
+                        None,
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn run_ignore(action: IgnoreAction, config: &mut Config) {
+    match action {
+        IgnoreAction::Add { pattern } => {
+            let pattern = filter::ignore_pattern(&pattern);
+            if config.ignore.contains(&pattern) {
+                println!("{}", tr(Msg::AlreadyIgnored, &[&pattern]));
+                return;
+            }
+            config.ignore.push(pattern.clone());
+            save_config(config);
+            println!("{}", tr(Msg::Ignoring, &[&pattern]));
+        }
+        IgnoreAction::Remove { pattern } => {
+            // Files were stored by their absolute path, so look for that form too
+            let absolute = filter::ignore_pattern(&pattern);
+            let Some(index) = config
+                .ignore
+                .iter()
+                .position(|p| *p == pattern || *p == absolute)
+            else {
+                eprintln!("{}", tr(Msg::NotIgnored, &[&pattern]));
+                std::process::exit(1);
+            };
+            let removed = config.ignore.remove(index);
+            save_config(config);
+            println!("{}", tr(Msg::Unignored, &[&removed]));
+        }
+        IgnoreAction::List => {
+            if config.ignore.is_empty() {
+                println!("{}", tr(Msg::NothingIgnored, &[]));
+            }
+            for pattern in &config.ignore {
+                println!("{}", pattern);
+            }
+        }
+    }
+}
+
+fn save_config(config: &Config) {
+    if let Err(e) = config.save() {
+        eprintln!("{}", tr(Msg::ConfigSaveError, &[&e]));
+        std::process::exit(1);
+    }
+}
+
+fn resolve_directory(directory: Option<PathBuf>) -> PathBuf {
+    let target_path =
+        directory.unwrap_or_else(|| env::current_dir().expect("Failed to get current directory"));
 
     if !target_path.is_dir() {
-        eprintln!("Usage: {} [directory]", args[0]);
-        eprintln!("If no path is provided, analyzes all MP3s in the current directory");
+        eprintln!("{}", tr(Msg::NotADirectory, &[&target_path.display()]));
+        eprintln!("{}", tr(Msg::DirectoryUsage, &[]));
         std::process::exit(1);
     }
 
-    analyze_directory(&target_path);
+    target_path
 }
 
-fn analyze_directory(dir_path: &Path) {
-    let cache_file = dir_path.join("file_calc_cache.json");
+fn import_metrics(dir_path: &Path, sources: &[PathBuf], cache_options: &CacheOptions) {
+    let cache_file = cache_file_path(dir_path, cache_options);
+    let _lock = lock_cache(&cache_file);
+    let mut cache = load_cache(&cache_file);
+
+    // Expand folders into the JSON and log files they contain
+    let mut files: Vec<PathBuf> = Vec::new();
+    for source in sources {
+        if source.is_dir() {
+            let Ok(entries) = fs::read_dir(source) else {
+                eprintln!("{}", tr(Msg::ReadDirError, &[&source.display()]));
+                continue;
+            };
+            let mut found: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension()
+                        .and_then(|ext| ext.to_str())
+                        .map(|ext| ["json", "log", "txt"].contains(&ext.to_lowercase().as_str()))
+                        .unwrap_or(false)
+                })
+                .collect();
+            found.sort();
+            files.extend(found);
+        } else {
+            files.push(source.clone());
+        }
+    }
+
+    let mut imported = 0;
+    for file in &files {
+        // Never re-import our own cache into itself
+        let is_own_cache = file
+            .file_name()
+            .map(|name| name.to_string_lossy().starts_with("file_calc_cache.json"))
+            .unwrap_or(false);
+        if is_own_cache && file.parent() == Some(dir_path) {
+            continue;
+        }
 
+        match import::parse_import_file(file) {
+            Ok(source) => imported += import::merge_import(source, &mut cache),
+            Err(e) => eprintln!("{}", tr(Msg::Skipping, &[&file.display(), &e])),
+        }
+    }
+
+    if imported > 0 {
+        save_cache(&cache_file, dir_path, &mut cache, cache_options);
+    }
+
+    let noun = tr(
+        if imported == 1 {
+            Msg::Entry
+        } else {
+            Msg::Entries
+        },
+        &[],
+    );
+    println!(
+        "{}",
+        tr(
+            Msg::Imported,
+            &[&imported, &noun, &files.len(), &cache_file.display()]
+        )
+    );
+}
+
+fn analyze_directory(
+    dir_path: &Path,
+    config: &Config,
+    cache_options: &CacheOptions,
+    args: &AnalyzeArgs,
+) -> HashMap<String, CachedMetrics> {
+    let cache_file = cache_file_path(dir_path, cache_options);
+    let _lock = lock_cache(&cache_file);
     let mut cache = load_cache(&cache_file);
 
-    // Read all entries in the directory
-    let entries = match fs::read_dir(dir_path) {
-        Ok(entries) => entries,
+    // A mastering target adds its own rules, and its loudness limits become warnings
+    let preset = load_target(args.target.as_deref(), config);
+    let extract = args.extract_target().unwrap_or_else(|e| {
+        eprintln!("{}", tr(Msg::Error, &[&e]));
+        std::process::exit(2);
+    });
+
+    // Collect all supported audio files that pass --include, --exclude, and --ext and
+    // aren't on the ignore list
+    let filter = args.file_filter();
+    let mut ignored = 0;
+    let audio_files: Vec<PathBuf> = match audio_files_in(dir_path) {
+        Ok(files) => files
+            .into_iter()
+            .filter(|path| filter.matches(path))
+            .filter(|path| {
+                let skip = is_ignored(&config.ignore, path);
+                ignored += skip as usize;
+                !skip
+            })
+            .collect(),
         Err(e) => {
-            eprintln!("Error reading directory: {}", e);
-            return;
+            eprintln!("{}", tr(Msg::ReadDirError, &[&e]));
+            return without_ignored(cache, dir_path, config);
         }
     };
 
-    // Collect all MP3 files
-    let mut mp3_files: Vec<PathBuf> = entries
-        .filter_map(|entry| entry.ok())
-        .map(|entry| entry.path())
+    if audio_files.is_empty() {
+        if ignored > 0 {
+            println!("{}", tr(Msg::AllIgnored, &[&dir_path.display(), &ignored]));
+        } else if filter.is_empty() {
+            println!(
+                "{}",
+                tr(
+                    Msg::NoAudioFiles,
+                    &[&AUDIO_EXTENSIONS.join(", "), &dir_path.display()]
+                )
+            );
+        } else {
+            println!("{}", tr(Msg::NoMatchingFiles, &[&dir_path.display()]));
+        }
+        return without_ignored(cache, dir_path, config);
+    }
+
+    // Terminal display plus any --out files, all fed from the same pass
+    let precision = args.precision.or(config.export_precision);
+    let mut sinks = output::open_sinks(&args.output_targets(), precision).unwrap_or_else(|e| {
+        eprintln!("{}", tr(Msg::Error, &[&e]));
+        std::process::exit(1);
+    });
+    let show_table = !args.quiet && args.format == DisplayFormat::Table;
+
+    if show_table {
+        println!(
+            "\n{}\n",
+            tr(Msg::FoundFiles, &[&audio_files.len(), &dir_path.display()])
+        );
+        frequency_bands::print_rule(80);
+        if args.legend {
+            output::print_legend(config.bands.as_deref());
+        }
+    }
+
+    let rules: Vec<WarningRule> = preset.with_rules(&config.warning_rules);
+
+    for metric in warnings::unknown_rules(&rules) {
+        eprintln!(
+            "{}",
+            tr(
+                Msg::UnknownRuleMetric,
+                &[&metric, &warnings::RULE_METRICS.join(", ")]
+            )
+        );
+    }
+
+    let mut updated = false;
+    // By file, so a --lazy correction replaces the cached verdict
+    let mut flagged: HashMap<String, bool> = HashMap::new();
+
+    let renames = adopt_renamed_files(&mut cache, &audio_files);
+    for (old_name, new_name) in &renames {
+        if show_table {
+            println!("{}", tr(Msg::Renamed, &[&old_name, &new_name]));
+        }
+        updated = true;
+    }
+
+    // Decoding and FFTs run in parallel; the cache and display are updated afterwards in
+    // directory order. --lazy leaves checking the files themselves until cached results
+    // are out, and analyzes once they are.
+    let stale: Vec<&Path> = audio_files
+        .iter()
         .filter(|path| {
-            path.extension()
-                .and_then(|ext| ext.to_str())
-                .map(|ext| ext.eq_ignore_ascii_case("mp3"))
-                .unwrap_or(false)
+            let filename = path.file_name().unwrap().to_string_lossy();
+            if args.lazy {
+                cache.get(filename.as_ref()).is_none_or(|cached| {
+                    entry_is_stale(cached, config.band_count(), args.transform)
+                })
+            } else {
+                should_analyze(path, &cache, &filename, config.band_count(), args.transform)
+            }
         })
+        .map(PathBuf::as_path)
         .collect();
+    let mut analyzed = if args.lazy {
+        HashMap::new()
+    } else {
+        analyze_paths(&stale, config, args)
+    };
 
-    if mp3_files.is_empty() {
-        println!("No MP3 files found in directory: {}", dir_path.display());
-        return;
+    if !args.quiet {
+        match args.format {
+            DisplayFormat::Table => sinks.insert(
+                0,
+                Box::new(output::TableSink {
+                    band_layout: config.bands.clone(),
+                    show_tags: args.show_tags,
+                    average_bands: args
+                        .relative
+                        .then(|| folder_profile(&audio_files, &analyzed, &cache)),
+                    standardized: args.standardize,
+                }),
+            ),
+            DisplayFormat::Json => sinks.insert(0, Box::new(output::JsonLinesSink::new(precision))),
+        }
     }
 
-    mp3_files.sort();
+    // --standardize reports z-scores against the library, or this folder when there is none
+    let distribution = args.standardize.then(|| {
+        let library = load_cache(&library::library_db_path(config));
+        let (distribution, source) = if library.is_empty() {
+            (
+                folder_distribution(&audio_files, &analyzed, &cache),
+                Msg::ThisFolder,
+            )
+        } else {
+            let tracks: Vec<SpectrumMetrics> =
+                library.values().map(CachedMetrics::to_metrics).collect();
+            (Distribution::from_tracks(&tracks), Msg::TheLibrary)
+        };
+        if show_table {
+            println!(
+                "{}\n",
+                tr(Msg::ZScores, &[&distribution.track_count, &tr(source, &[])])
+            );
+        }
+        distribution
+    });
 
-    println!(
-        "\nFound {} MP3 file(s) in {}\n",
-        mp3_files.len(),
-        dir_path.display()
-    );
-    println!("{}", "=".repeat(80));
+    // --offset and --limit pick which tracks are reported; every file is still cached
+    let window = args.offset..args.offset.saturating_add(args.limit.unwrap_or(usize::MAX));
+    let mut show = |order: Vec<&PathBuf>,
+                    analyzed: &mut HashMap<&Path, (Result<SpectrumMetrics, String>, usize)>,
+                    cache: &mut HashMap<String, CachedMetrics>| {
+        for (position, file_path) in order.into_iter().enumerate() {
+            let shown = window.contains(&position);
+            let filename = file_path.file_name().unwrap().to_string_lossy().to_string();
 
-    let mut updated = false;
+            if let Some((result, peak)) = analyzed.remove(file_path.as_path()) {
+                match result {
+                    Ok(metrics) => {
+                        // Keep values imported from other tools that we don't compute ourselves
+                        let previous = cache.remove(&filename);
+                        let entry = cache_entry(
+                            file_path,
+                            &filename,
+                            &metrics,
+                            previous,
+                            args.transform,
+                            args.tolerance.or(config.cache_tolerance),
+                        );
+                        cache.insert(filename.clone(), entry);
+                        updated = true;
 
-    for file_path in mp3_files.iter() {
-        let filename = file_path.file_name().unwrap().to_string_lossy().to_string();
+                        let entry = &cache[&filename];
+                        let warnings =
+                            analysis_warnings(entry, &metrics, &rules, &preset.constraints);
+                        flagged.insert(filename.clone(), !warnings.is_empty());
+                        if !shown {
+                            continue;
+                        }
+                        write_track(
+                            &mut sinks,
+                            distribution.as_ref(),
+                            entry,
+                            &metrics,
+                            &warnings,
+                        );
+                        if show_table && args.max_memory.is_some() {
+                            let megabytes = format!("{:.1}", peak as f64 / (1024.0 * 1024.0));
+                            println!("{}", tr(Msg::PeakMemory, &[&megabytes]));
+                        }
+                    }
+                    Err(e) if !show_table => {
+                        eprintln!("{}", tr(Msg::AnalyzeFailed, &[&filename, &e]))
+                    }
+                    Err(e) => println!(
+                        "\n{:<40}  {}",
+                        truncate_filename(&filename, 40),
+                        tr(Msg::AnalyzeFailedRow, &[&e])
+                    ),
+                }
+            } else {
+                // Use cached data
+                if let Some(cached) = cache.get_mut(&filename) {
+                    updated |= cached.touch();
+                    // Entries from before content hashing pick one up so later renames are caught
+                    if cached.content_hash.is_none() && cached.file_size.is_some() {
+                        cached.content_hash = content_hash(file_path);
+                        updated = true;
+                    }
+                    // Likewise for entries from before tags were read
+                    if args.show_tags && cached.tags.is_none() {
+                        cached.tags = tags::read_tags(file_path);
+                        updated |= cached.tags.is_some();
+                    }
+                    let metrics = cached.to_metrics();
+                    let warnings = analysis_warnings(cached, &metrics, &rules, &preset.constraints);
+                    flagged.insert(filename.clone(), !warnings.is_empty());
+                    if !shown {
+                        continue;
+                    }
+                    write_track(
+                        &mut sinks,
+                        distribution.as_ref(),
+                        cached,
+                        &metrics,
+                        &warnings,
+                    );
+                }
+            }
+        }
+    };
+
+    let mut retimed = Vec::new();
+    if args.lazy {
+        // Cached results go out while a background thread checks that their files haven't
+        // changed; the ones that have are analyzed with the new files and appended
+        let pending: HashSet<&Path> = stale.iter().copied().collect();
+        let cached_files: Vec<PathBuf> = audio_files
+            .iter()
+            .filter(|path| !pending.contains(path.as_path()))
+            .cloned()
+            .collect();
+        let recorded: Vec<(Option<u64>, Option<u64>, Option<String>)> = cached_files
+            .iter()
+            .map(|path| {
+                let cached = &cache[path.file_name().unwrap().to_string_lossy().as_ref()];
+                (
+                    cached.file_size,
+                    cached.modified_time,
+                    cached.content_hash.clone(),
+                )
+            })
+            .collect();
 
-        // Check if we need to analyze this file
-        let needs_analysis = should_analyze(&file_path, &cache, &filename);
-
-        if needs_analysis {
-            if let Ok(metrics) = analyze_frequency_distribution(&file_path) {
-                // Get file metadata
-                let metadata = fs::metadata(&file_path).ok();
-                let file_size = metadata.as_ref().and_then(|m| Some(m.len()));
-                let modified_time = metadata.as_ref().and_then(|m| {
-                    m.modified().ok().and_then(|t| {
-                        t.duration_since(std::time::UNIX_EPOCH)
-                            .ok()
-                            .map(|d| d.as_secs())
+        let verdicts: Vec<Verification> = std::thread::scope(|scope| {
+            let verifier = scope.spawn(|| {
+                cached_files
+                    .iter()
+                    .zip(&recorded)
+                    .map(|(path, (size, time, hash))| {
+                        verify_file(path, *size, *time, hash.as_deref())
                     })
-                });
+                    .collect()
+            });
+            let order = display_order(&cached_files, &analyzed, &cache, &args.sort_by);
+            show(order, &mut analyzed, &mut cache);
+            verifier.join().unwrap()
+        });
 
-                // Update cache
-                cache.insert(
-                    filename.clone(),
-                    CachedMetrics {
-                        filename: filename.clone(),
-                        centroid: metrics.centroid,
-                        spread: metrics.spread,
-                        zero_crossing_rate: metrics.zero_crossing_rate,
-                        loudness: metrics.loudness,
-                        duration_seconds: metrics.duration_seconds,
-                        band_percentages: metrics.band_percentages.clone(),
-                        file_size,
-                        modified_time,
-                    },
-                );
-                updated = true;
+        let mut changed = pending;
+        for (path, verdict) in cached_files.iter().zip(verdicts) {
+            match verdict {
+                Verification::Unchanged => {}
+                Verification::Retimed(time) => retimed.push((path.clone(), time)),
+                Verification::Changed => {
+                    changed.insert(path);
+                }
+            }
+        }
+        let changed_files: Vec<PathBuf> = audio_files
+            .iter()
+            .filter(|path| changed.contains(path.as_path()))
+            .cloned()
+            .collect();
+        if !changed_files.is_empty() {
+            if show_table {
+                println!("\n{}", tr(Msg::ChangedSinceCached, &[&changed_files.len()]));
+            }
+            let paths: Vec<&Path> = changed_files.iter().map(PathBuf::as_path).collect();
+            let mut analyzed = analyze_paths(&paths, config, args);
+            let order = display_order(&changed_files, &analyzed, &cache, &args.sort_by);
+            show(order, &mut analyzed, &mut cache);
+        }
+    } else {
+        let order = display_order(&audio_files, &analyzed, &cache, &args.sort_by);
+        show(order, &mut analyzed, &mut cache);
+    }
 
-                display_metrics(&filename, &metrics);
-            } else {
-                println!(
-                    "\n{:<40}  ERROR: Failed to analyze",
-                    truncate_filename(&filename, 40)
-                );
+    // Touched files whose contents turned out the same only need their new time
+    for (path, time) in retimed {
+        let filename = path.file_name().unwrap().to_string_lossy();
+        if let Some(cached) = cache.get_mut(filename.as_ref()) {
+            cached.modified_time = time;
+            updated = true;
+        }
+    }
+
+    for sink in sinks.iter_mut() {
+        if let Err(e) = sink.finish() {
+            eprintln!("{}", tr(Msg::OutputError, &[&e]));
+        }
+    }
+
+    let shown_end = window.end.min(audio_files.len());
+    if show_table && (window.start > 0 || shown_end < audio_files.len()) {
+        if window.start < shown_end {
+            println!(
+                "\n{}",
+                tr(
+                    Msg::ShowingTracks,
+                    &[&(window.start + 1), &shown_end, &audio_files.len()]
+                )
+            );
+        } else {
+            println!(
+                "\n{}",
+                tr(
+                    Msg::NoTracksPastOffset,
+                    &[&window.start, &audio_files.len()]
+                )
+            );
+        }
+    }
+
+    if args.write_tags {
+        write_metric_tags(&audio_files, &mut cache, show_table);
+        updated = true;
+    }
+
+    // Save cache if updated or over its size limit
+    if updated || cache_exceeds_limit(&cache_file, cache_options) {
+        save_cache(&cache_file, dir_path, &mut cache, cache_options);
+    }
+
+    if args.snapshot {
+        record_snapshot(
+            history::history_key(dir_path),
+            &present_entries(dir_path, &cache),
+            config,
+            show_table,
+        );
+    }
+
+    if let Some(grid_path) = &args.bar_grid {
+        export_bar_grid(grid_path, &audio_files, &cache, config);
+    }
+
+    if let Some((segment, clip_dir)) = &extract {
+        extract_segments(*segment, clip_dir, &audio_files, &cache);
+    }
+
+    if let Some(envelope_path) = &args.envelope {
+        export_envelopes(envelope_path, &audio_files, args.envelope_points);
+    }
+
+    if let Some(playlist_path) = &args.playlist {
+        export_playlist(
+            playlist_path,
+            &audio_files,
+            &cache,
+            &args.playlist_by,
+            args.descending,
+        );
+    }
+
+    if !args.probe.is_empty() {
+        report_probes(&args.probe, &audio_files);
+    }
+
+    if let Some(seconds) = args.segments {
+        report_segments(
+            seconds,
+            &audio_files,
+            &analyzer(config, args.transform, args.max_memory, None),
+        );
+    }
+
+    let mut flagged = flagged.values().filter(|&&warned| warned).count();
+    if let Some(reference_dir) = &args.swap_reference {
+        flagged += check_channel_order(reference_dir, &audio_files);
+    }
+
+    if flagged > 0 {
+        if show_table {
+            println!("\n{}", tr(Msg::FilesWithWarnings, &[&flagged]));
+        }
+        if args.strict {
+            std::process::exit(1);
+        }
+    }
+
+    without_ignored(cache, dir_path, config)
+}
+
+/// The cache as returned to summaries and the library: ignored files keep their entries
+/// on disk, but nothing is built from them.
+fn without_ignored(
+    mut cache: HashMap<String, CachedMetrics>,
+    dir_path: &Path,
+    config: &Config,
+) -> HashMap<String, CachedMetrics> {
+    if !config.ignore.is_empty() {
+        cache.retain(|filename, _| !is_ignored(&config.ignore, &dir_path.join(filename)));
+    }
+    cache
+}
+
+/// Run this command again without `--pager`, its output piped into `$PAGER` (by default
+/// `less`, which git's `FRX` options make quit on short output and keep colors). Returns
+/// the exit code, or `None` if the pager can't be started.
+fn run_paged(color: bool) -> Option<i32> {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less".to_string());
+    let mut words = pager.split_whitespace();
+    let mut pager = std::process::Command::new(words.next()?)
+        .args(words)
+        .env("LESS", env::var_os("LESS").unwrap_or("FRX".into()))
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .ok()?;
+
+    let mut run = std::process::Command::new(env::current_exe().ok()?);
+    run.args(env::args_os().skip(1).filter(|arg| arg != "--pager"))
+        .stdout(pager.stdin.take()?);
+    if color {
+        run.env(COLOR_FORCE, "1");
+    }
+    let status = run.status();
+    // Wait for the reader to quit the pager even if the run failed to start
+    let _ = pager.wait();
+    Some(status.ok()?.code().unwrap_or(1))
+}
+
+/// The average band profile over every analyzed or cached file, for `--relative`.
+fn folder_profile(
+    files: &[PathBuf],
+    analyzed: &HashMap<&Path, (Result<SpectrumMetrics, String>, usize)>,
+    cache: &HashMap<String, CachedMetrics>,
+) -> Vec<f32> {
+    let profiles: Vec<&[f32]> = files
+        .iter()
+        .filter_map(|path| match analyzed.get(path.as_path()) {
+            Some((Ok(metrics), _)) => Some(metrics.band_percentages.as_slice()),
+            Some(_) => None,
+            None => cache
+                .get(path.file_name()?.to_string_lossy().as_ref())
+                .map(|entry| entry.band_percentages.as_slice()),
+        })
+        .collect();
+    summary::average_profile(&profiles)
+}
+
+/// The distribution of every analyzed or cached file, for `--standardize` without a
+/// library.
+fn folder_distribution(
+    files: &[PathBuf],
+    analyzed: &HashMap<&Path, (Result<SpectrumMetrics, String>, usize)>,
+    cache: &HashMap<String, CachedMetrics>,
+) -> Distribution {
+    let cached: Vec<SpectrumMetrics> = files
+        .iter()
+        .filter(|path| !analyzed.contains_key(path.as_path()))
+        .filter_map(|path| cache.get(path.file_name()?.to_string_lossy().as_ref()))
+        .map(CachedMetrics::to_metrics)
+        .collect();
+    let tracks: Vec<&SpectrumMetrics> = analyzed
+        .values()
+        .filter_map(|(result, _)| result.as_ref().ok())
+        .chain(&cached)
+        .collect();
+    Distribution::from_tracks(&tracks)
+}
+
+/// Hand a track to every sink, as z-scores when `--standardize` gave a distribution.
+/// Warnings stay those of the raw readings.
+fn write_track(
+    sinks: &mut [Box<dyn Sink>],
+    distribution: Option<&Distribution>,
+    entry: &CachedMetrics,
+    metrics: &SpectrumMetrics,
+    warnings: &[String],
+) {
+    let standardized =
+        distribution.and_then(|d| Some((d.standardize(entry)?, d.standardize(metrics)?)));
+    let (entry, metrics) = match &standardized {
+        Some((entry, metrics)) => (entry, metrics),
+        None => (entry, metrics),
+    };
+    for sink in sinks.iter_mut() {
+        sink.write_track(entry, metrics, warnings);
+    }
+}
+
+/// `files` in `--sort-by` order, or directory order without keys. Files that failed to
+/// analyze go last.
+fn display_order<'a>(
+    files: &'a [PathBuf],
+    analyzed: &HashMap<&Path, (Result<SpectrumMetrics, String>, usize)>,
+    cache: &HashMap<String, CachedMetrics>,
+    keys: &[SortKey],
+) -> Vec<&'a PathBuf> {
+    let mut order: Vec<&PathBuf> = files.iter().collect();
+    if keys.is_empty() {
+        return order;
+    }
+
+    let name = |path: &Path| path.file_name().unwrap().to_string_lossy().to_string();
+    let cached: HashMap<&Path, SpectrumMetrics> = files
+        .iter()
+        .filter(|path| !analyzed.contains_key(path.as_path()))
+        .filter_map(|path| Some((path.as_path(), cache.get(&name(path))?.to_metrics())))
+        .collect();
+    let reading = |path: &Path| match analyzed.get(path) {
+        Some((Ok(metrics), _)) => Some(metrics),
+        Some(_) => None,
+        None => cached.get(path),
+    };
+    order.sort_by(|a, b| match (reading(a), reading(b)) {
+        (Some(x), Some(y)) => sorting::compare_tracks(keys, (&name(a), x), (&name(b), y)),
+        (x, y) => y.is_some().cmp(&x.is_some()).then_with(|| a.cmp(b)),
+    });
+    order
+}
+
+/// `--watch`: analyze audio files as they appear in or change under `dir_path`, updating
+/// the cache after each batch. Runs until interrupted.
+fn watch_directory(
+    dir_path: &Path,
+    config: &Config,
+    cache_options: &CacheOptions,
+    args: &AnalyzeArgs,
+) {
+    let (sender, events) = mpsc::channel();
+    let watching = notify::recommended_watcher(sender).and_then(|mut watcher| {
+        watcher.watch(dir_path, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    });
+    // Dropping the watcher stops the events
+    let _watcher = watching.unwrap_or_else(|e| {
+        eprintln!("{}", tr(Msg::WatchError, &[&dir_path.display(), &e]));
+        std::process::exit(1);
+    });
+
+    let precision = args.precision.or(config.export_precision);
+    let show_table = !args.quiet && args.format == DisplayFormat::Table;
+    let mut sinks: Vec<Box<dyn Sink>> = Vec::new();
+    if !args.quiet {
+        sinks.push(match args.format {
+            DisplayFormat::Table => Box::new(output::TableSink {
+                band_layout: config.bands.clone(),
+                show_tags: args.show_tags,
+                average_bands: None,
+                standardized: false,
+            }),
+            DisplayFormat::Json => Box::new(output::JsonLinesSink::new(precision)),
+        });
+    }
+    if let Some(path) = &args.watch_log {
+        match output::open_log(path, precision) {
+            Ok(sink) => sinks.push(sink),
+            Err(e) => {
+                eprintln!("{}", tr(Msg::Error, &[&e]));
+                std::process::exit(1);
             }
+        }
+    }
+
+    let filter = args.file_filter();
+    let watched = |path: &Path| {
+        is_audio_file(path) && filter.matches(path) && !is_ignored(&config.ignore, path)
+    };
+    if show_table {
+        println!("\n{}", tr(Msg::Watching, &[&dir_path.display()]));
+    }
+
+    let mut pending: HashSet<PathBuf> = HashSet::new();
+    loop {
+        // Copies and downloads arrive as a run of writes, so a batch waits for a quiet spell
+        let event = if pending.is_empty() {
+            events.recv().map_err(|_| RecvTimeoutError::Disconnected)
         } else {
-            // Use cached data
-            if let Some(cached) = cache.get(&filename) {
-                let metrics = SpectrumMetrics {
-                    centroid: cached.centroid,
-                    spread: cached.spread,
-                    zero_crossing_rate: cached.zero_crossing_rate,
-                    loudness: cached.loudness,
-                    duration_seconds: cached.duration_seconds,
-                    band_percentages: cached.band_percentages.clone(),
-                };
-                display_metrics(&filename, &metrics);
+            events.recv_timeout(WATCH_SETTLE)
+        };
+        match event {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    pending.extend(event.paths.into_iter().filter(|path| watched(path)));
+                }
+            }
+            Ok(Err(e)) => eprintln!("{}", tr(Msg::WatchEventError, &[&e])),
+            Err(RecvTimeoutError::Timeout) => {
+                let mut batch: Vec<PathBuf> =
+                    pending.drain().filter(|path| path.is_file()).collect();
+                batch.sort();
+                analyze_batch(dir_path, &batch, config, cache_options, args, &mut sinks);
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+// Analyze the files of one `--watch` batch that aren't cached yet or have changed, and
+// show and cache their results
+fn analyze_batch(
+    dir_path: &Path,
+    batch: &[PathBuf],
+    config: &Config,
+    cache_options: &CacheOptions,
+    args: &AnalyzeArgs,
+    sinks: &mut [Box<dyn Sink>],
+) {
+    let show_table = !args.quiet && args.format == DisplayFormat::Table;
+    let preset = load_target(args.target.as_deref(), config);
+    let rules = preset.with_rules(&config.warning_rules);
+    let cache_file = cache_file_path(dir_path, cache_options);
+    let _lock = lock_cache(&cache_file);
+    let mut cache = load_cache(&cache_file);
+
+    // Renames are matched against the whole folder, so a copy doesn't take over the
+    // original's entry
+    let present = audio_files_in(dir_path).unwrap_or_default();
+    let mut updated = false;
+    for (old_name, new_name) in adopt_renamed_files(&mut cache, &present) {
+        if batch.iter().any(|path| path.ends_with(&new_name)) && show_table {
+            println!("{}", tr(Msg::Renamed, &[&old_name, &new_name]));
+        }
+        updated = true;
+    }
+
+    let stale: Vec<&Path> = batch
+        .iter()
+        .filter(|path| {
+            let filename = path.file_name().unwrap().to_string_lossy();
+            should_analyze(path, &cache, &filename, config.band_count(), args.transform)
+        })
+        .map(PathBuf::as_path)
+        .collect();
+    for (file_path, (result, _)) in analyze_paths(&stale, config, args) {
+        let filename = file_path.file_name().unwrap().to_string_lossy().to_string();
+        match result {
+            Ok(metrics) => {
+                let previous = cache.remove(&filename);
+                let entry = cache_entry(
+                    file_path,
+                    &filename,
+                    &metrics,
+                    previous,
+                    args.transform,
+                    args.tolerance.or(config.cache_tolerance),
+                );
+                let warnings = analysis_warnings(&entry, &metrics, &rules, &preset.constraints);
+                write_track(sinks, None, &entry, &metrics, &warnings);
+                cache.insert(filename, entry);
+                updated = true;
             }
+            Err(e) => eprintln!("{}", tr(Msg::AnalyzeFailed, &[&filename, &e])),
         }
     }
 
-    // Save cache if updated
     if updated {
-        save_cache(&cache_file, &cache);
+        save_cache(&cache_file, dir_path, &mut cache, cache_options);
     }
 }
 
-fn display_metrics(filename: &str, metrics: &SpectrumMetrics) {
-    println!("\n{:<40}", truncate_filename(filename, 40));
+/// How a cached file compares with its entry, as checked by `--lazy`.
+enum Verification {
+    Unchanged,
+    Retimed(Option<u64>), // Touched, but the same size and contents
+    Changed,
+}
 
-    // Display spectral centroid
-    print!("Centroid: ");
-    print_spectrum_position(metrics.centroid);
-    print!(" ({:>5.1})", metrics.centroid);
+fn verify_file(
+    path: &Path,
+    size: Option<u64>,
+    time: Option<u64>,
+    hash: Option<&str>,
+) -> Verification {
+    if file_unchanged(path, size, time) {
+        return Verification::Unchanged;
+    }
+    let Ok(metadata) = fs::metadata(path) else {
+        return Verification::Changed;
+    };
+    if hash.is_some() && size == Some(metadata.len()) && content_hash(path).as_deref() == hash {
+        Verification::Retimed(modified_secs(&metadata))
+    } else {
+        Verification::Changed
+    }
+}
 
-    // Display spectral spread
-    print!("  │  Spread: ");
-    print_spread_bar(metrics.spread);
-    print!(" ({:>5.1})", metrics.spread);
+/// `analyze_files` keyed by path, as the display loop looks results up.
+fn analyze_paths<'a>(
+    files: &[&'a Path],
+    config: &Config,
+    args: &AnalyzeArgs,
+) -> HashMap<&'a Path, (Result<SpectrumMetrics, String>, usize)> {
+    files
+        .iter()
+        .copied()
+        .zip(analyze_files(
+            files,
+            config,
+            args.transform,
+            args.jobs,
+            args.max_memory,
+            !args.quiet && !args.no_progress,
+        ))
+        .collect()
+}
 
-    // Display zero-crossing rate
-    print!("  │  ZCR: ");
-    print_spread_bar(metrics.zero_crossing_rate);
-    print!(" ({:>5.1})", metrics.zero_crossing_rate);
+/// Analyze files on a pool of `jobs` threads (one per core by default), in input order,
+/// each with its peak memory use in bytes. `max_memory` is shared between the workers.
+fn analyze_files(
+    files: &[&Path],
+    config: &Config,
+    transform: Transform,
+    jobs: Option<usize>,
+    max_memory: Option<u64>,
+    show_progress: bool,
+) -> Vec<(Result<SpectrumMetrics, String>, usize)> {
+    // A redrawn bar is noise to a screen reader
+    let progress = AnalysisProgress::new(
+        files.len(),
+        show_progress && !frequency_bands::is_screen_reader(),
+    );
 
-    // Display loudness
-    print!("  │  Loudness: {:>6.1} dB", metrics.loudness);
+    // Errors become strings here since boxed errors can't cross threads
+    let results =
+        analyzer(config, transform, max_memory, jobs).map_paths(files, |analyzer, path| {
+            let file = progress.start_file(path);
+            let mut retries = 0;
+            loop {
+                let before = file_state(path);
+                let (result, peak) = memory::measure_peak(|| {
+                    analyzer
+                        .analyze_with_progress(path, &CancelToken::new(), |stage| file.stage(stage))
+                        .map_err(|e| e.to_string())
+                });
+                if file_state(path) == before {
+                    break (result, peak);
+                }
+                if retries == WRITE_RETRIES {
+                    break (Err(tr(Msg::StillBeingWritten, &[])), peak);
+                }
+                retries += 1;
+                std::thread::sleep(WRITE_RETRY_WAIT);
+            }
+        });
+    progress.finish();
+    results
+}
 
-    // Display track duration
-    print!("  │  Length: ");
-    print_duration(metrics.duration_seconds);
+// Size and modification time, to notice files written to while they're analyzed
+fn file_state(path: &Path) -> Option<(u64, Option<std::time::SystemTime>)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
 
-    // Display individual band percentages as histogram
-    println!("Frequency Bands:");
-    for pct in &metrics.band_percentages {
-        print!("  ");
-        print_histogram_bar(*pct);
+/// The analysis settings from the config and command line; exits on an invalid layout.
+fn analyzer(
+    config: &Config,
+    transform: Transform,
+    max_memory: Option<u64>,
+    jobs: Option<usize>,
+) -> Analyzer {
+    Analyzer::builder()
+        .bands(config.bands.clone())
+        .transform(transform)
+        .max_memory(max_memory)
+        .jobs(jobs)
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("{}", tr(Msg::Error, &[&e]));
+            std::process::exit(1);
+        })
+}
+
+/// A fresh cache entry for `metrics`, carrying over imported values from `previous`, and
+/// its readings too where they're within `tolerance`.
+fn cache_entry(
+    file_path: &Path,
+    filename: &str,
+    metrics: &SpectrumMetrics,
+    previous: Option<CachedMetrics>,
+    transform: Transform,
+    tolerance: Option<f32>,
+) -> CachedMetrics {
+    let metadata = fs::metadata(file_path).ok();
+    let file_size = metadata.as_ref().map(|m| m.len());
+    let modified_time = metadata.as_ref().and_then(modified_secs);
+
+    let mut entry = CachedMetrics {
+        filename: filename.to_string(),
+        centroid: metrics.centroid.0,
+        centroid_hz: metrics.centroid_hz.map(f32::from),
+        exact_centroid: metrics.exact_centroid.map(f32::from),
+        spread: metrics.spread.0,
+        zero_crossing_rate: metrics.zero_crossing_rate,
+        loudness: metrics.loudness.0,
+        duration_seconds: metrics.duration_seconds,
+        band_percentages: metrics.band_percentages.clone(),
+        ratios: metrics.ratios.clone(),
+        file_size,
+        modified_time,
+        content_hash: content_hash(file_path),
+        tags: tags::read_tags(file_path),
+        // Imported readings only fill in what couldn't be measured, e.g. silent tracks
+        integrated_lufs: metrics
+            .integrated_lufs
+            .map(f32::from)
+            .or(previous.as_ref().and_then(|p| p.integrated_lufs)),
+        loudness_range: metrics
+            .loudness_range
+            .map(f32::from)
+            .or(previous.as_ref().and_then(|p| p.loudness_range)),
+        true_peak_db: metrics
+            .true_peak_db
+            .map(f32::from)
+            .or(previous.as_ref().and_then(|p| p.true_peak_db)),
+        dr_score: metrics.dr_score.map(f32::from),
+        dynamics: metrics.dynamics.clone(),
+        clipping: metrics.clipping.clone(),
+        silence: metrics.silence.clone(),
+        imported_from: previous.as_ref().and_then(|p| p.imported_from.clone()),
+        last_accessed: Some(unix_now()),
+        tempo: metrics.tempo.clone(),
+        key: metrics.key.clone(),
+        groove: metrics.groove.clone(),
+        rhythm_pattern: metrics.rhythm_pattern.clone(),
+        chorus: metrics.chorus.clone(),
+        intro: metrics.intro.clone(),
+        outro: metrics.outro.clone(),
+        tilt: metrics.tilt,
+        rolloff: metrics.rolloff.map(f32::from),
+        bandwidth: metrics.bandwidth.clone(),
+        onset_strength: metrics.onset_strength.map(f32::from),
+        audio_stream: utils::selected_audio_stream(),
+        windowed: metrics.windowed,
+        reproducible: frequency_bands::is_reproducible(),
+        sharpness: metrics.sharpness,
+        roughness: metrics.roughness,
+        stereo: metrics.stereo.clone(),
+        surround: metrics.surround.clone(),
+        lfe: metrics.lfe.clone(),
+        stream: metrics.stream.clone(),
+        transform,
+        analysis_version: ANALYSIS_VERSION,
+        note: previous.as_ref().and_then(|p| p.note.clone()),
+        rating: previous.as_ref().and_then(|p| p.rating),
+        unknown_fields: previous
+            .as_ref()
+            .map(|p| p.unknown_fields.clone())
+            .unwrap_or_default(),
+    };
+    if let (Some(previous), Some(tolerance)) = (&previous, tolerance) {
+        entry.settle_jitter(previous, tolerance);
     }
+    entry
 }
 
-fn analyze_frequency_distribution(
-    path: &Path,
-) -> Result<SpectrumMetrics, Box<dyn std::error::Error>> {
-    let (all_samples, sample_rate) = get_samples(path)?;
+/// Rule warnings plus any measured loudness limit the file breaks.
+fn analysis_warnings(
+    entry: &CachedMetrics,
+    metrics: &SpectrumMetrics,
+    rules: &[WarningRule],
+    constraints: &Constraints,
+) -> Vec<String> {
+    let mut warnings = warnings::file_warnings(metrics, rules);
+    warnings.extend(
+        assertions::check_constraints(entry, constraints)
+            .iter()
+            .filter(|failure| failure.value.is_some())
+            .map(Failure::describe),
+    );
+    warnings
+}
 
-    if all_samples.is_empty() {
-        return Err("No audio data found".into());
+/// The named `--target` preset, or an empty one when none was given.
+fn load_target(name: Option<&str>, config: &Config) -> Preset {
+    let Some(name) = name else {
+        return Preset::default();
     };
+    presets::load_preset(name, &config.dir).unwrap_or_else(|e| {
+        eprintln!("{}", tr(Msg::Error, &[&e]));
+        std::process::exit(1);
+    })
+}
 
-    // Calculate duration in seconds
-    let duration_seconds = all_samples.len() as f32 / sample_rate as f32;
+fn run_assertions(
+    dir_path: &Path,
+    constraints: &Constraints,
+    rules: &[WarningRule],
+    config: &Config,
+    cache_options: &CacheOptions,
+) {
+    let args = AnalyzeArgs {
+        quiet: true,
+        ..AnalyzeArgs::default()
+    };
+    let cache = analyze_directory(dir_path, config, cache_options, &args);
 
-    // Calculate loudness (RMS in dB)
-    let loudness = calculate_loudness(&all_samples);
+    let entries = present_entries(dir_path, &cache);
+    let report = assertions::check_all(&entries, constraints, rules);
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("{}", tr(Msg::ReportError, &[&e])),
+    }
 
-    let bands = get_bands(sample_rate);
+    eprintln!(
+        "{}",
+        tr(
+            Msg::FilesChecked,
+            &[&report.files_checked, &report.failures.len()]
+        )
+    );
+    if !report.passed {
+        std::process::exit(1);
+    }
+}
 
-    // Calculate energy distribution
-    let band_energies = calculate_band_energies(&all_samples, sample_rate, &bands)?;
+fn check_release(
+    dir_path: &Path,
+    tolerances: &release::ReleaseTolerances,
+    config: &Config,
+    cache_options: &CacheOptions,
+) {
+    let args = AnalyzeArgs {
+        quiet: true,
+        ..AnalyzeArgs::default()
+    };
+    let cache = analyze_directory(dir_path, config, cache_options, &args);
+    let tracks = present_entries(dir_path, &cache);
+    if tracks.is_empty() {
+        eprintln!("{}", tr(Msg::NoAnalyzedTracks, &[&dir_path.display()]));
+        std::process::exit(1);
+    }
 
-    // Calculate zero-crossing rate
-    let zcr = calculate_zero_crossing_rate(&all_samples);
+    println!(
+        "\n{}\n",
+        tr(Msg::ReleaseCheck, &[&dir_path.display(), &tracks.len()])
+    );
+    let results = release::check_release(&tracks, tolerances);
+    release::print_report(&results);
 
-    // Calculate total energy
-    let total_energy: f64 = band_energies.iter().sum();
+    let failed = results.iter().filter(|r| !r.passed).count();
+    if failed > 0 {
+        println!("\n{}", tr(Msg::RulesFailed, &[&failed, &results.len()]));
+        std::process::exit(1);
+    }
+    println!("\n{}", tr(Msg::RulesPassed, &[&results.len()]));
+}
 
-    // Convert to percentages
-    let band_percentages: Vec<f32> = band_energies
-        .iter()
-        .map(|&energy| {
-            if total_energy > 0.0 {
-                (energy / total_energy * 100.0) as f32
+/// Append the current averages to the history under `key`: the directory's
+/// `history_key`, or the library's.
+fn record_snapshot(key: String, tracks: &[&CachedMetrics], config: &Config, verbose: bool) {
+    if tracks.is_empty() {
+        return;
+    }
+    let path = history::history_path(config);
+    let mut history = history::load_history(&path);
+    let snapshots = history.entry(key).or_default();
+    snapshots.push(history::take_snapshot(tracks));
+    let count = snapshots.len();
+
+    match history::save_history(&path, &history) {
+        Ok(()) if verbose => println!("\n{}", tr(Msg::SnapshotRecorded, &[&count])),
+        Ok(()) => {}
+        Err(e) => eprintln!("{}", tr(Msg::HistorySaveError, &[&e])),
+    }
+}
+
+fn suggest_loudnorm(
+    dir_path: &Path,
+    targets: &normalization::LoudnormTargets,
+    config: &Config,
+    cache_options: &CacheOptions,
+) {
+    let args = AnalyzeArgs {
+        quiet: true,
+        ..AnalyzeArgs::default()
+    };
+    let cache = analyze_directory(dir_path, config, cache_options, &args);
+
+    let mut unmeasured = 0;
+    for entry in present_entries(dir_path, &cache) {
+        match normalization::loudnorm_filter(entry, targets) {
+            Some(filter) => println!("{}\t{}", entry.filename, filter),
+            None => {
+                eprintln!("{}", tr(Msg::MissingLoudness, &[&entry.filename]));
+                unmeasured += 1;
+            }
+        }
+    }
+    if unmeasured > 0 {
+        eprintln!("{}", tr(Msg::LoudnessSkipped, &[&unmeasured]));
+    }
+}
+
+fn summarize_directory(
+    directory: Option<PathBuf>,
+    library: bool,
+    share: bool,
+    config: &Config,
+    cache_options: &CacheOptions,
+) {
+    let (dir_path, tracks) = query_tracks(directory, library, config, cache_options);
+
+    let name = if library {
+        tr(Msg::Library, &[])
+    } else {
+        dir_path
+            .canonicalize()
+            .ok()
+            .and_then(|path| path.file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_else(|| dir_path.display().to_string())
+    };
+    let summary = summary::summarize(&tracks.iter().collect::<Vec<_>>());
+    if share {
+        print!("{}", summary::share_card(&name, &summary));
+    } else {
+        summary::print_summary(&name, &summary);
+    }
+}
+
+fn print_spectrogram(file: &Path, height: usize, width: Option<usize>) {
+    let audio = match decode_audio(file) {
+        Ok(audio) => audio,
+        Err(e) => {
+            eprintln!("{}", tr(Msg::DecodeFailed, &[&file.display(), &e]));
+            std::process::exit(1);
+        }
+    };
+    let samples = utils::downmix(&audio.channels);
+    if samples.len() <= frequency_bands::FRAME_SIZE {
+        eprintln!("{} is too short for a spectrogram", file.display());
+        std::process::exit(1);
+    }
+
+    // Fill the terminal, or a standard 80 columns when piped
+    let width = width.unwrap_or_else(|| {
+        let columns = match std::io::stdout().is_terminal() {
+            true => ratatui::crossterm::terminal::size().map_or(80, |(columns, _)| columns),
+            false => 80,
+        };
+        spectrogram::plot_width(columns as usize)
+    });
+    let heatmap = spectrogram::compute(&samples, audio.sample_rate, width.max(1), height.max(1));
+    for line in spectrogram::render(&heatmap) {
+        println!("{}", line);
+    }
+}
+
+fn browse_directory(
+    directory: Option<PathBuf>,
+    library: bool,
+    config: &Config,
+    cache_options: &CacheOptions,
+) {
+    if !std::io::stderr().is_terminal() {
+        eprintln!("{}", tr(Msg::BrowseNeedsTerminal, &[]));
+        std::process::exit(1);
+    }
+    let (dir_path, tracks) = query_tracks(directory, library, config, cache_options);
+
+    let bands = get_bands(44100, config.bands.as_deref());
+    match browse::run(&tracks, &dir_path, &bands) {
+        Ok(queued) => {
+            for path in queued {
+                println!("{}", path.display());
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", tr(Msg::Error, &[&e]));
+            std::process::exit(1);
+        }
+    }
+}
+
+fn load_curve(name: &str, config: &Config) -> tonal::TonalCurve {
+    tonal::load_curve(name, &config.dir).unwrap_or_else(|e| {
+        eprintln!("{}", tr(Msg::Error, &[&e]));
+        std::process::exit(1);
+    })
+}
+
+fn compare_tonal_balance(
+    dir_path: &Path,
+    curve_name: &str,
+    config: &Config,
+    cache_options: &CacheOptions,
+) {
+    let curve = load_curve(curve_name, config);
+    let args = AnalyzeArgs {
+        quiet: true,
+        ..AnalyzeArgs::default()
+    };
+    let cache = analyze_directory(dir_path, config, cache_options, &args);
+    let tracks = present_entries(dir_path, &cache);
+
+    println!(
+        "\n{}\n",
+        tr(Msg::TonalBalance, &[&curve_name, &curve.description])
+    );
+    let mut layout: Option<(usize, Vec<FrequencyBand>)> = None;
+    for track in tracks {
+        // Band edges depend on the sample rate when they reach Nyquist
+        let sample_rate = track
+            .stream
+            .as_ref()
+            .map_or(44100, |s| s.sample_rate as usize);
+        if layout.as_ref().is_none_or(|(rate, _)| *rate != sample_rate) {
+            let bands = get_bands(sample_rate, config.bands.as_deref());
+            let header: Vec<String> = bands
+                .iter()
+                .map(|b| format!("{:>12}", format!("{}-{}", b.low_hz, b.high_hz)))
+                .collect();
+            println!(
+                "{:<40} {:>5} {}",
+                "File (dB per band)",
+                "Score",
+                header.join("")
+            );
+            println!("{}", "=".repeat(46 + 12 * bands.len()));
+            layout = Some((sample_rate, bands));
+        }
+        let Some((_, bands)) = &layout else {
+            continue;
+        };
+
+        let deviations = tonal::band_deviations(&track.band_percentages, &curve.band_shares(bands));
+        let columns: Vec<String> = deviations
+            .iter()
+            .map(|d| match d {
+                Some(db) => format!("{:>+12.1}", db),
+                None => format!("{:>12}", "-"),
+            })
+            .collect();
+        println!(
+            "{:<40} {:>5.0}{}",
+            truncate_filename(&track.filename, 40),
+            tonal::balance_score(&deviations),
+            columns.join("")
+        );
+    }
+}
+
+/// Cache entries for files still in the directory, by filename. The cache can still hold
+/// entries for files deleted since the last save.
+fn list_streams(file: &Path) {
+    let streams = match utils::list_audio_streams(file) {
+        Ok(streams) => streams,
+        Err(e) => {
+            eprintln!("{}", tr(Msg::Error, &[&e]));
+            std::process::exit(1);
+        }
+    };
+    if streams.is_empty() {
+        println!("{}", tr(Msg::NoStreams, &[&file.display()]));
+        return;
+    }
+    let selected = utils::selected_audio_stream().unwrap_or(0);
+    for (index, stream) in streams.iter().enumerate() {
+        println!(
+            "{} {}  {:<8} {:>6} Hz  {} ch  {}",
+            if index == selected { "*" } else { " " },
+            index,
+            if stream.codec.is_empty() {
+                "?"
             } else {
-                0.0
+                &stream.codec
+            },
+            stream.sample_rate,
+            stream.channels,
+            stream.language.as_deref().unwrap_or("")
+        );
+    }
+}
+
+fn export_aggregate(
+    directory: Option<PathBuf>,
+    library: bool,
+    output: Option<&Path>,
+    config: &Config,
+    cache_options: &CacheOptions,
+) {
+    let (dir_path, tracks) = query_tracks(directory, library, config, cache_options);
+    let tracks: Vec<&CachedMetrics> = tracks.iter().collect();
+    if tracks.len() < aggregate::MIN_TRACKS {
+        let source = if library {
+            tr(Msg::TheLibrary, &[])
+        } else {
+            dir_path.display().to_string()
+        };
+        eprintln!(
+            "{}",
+            tr(
+                Msg::AggregateTooSmall,
+                &[&aggregate::MIN_TRACKS, &tracks.len(), &source]
+            )
+        );
+        std::process::exit(1);
+    }
+
+    let json = match serde_json::to_string_pretty(&aggregate::aggregate(&tracks)) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("{}", tr(Msg::AggregateEncodeError, &[&e]));
+            std::process::exit(1);
+        }
+    };
+    match output {
+        Some(path) => match fs::write(path, json + "\n") {
+            Ok(()) => println!(
+                "{}",
+                tr(Msg::AggregateWritten, &[&tracks.len(), &path.display()])
+            ),
+            Err(e) => {
+                eprintln!("{}", tr(Msg::WriteError, &[&path.display(), &e]));
+                std::process::exit(1);
             }
+        },
+        None => println!("{}", json),
+    }
+}
+
+/// The tracks a query command reads, with the directory their filenames are relative to:
+/// one directory's, analyzed as needed, or with `--library` every registered root's from
+/// the database `library scan` keeps. Exits if there are none.
+fn query_tracks(
+    directory: Option<PathBuf>,
+    library: bool,
+    config: &Config,
+    cache_options: &CacheOptions,
+) -> (PathBuf, Vec<CachedMetrics>) {
+    if library {
+        let db = load_cache(&library::library_db_path(config));
+        let (base, tracks) = library::present_tracks(&db, &config.library_roots);
+        if tracks.is_empty() {
+            eprintln!(
+                "{}; {}",
+                tr(Msg::NoAnalyzedTracks, &[&tr(Msg::TheLibrary, &[])]),
+                tr(Msg::RunLibraryScan, &[])
+            );
+            std::process::exit(1);
+        }
+        return (base, tracks);
+    }
+
+    let dir_path = resolve_directory(directory);
+    let args = AnalyzeArgs {
+        quiet: true,
+        ..AnalyzeArgs::default()
+    };
+    let cache = analyze_directory(&dir_path, config, cache_options, &args);
+    let tracks: Vec<CachedMetrics> = present_entries(&dir_path, &cache)
+        .into_iter()
+        .cloned()
+        .collect();
+    if tracks.is_empty() {
+        eprintln!("{}", tr(Msg::NoAnalyzedTracks, &[&dir_path.display()]));
+        std::process::exit(1);
+    }
+    (dir_path, tracks)
+}
+
+fn present_entries<'a>(
+    dir_path: &Path,
+    cache: &'a HashMap<String, CachedMetrics>,
+) -> Vec<&'a CachedMetrics> {
+    let mut entries: Vec<&CachedMetrics> = cache
+        .values()
+        .filter(|entry| dir_path.join(&entry.filename).is_file())
+        .collect();
+    entries.sort_by(|a, b| a.filename.cmp(&b.filename));
+    entries
+}
+
+/// The directory holding a track and the track's cache key, exiting if it isn't a file path.
+fn split_track_path(track: &Path) -> (PathBuf, String) {
+    let Some(filename) = track.file_name().map(|n| n.to_string_lossy().to_string()) else {
+        eprintln!("{}", tr(Msg::NotAFile, &[&track.display()]));
+        std::process::exit(1);
+    };
+    let dir_path = match track.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    (dir_path, filename)
+}
+
+/// Update a track's note or rating in its directory's cache. Tracks that haven't been
+/// analyzed yet get a placeholder entry that the next scan fills in.
+fn annotate_track(
+    track: &Path,
+    cache_options: &CacheOptions,
+    apply: impl FnOnce(&mut CachedMetrics),
+) {
+    if !track.is_file() {
+        eprintln!("{}", tr(Msg::NotAFile, &[&track.display()]));
+        std::process::exit(1);
+    }
+    let (dir_path, filename) = split_track_path(track);
+    let cache_file = cache_file_path(&dir_path, cache_options);
+    let _lock = lock_cache(&cache_file);
+    let mut cache = load_cache(&cache_file);
+
+    let entry = cache
+        .entry(filename.clone())
+        .or_insert_with(|| CachedMetrics {
+            filename: filename.clone(),
+            loudness: -60.0,
+            ..Default::default()
+        });
+    apply(entry);
+    let summary = match (entry.rating, &entry.note) {
+        (Some(rating), Some(note)) => format!("{}  {}", output::stars(rating), note),
+        (Some(rating), None) => output::stars(rating),
+        (None, Some(note)) => note.clone(),
+        (None, None) => "no annotations".to_string(),
+    };
+
+    save_cache(&cache_file, &dir_path, &mut cache, cache_options);
+    println!("{}: {}", filename, summary);
+}
+
+fn report_track(
+    track: &Path,
+    html: Option<&Path>,
+    target: Option<&str>,
+    curve_name: &str,
+    config: &Config,
+    cache_options: &CacheOptions,
+) {
+    let (dir_path, filename) = split_track_path(track);
+    let preset = load_target(target, config);
+    let curve = load_curve(curve_name, config);
+    let cache_file = cache_file_path(&dir_path, cache_options);
+    let _lock = lock_cache(&cache_file);
+    let mut cache = load_cache(&cache_file);
+
+    let transform = Transform::default();
+    if should_analyze(track, &cache, &filename, config.band_count(), transform) {
+        let metrics = match analyzer(config, transform, None, None).analyze(track) {
+            Ok(metrics) => metrics,
+            Err(e) => {
+                eprintln!("{}", tr(Msg::AnalyzeTrackFailed, &[&track.display(), &e]));
+                std::process::exit(1);
+            }
+        };
+        let previous = cache.remove(&filename);
+        let entry = cache_entry(
+            track,
+            &filename,
+            &metrics,
+            previous,
+            transform,
+            config.cache_tolerance,
+        );
+        cache.insert(filename.clone(), entry);
+        save_cache(&cache_file, &dir_path, &mut cache, cache_options);
+    }
+
+    let entry = &cache[&filename];
+    let rules: Vec<WarningRule> = preset.with_rules(&config.warning_rules);
+    let issues = analysis_warnings(entry, &entry.to_metrics(), &rules, &preset.constraints);
+
+    let sample_rate = entry
+        .stream
+        .as_ref()
+        .map_or(44100, |s| s.sample_rate as usize);
+    let bands = get_bands(sample_rate, config.bands.as_deref());
+    let card = report::build_report(entry, &bands, curve_name, &curve, &issues);
+
+    match html {
+        Some(path) => match report::write_html(path, &card) {
+            Ok(()) => println!("{}", tr(Msg::ReportWritten, &[&path.display()])),
+            Err(e) => {
+                eprintln!("{}", tr(Msg::ReportError, &[&e]));
+                std::process::exit(1);
+            }
+        },
+        None => report::print_report(&card),
+    }
+}
+
+fn find_similar(
+    reference: &Path,
+    count: usize,
+    library: bool,
+    config: &Config,
+    cache_options: &CacheOptions,
+) {
+    let (dir_path, filename) = split_track_path(reference);
+
+    let cache = load_cache(&cache_file_path(&dir_path, cache_options));
+
+    // Analyze the reference on the spot if it hasn't been scanned yet
+    let reference_metrics = match cache.get(&filename) {
+        Some(cached) if !cached.band_percentages.is_empty() => cached.to_metrics(),
+        _ => match analyzer(config, Transform::default(), None, None).analyze(reference) {
+            Ok(metrics) => metrics,
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    tr(Msg::AnalyzeTrackFailed, &[&reference.display(), &e])
+                );
+                std::process::exit(1);
+            }
+        },
+    };
+
+    // The library database is keyed by full path, directory caches by filename
+    let (candidates, reference_key) = if library {
+        let key = fs::canonicalize(reference)
+            .unwrap_or_else(|_| reference.to_path_buf())
+            .to_string_lossy()
+            .to_string();
+        (load_cache(&library::library_db_path(config)), key)
+    } else {
+        (cache, filename.clone())
+    };
+
+    // Tracks with another band layout can't be compared band by band
+    let band_count = reference_metrics.band_percentages.len();
+    let mut ranked: Vec<(f32, &String)> = candidates
+        .iter()
+        .filter(|(name, entry)| {
+            **name != reference_key && entry.band_percentages.len() == band_count
+        })
+        .map(|(name, entry)| {
+            let distance = similarity::distance(
+                &reference_metrics,
+                &entry.to_metrics(),
+                similarity::DEFAULT_RHYTHM_WEIGHT,
+            );
+            (distance, name)
         })
         .collect();
+    ranked.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(b.1)));
+
+    if ranked.is_empty() {
+        if library {
+            println!(
+                "{}; {}",
+                tr(Msg::NoAnalyzedTracks, &[&tr(Msg::TheLibrary, &[])]),
+                tr(Msg::RunLibraryScan, &[])
+            );
+        } else {
+            println!("{}", tr(Msg::NoTracksToCompare, &[&dir_path.display()]));
+        }
+        return;
+    }
 
-    // Calculate spectral centroid (weighted average position)
-    // Map each band to a position: 0 (sub-bass) to 100 (highs)
-    let band_positions = calculate_band_positions(&bands, sample_rate);
+    // Library matches are shown from their root's name down, in a wider column
+    let width = if library { 60 } else { 40 };
+    let label = |name: &str| {
+        config
+            .library_roots
+            .iter()
+            .filter_map(|root| Path::new(name).strip_prefix(root.parent()?).ok())
+            .next()
+            .map_or_else(|| name.to_string(), |path| path.display().to_string())
+    };
+    println!("\n{}\n", tr(Msg::MostSimilar, &[&filename]));
+    let distance_label = tr(Msg::Distance, &[]);
+    for (rank, (distance, name)) in ranked.iter().take(count).enumerate() {
+        println!(
+            "{:>3}. {:<width$}  {} {:>6.1}",
+            rank + 1,
+            truncate_filename(&label(name), width),
+            distance_label,
+            distance
+        );
+    }
+}
 
-    let centroid = band_percentages
+fn extract_segments(
+    segment: clips::Segment,
+    clip_dir: &Path,
+    files: &[PathBuf],
+    cache: &HashMap<String, CachedMetrics>,
+) {
+    let mut written = 0;
+    for file_path in files {
+        let filename = file_path.file_name().unwrap().to_string_lossy().to_string();
+        let (Some(entry), Ok(audio)) = (cache.get(&filename), decode_audio(file_path)) else {
+            continue;
+        };
+        let sections = clips::find_segments(segment, &audio, entry);
+        match clips::write_clips(clip_dir, file_path, segment, &audio, &sections) {
+            Ok(paths) => written += paths.len(),
+            Err(e) => eprintln!("{}", tr(Msg::ExtractError, &[&filename, &e])),
+        }
+    }
+    println!(
+        "\n{}",
+        tr(Msg::ClipsExtracted, &[&written, &clip_dir.display()])
+    );
+}
+
+fn export_envelopes(path: &Path, files: &[PathBuf], points: usize) {
+    let tracks: Vec<(String, envelope::Envelope)> = files
         .iter()
-        .zip(band_positions.iter())
-        .map(|(pct, pos)| pct * pos)
-        .sum::<f32>()
-        / 100.0;
+        .filter_map(|file_path| {
+            let (samples, sample_rate) = get_samples(file_path).ok()?;
+            let filename = file_path.file_name()?.to_string_lossy().to_string();
+            Some((filename, envelope::envelope(&samples, sample_rate, points)))
+        })
+        .collect();
+
+    match export::write_envelopes_json(path, &tracks) {
+        Ok(()) => println!("\n{}", tr(Msg::EnvelopesWritten, &[&path.display()])),
+        Err(e) => eprintln!("{}", tr(Msg::EnvelopesError, &[&e])),
+    }
+}
+
+fn write_metric_tags(files: &[PathBuf], cache: &mut HashMap<String, CachedMetrics>, verbose: bool) {
+    let mut written = 0;
+    for file_path in files.iter().filter(|f| {
+        f.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("mp3"))
+    }) {
+        let filename = file_path.file_name().unwrap().to_string_lossy().to_string();
+        let Some(entry) = cache.get_mut(&filename) else {
+            continue;
+        };
+        if let Err(e) = tags::write_metric_tags(file_path, entry) {
+            eprintln!("{}", tr(Msg::TagWriteError, &[&filename, &e]));
+            continue;
+        }
+        written += 1;
+
+        // The new tag changes the file, which would otherwise look like an edit next run
+        if let Ok(metadata) = fs::metadata(file_path) {
+            entry.file_size = Some(metadata.len());
+            entry.modified_time = modified_secs(&metadata);
+        }
+        entry.content_hash = content_hash(file_path);
+    }
+    if verbose {
+        println!("\n{}", tr(Msg::TagsWritten, &[&written]));
+    }
+}
 
-    // Calculate spectral spread (standard deviation from centroid)
-    let variance = band_percentages
+fn export_playlist(
+    path: &Path,
+    files: &[PathBuf],
+    cache: &HashMap<String, CachedMetrics>,
+    metric: &str,
+    descending: bool,
+) {
+    let mut tracks: Vec<(Option<f32>, PathBuf, f32)> = files
         .iter()
-        .zip(band_positions.iter())
-        .map(|(pct, pos)| {
-            let diff = pos - centroid;
-            pct * diff * diff
+        .filter_map(|file| {
+            let entry = cache.get(&file.file_name()?.to_string_lossy().to_string())?;
+            let value = warnings::metric_value(&entry.to_metrics(), metric);
+            Some((value, file.clone(), entry.duration_seconds))
         })
-        .sum::<f32>()
-        / 100.0;
+        .collect();
+    // Tracks without a reading go last, in directory order
+    tracks.sort_by(|a, b| match (a.0, b.0) {
+        (Some(x), Some(y)) if descending => y.total_cmp(&x),
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    });
+
+    let entries: Vec<(PathBuf, f32)> = tracks
+        .into_iter()
+        .map(|(_, file, seconds)| (file, seconds))
+        .collect();
+    match export::write_m3u(path, &entries) {
+        Ok(()) => println!(
+            "\n{}",
+            tr(
+                Msg::PlaylistWritten,
+                &[&entries.len(), &metric, &path.display()]
+            )
+        ),
+        Err(e) => eprintln!("{}", tr(Msg::PlaylistError, &[&e])),
+    }
+}
 
-    let spread = variance.sqrt();
+fn export_bar_grid(
+    grid_path: &Path,
+    files: &[PathBuf],
+    cache: &HashMap<String, CachedMetrics>,
+    config: &Config,
+) {
+    let mut tracks = Vec::new();
 
-    // Normalize spread to 0-100 scale (typical spread ranges from 0-35)
-    let normalized_spread = (spread / 35.0 * 100.0).min(100.0);
+    for file_path in files {
+        let filename = file_path.file_name().unwrap().to_string_lossy().to_string();
+        let Ok((samples, sample_rate)) = get_samples(file_path) else {
+            continue;
+        };
 
-    Ok(SpectrumMetrics {
-        centroid,
-        spread: normalized_spread,
-        zero_crossing_rate: zcr,
-        loudness,
-        duration_seconds,
-        band_percentages,
-    })
+        // Reuse the cached tempo rather than estimating it twice
+        let bpm = match cache.get(&filename).and_then(|c| c.tempo.as_ref()) {
+            Some(tempo) => tempo.bpm,
+            None => match tempo::estimate_tempo(&samples, sample_rate) {
+                Some(tempo) => tempo.bpm,
+                None => continue,
+            },
+        };
+
+        let bands = get_bands(sample_rate, config.bands.as_deref());
+        let beats = beats::beat_positions(&samples, sample_rate, bpm);
+        let grid =
+            beats::bar_energy_grid(&samples, sample_rate, &bands, &beats, beats::BEATS_PER_BAR);
+        tracks.push((filename, bpm, grid));
+    }
+
+    match export::write_bar_grid_csv(grid_path, &tracks, config.band_count()) {
+        Ok(()) => println!("\n{}", tr(Msg::BarGridWritten, &[&grid_path.display()])),
+        Err(e) => eprintln!("{}", tr(Msg::BarGridError, &[&e])),
+    }
+}
+
+fn report_probes(frequencies: &[f32], files: &[PathBuf]) {
+    println!("\n{}\n", tr(Msg::FrequencyProbes, &[]));
+    print!("{:<40}", "");
+    for frequency in frequencies {
+        print!("  {:>9}", format!("{} Hz", frequency));
+    }
+    println!();
+
+    for file_path in files {
+        let filename = file_path.file_name().unwrap().to_string_lossy().to_string();
+        let Ok((samples, sample_rate)) = get_samples(file_path) else {
+            continue;
+        };
+
+        print!("{:<40}", truncate_filename(&filename, 40));
+        for &frequency in frequencies {
+            print!(
+                "  {:>9.1}",
+                probe::probe_level(&samples, sample_rate, frequency)
+            );
+        }
+        println!();
+    }
+}
+
+fn report_segments(seconds: f32, files: &[PathBuf], analyzer: &Analyzer) {
+    println!("\n{}", tr(Msg::Segments, &[&seconds]));
+
+    for file_path in files {
+        let filename = file_path.file_name().unwrap().to_string_lossy().to_string();
+        let segments = match analyzer.analyze_segments(file_path, seconds) {
+            Ok(segments) => segments,
+            Err(e) => {
+                eprintln!("{}", tr(Msg::SegmentError, &[&filename, &e]));
+                continue;
+            }
+        };
+
+        println!("\n{}", filename);
+        println!(
+            "  {:>6}  {:>8}  {:>6}  {:>6}  {:>6}  {:>5}  BANDS",
+            "START", "CENTROID", "SPREAD", "RMS", "LUFS", "BPM"
+        );
+        for (index, segment) in segments.iter().enumerate() {
+            let start = (index as f32 * seconds) as u32;
+            let optional =
+                |value: Option<f32>| value.map_or("-".to_string(), |v| format!("{:.1}", v));
+            println!(
+                "  {:>6}  {:>8.1}  {:>6.1}  {:>6.1}  {:>6}  {:>5}  {}",
+                format!("{}:{:02}", start / 60, start % 60),
+                segment.centroid,
+                segment.spread,
+                segment.loudness,
+                optional(segment.integrated_lufs.map(f32::from)),
+                segment
+                    .tempo
+                    .as_ref()
+                    .map_or("-".to_string(), |t| format!("{:.0}", t.bpm)),
+                summary::sparkline(&segment.band_percentages)
+            );
+        }
+    }
+}
+
+/// Returns the number of files with swapped channels.
+fn check_channel_order(reference_dir: &Path, files: &[PathBuf]) -> usize {
+    println!("\n{}\n", tr(Msg::ChannelOrder, &[&reference_dir.display()]));
+    println!("{:<40}  WARNINGS", "");
+
+    let mut swapped = 0;
+    for file_path in files {
+        let filename = file_path.file_name().unwrap().to_string_lossy().to_string();
+        let reference_path = reference_dir.join(&filename);
+
+        let status = if !reference_path.is_file() {
+            "no reference version"
+        } else {
+            match (decode_audio(file_path), decode_audio(&reference_path)) {
+                (Ok(audio), Ok(reference)) if audio.sample_rate == reference.sample_rate => {
+                    match stereo::compare_channel_order(
+                        &audio.channels,
+                        &reference.channels,
+                        audio.sample_rate,
+                    ) {
+                        stereo::ChannelOrder::Matches => "",
+                        stereo::ChannelOrder::Swapped => {
+                            swapped += 1;
+                            "swapped channels"
+                        }
+                        stereo::ChannelOrder::Inconclusive => "channel order inconclusive",
+                    }
+                }
+                (Ok(_), Ok(_)) => "sample rates differ, not compared",
+                _ => "failed to decode",
+            }
+        };
+
+        println!("{:<40}  {}", truncate_filename(&filename, 40), status);
+    }
+
+    swapped
 }
@@ -0,0 +1,61 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// Per-thread, so files analyzed in parallel each get their own figure. Cells without
+// destructors are safe to touch from inside the allocator.
+thread_local! {
+    static CURRENT: Cell<usize> = const { Cell::new(0) };
+    static PEAK: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The system allocator, counting the live bytes each thread holds.
+pub struct PeakTracker;
+
+fn track(grown: usize, shrunk: usize) {
+    let _ = CURRENT.try_with(|current| {
+        // Memory freed on another thread than it was allocated on can't go below zero
+        let now = current.get().saturating_add(grown).saturating_sub(shrunk);
+        current.set(now);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(now)));
+    });
+}
+
+unsafe impl GlobalAlloc for PeakTracker {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            track(layout.size(), 0);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        track(0, layout.size());
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            track(layout.size(), 0);
+        }
+        ptr
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            track(new_size, layout.size());
+        }
+        new_ptr
+    }
+}
+
+/// Run `f` and return its result with the most memory this thread held above its
+/// starting level meanwhile.
+pub fn measure_peak<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let baseline = CURRENT.with(Cell::get);
+    PEAK.with(|peak| peak.set(baseline));
+    let result = f();
+    (result, PEAK.with(Cell::get).saturating_sub(baseline))
+}
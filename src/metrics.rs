@@ -0,0 +1,332 @@
+/// Describes one reported metric. `explain` and the man page are generated from this table.
+pub struct MetricInfo {
+    pub key: &'static str,
+    pub name: &'static str,
+    pub meaning: &'static str,
+    pub scale: &'static str,
+    pub computation: &'static str,
+    pub normalization: &'static str,
+}
+
+pub const METRICS: &[MetricInfo] = &[
+    MetricInfo {
+        key: "centroid",
+        name: "Spectral centroid",
+        meaning: "Where the track's energy sits on the spectrum: dark, bass-led tracks score low, bright or airy tracks score high.",
+        scale: "0-100, low to high frequencies",
+        computation: "Energy-weighted average of the band positions, using each band's share of total energy as its weight.",
+        normalization: "Each band is placed at its geometric center frequency on a logarithmic axis from 20 Hz (0) to Nyquist (100).",
+    },
+    MetricInfo {
+        key: "centroid-hz",
+        name: "Exact spectral centroid",
+        meaning: "The spectrum's center of mass in Hz, without the band approximation: the frequency a track's brightness balances on.",
+        scale: "Hz, with the same 0-100 log position as the band centroid alongside",
+        computation: "Magnitude-weighted mean frequency of every FFT bin (2048-sample frames), summed over the whole track.",
+        normalization: "The position maps the Hz value logarithmically from 20 Hz (0) to Nyquist (100).",
+    },
+    MetricInfo {
+        key: "tilt",
+        name: "Spectral tilt",
+        meaning: "How fast energy falls off toward the highs, a single number for how dark or bright a master is.",
+        scale: "dB per octave; pink noise is -3, white noise 0, most masters sit between -6 and -3",
+        computation: "Slope of a least-squares line through the long-term power spectrum (8192-sample frames) on log-log axes.",
+        normalization: "Power is averaged into third-octave groups from 50 Hz to 16 kHz (or Nyquist) before fitting, so each octave counts equally.",
+    },
+    MetricInfo {
+        key: "rolloff",
+        name: "Spectral rolloff",
+        meaning: "The frequency where the top end gives out: dull or lo-fi masters roll off early, airy ones extend well past 10 kHz.",
+        scale: "Hz, up to half the sample rate",
+        computation: "Frequency of the FFT bin at which the running sum of the long-term power spectrum (2048-sample frames) reaches 85% of the total.",
+        normalization: "None; bins are about 21 Hz wide at 44.1 kHz.",
+    },
+    MetricInfo {
+        key: "ratios",
+        name: "Energy ratios",
+        meaning: "The band distribution folded into low, mid, and high thirds, and the ratios between them: easier to compare than seven band shares when asking whether a track is bass-heavy or mid-forward.",
+        scale: "Percent per third; low:high and mid prominence in dB, positive meaning more low end and more midrange respectively",
+        computation: "Each band's share goes to the third holding its geometric center: below 250 Hz, 250 Hz to 4 kHz, or above. Low:high is 10·log10(low/high); mid prominence compares the mid third with the mean of the other two.",
+        normalization: "Thirds add up to 100%. A ratio is left out when one of its thirds is empty. Rule and sort keys: low-share, mid-share, high-share, low-high, mid-prominence.",
+    },
+    MetricInfo {
+        key: "cutoff",
+        name: "Lowpass cutoff",
+        meaning: "Where a lossy encoder's lowpass chopped off the top end. A cutoff too low for the file's bitrate means it was re-encoded from a smaller file, e.g. a 128 kbps MP3 saved again at 320 or as FLAC.",
+        scale: "Hz, or half the sample rate when nothing is cut off; flagged when the bitrate whose usual lowpass matches is more than 25% under the file's",
+        computation: "The long-term power spectrum (2048-sample frames) in 200 Hz groups; from 11 kHz up, the level over the 1 kHz below each group edge is compared with everything above it, and the steepest drop wins.",
+        normalization: "A drop of at least 25 dB counts as a lowpass; gentler slopes are natural rolloff. Not measured below a 32 kHz sample rate.",
+    },
+    MetricInfo {
+        key: "spread",
+        name: "Spectral spread",
+        meaning: "How widely energy is distributed around the centroid: focused mixes score low, full-range mixes score high.",
+        scale: "0-100, focused to broad",
+        computation: "Energy-weighted standard deviation of the band positions around the centroid.",
+        normalization: "Divided by 35 (the typical maximum) and scaled to 100, capped at 100.",
+    },
+    MetricInfo {
+        key: "zcr",
+        name: "Zero-crossing rate",
+        meaning: "How often the waveform changes sign, a proxy for sharpness and noisiness such as hi-hats, distortion, or hiss.",
+        scale: "0-100, smooth to noisy",
+        computation: "Sign changes between consecutive mono samples divided by the sample count.",
+        normalization: "A rate of 0.15 crossings per sample maps to 100, capped at 100.",
+    },
+    MetricInfo {
+        key: "onset",
+        name: "Onset strength",
+        meaning: "How punchy or transient-heavy a track is: drum-led mixes score high, pads, drones, and smeared masters low.",
+        scale: "0-100, smooth to punchy",
+        computation: "Rectified log-magnitude spectral flux between 2048-sample frames (512-sample hop); the part of each frame's flux above a ~0.5s moving average counts as onset energy.",
+        normalization: "Onset energy as a percentage of total flux, so it doesn't depend on level.",
+    },
+    MetricInfo {
+        key: "loudness",
+        name: "Loudness",
+        meaning: "Average signal level of the whole track.",
+        scale: "dB relative to full scale, -60 to 0",
+        computation: "Root mean square of all mono samples, converted to decibels.",
+        normalization: "Clamped to the -60 dB to 0 dB range.",
+    },
+    MetricInfo {
+        key: "lufs",
+        name: "Integrated loudness",
+        meaning: "Perceived loudness of the whole track as streaming services and broadcasters measure it.",
+        scale: "LUFS, typically -30 to -5 (streaming targets sit around -14)",
+        computation: "EBU R128 / ITU-R BS.1770-4: K-weighted mean square in 400 ms blocks (75% overlap), summed over channels (surrounds weighted 1.41, LFE excluded).",
+        normalization: "Blocks below -70 LUFS are dropped, then blocks more than 10 LU below the remaining average.",
+    },
+    MetricInfo {
+        key: "lra",
+        name: "Loudness range",
+        meaning: "How much the loudness varies between quiet and loud passages; low values mean heavy compression.",
+        scale: "LU, typically 3-20",
+        computation: "Spread between the 10th and 95th percentiles of 3 s short-term loudness, per EBU Tech 3342.",
+        normalization: "Windows below -70 LUFS or more than 20 LU under the average are ignored.",
+    },
+    MetricInfo {
+        key: "true-peak",
+        name: "True peak",
+        meaning: "The highest level the waveform reaches between samples, which is what clips in lossy encoders and DACs.",
+        scale: "dBTP, 0 and above means inter-sample clipping",
+        computation: "Maximum absolute value of each channel upsampled 4x with a 16-tap windowed-sinc interpolator.",
+        normalization: "Converted to dB relative to full scale.",
+    },
+    MetricInfo {
+        key: "dr",
+        name: "Dynamic range (DR)",
+        meaning: "Crest factor of the loudest passages, as the DR meter reports it: brickwalled masters score low, dynamic pressings high.",
+        scale: "dB, shown rounded as DR4-DR20 (below 8 is heavily limited)",
+        computation: "Per channel, 3 s blocks give a peak and an RMS level; the second-highest peak is compared with the RMS of the loudest 20% of blocks.",
+        normalization: "RMS is scaled by sqrt(2) so a full-scale sine reads 0 dB; channels are averaged.",
+    },
+    MetricInfo {
+        key: "rms",
+        name: "RMS level",
+        meaning: "Average signal level of the whole track; limited, loudness-war masters sit high.",
+        scale: "dBFS, typically -20 to -6 (a full-scale sine is -3)",
+        computation: "Root mean square of every sample of every channel, before the mono downmix.",
+        normalization: "Converted to dB relative to full scale; not clamped, unlike Loudness.",
+    },
+    MetricInfo {
+        key: "crest",
+        name: "Crest factor",
+        meaning: "How far the peaks stand above the average level: over-compressed masters read low, untouched recordings high.",
+        scale: "dB, typically 6-20 (below 8 is heavily compressed; a sine is 3)",
+        computation: "Highest absolute sample over the RMS level, across all channels.",
+        normalization: "Converted to dB.",
+    },
+    MetricInfo {
+        key: "duration",
+        name: "Duration",
+        meaning: "Track length.",
+        scale: "minutes:seconds",
+        computation: "Decoded sample count divided by the sample rate.",
+        normalization: "None.",
+    },
+    MetricInfo {
+        key: "bands",
+        name: "Frequency bands",
+        meaning: "Share of the track's energy in each frequency band, from sub-bass to air.",
+        scale: "Percent of total energy per band, summing to 100",
+        computation: "Hann-windowed 2048-sample FFT frames with a 512-sample hop; squared magnitudes are summed per band and averaged over frames. Bands below 250 Hz are measured with 8192-sample frames instead, rescaled to match. With `--transform cqt`, energies come from a constant-Q transform (12 bins per octave from 27.5 Hz) with each bin assigned to the band containing its center.",
+        normalization: "Each band's energy is divided by the total energy across all bands.",
+    },
+    MetricInfo {
+        key: "bpm",
+        name: "Tempo",
+        meaning: "Estimated beats per minute, with half/double-time and other plausible readings listed as alternatives.",
+        scale: "BPM from 40 to 240, with a confidence from 0-100%",
+        computation: "Spectral-flux onset envelopes (full band and below 250 Hz) are autocorrelated; each periodicity peak and its octave relatives become hypotheses scored by pulse strength at one to four beats.",
+        normalization: "Scores are weighted by a log-normal prior centered on 120 BPM and divided by their total to give confidences.",
+    },
+    MetricInfo {
+        key: "key",
+        name: "Musical key",
+        meaning: "The most likely major or minor key, for harmonic mixing and playlist flow.",
+        scale: "Key name such as \"A minor\", with a profile match from -1 to 1",
+        computation: "A 12-bin chroma vector (55 Hz to 5 kHz) summed over the whole track is correlated with the Krumhansl-Kessler major and minor profiles rotated to all 12 tonics; the best of the 24 wins.",
+        normalization: "Chroma is scaled to unit length; the match is a Pearson correlation, so level doesn't matter.",
+    },
+    MetricInfo {
+        key: "swing",
+        name: "Swing",
+        meaning: "Where off-beat onsets fall between beats: straight grooves sit at 50%, shuffled and triplet-swung grooves push toward 67% and beyond.",
+        scale: "Percent of the beat, 35-85 (50 = straight, ~67 = triplet swing), plus on-beat timing spread in ms",
+        computation: "Onsets are peak-picked from the spectral-flux envelope and placed against the beat grid; off-beat onsets are averaged by strength, on-beat onsets give the mean distance from the grid.",
+        normalization: "Reported only when at least 8 off-beat onsets are found.",
+    },
+    MetricInfo {
+        key: "rhythm",
+        name: "Rhythm fingerprint",
+        meaning: "The shape of the groove over one bar, e.g. four-on-the-floor versus breakbeat; used by `similar` alongside timbre.",
+        scale: "16 steps per 4/4 bar, each the share of onset strength landing on that 16th note",
+        computation: "The spectral-flux envelope is folded onto the bar grid starting at the estimated downbeat and summed per step.",
+        normalization: "Steps are divided by their total so patterns compare independently of tempo and level.",
+    },
+    MetricInfo {
+        key: "sharpness",
+        name: "Sharpness",
+        meaning: "Perceived shrillness, driven by how much loudness sits in the upper critical bands; tracks the listening fatigue of bright mixes better than ZCR.",
+        scale: "acum; a narrow noise band at 1 kHz is about 1, bright mixes reach 2-3",
+        computation: "FFT energy is grouped into 24 Bark bands, raised to the 0.23 power for specific loudness, and averaged over frames; sharpness is the loudness-weighted mean band number, with bands above 15.8 Bark boosted as in DIN 45692.",
+        normalization: "Multiplied by 0.11 to give acum. Independent of playback level, since levels are relative to full scale.",
+    },
+    MetricInfo {
+        key: "roughness",
+        name: "Roughness",
+        meaning: "The grating, buzzy quality of fast amplitude fluctuations (around 15-300 Hz), as from distortion, beating partials, or dense saturation.",
+        scale: "asper; a 1 kHz tone fully modulated at 70 Hz is 1",
+        computation: "Bark-band envelopes from 512-sample frames with a 128-sample hop are split into ~0.75s blocks; each band's strongest modulation, weighted toward 70 Hz, gives a level swing in dB, summed across bands by relative loudness.",
+        normalization: "Approximation of Zwicker's model, scaled by a fixed calibration so the reference tone reads 1 asper; averaged over blocks.",
+    },
+    MetricInfo {
+        key: "balance",
+        name: "Stereo balance",
+        meaning: "Whether one channel is louder than the other; a lopsided track often points to a bad transfer, a misaligned cartridge, or a channel fault.",
+        scale: "dB, positive when the left channel is louder; flagged beyond ±1.5 dB",
+        computation: "Ratio of the summed squared samples of the left and right channels, in decibels.",
+        normalization: "None. Mono files have no balance reading.",
+    },
+    MetricInfo {
+        key: "mono-below",
+        name: "Mono bass crossover",
+        meaning: "Frequency below which the mix is effectively mono; a safe point to mono-sum the low end for vinyl cutting or club systems.",
+        scale: "Hz, up to 2000; shown as wide bass when even the lowest band carries stereo content",
+        computation: "Mid (L+R) and side (L-R) long-term spectra from 8192-sample frames, compared in third-octave bands upward from 20 Hz until a band's side exceeds the threshold.",
+        normalization: "A band is mono when its side energy is at least 20 dB below its mid energy; bands 60 dB below the loudest scanned band are ignored.",
+    },
+    MetricInfo {
+        key: "dual-mono",
+        name: "Dual-mono detection",
+        meaning: "Flags stereo files whose two channels carry the same signal, i.e. a mono source stored as stereo.",
+        scale: "Side energy relative to mid energy in dB; flagged below -35 dB",
+        computation: "Energy of L-R divided by the energy of L+R over the whole track. With --swap-reference, half-second L/R balance readings are also matched against the same-named file in another directory to spot swapped channels.",
+        normalization: "None. Only reported for two-channel files.",
+    },
+    MetricInfo {
+        key: "channels",
+        name: "Surround channel energy",
+        meaning: "How a 5.1 or 7.1 mix spreads across its speakers, e.g. how much lands in the LFE or the surrounds. The other metrics use the stereo fold-down.",
+        scale: "Percent of the energy summed over every channel, with each channel's own band distribution",
+        computation: "Summed squared samples per channel; band shares from each channel's own spectrum. The fold-down adds centre and surrounds to the fronts at -3 dB and leaves out the LFE (ITU-R BS.775).",
+        normalization: "Shares add up to 100%. Only reported for files with more than two channels; layouts other than 5.1 and 7.1 are averaged to mono instead of folded down.",
+    },
+    MetricInfo {
+        key: "lfe",
+        name: "LFE usage",
+        meaning: "How much of a surround mix goes to the LFE and whether it stays in the low-frequency-effects range. Full-range material in the LFE is cut off or muddied by home-theater bass management.",
+        scale: "Percent of the mix's energy, the frequency below which 99% of the LFE's energy lies, and the percent of it above 120 Hz; flagged above 5%",
+        computation: "Long-term power spectrum of the LFE channel from 8192-sample Hann frames with 50% overlap.",
+        normalization: "None. Only reported for 5.1 and 7.1 files (channel 4 is the LFE).",
+    },
+    MetricInfo {
+        key: "preview",
+        name: "Preview point",
+        meaning: "Start of the most repeated high-energy section, usually the chorus or hook; use it as a custom preview offset in players that support one.",
+        scale: "minutes:seconds from the start of the track, with the end of the four-bar phrase",
+        computation: "Each bar gets a 12-bin chroma vector and mean spectral energy; four-bar phrases are matched against every non-overlapping phrase, and the one with the most repeats weighted by energy wins.",
+        normalization: "Phrases count as repeats when their mean chroma cosine similarity reaches 0.9; energy is relative to the loudest phrase. Tracks without a tempo use a 120 BPM grid.",
+    },
+    MetricInfo {
+        key: "clipping",
+        name: "Clipping",
+        meaning: "How much of the waveform is flattened against full scale, which finds distorted encodes and over-limited masters. Flagged above 0.01% of samples.",
+        scale: "Percent of samples, with the number of clipped runs and the longest one",
+        computation: "Runs of at least 3 consecutive samples at or above 99.9% of full scale, counted in each channel separately before any downmix.",
+        normalization: "Percent of the samples in every channel. Inter-sample overs between unclipped samples show up in true peak instead.",
+    },
+    MetricInfo {
+        key: "silence",
+        name: "Silence",
+        meaning: "Silent lead-in and tail, and silent gaps inside the track, to catch badly trimmed rips before they go into a set. Flagged above 2 s at the start, 5 s at the end, or for any gap.",
+        scale: "Seconds at the start and end, plus the start time and length of each gap",
+        computation: "RMS level of consecutive 50 ms blocks of the mono downmix; blocks at or below -60 dBFS are silent. Inside the track only runs of at least 2 s count as gaps.",
+        normalization: "None. A track that never rises above the threshold is all lead-in.",
+    },
+    MetricInfo {
+        key: "intro-outro",
+        name: "Intro and outro profiles",
+        meaning: "The tone and level of a track's first and last 30 seconds, for DJ transitions: pair a track that ends bright with one that starts bright rather than one that opens dark.",
+        scale: "Centroid 0-100 as for the whole track, band shares in percent, and RMS level in dB",
+        computation: "The band energies, centroid, and RMS level computed exactly as for the whole track, over the opening and closing 30 seconds of the mono downmix.",
+        normalization: "None. On tracks shorter than a minute the two excerpts overlap.",
+    },
+    MetricInfo {
+        key: "tonal-balance",
+        name: "Tonal balance score",
+        meaning: "How closely the long-term spectrum follows a reference curve such as pink noise or a genre average; shown by `tonal` and `report`.",
+        scale: "0-100 overall, plus a per-band deviation in dB (positive means more energy than the curve)",
+        computation: "The curve is integrated over each band to get its expected share of energy, and each band's measured share is compared to it in dB.",
+        normalization: "100 minus 10 points per dB of RMS deviation across bands, floored at 0; silent bands count as 30 dB low.",
+    },
+];
+
+// Other names `explain` accepts, as (alias, key)
+const ALIASES: &[(&str, &str)] = &[("flux", "onset")]; // Onset strength is spectral flux
+
+pub fn find_metric(key: &str) -> Option<&'static MetricInfo> {
+    let key = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == key)
+        .map_or(key, |(_, target)| target);
+    METRICS.iter().find(|metric| metric.key == key)
+}
+
+/// Every key `explain` accepts, aliases included.
+pub fn metric_keys() -> Vec<&'static str> {
+    METRICS
+        .iter()
+        .map(|metric| metric.key)
+        .chain(ALIASES.iter().map(|(alias, _)| *alias))
+        .collect()
+}
+
+pub fn print_explanation(metric: &MetricInfo) {
+    println!("{} ({})", metric.name, metric.key);
+    println!("{}", "-".repeat(metric.name.len() + metric.key.len() + 3));
+    println!("{}\n", metric.meaning);
+    println!("Scale:          {}", metric.scale);
+    println!("Computation:    {}", metric.computation);
+    println!("Normalization:  {}", metric.normalization);
+}
+
+/// Roff METRICS section appended to the generated man page.
+pub fn man_section() -> String {
+    let escape = |text: &str| text.replace('\\', "\\\\").replace('-', "\\-");
+
+    let mut roff = String::from(".SH METRICS\n");
+    for metric in METRICS {
+        roff.push_str(&format!(
+            ".TP\n\\fB{}\\fR \\- {}\n{}\n.br\nScale: {}\n.br\nComputed: {}\n.br\nNormalized: {}\n",
+            metric.key,
+            escape(metric.name),
+            escape(metric.meaning),
+            escape(metric.scale),
+            escape(metric.computation),
+            escape(metric.normalization),
+        ));
+    }
+    roff
+}
@@ -0,0 +1,116 @@
+use crate::utils::CachedMetrics;
+
+/// How a streaming service levels playback.
+pub struct Platform {
+    pub name: &'static str,
+    pub target_lufs: f32,
+    /// Whether quiet tracks are turned up, not just loud ones down
+    pub boosts: bool,
+}
+
+// Boosts stop short of pushing true peaks above this
+const BOOST_PEAK_CEILING_DB: f32 = -1.0;
+
+/// Default playback normalization on the major services.
+pub const PLATFORMS: &[Platform] = &[
+    Platform {
+        name: "Spotify",
+        target_lufs: -14.0,
+        boosts: true,
+    },
+    Platform {
+        name: "YouTube",
+        target_lufs: -14.0,
+        boosts: false,
+    },
+    Platform {
+        name: "Apple Music",
+        target_lufs: -16.0,
+        boosts: true,
+    },
+    Platform {
+        name: "Tidal",
+        target_lufs: -14.0,
+        boosts: false,
+    },
+    Platform {
+        name: "Amazon Music",
+        target_lufs: -14.0,
+        boosts: false,
+    },
+];
+
+/// The gain in dB a platform applies on playback: negative turns the track down. Boosts are
+/// capped by the true-peak headroom when it's known.
+pub fn playback_gain(platform: &Platform, integrated_lufs: f32, true_peak_db: Option<f32>) -> f32 {
+    let gain = platform.target_lufs - integrated_lufs;
+    if gain <= 0.0 {
+        return gain;
+    }
+    if !platform.boosts {
+        return 0.0;
+    }
+    match true_peak_db {
+        Some(peak) => gain.min(BOOST_PEAK_CEILING_DB - peak).max(0.0),
+        None => gain,
+    }
+}
+
+/// "Spotify -3.2 dB" for every platform, or `None` without a loudness measurement.
+pub fn playback_summary(
+    integrated_lufs: Option<f32>,
+    true_peak_db: Option<f32>,
+) -> Option<Vec<String>> {
+    let lufs = integrated_lufs?;
+    Some(
+        PLATFORMS
+            .iter()
+            .map(|platform| {
+                format!(
+                    "{} {:+.1} dB",
+                    platform.name,
+                    playback_gain(platform, lufs, true_peak_db)
+                )
+            })
+            .collect(),
+    )
+}
+
+// BS.1770 gates loudness blocks 10 LU below the ungated integrated level
+const RELATIVE_GATE_LU: f32 = 10.0;
+
+/// Output targets for the suggested `loudnorm` filter.
+#[derive(clap::Args, Clone)]
+pub struct LoudnormTargets {
+    /// Integrated loudness to normalize to, in LUFS
+    #[arg(long, value_name = "LUFS", default_value_t = -14.0, allow_negative_numbers = true)]
+    pub integrated: f32,
+
+    /// Maximum true peak, in dBTP
+    #[arg(long, value_name = "DBTP", default_value_t = -1.0, allow_negative_numbers = true)]
+    pub true_peak: f32,
+
+    /// Loudness range target, in LU
+    #[arg(long, value_name = "LU", default_value_t = 11.0)]
+    pub lra: f32,
+}
+
+/// The second-pass ffmpeg `loudnorm` filter for a track, feeding in its measured loudness
+/// so ffmpeg can normalize linearly in one go. `None` until the track has integrated
+/// loudness, loudness range, and true peak readings.
+pub fn loudnorm_filter(entry: &CachedMetrics, targets: &LoudnormTargets) -> Option<String> {
+    let lufs = entry.integrated_lufs?;
+    let range = entry.loudness_range?;
+    let peak = entry.true_peak_db?;
+    Some(format!(
+        "loudnorm=I={:.1}:TP={:.1}:LRA={:.1}:measured_I={:.2}:measured_LRA={:.2}:\
+         measured_TP={:.2}:measured_thresh={:.2}:linear=true",
+        targets.integrated,
+        targets.true_peak,
+        targets.lra,
+        lufs,
+        range,
+        peak,
+        lufs - RELATIVE_GATE_LU
+    ))
+}
@@ -0,0 +1,984 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::derived;
+use crate::export::csv_field;
+use crate::frequency_bands::{
+    BAND_NAMES, FrequencyBand, SpectrumMetrics, band_labels, get_bands, is_screen_reader,
+    print_deviation_bar, print_duration, print_histogram_bar, print_rhythm_pattern, print_rule,
+    print_spectrum_position, print_spread_bar, separator,
+};
+use crate::i18n::{Msg, tr};
+use crate::metrics::find_metric;
+use crate::normalization::playback_summary;
+use crate::structure::EdgeProfile;
+use crate::summary::sparkline;
+use crate::surround::LFE_CROSSOVER_HZ;
+use crate::utils::{CachedMetrics, MAX_RATING, truncate_filename};
+use crate::warnings::{RULE_METRICS, metric_value};
+
+/// How per-file results are shown on stdout.
+#[derive(clap::ValueEnum, Clone, Copy, Default, PartialEq)]
+pub enum DisplayFormat {
+    #[default]
+    Table,
+    /// One JSON object per line, for piping into other tools
+    Json,
+}
+
+/// File formats for `--out FORMAT=PATH`.
+#[derive(Clone, Copy)]
+pub enum FileFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Clone)]
+pub struct OutputTarget {
+    pub format: FileFormat,
+    pub path: PathBuf,
+}
+
+pub fn parse_output_target(value: &str) -> Result<OutputTarget, String> {
+    let (format, path) = value.split_once('=').ok_or_else(|| {
+        format!(
+            "expected FORMAT=PATH, e.g. json=metrics.json, got '{}'",
+            value
+        )
+    })?;
+    let format = match format.to_ascii_lowercase().as_str() {
+        "json" => FileFormat::Json,
+        "csv" => FileFormat::Csv,
+        other => {
+            return Err(format!(
+                "unknown output format '{}' (expected json or csv)",
+                other
+            ));
+        }
+    };
+    if path.is_empty() {
+        return Err("missing output path".to_string());
+    }
+    Ok(OutputTarget {
+        format,
+        path: PathBuf::from(path),
+    })
+}
+
+/// A destination for analysis results. Every file is passed in directory order, so one
+/// analysis pass can feed the terminal and any number of export files.
+pub trait Sink {
+    fn write_track(
+        &mut self,
+        entry: &CachedMetrics,
+        metrics: &SpectrumMetrics,
+        warnings: &[String],
+    );
+
+    /// Called once after the last file.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct TableSink {
+    /// The configured band layout, for labeling the band rows
+    pub band_layout: Option<Vec<FrequencyBand>>,
+    /// Head each file with its tagged artist and title instead of the filename
+    pub show_tags: bool,
+    /// Folder average to draw band shares against (`--relative`)
+    pub average_bands: Option<Vec<f32>>,
+    /// Tracks arrive as z-scores (`--standardize`), which the usual bars and units don't fit
+    pub standardized: bool,
+}
+
+impl Sink for TableSink {
+    fn write_track(
+        &mut self,
+        entry: &CachedMetrics,
+        metrics: &SpectrumMetrics,
+        warnings: &[String],
+    ) {
+        if self.standardized {
+            display_z_scores(entry, metrics, warnings, self.band_layout.as_deref());
+            return;
+        }
+        display_metrics(
+            entry,
+            metrics,
+            warnings,
+            self.band_layout.as_deref(),
+            self.show_tags,
+            self.average_bands.as_deref(),
+        );
+    }
+}
+
+// Metrics on the table's first line, in column order
+const LEGEND_METRICS: &[&str] = &[
+    "centroid", "tilt", "rolloff", "spread", "zcr", "onset", "loudness", "bpm", "duration",
+];
+
+/// What each table column and band row means, printed once above the results.
+pub fn print_legend(band_layout: Option<&[FrequencyBand]>) {
+    println!("{}", tr(Msg::Legend, &[]));
+    for metric in LEGEND_METRICS.iter().filter_map(|key| find_metric(key)) {
+        println!("  {:<22} {}", metric.name, metric.scale);
+    }
+
+    println!("\n{}", tr(Msg::BandShares, &[]));
+    match band_layout {
+        None => {
+            for (band, (name, description)) in get_bands(usize::MAX, None).iter().zip(BAND_NAMES) {
+                let range = if band.high_hz == usize::MAX / 2 {
+                    tr(Msg::HzAndUp, &[&band.low_hz])
+                } else {
+                    format!("{}-{} Hz", band.low_hz, band.high_hz)
+                };
+                println!("  {:<9} {:<16} {}", name, range, description);
+            }
+        }
+        Some(bands) => {
+            for (band, label) in bands.iter().zip(band_labels(bands)) {
+                println!("  {:<9} {}-{} Hz", label, band.low_hz, band.high_hz);
+            }
+        }
+    }
+    println!("\n{}", tr(Msg::ExplainHint, &[]));
+    print_rule(80);
+}
+
+/// Round every fractional number in a JSON value to `precision` decimal places.
+fn round_json(value: &mut serde_json::Value, precision: usize) {
+    match value {
+        serde_json::Value::Number(number) if number.is_f64() => {
+            let scale = 10f64.powi(precision as i32);
+            let rounded = (number.as_f64().unwrap_or_default() * scale).round() / scale;
+            if let Some(rounded) = serde_json::Number::from_f64(rounded) {
+                *number = rounded;
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|v| round_json(v, precision)),
+        serde_json::Value::Object(fields) => {
+            fields.values_mut().for_each(|v| round_json(v, precision))
+        }
+        _ => {}
+    }
+}
+
+/// A file's cache entry plus its derived metrics and warnings, as exported to JSON.
+fn json_record(
+    entry: &CachedMetrics,
+    metrics: &SpectrumMetrics,
+    warnings: &[String],
+    precision: Option<usize>,
+) -> serde_json::Value {
+    let mut record = serde_json::to_value(entry).unwrap_or_default();
+    if let Some(object) = record.as_object_mut() {
+        let derived = derived::evaluate(metrics);
+        if !derived.is_empty() {
+            let values: serde_json::Map<String, serde_json::Value> = derived
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.into()))
+                .collect();
+            object.insert("derived".to_string(), values.into());
+        }
+    }
+    if let Some(precision) = precision {
+        round_json(&mut record, precision);
+    }
+    if let Some(object) = record.as_object_mut() {
+        object.insert("warnings".to_string(), warnings.into());
+    }
+    record
+}
+
+/// `--format json`: one compact object per line on stdout, written as each file finishes.
+pub struct JsonLinesSink {
+    precision: Option<usize>,
+    error: Option<io::Error>, // First write failure, reported from `finish`
+}
+
+impl JsonLinesSink {
+    /// Fractional values are rounded to `precision` decimal places when given.
+    pub fn new(precision: Option<usize>) -> Self {
+        JsonLinesSink {
+            precision,
+            error: None,
+        }
+    }
+}
+
+impl Sink for JsonLinesSink {
+    fn write_track(
+        &mut self,
+        entry: &CachedMetrics,
+        metrics: &SpectrumMetrics,
+        warnings: &[String],
+    ) {
+        if self.error.is_some() {
+            return;
+        }
+        let mut stdout = io::stdout().lock();
+        let record = json_record(entry, metrics, warnings, self.precision);
+        let result = serde_json::to_writer(&mut stdout, &record)
+            .map_err(io::Error::other)
+            .and_then(|()| writeln!(stdout));
+        self.error = result.err();
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.error.take().map_or(Ok(()), Err)
+    }
+}
+
+/// `--watch-log`: one compact object per line, appended to a file and flushed after each
+/// track so the log can be followed while watching.
+struct JsonLogSink {
+    path: PathBuf,
+    file: File,
+    precision: Option<usize>,
+}
+
+impl Sink for JsonLogSink {
+    fn write_track(
+        &mut self,
+        entry: &CachedMetrics,
+        metrics: &SpectrumMetrics,
+        warnings: &[String],
+    ) {
+        let record = json_record(entry, metrics, warnings, self.precision);
+        let mut line = serde_json::to_string(&record).unwrap_or_default();
+        line.push('\n');
+        // Each line goes out in one write, so readers never see half a record
+        if let Err(e) = self.file.write_all(line.as_bytes()) {
+            eprintln!("{}", tr(Msg::WriteError, &[&self.path.display(), &e]));
+        }
+    }
+}
+
+/// Open `path` for appending JSON lines, creating it if needed.
+pub fn open_log(path: &Path, precision: Option<usize>) -> io::Result<Box<dyn Sink>> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| with_path(e, path))?;
+    Ok(Box::new(JsonLogSink {
+        path: path.to_path_buf(),
+        file,
+        precision,
+    }))
+}
+
+/// Writes a JSON array of cache entries, each with its warnings.
+struct JsonSink {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    records: Vec<serde_json::Value>,
+    precision: Option<usize>,
+}
+
+impl Sink for JsonSink {
+    fn write_track(
+        &mut self,
+        entry: &CachedMetrics,
+        metrics: &SpectrumMetrics,
+        warnings: &[String],
+    ) {
+        self.records
+            .push(json_record(entry, metrics, warnings, self.precision));
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut self.writer, &self.records)
+            .map_err(io::Error::other)
+            .and_then(|()| self.writer.flush())
+            .map_err(|e| with_path(e, &self.path))
+    }
+}
+
+const CSV_COLUMNS: &[&str] = &[
+    "filename",
+    "artist",
+    "title",
+    "album",
+    "genre",
+    "codec",
+    "sample_rate",
+    "channels",
+    "bitrate_kbps",
+    "bitrate_mode",
+    "encoder",
+    "undecodable_percent",
+    "duration_seconds",
+    "centroid",
+    "centroid_hz",
+    "exact_centroid",
+    "tilt_db_per_octave",
+    "rolloff_hz",
+    "cutoff_hz",
+    "spread",
+    "zcr",
+    "onset_strength",
+    "loudness_db",
+    "integrated_lufs",
+    "loudness_range",
+    "true_peak_db",
+    "dr_score",
+    "rms_db",
+    "crest_factor_db",
+    "clipped_percent",
+    "clipped_runs",
+    "leading_silence_seconds",
+    "trailing_silence_seconds",
+    "silent_gaps",
+    "low_share",
+    "mid_share",
+    "high_share",
+    "low_high_db",
+    "mid_prominence_db",
+    "bpm",
+    "key",
+    "swing_percent",
+    "timing_deviation_ms",
+    "sharpness",
+    "roughness",
+    "balance_db",
+    "side_db",
+    "mono_below_hz",
+    "lfe_share_pct",
+    "lfe_above_crossover_pct",
+    "preview_seconds",
+    "intro_centroid",
+    "intro_loudness_db",
+    "outro_centroid",
+    "outro_loudness_db",
+    "rating",
+    "note",
+];
+
+/// Writes one row per file. Rows are held until the end so the band columns can cover the
+/// widest layout seen.
+struct CsvSink {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    rows: Vec<CsvRow>,
+    band_count: usize,
+    precision: Option<usize>,
+}
+
+struct CsvRow {
+    fields: Vec<String>,
+    bands: Vec<f32>,
+    warnings: String,
+}
+
+/// A metric as a CSV cell, rounded when a precision is set so float jitter doesn't churn
+/// diffs of exported files.
+fn number(value: f32, precision: Option<usize>) -> String {
+    match precision {
+        Some(precision) => format!("{:.*}", precision, value),
+        None => value.to_string(),
+    }
+}
+
+fn optional(value: Option<f32>, precision: Option<usize>) -> String {
+    value.map(|v| number(v, precision)).unwrap_or_default()
+}
+
+impl CsvSink {
+    fn write_rows(&mut self) -> io::Result<()> {
+        let band_columns = (1..=self.band_count).map(|i| format!("band_{}_pct", i));
+        let derived_columns = derived::derived().iter().map(|d| d.name.clone());
+        let header: Vec<String> = CSV_COLUMNS
+            .iter()
+            .map(|c| c.to_string())
+            .chain(derived_columns)
+            .chain(band_columns)
+            .chain(["warnings".to_string()])
+            .collect();
+        writeln!(self.writer, "{}", header.join(","))?;
+
+        for row in &self.rows {
+            let mut fields: Vec<String> = row.fields.iter().map(|f| csv_field(f)).collect();
+            // Files with fewer bands get empty cells so the warnings column stays aligned
+            fields.extend(
+                (0..self.band_count).map(|i| optional(row.bands.get(i).copied(), self.precision)),
+            );
+            fields.push(csv_field(&row.warnings));
+            writeln!(self.writer, "{}", fields.join(","))?;
+        }
+        self.writer.flush()
+    }
+}
+
+impl Sink for CsvSink {
+    fn write_track(
+        &mut self,
+        entry: &CachedMetrics,
+        metrics: &SpectrumMetrics,
+        warnings: &[String],
+    ) {
+        let stream = metrics.stream.as_ref();
+        let stereo = metrics.stereo.as_ref();
+        let ratios = metrics.ratios.as_ref();
+        let precision = self.precision;
+        let tags = entry.tags.clone().unwrap_or_default();
+        let fields = vec![
+            entry.filename.clone(),
+            tags.artist.unwrap_or_default(),
+            tags.title.unwrap_or_default(),
+            tags.album.unwrap_or_default(),
+            tags.genre.unwrap_or_default(),
+            stream.map(|s| s.codec.clone()).unwrap_or_default(),
+            stream
+                .map(|s| s.sample_rate.to_string())
+                .unwrap_or_default(),
+            stream.map(|s| s.channels.to_string()).unwrap_or_default(),
+            optional(stream.map(|s| s.bitrate_kbps), precision),
+            stream
+                .and_then(|s| s.bitrate_mode)
+                .map_or(String::new(), |mode| mode.to_string()),
+            stream.and_then(|s| s.encoder.clone()).unwrap_or_default(),
+            optional(stream.and_then(|s| s.undecodable_percent), precision),
+            number(metrics.duration_seconds, precision),
+            number(metrics.centroid.0, precision),
+            optional(metrics.centroid_hz.map(f32::from), precision),
+            optional(metrics.exact_centroid.map(f32::from), precision),
+            optional(metrics.tilt, precision),
+            optional(metrics.rolloff.map(f32::from), precision),
+            optional(metrics.bandwidth.as_ref().map(|b| b.cutoff_hz), precision),
+            number(metrics.spread.0, precision),
+            number(metrics.zero_crossing_rate, precision),
+            optional(metrics.onset_strength.map(f32::from), precision),
+            number(metrics.loudness.0, precision),
+            optional(entry.integrated_lufs, precision),
+            optional(entry.loudness_range, precision),
+            optional(entry.true_peak_db, precision),
+            optional(metrics.dr_score.map(f32::from), precision),
+            optional(metrics.dynamics.as_ref().map(|d| d.rms_db), precision),
+            optional(
+                metrics.dynamics.as_ref().map(|d| d.crest_factor_db),
+                precision,
+            ),
+            optional(
+                metrics.clipping.as_ref().map(|c| c.clipped_percent),
+                precision,
+            ),
+            metrics
+                .clipping
+                .as_ref()
+                .map_or(String::new(), |c| c.runs.to_string()),
+            optional(
+                metrics.silence.as_ref().map(|s| s.leading_seconds),
+                precision,
+            ),
+            optional(
+                metrics.silence.as_ref().map(|s| s.trailing_seconds),
+                precision,
+            ),
+            metrics
+                .silence
+                .as_ref()
+                .map_or(String::new(), |s| s.gaps.len().to_string()),
+            optional(ratios.map(|r| r.low_percent), precision),
+            optional(ratios.map(|r| r.mid_percent), precision),
+            optional(ratios.map(|r| r.high_percent), precision),
+            optional(ratios.and_then(|r| r.low_high_db), precision),
+            optional(ratios.and_then(|r| r.mid_prominence_db), precision),
+            optional(metrics.tempo.as_ref().map(|t| t.bpm), precision),
+            metrics
+                .key
+                .as_ref()
+                .map_or(String::new(), |k| k.to_string()),
+            optional(metrics.groove.as_ref().map(|g| g.swing_percent), precision),
+            optional(
+                metrics.groove.as_ref().map(|g| g.timing_deviation_ms),
+                precision,
+            ),
+            optional(metrics.sharpness, precision),
+            optional(metrics.roughness, precision),
+            optional(stereo.map(|s| s.balance_db), precision),
+            optional(stereo.map(|s| s.side_db), precision),
+            optional(stereo.and_then(|s| s.mono_below_hz), precision),
+            optional(metrics.lfe.as_ref().map(|l| l.share_percent), precision),
+            optional(
+                metrics.lfe.as_ref().map(|l| l.above_crossover_percent),
+                precision,
+            ),
+            optional(metrics.chorus.as_ref().map(|c| c.start_seconds), precision),
+            optional(metrics.intro.as_ref().map(|p| p.centroid), precision),
+            optional(metrics.intro.as_ref().map(|p| p.loudness), precision),
+            optional(metrics.outro.as_ref().map(|p| p.centroid), precision),
+            optional(metrics.outro.as_ref().map(|p| p.loudness), precision),
+            entry.rating.map(|r| r.to_string()).unwrap_or_default(),
+            entry.note.clone().unwrap_or_default(),
+        ]
+        .into_iter()
+        .chain(
+            derived::evaluate(metrics)
+                .into_iter()
+                .map(|(_, value)| optional(value, precision)),
+        )
+        .collect();
+        self.band_count = self.band_count.max(metrics.band_percentages.len());
+        self.rows.push(CsvRow {
+            fields,
+            bands: metrics.band_percentages.clone(),
+            warnings: warnings.join("; "),
+        });
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.write_rows().map_err(|e| with_path(e, &self.path))
+    }
+}
+
+fn with_path(error: io::Error, path: &Path) -> io::Error {
+    io::Error::new(error.kind(), format!("{}: {}", path.display(), error))
+}
+
+/// Create a file sink for each `--out` target, failing before any analysis if a file
+/// can't be created.
+pub fn open_sinks(
+    targets: &[OutputTarget],
+    precision: Option<usize>,
+) -> io::Result<Vec<Box<dyn Sink>>> {
+    let mut sinks: Vec<Box<dyn Sink>> = Vec::new();
+    for target in targets {
+        let writer =
+            BufWriter::new(File::create(&target.path).map_err(|e| with_path(e, &target.path))?);
+        let path = target.path.clone();
+        sinks.push(match target.format {
+            FileFormat::Json => Box::new(JsonSink {
+                path,
+                writer,
+                records: Vec::new(),
+                precision,
+            }),
+            FileFormat::Csv => Box::new(CsvSink {
+                path,
+                writer,
+                rows: Vec::new(),
+                band_count: 0,
+                precision,
+            }),
+        });
+    }
+    Ok(sinks)
+}
+
+/// A 0-5 rating as filled and empty stars.
+pub fn stars(rating: u8) -> String {
+    let filled = rating.min(MAX_RATING) as usize;
+    if is_screen_reader() {
+        return format!("{} of {} stars", filled, MAX_RATING);
+    }
+    "★".repeat(filled) + &"☆".repeat(MAX_RATING as usize - filled)
+}
+
+// The track's bands and their labels. The top band ends at Nyquist, so ranges need the
+// stream's sample rate; without it bands are just numbered.
+fn track_bands(
+    metrics: &SpectrumMetrics,
+    band_layout: Option<&[FrequencyBand]>,
+) -> (Option<Vec<FrequencyBand>>, Vec<String>) {
+    let bands = metrics
+        .stream
+        .as_ref()
+        .map(|stream| get_bands(stream.sample_rate as usize, band_layout))
+        .filter(|bands| bands.len() == metrics.band_percentages.len());
+    let labels = match &bands {
+        Some(bands) => band_labels(bands),
+        None => (1..=metrics.band_percentages.len())
+            .map(|i| format!("Band {}", i))
+            .collect(),
+    };
+    (bands, labels)
+}
+
+/// The `--standardize` view: every metric the track has as a z-score, four to a line,
+/// then the bands.
+pub fn display_z_scores(
+    entry: &CachedMetrics,
+    metrics: &SpectrumMetrics,
+    warnings: &[String],
+    band_layout: Option<&[FrequencyBand]>,
+) {
+    println!("\n{:<40}", truncate_filename(&entry.filename, 40));
+    let scores: Vec<String> = RULE_METRICS
+        .iter()
+        .filter_map(|&key| Some(format!("{:<13}{:>+6.2}", key, metric_value(metrics, key)?)))
+        .collect();
+    for line in scores.chunks(4) {
+        println!("  {}", line.join("  │  "));
+    }
+    let (_, labels) = track_bands(metrics, band_layout);
+    let bands: Vec<String> = labels
+        .iter()
+        .zip(&metrics.band_percentages)
+        .map(|(label, z)| format!("{} {:+.2}", label, z))
+        .collect();
+    println!("  Bands: {}", bands.join("  "));
+    if !warnings.is_empty() {
+        println!("Warnings: {}", warnings.join(", "));
+    }
+}
+
+/// The ASCII table view of one file's metrics.
+pub fn display_metrics(
+    entry: &CachedMetrics,
+    metrics: &SpectrumMetrics,
+    warnings: &[String],
+    band_layout: Option<&[FrequencyBand]>,
+    show_tags: bool,
+    average_bands: Option<&[f32]>,
+) {
+    let sep = separator();
+    let tagged = entry
+        .tags
+        .as_ref()
+        .and_then(|t| t.display_name())
+        .filter(|_| show_tags);
+    // Format details go in a column after the name
+    let stream = metrics
+        .stream
+        .as_ref()
+        .map(|stream| format!("  {}", stream.summary()))
+        .unwrap_or_default();
+    match tagged {
+        Some(name) => println!("\n{}  ({}){}", name, entry.filename, stream),
+        None => println!("\n{:<40}{}", truncate_filename(&entry.filename, 40), stream),
+    }
+    if let Some(tags) = entry.tags.as_ref().filter(|_| show_tags) {
+        let details: Vec<&str> = [&tags.album, &tags.genre]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        if !details.is_empty() {
+            println!("{}", details.join(sep));
+        }
+    }
+
+    // Display spectral centroid
+    print!("Centroid: ");
+    print_spectrum_position(metrics.centroid.0);
+    print!(" ({:>5.1})", metrics.centroid);
+    match metrics.centroid_hz {
+        Some(hz) => print!(" {:>5.0} Hz", hz),
+        None => print!("    -- Hz"),
+    }
+
+    // Display spectral tilt next to the centroid it complements
+    match metrics.tilt {
+        Some(tilt) => print!("{}Tilt: {:>+5.1} dB/oct", sep, tilt),
+        None => print!("{}Tilt:    --       ", sep),
+    }
+
+    // Display where the top end gives out
+    match metrics.rolloff {
+        Some(hz) => print!("{}Rolloff: {:>4.1} kHz", sep, hz.0 / 1000.0),
+        None => print!("{}Rolloff:   --    ", sep),
+    }
+
+    // Display spectral spread
+    print!("{}Spread: ", sep);
+    print_spread_bar(metrics.spread.0);
+    print!(" ({:>5.1})", metrics.spread);
+
+    // Display zero-crossing rate
+    print!("{}ZCR: ", sep);
+    print_spread_bar(metrics.zero_crossing_rate);
+    print!(" ({:>5.1})", metrics.zero_crossing_rate);
+
+    // Display how transient-heavy the track is
+    match metrics.onset_strength {
+        Some(strength) => {
+            print!("{}Onsets: ", sep);
+            print_spread_bar(strength.0);
+            print!(" ({:>5.1})", strength);
+        }
+        None => print!("{}Onsets:    --      ", sep),
+    }
+
+    // Display loudness
+    print!("{}Loudness: {:>6.1} dB", sep, metrics.loudness);
+
+    // Display estimated tempo
+    match &metrics.tempo {
+        Some(tempo) => print!(
+            "{}BPM: {:>5.1} ({:>3.0}%)",
+            sep,
+            tempo.bpm,
+            tempo.confidence * 100.0
+        ),
+        None => print!("{}BPM:    --       ", sep),
+    }
+
+    // Display track duration
+    print!("{}Length: ", sep);
+    print_duration(metrics.duration_seconds);
+
+    // Half/double-time and other plausible readings
+    if let Some(tempo) = metrics
+        .tempo
+        .as_ref()
+        .filter(|t| !t.alternatives.is_empty())
+    {
+        let alternatives: Vec<String> = tempo
+            .alternatives
+            .iter()
+            .map(|alt| format!("{:.1} ({:.0}%)", alt.bpm, alt.confidence * 100.0))
+            .collect();
+        println!("Alternative tempos: {}", alternatives.join(", "));
+    }
+
+    // Display the estimated key
+    if let Some(key) = &metrics.key {
+        println!("Key: {}  ({:.2} match)", key, key.correlation);
+    }
+
+    // Display swing and micro-timing
+    if let Some(groove) = &metrics.groove {
+        println!(
+            "Groove: swing {:.0}%{}timing ±{:.1} ms",
+            groove.swing_percent, sep, groove.timing_deviation_ms
+        );
+    }
+
+    // Display the one-bar onset pattern
+    if !metrics.rhythm_pattern.is_empty() {
+        print!("Rhythm: ");
+        print_rhythm_pattern(&metrics.rhythm_pattern);
+        println!();
+    }
+
+    // Display psychoacoustic harshness
+    if let (Some(sharpness), Some(roughness)) = (metrics.sharpness, metrics.roughness) {
+        println!(
+            "Sharpness: {:.2} acum{}Roughness: {:.2} asper",
+            sharpness, sep, roughness
+        );
+    }
+
+    // Display the L/R balance
+    if let Some(stereo) = &metrics.stereo {
+        print!("Stereo balance: {:+.1} dB", stereo.balance_db);
+        match stereo.mono_below_hz {
+            Some(hz) if hz > 0.0 => println!("{}Mono below: {:.0} Hz", sep, hz),
+            Some(_) => println!("{}Mono below: -- (wide bass)", sep),
+            None => println!(),
+        }
+    }
+
+    // Display each surround channel's share and spectrum
+    if let Some(surround) = &metrics.surround {
+        let channels: Vec<String> = surround
+            .iter()
+            .map(|c| {
+                format!(
+                    "{} {:.1}% {}",
+                    c.name,
+                    c.share_percent,
+                    sparkline(&c.band_percentages)
+                )
+            })
+            .collect();
+        println!("Channels: {}", channels.join(sep));
+    }
+    if let Some(lfe) = &metrics.lfe {
+        print!("LFE: {:.1}% of energy", lfe.share_percent);
+        if let Some(edge) = lfe.upper_edge_hz {
+            print!("{}99% below {:.0} Hz", sep, edge);
+        }
+        println!(
+            "{}{:.1}% above {:.0} Hz",
+            sep, lfe.above_crossover_percent, LFE_CROSSOVER_HZ
+        );
+    }
+
+    // Display EBU R128 loudness, measured or imported
+    if let Some(lufs) = entry.integrated_lufs {
+        print!("Integrated: {:.1} LUFS", lufs);
+        if let Some(range) = entry.loudness_range {
+            print!("{}LRA: {:.1} LU", sep, range);
+        }
+        if let Some(peak) = entry.true_peak_db {
+            print!("{}True peak: {:+.1} dBTP", sep, peak);
+        }
+        if let Some(dr) = entry.dr_score {
+            print!("{}DR{:.0}", sep, dr);
+        }
+        println!();
+    }
+
+    // Display the overall level against how far the peaks rise above it
+    if let Some(dynamics) = &metrics.dynamics {
+        print!("Dynamics: RMS ");
+        print_spread_bar(dynamics.rms_position());
+        print!(" {:.1} dBFS{}Crest ", dynamics.rms_db, sep);
+        print_spread_bar(dynamics.crest_position());
+        println!(" {:.1} dB", dynamics.crest_factor_db);
+    }
+
+    // Display flat-topped runs left by clipping
+    if let Some(clipping) = metrics.clipping.as_ref().filter(|c| c.runs > 0) {
+        println!(
+            "Clipping: {:.3}% of samples in {} run(s), longest {} samples",
+            clipping.clipped_percent, clipping.runs, clipping.longest_run
+        );
+    }
+
+    // Display the lowpass shelf a lossy encode leaves behind
+    if let Some(bandwidth) = metrics.bandwidth.as_ref().filter(|b| b.is_lowpassed()) {
+        print!(
+            "Bandwidth: content stops at {:.1} kHz ({:.0} dB shelf)",
+            bandwidth.cutoff_hz / 1000.0,
+            bandwidth.shelf_db
+        );
+        if let Some(kbps) = bandwidth.source_kbps() {
+            print!(", typical of {} kbps", kbps);
+        }
+        println!();
+    }
+
+    // Display untrimmed edges and dropouts
+    if let Some(silence) = metrics.silence.as_ref().filter(|s| s.total_seconds() > 0.0) {
+        let mut parts = vec![
+            format!("{:.1} s at start", silence.leading_seconds),
+            format!("{:.1} s at end", silence.trailing_seconds),
+        ];
+        parts.extend(silence.gaps.iter().map(|gap| {
+            format!(
+                "gap at {}:{:02} ({:.1} s)",
+                gap.start_seconds as u32 / 60,
+                gap.start_seconds as u32 % 60,
+                gap.end_seconds - gap.start_seconds
+            )
+        }));
+        println!("Silence: {}", parts.join(sep));
+    }
+
+    // Display how much each streaming service would turn the track up or down
+    if let Some(gains) = playback_summary(entry.integrated_lufs, entry.true_peak_db) {
+        println!("Playback: {}", gains.join(sep));
+    }
+
+    // Display the suggested preview point
+    if let Some(chorus) = &metrics.chorus {
+        let clock = |seconds: f32| format!("{}:{:02}", seconds as u32 / 60, seconds as u32 % 60);
+        println!(
+            "Preview: {}  (chorus {}-{})",
+            clock(chorus.start_seconds),
+            clock(chorus.start_seconds),
+            clock(chorus.end_seconds)
+        );
+    }
+
+    // Display the opening and closing tone for planning transitions
+    let profile = |label: &str, edge: &Option<EdgeProfile>| {
+        edge.as_ref().map(|p| {
+            format!(
+                "{}: centroid {:.1}, {:.1} dB {}",
+                label,
+                p.centroid,
+                p.loudness,
+                sparkline(&p.band_percentages)
+            )
+        })
+    };
+    let edges: Vec<String> = [
+        profile("Intro", &metrics.intro),
+        profile("Outro", &metrics.outro),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if !edges.is_empty() {
+        println!("{}", edges.join(sep));
+    }
+
+    // Display the config's derived metrics
+    let derived: Vec<String> = derived::evaluate(metrics)
+        .into_iter()
+        .map(|(name, value)| match value {
+            Some(value) => format!("{} {:.2}", name, value),
+            None => format!("{} --", name),
+        })
+        .collect();
+    if !derived.is_empty() {
+        println!("Derived: {}", derived.join(sep));
+    }
+
+    // Display built-in and configured warnings
+    if !warnings.is_empty() {
+        println!("Warnings: {}", warnings.join(", "));
+    }
+
+    // Display the user's own annotations
+    match (entry.rating, &entry.note) {
+        (Some(rating), Some(note)) => println!("Rating: {}{}Note: {}", stars(rating), sep, note),
+        (Some(rating), None) => println!("Rating: {}", stars(rating)),
+        (None, Some(note)) => println!("Note: {}", note),
+        (None, None) => {}
+    }
+
+    // Display the bands folded into thirds
+    if let Some(ratios) = &metrics.ratios {
+        print!(
+            "Energy: low {:.1}%{}mid {:.1}%{}high {:.1}%",
+            ratios.low_percent, sep, ratios.mid_percent, sep, ratios.high_percent
+        );
+        if let Some(db) = ratios.low_high_db {
+            print!("{}Low:high {:+.1} dB", sep, db);
+        }
+        if let Some(db) = ratios.mid_prominence_db {
+            print!("{}Mid prominence {:+.1} dB", sep, db);
+        }
+        println!();
+    }
+
+    // Display individual band percentages as a labeled histogram
+    let (bands, labels) = track_bands(metrics, band_layout);
+    // Deviations only make sense against an average over the same layout
+    let average = average_bands.filter(|average| average.len() == metrics.band_percentages.len());
+    println!("{}", tr(Msg::FrequencyBands, &[]));
+    if !is_screen_reader() {
+        println!(
+            "  {:<9} {:>11}  {:>6}",
+            tr(Msg::Band, &[]),
+            tr(Msg::RangeHz, &[]),
+            tr(
+                if average.is_some() {
+                    Msg::VsAverage
+                } else {
+                    Msg::Share
+                },
+                &[]
+            )
+        );
+    }
+    for (i, (pct, label)) in metrics.band_percentages.iter().zip(&labels).enumerate() {
+        let band = bands.as_ref().and_then(|bands| bands.get(i));
+        if is_screen_reader() {
+            match band {
+                Some(band) => print!("  {}, {} to {} Hz: ", label, band.low_hz, band.high_hz),
+                None => print!("  {}: ", label),
+            }
+        } else {
+            let range = match band {
+                Some(band) => format!("{}-{}", band.low_hz, band.high_hz),
+                None => "--".to_string(),
+            };
+            print!("  {:<9} {:>11}  ", label, range);
+        }
+        let position = i as f32 / (labels.len() - 1).max(1) as f32 * 100.0;
+        match average {
+            Some(average) => print_deviation_bar(pct - average[i], position),
+            None => print_histogram_bar(*pct, position),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::assertions::Constraints;
+use crate::warnings::WarningRule;
+
+const PRESETS_DIR: &str = "presets";
+
+/// A named mastering target: loudness limits for `assert` and warnings, plus metric rules.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Preset {
+    #[serde(default)]
+    pub description: String,
+    #[serde(flatten)]
+    pub constraints: Constraints,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<WarningRule>,
+}
+
+impl Preset {
+    /// `base` rules followed by the preset's own.
+    pub fn with_rules(&self, base: &[WarningRule]) -> Vec<WarningRule> {
+        base.iter().chain(&self.rules).cloned().collect()
+    }
+}
+
+fn rule(metric: &str, above: Option<f32>, below: Option<f32>, message: &str) -> WarningRule {
+    WarningRule {
+        metric: metric.to_string(),
+        above,
+        below,
+        message: Some(message.to_string()),
+    }
+}
+
+fn built_in(name: &str) -> Option<Preset> {
+    let preset = match name {
+        "streaming" => Preset {
+            description: "Spotify, Apple Music, and YouTube normalize to around -14 to -16 LUFS"
+                .to_string(),
+            constraints: Constraints {
+                max_true_peak: Some(-1.0),
+                min_lufs: Some(-18.0),
+                max_lufs: Some(-11.0),
+            },
+            rules: vec![rule(
+                "sharpness",
+                Some(2.6),
+                None,
+                "harsh top end for long listening sessions",
+            )],
+        },
+        "club" => Preset {
+            description: "Loud, bass-forward masters for DJ sets and club systems".to_string(),
+            constraints: Constraints {
+                max_true_peak: Some(-0.3),
+                min_lufs: Some(-10.0),
+                max_lufs: Some(-6.0),
+            },
+            rules: vec![
+                rule(
+                    "centroid",
+                    Some(60.0),
+                    None,
+                    "thin low end for club systems",
+                ),
+                rule(
+                    "balance",
+                    Some(1.0),
+                    None,
+                    "image leans left on mono club rigs",
+                ),
+                rule(
+                    "balance",
+                    None,
+                    Some(-1.0),
+                    "image leans right on mono club rigs",
+                ),
+            ],
+        },
+        "vinyl" => Preset {
+            description: "Moderate level and tame highs that cut cleanly to lacquer".to_string(),
+            constraints: Constraints {
+                max_true_peak: Some(-0.5),
+                min_lufs: None,
+                max_lufs: Some(-9.0),
+            },
+            rules: vec![
+                rule(
+                    "sharpness",
+                    Some(2.2),
+                    None,
+                    "sibilant highs may distort on vinyl",
+                ),
+                rule("balance", Some(1.5), None, "left-heavy image cuts unevenly"),
+                rule(
+                    "balance",
+                    None,
+                    Some(-1.5),
+                    "right-heavy image cuts unevenly",
+                ),
+            ],
+        },
+        _ => return None,
+    };
+    Some(preset)
+}
+
+pub const BUILT_IN_PRESETS: &[&str] = &["streaming", "club", "vinyl"];
+
+/// Look up a preset, preferring `<config dir>/presets/<name>.json` over the built-ins so
+/// users can tweak a shipped target or add their own.
+pub fn load_preset(name: &str, config_dir: &Path) -> Result<Preset, String> {
+    let path = config_dir.join(PRESETS_DIR).join(format!("{}.json", name));
+    if let Ok(file) = File::open(&path) {
+        return serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("invalid preset file {}: {}", path.display(), e));
+    }
+
+    built_in(name).ok_or_else(|| {
+        format!(
+            "unknown target '{}' (built-in: {}; custom presets go in {})",
+            name,
+            BUILT_IN_PRESETS.join(", "),
+            config_dir.join(PRESETS_DIR).display()
+        )
+    })
+}
@@ -0,0 +1,38 @@
+// Blocks of 0.2s resolve probes 5 Hz apart, enough to tell 50 Hz from 60 Hz hum
+const BLOCK_SECONDS: f32 = 0.2;
+const SILENCE_DB: f32 = -120.0;
+
+/// Level of a single frequency in dBFS (a full-scale sine reads 0 dB), measured with a
+/// Goertzel filter over consecutive blocks and averaged by power.
+pub fn probe_level(samples: &[f32], sample_rate: usize, frequency: f32) -> f32 {
+    let block = ((sample_rate as f32 * BLOCK_SECONDS) as usize).max(1);
+    if samples.len() < block || frequency <= 0.0 || frequency >= sample_rate as f32 / 2.0 {
+        return SILENCE_DB;
+    }
+
+    let omega = 2.0 * std::f32::consts::PI * frequency / sample_rate as f32;
+    let coefficient = 2.0 * omega.cos();
+
+    let mut power_sum = 0.0f64;
+    let mut block_count = 0;
+    for chunk in samples.chunks_exact(block) {
+        let (mut s1, mut s2) = (0.0f32, 0.0f32);
+        for &x in chunk {
+            let s0 = x + coefficient * s1 - s2;
+            s2 = s1;
+            s1 = s0;
+        }
+        let power = s1 * s1 + s2 * s2 - coefficient * s1 * s2;
+
+        // Scale so a sine of amplitude A gives A
+        let amplitude = 2.0 * power.max(0.0).sqrt() / block as f32;
+        power_sum += (amplitude * amplitude) as f64;
+        block_count += 1;
+    }
+
+    let mean_power = power_sum / block_count as f64;
+    if mean_power <= 0.0 {
+        return SILENCE_DB;
+    }
+    ((10.0 * mean_power.log10()) as f32).max(SILENCE_DB)
+}
@@ -0,0 +1,85 @@
+use std::path::Path;
+use std::time::Duration;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use rust_audio_analysis::analysis::Stage;
+
+// Stage fractions are scaled to whole steps for the per-file bars
+const FILE_STEPS: u64 = 100;
+const TICK: Duration = Duration::from_millis(120);
+
+/// Overall and per-file progress on stderr, so piped stdout stays clean. Draws nothing
+/// when hidden or when stderr isn't a terminal.
+pub struct AnalysisProgress {
+    bars: MultiProgress,
+    overall: ProgressBar,
+}
+
+/// One file's bar, removed once its analysis finishes.
+pub struct FileProgress<'a> {
+    bar: ProgressBar,
+    overall: &'a ProgressBar,
+}
+
+impl AnalysisProgress {
+    pub fn new(file_count: usize, visible: bool) -> Self {
+        let bars = MultiProgress::with_draw_target(if visible {
+            ProgressDrawTarget::stderr()
+        } else {
+            ProgressDrawTarget::hidden()
+        });
+        let overall = bars.add(
+            ProgressBar::new(file_count as u64).with_style(
+                ProgressStyle::with_template(
+                    "{pos}/{len} files [{wide_bar}] {elapsed_precise}, about {eta} left",
+                )
+                .unwrap()
+                .progress_chars("=> "),
+            ),
+        );
+        overall.enable_steady_tick(TICK);
+        AnalysisProgress { bars, overall }
+    }
+
+    pub fn start_file(&self, path: &Path) -> FileProgress<'_> {
+        let bar = self.bars.insert_before(
+            &self.overall,
+            ProgressBar::new(FILE_STEPS)
+                .with_style(
+                    ProgressStyle::with_template("{spinner} {prefix:<9} [{bar:20}] {wide_msg}")
+                        .unwrap()
+                        .progress_chars("=> "),
+                )
+                .with_message(
+                    path.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string(),
+                ),
+        );
+        bar.enable_steady_tick(TICK);
+        FileProgress {
+            bar,
+            overall: &self.overall,
+        }
+    }
+
+    pub fn finish(&self) {
+        self.overall.finish_and_clear();
+    }
+}
+
+impl FileProgress<'_> {
+    pub fn stage(&self, stage: Stage) {
+        self.bar.set_prefix(format!("{:?}", stage).to_lowercase());
+        self.bar
+            .set_position((stage.fraction() * FILE_STEPS as f32) as u64);
+    }
+}
+
+impl Drop for FileProgress<'_> {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+        self.overall.inc(1);
+    }
+}
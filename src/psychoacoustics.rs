@@ -0,0 +1,190 @@
+use rustfft::num_complex::Complex;
+
+use crate::frequency_bands::{FRAME_SIZE, HOP_SIZE, for_each_spectrum, plan_fft};
+
+const BARK_BANDS: usize = 24;
+// Zwicker's power law between band energy and specific loudness
+const LOUDNESS_EXPONENT: f32 = 0.23;
+
+// Roughness needs band envelopes sampled well above the ~70 Hz modulations it responds to
+const ROUGHNESS_FRAME_SIZE: usize = 512;
+const ROUGHNESS_HOP_SIZE: usize = 128;
+// Envelope blocks of ~0.75s at 44.1 kHz, analyzed for modulation one at a time
+const MODULATION_BLOCK: usize = 256;
+const MODULATION_MIN_HZ: f32 = 15.0;
+const MODULATION_MAX_HZ: f32 = 300.0;
+// Perceived roughness peaks for modulations near 70 Hz and falls off about an octave away
+const ROUGHNESS_PEAK_HZ: f32 = 70.0;
+const ROUGHNESS_WIDTH_OCTAVES: f32 = 0.8;
+// Modulation depth is capped so fully modulated bands give a finite level swing
+const MAX_MODULATION_DEPTH: f32 = 0.95;
+// Scales the result so a 1 kHz tone fully amplitude-modulated at 70 Hz reads 1 asper
+const ROUGHNESS_CALIBRATION: f32 = 0.0276;
+
+/// Critical-band rate in Bark for a frequency in Hz (Zwicker & Terhardt).
+pub fn hz_to_bark(hz: f32) -> f32 {
+    13.0 * (0.00076 * hz).atan() + 3.5 * (hz / 7500.0).powi(2).atan()
+}
+
+/// Bark band (0-23) of each bin of a `frame_size` spectrum, `None` above the last band.
+fn bark_bins(sample_rate: usize, frame_size: usize) -> Vec<Option<usize>> {
+    (0..frame_size / 2)
+        .map(|bin| {
+            let band = hz_to_bark(bin as f32 * sample_rate as f32 / frame_size as f32) as usize;
+            (band < BARK_BANDS).then_some(band)
+        })
+        .collect()
+}
+
+fn bark_energies(magnitude: &[f32], bins: &[Option<usize>], energies: &mut [f32; BARK_BANDS]) {
+    energies.fill(0.0);
+    for (&m, band) in magnitude.iter().zip(bins) {
+        if let Some(band) = band {
+            energies[*band] += m * m;
+        }
+    }
+}
+
+/// Mean specific loudness per Bark band, in arbitrary units (levels are relative to full scale).
+fn specific_loudness(samples: &[f32], sample_rate: usize) -> [f32; BARK_BANDS] {
+    let bins = bark_bins(sample_rate, FRAME_SIZE);
+    let mut loudness = [0.0f32; BARK_BANDS];
+    let mut energies = [0.0f32; BARK_BANDS];
+    let mut frame_count = 0;
+
+    for_each_spectrum(samples, FRAME_SIZE, HOP_SIZE, |magnitude| {
+        bark_energies(magnitude, &bins, &mut energies);
+        for (total, &energy) in loudness.iter_mut().zip(&energies) {
+            *total += energy.powf(LOUDNESS_EXPONENT);
+        }
+        frame_count += 1;
+    });
+
+    if frame_count > 0 {
+        loudness.iter_mut().for_each(|n| *n /= frame_count as f32);
+    }
+    loudness
+}
+
+/// Zwicker sharpness in acum, with the DIN 45692 high-band weighting. `None` for silence.
+pub fn sharpness(samples: &[f32], sample_rate: usize) -> Option<f32> {
+    let loudness = specific_loudness(samples, sample_rate);
+    let total: f32 = loudness.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    // Each band is weighted at its center, half a Bark above its lower edge
+    let weighted: f32 = loudness
+        .iter()
+        .enumerate()
+        .map(|(band, &n)| {
+            let z = band as f32 + 0.5;
+            let g = if z < 15.8 {
+                1.0
+            } else {
+                0.15 * (0.42 * (z - 15.8)).exp() + 0.85
+            };
+            n * g * z
+        })
+        .sum();
+
+    Some(0.11 * weighted / total)
+}
+
+/// Roughness in asper, approximated from how deeply each Bark band's envelope is modulated
+/// in the 15-300 Hz range. `None` when the track is too short or silent.
+pub fn roughness(samples: &[f32], sample_rate: usize) -> Option<f32> {
+    let bins = bark_bins(sample_rate, ROUGHNESS_FRAME_SIZE);
+    let mut envelopes: Vec<Vec<f32>> = vec![Vec::new(); BARK_BANDS];
+    let mut energies = [0.0f32; BARK_BANDS];
+
+    for_each_spectrum(
+        samples,
+        ROUGHNESS_FRAME_SIZE,
+        ROUGHNESS_HOP_SIZE,
+        |magnitude| {
+            bark_energies(magnitude, &bins, &mut energies);
+            for (envelope, &energy) in envelopes.iter_mut().zip(&energies) {
+                envelope.push(energy.sqrt());
+            }
+        },
+    );
+
+    let frame_count = envelopes[0].len();
+    if frame_count < MODULATION_BLOCK {
+        return None;
+    }
+
+    let frame_rate = sample_rate as f32 / ROUGHNESS_HOP_SIZE as f32;
+    let weights: Vec<f32> = (0..MODULATION_BLOCK / 2)
+        .map(|bin| {
+            let hz = bin as f32 * frame_rate / MODULATION_BLOCK as f32;
+            if (MODULATION_MIN_HZ..=MODULATION_MAX_HZ).contains(&hz) {
+                let octaves = (hz / ROUGHNESS_PEAK_HZ).log2() / ROUGHNESS_WIDTH_OCTAVES;
+                (-0.5 * octaves * octaves).exp()
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    // Quiet bands contribute in proportion to their loudness, so hiss riding on a loud
+    // mix doesn't read as rough
+    let band_loudness: Vec<f32> = envelopes
+        .iter()
+        .map(|envelope| {
+            let mean = envelope.iter().sum::<f32>() / envelope.len() as f32;
+            (mean * mean).powf(LOUDNESS_EXPONENT)
+        })
+        .collect();
+    let loudest = band_loudness.iter().cloned().fold(0.0f32, f32::max);
+    if loudest <= 0.0 {
+        return None;
+    }
+
+    let fft = plan_fft(MODULATION_BLOCK);
+    let window: Vec<f32> = (0..MODULATION_BLOCK)
+        .map(|i| {
+            0.5 * (1.0
+                - (2.0 * std::f32::consts::PI * i as f32 / (MODULATION_BLOCK as f32 - 1.0)).cos())
+        })
+        .collect();
+    let window_sum: f32 = window.iter().sum();
+    let mut buffer = vec![Complex::new(0.0f32, 0.0); MODULATION_BLOCK];
+
+    let mut total = 0.0;
+    let mut block_count = 0;
+    for start in (0..=frame_count - MODULATION_BLOCK).step_by(MODULATION_BLOCK) {
+        let mut block_roughness = 0.0;
+
+        for (envelope, &loudness) in envelopes.iter().zip(&band_loudness) {
+            let block = &envelope[start..start + MODULATION_BLOCK];
+            let mean = block.iter().sum::<f32>() / MODULATION_BLOCK as f32;
+            if mean <= 0.0 {
+                continue;
+            }
+
+            for ((slot, &value), &w) in buffer.iter_mut().zip(block).zip(&window) {
+                *slot = Complex::new((value - mean) * w, 0.0);
+            }
+            fft.process(&mut buffer);
+
+            // Strongest perceptually weighted modulation, as a depth relative to the mean
+            let depth = buffer[..MODULATION_BLOCK / 2]
+                .iter()
+                .zip(&weights)
+                .map(|(c, &weight)| 2.0 * c.norm() / window_sum / mean * weight)
+                .fold(0.0f32, f32::max)
+                .min(MAX_MODULATION_DEPTH);
+
+            let level_swing = 20.0 * ((1.0 + depth) / (1.0 - depth)).log10();
+            block_roughness += level_swing * loudness / loudest;
+        }
+
+        total += block_roughness;
+        block_count += 1;
+    }
+
+    Some(ROUGHNESS_CALIBRATION * total / block_count as f32)
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frequency_bands::FrequencyBand;
+
+// Edges of the low, mid, and high thirds; bands go to the third holding their center
+pub const LOW_MAX_HZ: f32 = 250.0;
+pub const HIGH_MIN_HZ: f32 = 4000.0;
+
+/// The band distribution folded into low, mid, and high thirds, with the ratios between
+/// them that are easier to compare than the individual bands.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EnergyRatios {
+    pub low_percent: f32,
+    pub mid_percent: f32,
+    pub high_percent: f32,
+    pub low_high_db: Option<f32>, // Low over high; positive means bass-heavy. None if either is empty
+    pub mid_prominence_db: Option<f32>, // Mid over the mean of low and high
+}
+
+fn ratio_db(numerator: f32, denominator: f32) -> Option<f32> {
+    (numerator > 0.0 && denominator > 0.0).then(|| 10.0 * (numerator / denominator).log10())
+}
+
+/// Fold band shares into thirds by each band's geometric center. `None` when the track
+/// has no energy.
+pub fn energy_ratios(band_percentages: &[f32], bands: &[FrequencyBand]) -> Option<EnergyRatios> {
+    let mut thirds = [0.0f32; 3];
+    for (&pct, band) in band_percentages.iter().zip(bands) {
+        let center = (band.low_hz.max(1) as f32 * band.high_hz.max(1) as f32).sqrt();
+        let third = if center < LOW_MAX_HZ {
+            0
+        } else if center < HIGH_MIN_HZ {
+            1
+        } else {
+            2
+        };
+        thirds[third] += pct;
+    }
+    let [low, mid, high] = thirds;
+    if low + mid + high <= 0.0 {
+        return None;
+    }
+
+    Some(EnergyRatios {
+        low_percent: low,
+        mid_percent: mid,
+        high_percent: high,
+        low_high_db: ratio_db(low, high),
+        mid_prominence_db: ratio_db(mid, (low + high) / 2.0),
+    })
+}
@@ -0,0 +1,207 @@
+use crate::utils::{CachedMetrics, truncate_filename};
+
+// Average bitrates within this share of the release median count as the same encode
+const BITRATE_TOLERANCE: f32 = 0.1;
+
+/// Tolerances for `check-release`.
+#[derive(clap::Args, Clone)]
+pub struct ReleaseTolerances {
+    /// Maximum loudness spread between the quietest and loudest track, in LU
+    #[arg(long, value_name = "LU", default_value_t = 2.0)]
+    pub loudness_tolerance: f32,
+
+    /// How far a track's band balance may stray from the release average (percentage points)
+    #[arg(long, value_name = "POINTS", default_value_t = 12.0)]
+    pub tonal_tolerance: f32,
+}
+
+pub struct RuleResult {
+    pub name: &'static str,
+    pub passed: bool,
+    pub details: Vec<String>,
+}
+
+pub fn check_release(tracks: &[&CachedMetrics], tolerances: &ReleaseTolerances) -> Vec<RuleResult> {
+    vec![
+        check_loudness(tracks, tolerances.loudness_tolerance),
+        check_sample_rate(tracks),
+        check_bitrate(tracks),
+        check_tonal_balance(tracks, tolerances.tonal_tolerance),
+    ]
+}
+
+fn check_loudness(tracks: &[&CachedMetrics], tolerance: f32) -> RuleResult {
+    // Integrated loudness when every track has it, otherwise the RMS level
+    let (unit, levels): (&str, Vec<f32>) = match tracks
+        .iter()
+        .map(|t| t.integrated_lufs)
+        .collect::<Option<Vec<f32>>>()
+    {
+        Some(lufs) => ("LUFS", lufs),
+        None => ("dB RMS", tracks.iter().map(|t| t.loudness).collect()),
+    };
+
+    let (quietest, loudest) = min_max(tracks, &levels);
+    let spread = loudest.1 - quietest.1;
+    RuleResult {
+        name: "Loudness matched",
+        passed: spread <= tolerance,
+        details: vec![format!(
+            "spread {:.1} LU (limit {:.1}): quietest {} at {:.1} {}, loudest {} at {:.1} {}",
+            spread, tolerance, quietest.0, quietest.1, unit, loudest.0, loudest.1, unit
+        )],
+    }
+}
+
+fn check_sample_rate(tracks: &[&CachedMetrics]) -> RuleResult {
+    let rates: Vec<Option<u32>> = tracks
+        .iter()
+        .map(|t| t.stream.as_ref().map(|s| s.sample_rate))
+        .collect();
+    let expected = most_common(&rates);
+
+    let details: Vec<String> = tracks
+        .iter()
+        .zip(&rates)
+        .filter(|(_, rate)| **rate != expected)
+        .map(|(track, rate)| match rate {
+            Some(rate) => format!("{}: {} Hz", short_name(track), rate),
+            None => format!("{}: unknown sample rate", short_name(track)),
+        })
+        .collect();
+
+    RuleResult {
+        name: "Same sample rate",
+        passed: details.is_empty() && expected.is_some(),
+        details: if details.is_empty() {
+            expected
+                .map(|rate| vec![format!("all tracks at {} Hz", rate)])
+                .unwrap_or_default()
+        } else {
+            details
+        },
+    }
+}
+
+fn check_bitrate(tracks: &[&CachedMetrics]) -> RuleResult {
+    let bitrates: Vec<Option<f32>> = tracks
+        .iter()
+        .map(|t| t.stream.as_ref().map(|s| s.bitrate_kbps))
+        .collect();
+
+    let mut known: Vec<f32> = bitrates.iter().flatten().copied().collect();
+    known.sort_by(f32::total_cmp);
+    let Some(&median) = known.get(known.len() / 2) else {
+        return RuleResult {
+            name: "Same bitrate",
+            passed: false,
+            details: vec!["no bitrate information".to_string()],
+        };
+    };
+
+    let details: Vec<String> = tracks
+        .iter()
+        .zip(&bitrates)
+        .filter_map(|(track, bitrate)| match bitrate {
+            Some(b) if (b / median - 1.0).abs() <= BITRATE_TOLERANCE => None,
+            Some(b) => Some(format!(
+                "{}: {:.0} kbps (release median {:.0})",
+                short_name(track),
+                b,
+                median
+            )),
+            None => Some(format!("{}: unknown bitrate", short_name(track))),
+        })
+        .collect();
+
+    RuleResult {
+        name: "Same bitrate",
+        passed: details.is_empty(),
+        details: if details.is_empty() {
+            vec![format!("all tracks near {:.0} kbps", median)]
+        } else {
+            details
+        },
+    }
+}
+
+fn check_tonal_balance(tracks: &[&CachedMetrics], tolerance: f32) -> RuleResult {
+    let band_count = tracks.first().map_or(0, |t| t.band_percentages.len());
+    let comparable: Vec<&&CachedMetrics> = tracks
+        .iter()
+        .filter(|t| t.band_percentages.len() == band_count && band_count > 0)
+        .collect();
+
+    let mut average = vec![0.0f32; band_count];
+    for track in &comparable {
+        for (sum, pct) in average.iter_mut().zip(&track.band_percentages) {
+            *sum += pct / comparable.len() as f32;
+        }
+    }
+
+    let details: Vec<String> = comparable
+        .iter()
+        .filter_map(|track| {
+            let distance = track
+                .band_percentages
+                .iter()
+                .zip(&average)
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum::<f32>()
+                .sqrt();
+            (distance > tolerance).then(|| {
+                format!(
+                    "{}: {:.1} points from the release average (limit {:.1})",
+                    short_name(track),
+                    distance,
+                    tolerance
+                )
+            })
+        })
+        .collect();
+
+    RuleResult {
+        name: "No tonal outliers",
+        passed: details.is_empty(),
+        details,
+    }
+}
+
+fn min_max(tracks: &[&CachedMetrics], values: &[f32]) -> ((String, f32), (String, f32)) {
+    let mut quietest = (String::new(), f32::INFINITY);
+    let mut loudest = (String::new(), f32::NEG_INFINITY);
+    for (track, &value) in tracks.iter().zip(values) {
+        if value < quietest.1 {
+            quietest = (short_name(track), value);
+        }
+        if value > loudest.1 {
+            loudest = (short_name(track), value);
+        }
+    }
+    (quietest, loudest)
+}
+
+fn most_common<T: PartialEq + Copy + Default>(values: &[T]) -> T {
+    values
+        .iter()
+        .max_by_key(|v| values.iter().filter(|w| w == v).count())
+        .copied()
+        .unwrap_or_default()
+}
+
+fn short_name(track: &CachedMetrics) -> String {
+    truncate_filename(&track.filename, 40)
+}
+
+pub fn print_report(results: &[RuleResult]) {
+    for result in results {
+        println!(
+            "[{}] {}",
+            if result.passed { "PASS" } else { "FAIL" },
+            result.name
+        );
+        for detail in &result.details {
+            println!("       {}", detail);
+        }
+    }
+}
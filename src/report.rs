@@ -0,0 +1,302 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::frequency_bands::FrequencyBand;
+use crate::normalization::{PLATFORMS, playback_gain};
+use crate::summary::sparkline;
+use crate::surround::LFE_CROSSOVER_HZ;
+use crate::tonal::{TonalCurve, balance_score, band_deviations};
+use crate::utils::CachedMetrics;
+
+// Bands within this many dB of the reference curve count as balanced
+const BALANCED_DB: f32 = 3.0;
+
+pub struct ReportSection {
+    pub title: &'static str,
+    pub rows: Vec<(String, String)>,
+}
+
+/// A one-page mastering QC summary for a single track.
+pub struct ReportCard {
+    pub filename: String,
+    pub sections: Vec<ReportSection>,
+}
+
+fn row(label: &str, value: String) -> (String, String) {
+    (label.to_string(), value)
+}
+
+fn measured(value: Option<f32>, unit: &str) -> String {
+    match value {
+        Some(value) => format!("{:.1} {}", value, unit),
+        None => "not measured".to_string(),
+    }
+}
+
+fn clock(seconds: f32) -> String {
+    format!("{}:{:02}", seconds as u32 / 60, seconds as u32 % 60)
+}
+
+pub fn build_report(
+    entry: &CachedMetrics,
+    bands: &[FrequencyBand],
+    curve_name: &str,
+    curve: &TonalCurve,
+    issues: &[String],
+) -> ReportCard {
+    let mut overview = vec![row("Duration", clock(entry.duration_seconds))];
+    if let Some(stream) = &entry.stream {
+        overview.push(row("Stream", stream.summary()));
+    }
+    if let Some(tempo) = &entry.tempo {
+        overview.push(row("Tempo", format!("{:.1} BPM", tempo.bpm)));
+    }
+    if let Some(key) = &entry.key {
+        overview.push(row("Key", key.to_string()));
+    }
+
+    let mut loudness = vec![
+        row("RMS level", format!("{:.1} dB", entry.loudness)),
+        row("Integrated", measured(entry.integrated_lufs, "LUFS")),
+        row("True peak", measured(entry.true_peak_db, "dBTP")),
+    ];
+    if let Some(lufs) = entry.integrated_lufs {
+        for platform in PLATFORMS {
+            let gain = playback_gain(platform, lufs, entry.true_peak_db);
+            loudness.push(row(platform.name, format!("{:+.1} dB on playback", gain)));
+        }
+    }
+
+    let peak_to_loudness = entry
+        .true_peak_db
+        .zip(entry.integrated_lufs)
+        .map(|(peak, lufs)| peak - lufs);
+    let dynamics = vec![
+        row("Loudness range", measured(entry.loudness_range, "LU")),
+        row("Peak to loudness", measured(peak_to_loudness, "LU")),
+        row(
+            "DR score",
+            match entry.dr_score {
+                Some(dr) => format!("DR{:.0} ({:.1} dB)", dr, dr),
+                None => "not measured".to_string(),
+            },
+        ),
+        row(
+            "Crest factor",
+            match &entry.dynamics {
+                Some(d) => format!("{:.1} dB (RMS {:.1} dBFS)", d.crest_factor_db, d.rms_db),
+                None => "not measured".to_string(),
+            },
+        ),
+        row(
+            "Onset strength",
+            match entry.onset_strength {
+                Some(strength) => format!("{:.1} / 100", strength),
+                None => "not measured".to_string(),
+            },
+        ),
+    ];
+
+    let mut tonal = vec![
+        row("Centroid", format!("{:.1} / 100", entry.centroid)),
+        row(
+            "Exact centroid",
+            match entry.centroid_hz.zip(entry.exact_centroid) {
+                Some((hz, position)) => format!("{:.0} Hz ({:.1} / 100)", hz, position),
+                None => "not measured".to_string(),
+            },
+        ),
+        row("Tilt", measured(entry.tilt, "dB/octave")),
+        row("Rolloff (85%)", measured(entry.rolloff, "Hz")),
+        row(
+            "Cutoff",
+            measured(entry.bandwidth.as_ref().map(|b| b.cutoff_hz), "Hz"),
+        ),
+        row("Spread", format!("{:.1} / 100", entry.spread)),
+        row("Sharpness", measured(entry.sharpness, "acum")),
+    ];
+    let reference = curve.band_shares(bands);
+    let deviations = band_deviations(&entry.band_percentages, &reference);
+    for ((band, pct), deviation) in bands.iter().zip(&entry.band_percentages).zip(&deviations) {
+        let versus = match deviation {
+            Some(db) => format!("{:+.1} dB vs {}", db, curve_name),
+            None => "silent".to_string(),
+        };
+        tonal.push(row(
+            &format!("{}-{} Hz", band.low_hz, band.high_hz),
+            format!("{:5.1}%  {}", pct, versus),
+        ));
+    }
+    let balanced = deviations
+        .iter()
+        .flatten()
+        .filter(|db| db.abs() <= BALANCED_DB)
+        .count();
+    tonal.push(row(
+        "Balance",
+        format!(
+            "{:.0}/100 ({} of {} bands within {} dB of {})",
+            balance_score(&deviations),
+            balanced,
+            deviations.len(),
+            BALANCED_DB,
+            curve_name
+        ),
+    ));
+
+    let stereo = match &entry.stereo {
+        Some(stereo) => vec![
+            row("L/R balance", format!("{:+.1} dB", stereo.balance_db)),
+            row("Side level", format!("{:.1} dB vs mid", stereo.side_db)),
+            row(
+                "Mono below",
+                match stereo.mono_below_hz {
+                    Some(hz) if hz > 0.0 => format!("{:.0} Hz", hz),
+                    Some(_) => "wide down to the lowest bass".to_string(),
+                    None => "not measured".to_string(),
+                },
+            ),
+            row(
+                "Image",
+                if stereo.is_dual_mono() {
+                    "dual-mono".to_string()
+                } else {
+                    "stereo".to_string()
+                },
+            ),
+        ],
+        None => vec![row("Image", "mono".to_string())],
+    };
+
+    let mut channels: Vec<(String, String)> = entry
+        .surround
+        .iter()
+        .flatten()
+        .map(|c| {
+            row(
+                &c.name,
+                format!(
+                    "{:5.1}% of energy  {}  (low to high)",
+                    c.share_percent,
+                    sparkline(&c.band_percentages)
+                ),
+            )
+        })
+        .collect();
+
+    let issues = if issues.is_empty() {
+        vec![row("Status", "no issues detected".to_string())]
+    } else {
+        issues
+            .iter()
+            .map(|issue| row("Warning", issue.clone()))
+            .collect()
+    };
+
+    let mut sections = vec![
+        ReportSection {
+            title: "Overview",
+            rows: overview,
+        },
+        ReportSection {
+            title: "Loudness",
+            rows: loudness,
+        },
+        ReportSection {
+            title: "Dynamics",
+            rows: dynamics,
+        },
+        ReportSection {
+            title: "Tonal balance",
+            rows: tonal,
+        },
+        ReportSection {
+            title: "Stereo",
+            rows: stereo,
+        },
+    ];
+    if let Some(lfe) = &entry.lfe {
+        channels.push(row(
+            "LFE range",
+            format!(
+                "{}{:.1}% above the {:.0} Hz crossover",
+                match lfe.upper_edge_hz {
+                    Some(edge) => format!("99% below {:.0} Hz, ", edge),
+                    None => String::new(),
+                },
+                lfe.above_crossover_percent,
+                LFE_CROSSOVER_HZ
+            ),
+        ));
+    }
+    // Only multichannel files have a breakdown
+    if !channels.is_empty() {
+        sections.push(ReportSection {
+            title: "Channels",
+            rows: channels,
+        });
+    }
+    sections.push(ReportSection {
+        title: "Issues",
+        rows: issues,
+    });
+
+    ReportCard {
+        filename: entry.filename.clone(),
+        sections,
+    }
+}
+
+pub fn print_report(card: &ReportCard) {
+    println!("\nMastering report: {}", card.filename);
+    println!("{}", "=".repeat(80));
+    for section in &card.sections {
+        println!("\n{}", section.title);
+        println!("{}", "-".repeat(section.title.len()));
+        for (label, value) in &section.rows {
+            println!("  {:<18} {}", label, value);
+        }
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A self-contained HTML page with one table per section.
+pub fn write_html(path: &Path, card: &ReportCard) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let title = escape_html(&card.filename);
+
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html><head><meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>Mastering report: {}</title>", title)?;
+    writeln!(
+        writer,
+        "<style>body{{font-family:sans-serif;max-width:44em;margin:2em auto}}\
+         table{{border-collapse:collapse;width:100%}}\
+         td{{padding:.2em .6em;border-bottom:1px solid #ddd}}\
+         td:first-child{{width:12em;color:#555}}</style>"
+    )?;
+    writeln!(writer, "</head><body>")?;
+    writeln!(writer, "<h1>Mastering report: {}</h1>", title)?;
+    for section in &card.sections {
+        writeln!(writer, "<h2>{}</h2>\n<table>", section.title)?;
+        for (label, value) in &section.rows {
+            writeln!(
+                writer,
+                "<tr><td>{}</td><td>{}</td></tr>",
+                escape_html(label),
+                escape_html(value)
+            )?;
+        }
+        writeln!(writer, "</table>")?;
+    }
+    writeln!(writer, "</body></html>")?;
+
+    writer.flush()
+}
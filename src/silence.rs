@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use crate::structure::Section;
+
+// Level is read in blocks this long; shorter dropouts don't count as silence
+const BLOCK_SECONDS: f32 = 0.05;
+// RMS at or below this counts as silent: well under any fade or room tone
+pub const SILENCE_THRESHOLD_DB: f32 = -60.0;
+// Internal stretches at least this long are reported as gaps
+pub const MIN_GAP_SECONDS: f32 = 2.0;
+// Edges past these are flagged as badly trimmed; tails get more room for reverb tails and
+// hidden-track pauses are caught as gaps anyway
+const MAX_LEADING_SECONDS: f32 = 2.0;
+const MAX_TRAILING_SECONDS: f32 = 5.0;
+
+/// Silent stretches at the start, at the end, and in between.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Silence {
+    pub leading_seconds: f32,
+    pub trailing_seconds: f32,
+    pub gaps: Vec<Section>, // Internal stretches of at least `MIN_GAP_SECONDS`
+}
+
+impl Silence {
+    pub fn total_seconds(&self) -> f32 {
+        self.leading_seconds
+            + self.trailing_seconds
+            + self
+                .gaps
+                .iter()
+                .map(|gap| gap.end_seconds - gap.start_seconds)
+                .sum::<f32>()
+    }
+
+    /// A description of each badly trimmed edge or gap, empty for a clean rip.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.leading_seconds > MAX_LEADING_SECONDS {
+            problems.push(format!(
+                "{:.1} s of silence at the start",
+                self.leading_seconds
+            ));
+        }
+        if self.trailing_seconds > MAX_TRAILING_SECONDS {
+            problems.push(format!(
+                "{:.1} s of silence at the end",
+                self.trailing_seconds
+            ));
+        }
+        if !self.gaps.is_empty() {
+            problems.push(format!("{} silent gap(s) inside", self.gaps.len()));
+        }
+        problems
+    }
+}
+
+/// Find silence from the RMS level of consecutive 50 ms blocks of the mono downmix. A
+/// track that never rises above the threshold is all leading silence.
+pub fn detect_silence(samples: &[f32], sample_rate: usize) -> Silence {
+    let block = ((BLOCK_SECONDS * sample_rate as f32) as usize).max(1);
+    let threshold = 10f32.powf(SILENCE_THRESHOLD_DB / 10.0); // Mean square, not amplitude
+    let silent: Vec<bool> = samples
+        .chunks(block)
+        .map(|chunk| chunk.iter().map(|&x| x * x).sum::<f32>() / chunk.len() as f32 <= threshold)
+        .collect();
+    let seconds = |blocks: usize| (blocks * block).min(samples.len()) as f32 / sample_rate as f32;
+
+    let Some(first) = silent.iter().position(|&s| !s) else {
+        return Silence {
+            leading_seconds: seconds(silent.len()),
+            ..Silence::default()
+        };
+    };
+    let last = silent.iter().rposition(|&s| !s).unwrap_or(first);
+
+    let mut gaps = Vec::new();
+    let mut run_start = None;
+    for (index, &is_silent) in silent.iter().enumerate().take(last + 1).skip(first) {
+        match (is_silent, run_start) {
+            (true, None) => run_start = Some(index),
+            (false, Some(start)) => {
+                let gap = Section {
+                    start_seconds: seconds(start),
+                    end_seconds: seconds(index),
+                };
+                if gap.end_seconds - gap.start_seconds >= MIN_GAP_SECONDS {
+                    gaps.push(gap);
+                }
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    Silence {
+        leading_seconds: seconds(first),
+        trailing_seconds: seconds(silent.len()) - seconds(last + 1),
+        gaps,
+    }
+}
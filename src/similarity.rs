@@ -0,0 +1,59 @@
+use crate::frequency_bands::SpectrumMetrics;
+
+// Share of the distance taken by rhythm when both tracks have a fingerprint
+pub const DEFAULT_RHYTHM_WEIGHT: f32 = 0.4;
+
+// Within rhythm, how much the onset pattern counts against the tempo difference
+const PATTERN_WEIGHT: f32 = 0.7;
+
+/// Euclidean distance over band percentages, centroid, and ZCR (all 0-100 scales).
+pub fn timbre_distance(a: &SpectrumMetrics, b: &SpectrumMetrics) -> f32 {
+    let bands: f32 = a
+        .band_percentages
+        .iter()
+        .zip(&b.band_percentages)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum();
+    let centroid = (a.centroid.0 - b.centroid.0).powi(2);
+    let zcr = (a.zero_crossing_rate - b.zero_crossing_rate).powi(2);
+
+    (bands + centroid + zcr).sqrt()
+}
+
+/// Rhythm distance on a 0-100 scale from the onset patterns and tempos, or `None` when
+/// either track has no rhythm fingerprint.
+pub fn rhythm_distance(a: &SpectrumMetrics, b: &SpectrumMetrics) -> Option<f32> {
+    if a.rhythm_pattern.is_empty() || a.rhythm_pattern.len() != b.rhythm_pattern.len() {
+        return None;
+    }
+
+    // Both patterns sum to 1, so half the L1 distance lies in 0-1
+    let pattern: f32 = a
+        .rhythm_pattern
+        .iter()
+        .zip(&b.rhythm_pattern)
+        .map(|(x, y)| (x - y).abs())
+        .sum::<f32>()
+        / 2.0
+        * 100.0;
+
+    // Fold tempo ratios by octave so half/double-time tracks still mix together
+    let tempo = match (&a.tempo, &b.tempo) {
+        (Some(ta), Some(tb)) => {
+            let octaves = (ta.bpm / tb.bpm).log2();
+            (octaves - octaves.round()).abs() * 200.0
+        }
+        _ => 0.0,
+    };
+
+    Some(PATTERN_WEIGHT * pattern + (1.0 - PATTERN_WEIGHT) * tempo)
+}
+
+/// Combined distance: timbre, blended with rhythm when both fingerprints are available.
+pub fn distance(a: &SpectrumMetrics, b: &SpectrumMetrics, rhythm_weight: f32) -> f32 {
+    let timbre = timbre_distance(a, b);
+    match rhythm_distance(a, b) {
+        Some(rhythm) => (1.0 - rhythm_weight) * timbre + rhythm_weight * rhythm,
+        None => timbre,
+    }
+}
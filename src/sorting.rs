@@ -0,0 +1,51 @@
+use std::cmp::Ordering;
+
+use crate::frequency_bands::SpectrumMetrics;
+use crate::warnings::{RULE_METRICS, metric_value};
+
+/// One `--sort-by` key: a metric from `RULE_METRICS`, lowest first unless prefixed with
+/// `-`. A `+` prefix is accepted for symmetry.
+#[derive(Clone, Debug)]
+pub struct SortKey {
+    pub metric: String,
+    pub descending: bool,
+}
+
+pub fn parse_sort_key(value: &str) -> Result<SortKey, String> {
+    let value = value.trim();
+    let (metric, descending) = match value.strip_prefix('-') {
+        Some(metric) => (metric, true),
+        None => (value.strip_prefix('+').unwrap_or(value), false),
+    };
+    if !RULE_METRICS.contains(&metric) {
+        return Err(format!(
+            "unknown metric '{}' (expected one of: {})",
+            metric,
+            RULE_METRICS.join(", ")
+        ));
+    }
+    Ok(SortKey {
+        metric: metric.to_string(),
+        descending,
+    })
+}
+
+/// Compare two tracks key by key. A track without a reading for a key goes after those
+/// with one, whatever the direction, and tracks that tie on every key fall back to
+/// filename order so the result never depends on the order they came in.
+pub fn compare_tracks(
+    keys: &[SortKey],
+    (a_name, a): (&str, &SpectrumMetrics),
+    (b_name, b): (&str, &SpectrumMetrics),
+) -> Ordering {
+    keys.iter()
+        .map(
+            |key| match (metric_value(a, &key.metric), metric_value(b, &key.metric)) {
+                (Some(x), Some(y)) if key.descending => y.total_cmp(&x),
+                (Some(x), Some(y)) => x.total_cmp(&y),
+                (x, y) => y.is_some().cmp(&x.is_some()),
+            },
+        )
+        .find(|order| order.is_ne())
+        .unwrap_or_else(|| a_name.cmp(b_name))
+}
@@ -0,0 +1,135 @@
+use crate::frequency_bands::{FRAME_SIZE, HOP_SIZE, for_each_spectrum, paint};
+
+const MIN_HZ: f32 = 20.0;
+const RANGE_DB: f32 = 60.0; // Cells this far below the loudest one print blank
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+const LABEL_WIDTH: usize = 6; // Frequency axis, e.g. " 10k │"
+
+/// Power per cell, `rows[0]` being the highest frequencies, with time running left to
+/// right across `width` columns.
+pub struct Spectrogram {
+    pub rows: Vec<Vec<f64>>,
+    pub row_edges_hz: Vec<f32>, // Lower edge of each row, top row first
+    pub duration_seconds: f32,
+}
+
+/// Average each column's frames into `height` log-spaced frequency rows from 20 Hz up to
+/// Nyquist.
+pub fn compute(samples: &[f32], sample_rate: usize, width: usize, height: usize) -> Spectrogram {
+    let nyquist = sample_rate as f32 / 2.0;
+    let edge = |row: usize| MIN_HZ * (nyquist / MIN_HZ).powf(row as f32 / height as f32);
+    let bin_hz = sample_rate as f32 / FRAME_SIZE as f32;
+    // Low rows are narrower than a bin, so each takes at least the bin nearest its range
+    let bins: Vec<(usize, usize)> = (0..height)
+        .map(|row| {
+            let low = (edge(row) / bin_hz).round() as usize;
+            let high = ((edge(row + 1) / bin_hz).round() as usize).min(FRAME_SIZE / 2);
+            (
+                low.min(FRAME_SIZE / 2 - 1),
+                high.max(low + 1).min(FRAME_SIZE / 2),
+            )
+        })
+        .collect();
+
+    let frame_count = samples.len().saturating_sub(FRAME_SIZE).div_ceil(HOP_SIZE);
+    let mut power = vec![vec![0.0f64; width]; height];
+    let mut frames_per_column = vec![0usize; width];
+    let mut frame = 0;
+    for_each_spectrum(samples, FRAME_SIZE, HOP_SIZE, |magnitude| {
+        let column = frame * width / frame_count;
+        for (row, &(low, high)) in bins.iter().enumerate() {
+            let sum: f64 = magnitude[low..high]
+                .iter()
+                .map(|&m| m as f64 * m as f64)
+                .sum();
+            power[row][column] += sum / (high - low) as f64;
+        }
+        frames_per_column[column] += 1;
+        frame += 1;
+    });
+    for row in power.iter_mut() {
+        for (cell, &frames) in row.iter_mut().zip(&frames_per_column) {
+            *cell /= frames.max(1) as f64;
+        }
+    }
+
+    power.reverse();
+    Spectrogram {
+        rows: power,
+        row_edges_hz: (0..height).rev().map(edge).collect(),
+        duration_seconds: samples.len() as f32 / sample_rate as f32,
+    }
+}
+
+fn frequency_label(hz: f32) -> String {
+    if hz >= 1000.0 {
+        format!("{:.0}k", hz / 1000.0)
+    } else {
+        format!("{:.0}", hz)
+    }
+}
+
+fn clock(seconds: f32) -> String {
+    format!("{}:{:02}", seconds as u32 / 60, seconds as u32 % 60)
+}
+
+/// The heatmap as text lines, with a frequency axis on the left and a time axis below.
+pub fn render(spectrogram: &Spectrogram) -> Vec<String> {
+    let loudest = spectrogram
+        .rows
+        .iter()
+        .flatten()
+        .copied()
+        .fold(0.0f64, f64::max);
+    let width = spectrogram.rows.first().map_or(0, Vec::len);
+    let height = spectrogram.rows.len();
+
+    let mut lines: Vec<String> = spectrogram
+        .rows
+        .iter()
+        .zip(&spectrogram.row_edges_hz)
+        .enumerate()
+        .map(|(index, (row, &hz))| {
+            // Label every fourth row and the bottom one
+            let label = if index % 4 == 0 || index + 1 == height {
+                frequency_label(hz)
+            } else {
+                String::new()
+            };
+            let cells: String = row
+                .iter()
+                .map(|&power| {
+                    let db = 10.0 * (power / loudest).max(f64::MIN_POSITIVE).log10() as f32;
+                    let level = ((db + RANGE_DB) / RANGE_DB).clamp(0.0, 1.0);
+                    let shade = (level * (SHADES.len() - 1) as f32).round() as usize;
+                    paint(&SHADES[shade].to_string(), level * 100.0)
+                })
+                .collect();
+            format!("{:>w$} │{}", label, cells, w = LABEL_WIDTH - 2)
+        })
+        .collect();
+
+    lines.push(format!(
+        "{}└{}",
+        " ".repeat(LABEL_WIDTH - 1),
+        "─".repeat(width)
+    ));
+    let end = clock(spectrogram.duration_seconds);
+    let middle = clock(spectrogram.duration_seconds / 2.0);
+    let gap = width.saturating_sub(4 + middle.len() + end.len()) / 2;
+    lines.push(format!(
+        "{}{}{}{}{}{}",
+        " ".repeat(LABEL_WIDTH),
+        "0:00",
+        " ".repeat(gap),
+        middle,
+        " ".repeat(width.saturating_sub(4 + gap + middle.len() + end.len())),
+        end
+    ));
+    lines
+}
+
+/// Columns left for the heatmap in a terminal `columns` wide.
+pub fn plot_width(columns: usize) -> usize {
+    columns.saturating_sub(LABEL_WIDTH).max(1)
+}
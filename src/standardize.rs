@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+// Format details and positions in time, not measurements
+const SKIPPED_FIELDS: &[&str] = &["stream", "chorus"];
+
+/// Mean and standard deviation of every measured number across a set of tracks, keyed by
+/// its path in the serialized metrics, e.g. `tempo.bpm` or `band_percentages.2`.
+pub struct Distribution {
+    pub track_count: usize,
+    stats: HashMap<String, (f64, f64)>,
+}
+
+// Each fractional leaf under `value`, with its dotted path. Arrays of objects, such as
+// silent gaps, have no per-index meaning and are left out.
+fn leaves(value: &Value, path: &str, out: &mut Vec<(String, f64)>) {
+    match value {
+        Value::Number(number) if number.is_f64() => {
+            out.push((path.to_string(), number.as_f64().unwrap_or_default()));
+        }
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                if path.is_empty() && SKIPPED_FIELDS.contains(&key.as_str()) {
+                    continue;
+                }
+                leaves(field, &join(path, key), out);
+            }
+        }
+        Value::Array(items) if items.iter().all(Value::is_number) => {
+            for (index, item) in items.iter().enumerate() {
+                leaves(item, &join(path, &index.to_string()), out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+// The leaf at `path`, for rewriting in place
+fn leaf_mut<'a>(value: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Object(fields) => fields.get_mut(key),
+        Value::Array(items) => items.get_mut(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+impl Distribution {
+    /// Gather the distribution from anything serialized under the cache's keys.
+    pub fn from_tracks<T: Serialize>(tracks: &[T]) -> Distribution {
+        let mut samples: HashMap<String, Vec<f64>> = HashMap::new();
+        for track in tracks {
+            let mut values = Vec::new();
+            leaves(
+                &serde_json::to_value(track).unwrap_or_default(),
+                "",
+                &mut values,
+            );
+            for (path, value) in values {
+                samples.entry(path).or_default().push(value);
+            }
+        }
+
+        let stats = samples
+            .into_iter()
+            .map(|(path, values)| {
+                let n = values.len() as f64;
+                let mean = values.iter().sum::<f64>() / n;
+                let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+                (path, (mean, variance.sqrt()))
+            })
+            .collect();
+        Distribution {
+            track_count: tracks.len(),
+            stats,
+        }
+    }
+
+    /// A copy of `track` with each measured number replaced by its z-score. Readings that
+    /// don't vary across the distribution come out as 0.
+    pub fn standardize<T: Serialize + DeserializeOwned>(&self, track: &T) -> Option<T> {
+        let mut value = serde_json::to_value(track).ok()?;
+        let mut values = Vec::new();
+        leaves(&value, "", &mut values);
+        for (path, reading) in values {
+            let Some(&(mean, deviation)) = self.stats.get(&path) else {
+                continue;
+            };
+            let z = if deviation > 0.0 {
+                (reading - mean) / deviation
+            } else {
+                0.0
+            };
+            if let (Some(leaf), Some(z)) =
+                (leaf_mut(&mut value, &path), serde_json::Number::from_f64(z))
+            {
+                *leaf = Value::Number(z);
+            }
+        }
+        serde_json::from_value(value).ok()
+    }
+}
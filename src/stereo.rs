@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frequency_bands::for_each_spectrum;
+
+// Bass is scanned in third-octave bands from 20 Hz; above 2 kHz a mono image stops being
+// about bass management
+const CROSSOVER_MIN_HZ: f32 = 20.0;
+const CROSSOVER_MAX_HZ: f32 = 2000.0;
+const CROSSOVER_FRAME_SIZE: usize = 8192;
+const CROSSOVER_HOP_SIZE: usize = 4096;
+// A band counts as mono when its side signal is this far below its mid signal
+const MONO_SIDE_DB: f32 = -20.0;
+// Bands this far below the loudest scanned band are too quiet to matter either way
+const CROSSOVER_SILENT_DB: f32 = -60.0;
+// Level differences beyond this are audible as an off-center image
+const LOPSIDED_DB: f32 = 1.5;
+// Identical channels leave only codec noise in the side signal; real mixes sit far above this
+const DUAL_MONO_SIDE_DB: f32 = -35.0;
+// Balance is tracked in half-second blocks when comparing against a reference version
+const SWAP_BLOCK_SECONDS: f32 = 0.5;
+// Reference versions may be trimmed differently, so blocks are aligned within ±10s
+const SWAP_MAX_LAG_SECONDS: f32 = 10.0;
+const SWAP_MIN_SIMILARITY: f32 = 0.5;
+// Balance swings smaller than this can't tell left from right
+const SWAP_MIN_BALANCE_DB: f32 = 0.5;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Stereo {
+    pub balance_db: f32, // Left energy over right energy; positive leans left
+    #[serde(default)]
+    pub side_db: f32, // Side (L-R) energy relative to mid (L+R)
+    // Frequency below which the mix is effectively mono; 0 when even the lowest bass is wide
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mono_below_hz: Option<f32>,
+}
+
+pub enum ChannelOrder {
+    Matches,
+    Swapped,
+    Inconclusive,
+}
+
+impl Stereo {
+    pub fn is_dual_mono(&self) -> bool {
+        self.side_db < DUAL_MONO_SIDE_DB
+    }
+
+    pub fn is_lopsided(&self) -> bool {
+        self.balance_db.abs() > LOPSIDED_DB
+    }
+
+    /// Which side is louder, or `None` when the balance is within tolerance.
+    pub fn heavy_side(&self) -> Option<&'static str> {
+        if !self.is_lopsided() {
+            None
+        } else if self.balance_db > 0.0 {
+            Some("left")
+        } else {
+            Some("right")
+        }
+    }
+}
+
+/// L/R energy comparison for two-channel audio; `None` for mono or silent tracks.
+pub fn measure_stereo(channels: &[Vec<f32>], sample_rate: usize) -> Option<Stereo> {
+    let [left, right] = channels else {
+        return None;
+    };
+
+    let energy = |samples: &[f32]| {
+        samples
+            .iter()
+            .map(|&x| (x as f64) * (x as f64))
+            .sum::<f64>()
+    };
+    let (left_energy, right_energy) = (energy(left), energy(right));
+    if left_energy <= 0.0 && right_energy <= 0.0 {
+        return None;
+    }
+
+    let (mut mid_energy, mut side_energy) = (0.0f64, 0.0f64);
+    for (&l, &r) in left.iter().zip(right) {
+        mid_energy += ((l + r) as f64).powi(2);
+        side_energy += ((l - r) as f64).powi(2);
+    }
+
+    // A dead channel reads as a very large imbalance rather than infinity
+    let floor = 1e-10 * (left_energy + right_energy);
+    Some(Stereo {
+        balance_db: (10.0 * ((left_energy + floor) / (right_energy + floor)).log10()) as f32,
+        side_db: (10.0 * ((side_energy + floor) / (mid_energy + floor)).log10()) as f32,
+        mono_below_hz: mono_crossover(left, right, sample_rate),
+    })
+}
+
+/// The highest frequency up to which every bass band is effectively mono, i.e. a safe
+/// crossover for mono-summing the low end. Capped at 2 kHz.
+fn mono_crossover(left: &[f32], right: &[f32], sample_rate: usize) -> Option<f32> {
+    let power_spectrum = |samples: &[f32]| {
+        let mut power = vec![0.0f64; CROSSOVER_FRAME_SIZE / 2];
+        for_each_spectrum(
+            samples,
+            CROSSOVER_FRAME_SIZE,
+            CROSSOVER_HOP_SIZE,
+            |magnitude| {
+                for (total, &m) in power.iter_mut().zip(magnitude) {
+                    *total += (m as f64) * (m as f64);
+                }
+            },
+        );
+        power
+    };
+    let mid: Vec<f32> = left.iter().zip(right).map(|(l, r)| (l + r) / 2.0).collect();
+    let mid_power = power_spectrum(&mid);
+    drop(mid);
+    let side: Vec<f32> = left.iter().zip(right).map(|(l, r)| (l - r) / 2.0).collect();
+    let side_power = power_spectrum(&side);
+
+    // (upper edge, mid energy, side energy) per third-octave band
+    let bin_hz = sample_rate as f32 / CROSSOVER_FRAME_SIZE as f32;
+    let max_hz = CROSSOVER_MAX_HZ.min(sample_rate as f32 / 2.0);
+    let mut bands: Vec<(f32, f64, f64)> = Vec::new();
+    let mut low = CROSSOVER_MIN_HZ;
+    while low < max_hz {
+        let high = (low * 2f32.powf(1.0 / 3.0)).min(max_hz);
+        let bins = (low / bin_hz).ceil() as usize..(high / bin_hz).ceil() as usize;
+        let mid_energy: f64 = mid_power.get(bins.clone()).map_or(0.0, |b| b.iter().sum());
+        let side_energy: f64 = side_power.get(bins).map_or(0.0, |b| b.iter().sum());
+        bands.push((high, mid_energy, side_energy));
+        low = high;
+    }
+
+    let loudest = bands
+        .iter()
+        .map(|&(_, mid, side)| mid + side)
+        .fold(0.0f64, f64::max);
+    if loudest <= 0.0 {
+        return None;
+    }
+
+    let mut crossover = 0.0;
+    for &(high, mid, side) in &bands {
+        let level_db = 10.0 * ((mid + side) / loudest).log10();
+        let side_db = 10.0 * (side / mid.max(f64::MIN_POSITIVE)).log10();
+        if level_db >= CROSSOVER_SILENT_DB as f64 && side_db > MONO_SIDE_DB as f64 {
+            break;
+        }
+        crossover = high;
+    }
+    Some(crossover)
+}
+
+/// Whether the channels are in the same order as in another version of the track, judged
+/// by how the L/R balance moves over time.
+pub fn compare_channel_order(
+    channels: &[Vec<f32>],
+    reference: &[Vec<f32>],
+    sample_rate: usize,
+) -> ChannelOrder {
+    let block = ((sample_rate as f32 * SWAP_BLOCK_SECONDS) as usize).max(1);
+    let (Some(ours), Some(theirs)) = (
+        balance_series(channels, block),
+        balance_series(reference, block),
+    ) else {
+        return ChannelOrder::Inconclusive;
+    };
+
+    let loudest = |series: &[f32]| series.iter().fold(0.0f32, |max, &b| max.max(b.abs()));
+    if loudest(&ours) < SWAP_MIN_BALANCE_DB || loudest(&theirs) < SWAP_MIN_BALANCE_DB {
+        return ChannelOrder::Inconclusive;
+    }
+
+    // Cosine similarity keeps the sign of a steady lean, which a correlation would discard
+    let max_lag = (SWAP_MAX_LAG_SECONDS / SWAP_BLOCK_SECONDS) as isize;
+    let best = (-max_lag..=max_lag)
+        .filter_map(|lag| {
+            let pairs = ours.iter().enumerate().filter_map(|(i, &a)| {
+                let j = i as isize + lag;
+                (0..theirs.len() as isize)
+                    .contains(&j)
+                    .then(|| (a, theirs[j as usize]))
+            });
+            let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+            for (a, b) in pairs {
+                dot += a * b;
+                norm_a += a * a;
+                norm_b += b * b;
+            }
+            (norm_a > 0.0 && norm_b > 0.0).then(|| dot / (norm_a * norm_b).sqrt())
+        })
+        .max_by(|a, b| a.abs().total_cmp(&b.abs()));
+
+    match best {
+        Some(similarity) if similarity >= SWAP_MIN_SIMILARITY => ChannelOrder::Matches,
+        Some(similarity) if similarity <= -SWAP_MIN_SIMILARITY => ChannelOrder::Swapped,
+        _ => ChannelOrder::Inconclusive,
+    }
+}
+
+/// Balance in dB for each block, with silent blocks reading 0.
+fn balance_series(channels: &[Vec<f32>], block: usize) -> Option<Vec<f32>> {
+    let [left, right] = channels else {
+        return None;
+    };
+
+    Some(
+        left.chunks(block)
+            .zip(right.chunks(block))
+            .map(|(l, r)| {
+                let left_energy: f32 = l.iter().map(|&x| x * x).sum();
+                let right_energy: f32 = r.iter().map(|&x| x * x).sum();
+                if left_energy <= 0.0 || right_energy <= 0.0 {
+                    0.0
+                } else {
+                    10.0 * (left_energy / right_energy).log10()
+                }
+            })
+            .collect(),
+    )
+}
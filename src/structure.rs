@@ -0,0 +1,274 @@
+use serde::{Deserialize, Serialize};
+
+use crate::beats::{BEATS_PER_BAR, beat_positions};
+use crate::cqt::{CQT_HOP_SIZE, Cqt, pitch_class};
+use crate::frequency_bands::{
+    FRAME_SIZE, FrequencyBand, HOP_SIZE, Transform, band_energies_with_frame,
+    calculate_band_positions, calculate_loudness, for_each_spectrum,
+};
+
+// Choruses are compared as four-bar phrases
+const PHRASE_BARS: usize = 4;
+// Mean chroma cosine above which two phrases count as the same material
+const REPEAT_THRESHOLD: f32 = 0.9;
+// Pitch classes are only collected where notes have clear harmonics
+const CHROMA_MIN_HZ: f32 = 55.0;
+const CHROMA_MAX_HZ: f32 = 5000.0;
+// Phrases scoring within this fraction of the best count as the same section
+const NEAR_TIE: f32 = 0.05;
+// Beat grid used when the track has no detectable tempo
+const FALLBACK_BPM: f32 = 120.0;
+// A drop is a bar at least this much louder than the bars building up to it (4x = +6 dB)
+const DROP_ENERGY_RISE: f32 = 4.0;
+const DROP_BUILDUP_BARS: usize = 4;
+// Intro and outro profiles cover this much of each end of the track
+pub const EDGE_SECONDS: f32 = 30.0;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Section {
+    pub start_seconds: f32,
+    pub end_seconds: f32,
+}
+
+/// Tone and level of a track's opening or closing seconds, to tell which tracks end
+/// bright and which start dark when planning transitions.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EdgeProfile {
+    pub centroid: f32, // Same 0-100 scale as the whole track's centroid
+    pub band_percentages: Vec<f32>,
+    pub loudness: f32, // RMS level in dB
+}
+
+/// Profile an intro or outro excerpt against the track's band layout.
+pub fn edge_profile(
+    samples: &[f32],
+    sample_rate: usize,
+    bands: &[FrequencyBand],
+    frame_size: usize,
+) -> Result<EdgeProfile, Box<dyn std::error::Error>> {
+    let energies = band_energies_with_frame(samples, sample_rate, bands, frame_size)?;
+    let total: f64 = energies.iter().sum();
+    let band_percentages: Vec<f32> = energies
+        .iter()
+        .map(|&e| {
+            if total > 0.0 {
+                (e / total * 100.0) as f32
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let centroid = band_percentages
+        .iter()
+        .zip(calculate_band_positions(bands, sample_rate))
+        .map(|(pct, pos)| pct * pos)
+        .sum::<f32>()
+        / 100.0;
+
+    Ok(EdgeProfile {
+        centroid,
+        band_percentages,
+        loudness: calculate_loudness(samples),
+    })
+}
+
+/// The most repeated high-energy phrase, usually the chorus or hook. Its start is a good
+/// preview offset for players that support one.
+pub fn find_chorus(
+    samples: &[f32],
+    sample_rate: usize,
+    bpm: Option<f32>,
+    transform: Transform,
+) -> Option<Section> {
+    let beats = beat_positions(samples, sample_rate, bpm.unwrap_or(FALLBACK_BPM));
+    let bar_starts: Vec<usize> = beats.iter().step_by(BEATS_PER_BAR).copied().collect();
+    if bar_starts.len() < PHRASE_BARS + 1 {
+        return None;
+    }
+
+    let (chroma, energy) = bar_features(samples, sample_rate, &bar_starts, transform);
+    let phrase_count = bar_starts.len() - PHRASE_BARS;
+
+    let phrase_energy: Vec<f32> = (0..phrase_count)
+        .map(|i| energy[i..i + PHRASE_BARS].iter().sum::<f32>() / PHRASE_BARS as f32)
+        .collect();
+    let max_energy = phrase_energy.iter().cloned().fold(0.0f32, f32::max);
+    if max_energy <= 0.0 {
+        return None;
+    }
+
+    // Count how often each phrase recurs elsewhere in the track. Neighbouring matches are
+    // the same occurrence seen a bar early or late, so only separate runs are counted.
+    let repeats: Vec<usize> = (0..phrase_count)
+        .map(|i| {
+            let mut occurrences = 0;
+            let mut in_run = false;
+            for j in 0..phrase_count {
+                let matches = i.abs_diff(j) >= PHRASE_BARS
+                    && (0..PHRASE_BARS)
+                        .map(|k| cosine(&chroma[i + k], &chroma[j + k]))
+                        .sum::<f32>()
+                        / PHRASE_BARS as f32
+                        >= REPEAT_THRESHOLD;
+                if matches && !in_run {
+                    occurrences += 1;
+                }
+                in_run = matches;
+            }
+            occurrences
+        })
+        .collect();
+
+    // A phrase heard once more still beats a louder one-off; energy breaks ties between
+    // repeated phrases so the chorus wins over a repeated verse
+    let scores: Vec<f32> = (0..phrase_count)
+        .map(|i| (1 + repeats[i]) as f32 * phrase_energy[i] / max_energy)
+        .collect();
+    let top = scores.iter().cloned().fold(0.0f32, f32::max);
+
+    // Every occurrence of the chorus scores about the same; previews want the first one
+    let best = scores
+        .iter()
+        .position(|&score| score >= top * (1.0 - NEAR_TIE))?;
+
+    let end = bar_starts[best + PHRASE_BARS].min(samples.len());
+    Some(Section {
+        start_seconds: bar_starts[best] as f32 / sample_rate as f32,
+        end_seconds: end as f32 / sample_rate as f32,
+    })
+}
+
+/// Bars where the energy jumps well above the preceding buildup and past the track's
+/// median, each spanning one bar of lead-in and a phrase after the drop.
+pub fn find_drops(samples: &[f32], sample_rate: usize, bpm: Option<f32>) -> Vec<Section> {
+    let beats = beat_positions(samples, sample_rate, bpm.unwrap_or(FALLBACK_BPM));
+    let bar_starts: Vec<usize> = beats.iter().step_by(BEATS_PER_BAR).copied().collect();
+    if bar_starts.len() < DROP_BUILDUP_BARS + 2 {
+        return Vec::new();
+    }
+
+    let energy: Vec<f32> = bar_starts
+        .windows(2)
+        .map(|bar| {
+            let bar = &samples[bar[0].min(samples.len())..bar[1].min(samples.len())];
+            bar.iter().map(|&x| x * x).sum::<f32>() / bar.len().max(1) as f32
+        })
+        .collect();
+    let mut sorted = energy.clone();
+    sorted.sort_by(f32::total_cmp);
+    let median = sorted[sorted.len() / 2];
+
+    let mut drops = Vec::new();
+    let mut bar = DROP_BUILDUP_BARS;
+    while bar < energy.len() {
+        let buildup =
+            energy[bar - DROP_BUILDUP_BARS..bar].iter().sum::<f32>() / DROP_BUILDUP_BARS as f32;
+        if energy[bar] > median && energy[bar] >= buildup * DROP_ENERGY_RISE {
+            let end = bar_starts[(bar + PHRASE_BARS).min(bar_starts.len() - 1)];
+            drops.push(Section {
+                start_seconds: bar_starts[bar - 1] as f32 / sample_rate as f32,
+                end_seconds: end.min(samples.len()) as f32 / sample_rate as f32,
+            });
+            // The bars right after a drop are loud too; skip past its phrase
+            bar += PHRASE_BARS;
+        } else {
+            bar += 1;
+        }
+    }
+    drops
+}
+
+/// Normalized 12-bin chroma (0 = C) of the whole track.
+pub fn track_chroma(samples: &[f32], sample_rate: usize, transform: Transform) -> [f32; 12] {
+    let (chroma, _) = bar_features(samples, sample_rate, &[0], transform);
+    chroma[0]
+}
+
+/// Normalized 12-bin chroma (0 = C) and mean spectral energy for each bar.
+fn bar_features(
+    samples: &[f32],
+    sample_rate: usize,
+    bar_starts: &[usize],
+    transform: Transform,
+) -> (Vec<[f32; 12]>, Vec<f32>) {
+    let mut chroma = vec![[0.0f32; 12]; bar_starts.len()];
+    let mut energy = vec![0.0f32; bar_starts.len()];
+    let mut frames = vec![0usize; bar_starts.len()];
+
+    // Frames before the first downbeat don't belong to any bar
+    let mut add_frame = |center: usize, magnitude: &[f32], classes: &[Option<usize>]| {
+        let bar = bar_starts.partition_point(|&start| start <= center);
+        if bar == 0 {
+            return;
+        }
+        let bar = bar - 1;
+
+        for (&m, class) in magnitude.iter().zip(classes) {
+            if let Some(class) = class {
+                chroma[bar][*class] += m;
+            }
+        }
+        energy[bar] += magnitude.iter().map(|&m| m * m).sum::<f32>();
+        frames[bar] += 1;
+    };
+
+    match transform {
+        Transform::Stft => {
+            let bin_hz = sample_rate as f32 / FRAME_SIZE as f32;
+            let classes: Vec<Option<usize>> = (0..FRAME_SIZE / 2)
+                .map(|bin| {
+                    let hz = bin as f32 * bin_hz;
+                    (CHROMA_MIN_HZ..=CHROMA_MAX_HZ)
+                        .contains(&hz)
+                        // Semitones from A440, shifted so C is class 0 as with the CQT
+                        .then(|| {
+                            (12.0 * (hz / 440.0).log2() + 9.0).round().rem_euclid(12.0) as usize
+                        })
+                })
+                .collect();
+
+            let mut frame_start = 0;
+            for_each_spectrum(samples, FRAME_SIZE, HOP_SIZE, |magnitude| {
+                add_frame(frame_start + FRAME_SIZE / 2, magnitude, &classes);
+                frame_start += HOP_SIZE;
+            });
+        }
+        Transform::Cqt => {
+            // Log-spaced bins map straight onto pitch classes
+            let cqt = Cqt::new(sample_rate);
+            let classes: Vec<Option<usize>> = cqt
+                .frequencies()
+                .iter()
+                .enumerate()
+                .map(|(bin, hz)| {
+                    (CHROMA_MIN_HZ..=CHROMA_MAX_HZ)
+                        .contains(hz)
+                        .then(|| pitch_class(bin))
+                })
+                .collect();
+
+            let mut frame_start = 0;
+            cqt.for_each_frame(samples, |magnitude| {
+                add_frame(frame_start + cqt.frame_size() / 2, magnitude, &classes);
+                frame_start += CQT_HOP_SIZE;
+            });
+        }
+    }
+
+    for ((bins, total), count) in chroma.iter_mut().zip(&mut energy).zip(&frames) {
+        let norm = bins.iter().map(|&x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            bins.iter_mut().for_each(|x| *x /= norm);
+        }
+        if *count > 0 {
+            *total /= *count as f32;
+        }
+    }
+
+    (chroma, energy)
+}
+
+fn cosine(a: &[f32; 12], b: &[f32; 12]) -> f32 {
+    // Both vectors are already unit length
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
@@ -0,0 +1,189 @@
+use crate::frequency_bands::{is_screen_reader, print_rule};
+use crate::i18n::{Msg, tr};
+use crate::utils::{CachedMetrics, truncate_filename};
+
+const OUTLIER_COUNT: usize = 5;
+const SPARK_LEVELS: &[char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Library-wide totals for `summary`.
+pub struct LibrarySummary {
+    pub track_count: usize,
+    pub total_seconds: f32,
+    pub loudness_unit: &'static str,
+    pub loudness_range: Option<(f32, f32)>,
+    pub average_bands: Vec<f32>,
+    /// Tracks furthest from the average band profile, in percentage points, furthest first
+    pub outliers: Vec<(String, f32)>,
+}
+
+pub fn summarize(tracks: &[&CachedMetrics]) -> LibrarySummary {
+    // Integrated loudness when every track has it, otherwise the RMS level
+    let (loudness_unit, levels): (&str, Vec<f32>) = match tracks
+        .iter()
+        .map(|t| t.integrated_lufs)
+        .collect::<Option<Vec<f32>>>()
+    {
+        Some(lufs) => ("LUFS", lufs),
+        None => ("dB RMS", tracks.iter().map(|t| t.loudness).collect()),
+    };
+    let loudness_range = levels.iter().fold(None, |range, &level| match range {
+        None => Some((level, level)),
+        Some((low, high)) => Some((f32::min(low, level), f32::max(high, level))),
+    });
+
+    let profiles: Vec<&[f32]> = tracks
+        .iter()
+        .map(|t| t.band_percentages.as_slice())
+        .collect();
+    let average_bands = average_profile(&profiles);
+    let comparable: Vec<&&CachedMetrics> = tracks
+        .iter()
+        .filter(|t| t.band_percentages.len() == average_bands.len() && !average_bands.is_empty())
+        .collect();
+
+    let mut outliers: Vec<(String, f32)> = comparable
+        .iter()
+        .map(|track| {
+            let distance = track
+                .band_percentages
+                .iter()
+                .zip(&average_bands)
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum::<f32>()
+                .sqrt();
+            (track.filename.clone(), distance)
+        })
+        .collect();
+    outliers.sort_by(|a, b| b.1.total_cmp(&a.1));
+    outliers.truncate(OUTLIER_COUNT);
+
+    LibrarySummary {
+        track_count: tracks.len(),
+        total_seconds: tracks.iter().map(|t| t.duration_seconds).sum(),
+        loudness_unit,
+        loudness_range,
+        average_bands,
+        outliers,
+    }
+}
+
+/// Mean band shares over the profiles with the most common band count; only those can
+/// be averaged together.
+pub fn average_profile(profiles: &[&[f32]]) -> Vec<f32> {
+    let band_count = profiles
+        .iter()
+        .map(|p| p.len())
+        .max_by_key(|&len| profiles.iter().filter(|p| p.len() == len).count())
+        .unwrap_or(0);
+    let comparable: Vec<&&[f32]> = profiles.iter().filter(|p| p.len() == band_count).collect();
+    let mut average = vec![0.0f32; band_count];
+    for profile in &comparable {
+        for (sum, pct) in average.iter_mut().zip(profile.iter()) {
+            *sum += pct / comparable.len() as f32;
+        }
+    }
+    average
+}
+
+/// One block character per band, scaled to the loudest band. Screen readers get the
+/// shares themselves.
+pub fn sparkline(values: &[f32]) -> String {
+    if is_screen_reader() {
+        let shares: Vec<String> = values.iter().map(|v| format!("{:.0}", v)).collect();
+        return format!("bands {} percent", shares.join(", "));
+    }
+    let max = values.iter().copied().fold(0.0, f32::max);
+    values
+        .iter()
+        .map(|&v| {
+            let level = if max > 0.0 {
+                (v / max * (SPARK_LEVELS.len() - 1) as f32).round() as usize
+            } else {
+                0
+            };
+            SPARK_LEVELS[level.min(SPARK_LEVELS.len() - 1)]
+        })
+        .collect()
+}
+
+fn total_time(seconds: f32) -> String {
+    let seconds = seconds.round() as u32;
+    if seconds >= 3600 {
+        format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+fn loudness_text(summary: &LibrarySummary) -> String {
+    match summary.loudness_range {
+        Some((low, high)) => format!("{:.1} to {:.1} {}", low, high, summary.loudness_unit),
+        None => "--".to_string(),
+    }
+}
+
+pub fn print_summary(name: &str, summary: &LibrarySummary) {
+    let rows = [
+        (Msg::Tracks, summary.track_count.to_string()),
+        (Msg::TotalTime, total_time(summary.total_seconds)),
+        (Msg::Loudness, loudness_text(summary)),
+        (
+            Msg::AverageSpectrum,
+            format!(
+                "{}  {}",
+                sparkline(&summary.average_bands),
+                tr(Msg::LowToHigh, &[])
+            ),
+        ),
+    ];
+    // Labels are padded to the longest one, which depends on the language
+    let labels: Vec<String> = rows.iter().map(|(label, _)| tr(*label, &[])).collect();
+    let width = labels
+        .iter()
+        .map(|label| label.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(18);
+
+    println!("\n{}", tr(Msg::LibrarySummary, &[&name]));
+    print_rule(80);
+    for (label, (_, value)) in labels.iter().zip(&rows) {
+        println!("  {:<width$} {}", label, value, width = width);
+    }
+    if !summary.outliers.is_empty() {
+        println!("\n{}", tr(Msg::FurthestFromAverage, &[]));
+        for (i, (filename, distance)) in summary.outliers.iter().enumerate() {
+            println!(
+                "  {}. {:<40} {:5.1} {}",
+                i + 1,
+                truncate_filename(filename, 40),
+                distance,
+                tr(Msg::Points, &[])
+            );
+        }
+    }
+}
+
+/// A short Markdown card for forum posts and release notes.
+pub fn share_card(name: &str, summary: &LibrarySummary) -> String {
+    let mut card = format!(
+        "**{}** · {} tracks · {}\n\n",
+        name,
+        summary.track_count,
+        total_time(summary.total_seconds)
+    );
+    card += &format!("- Loudness: {}\n", loudness_text(summary));
+    card += &format!(
+        "- Spectrum (low → high): `{}`\n",
+        sparkline(&summary.average_bands)
+    );
+    if !summary.outliers.is_empty() {
+        let outliers: Vec<String> = summary
+            .outliers
+            .iter()
+            .map(|(filename, distance)| format!("{} ({:.0} pts)", filename, distance))
+            .collect();
+        card += &format!("- Outliers: {}\n", outliers.join(", "));
+    }
+    card
+}
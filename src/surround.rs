@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frequency_bands::{FrequencyBand, calculate_band_energies, for_each_spectrum};
+
+// ITU-R BS.775 fold-down: centre and surrounds at -3 dB, LFE left out
+const FOLD_DOWN_GAIN: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+// Bass management hands the LFE to the subwoofer below 80-120 Hz, so anything above the
+// top of that range is lost or muddied on most home-theater systems
+pub const LFE_CROSSOVER_HZ: f32 = 120.0;
+const LFE_LEAK_PERCENT: f32 = 5.0; // Of the LFE's energy above the crossover
+const LFE_EDGE_SHARE: f64 = 0.99;
+const LFE_FRAME_SIZE: usize = 8192; // Fine enough to resolve the crossover region
+
+/// How much of a multichannel file one channel carries, and where in the spectrum.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ChannelEnergy {
+    pub name: String,
+    pub share_percent: f32,         // Of the energy summed over every channel
+    pub band_percentages: Vec<f32>, // The channel's own band distribution
+}
+
+/// What a surround mix sends to its LFE channel.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LfeUsage {
+    pub share_percent: f32,           // Of the energy summed over every channel
+    pub above_crossover_percent: f32, // Of the LFE's own energy, above the crossover
+    pub upper_edge_hz: Option<f32>,   // 99% of the LFE's energy lies below this; None if silent
+}
+
+impl LfeUsage {
+    /// Mains material in the LFE rather than just low-frequency effects.
+    pub fn is_full_range(&self) -> bool {
+        self.above_crossover_percent > LFE_LEAK_PERCENT
+    }
+}
+
+/// Channel names in WAV/FLAC order, numbered for layouts without a standard downmix.
+pub fn channel_names(count: usize) -> Vec<String> {
+    let names: &[&str] = match count {
+        6 => &["L", "R", "C", "LFE", "Ls", "Rs"],
+        8 => &["L", "R", "C", "LFE", "Lb", "Rb", "Ls", "Rs"],
+        _ => &[],
+    };
+    (0..count)
+        .map(|i| match names.get(i) {
+            Some(name) => name.to_string(),
+            None => format!("Ch{}", i + 1),
+        })
+        .collect()
+}
+
+/// Left and right fold-down of 5.1 and 7.1, or `None` for other layouts.
+pub fn stereo_downmix(channels: &[Vec<f32>]) -> Option<Vec<Vec<f32>>> {
+    let (left_surrounds, right_surrounds): (&[usize], &[usize]) = match channels.len() {
+        6 => (&[4], &[5]),
+        8 => (&[4, 6], &[5, 7]),
+        _ => return None,
+    };
+    let length = channels.iter().map(|c| c.len()).min().unwrap_or(0);
+    let fold = |front: usize, surrounds: &[usize]| -> Vec<f32> {
+        (0..length)
+            .map(|i| {
+                let surround: f32 = surrounds.iter().map(|&s| channels[s][i]).sum();
+                channels[front][i] + FOLD_DOWN_GAIN * (channels[2][i] + surround)
+            })
+            .collect()
+    };
+    Some(vec![fold(0, left_surrounds), fold(1, right_surrounds)])
+}
+
+fn percent(part: f64, total: f64) -> f32 {
+    if total > 0.0 {
+        (part / total * 100.0) as f32
+    } else {
+        0.0
+    }
+}
+
+/// Each channel's share of the total energy and its own band distribution.
+pub fn channel_energies(
+    channels: &[Vec<f32>],
+    sample_rate: usize,
+    bands: &[FrequencyBand],
+) -> Result<Vec<ChannelEnergy>, Box<dyn std::error::Error>> {
+    let energies: Vec<f64> = channels
+        .iter()
+        .map(|c| c.iter().map(|&x| x as f64 * x as f64).sum())
+        .collect();
+    let total: f64 = energies.iter().sum();
+
+    channels
+        .iter()
+        .zip(&energies)
+        .zip(channel_names(channels.len()))
+        .map(|((samples, &energy), name)| {
+            let band_energies = calculate_band_energies(samples, sample_rate, bands)?;
+            let band_total: f64 = band_energies.iter().sum();
+            Ok(ChannelEnergy {
+                name,
+                share_percent: percent(energy, total),
+                band_percentages: band_energies
+                    .iter()
+                    .map(|&e| percent(e, band_total))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// How much of the mix sits in the LFE and how far up the spectrum it reaches, for
+/// layouts with an LFE channel.
+pub fn lfe_usage(
+    channels: &[Vec<f32>],
+    sample_rate: usize,
+    energies: &[ChannelEnergy],
+) -> Option<LfeUsage> {
+    let index = energies.iter().position(|c| c.name == "LFE")?;
+    let mut spectrum = vec![0.0f64; LFE_FRAME_SIZE / 2];
+    for_each_spectrum(
+        channels.get(index)?,
+        LFE_FRAME_SIZE,
+        LFE_FRAME_SIZE / 2,
+        |magnitude| {
+            for (power, &m) in spectrum.iter_mut().zip(magnitude) {
+                *power += m as f64 * m as f64;
+            }
+        },
+    );
+
+    let total: f64 = spectrum.iter().sum();
+    let bin_hz = sample_rate as f32 / LFE_FRAME_SIZE as f32;
+    let crossover_bin = ((LFE_CROSSOVER_HZ / bin_hz).ceil() as usize).min(spectrum.len());
+    let above: f64 = spectrum[crossover_bin..].iter().sum();
+
+    let mut cumulative = 0.0;
+    let upper_edge_hz = (total > 0.0)
+        .then(|| {
+            spectrum.iter().position(|&power| {
+                cumulative += power;
+                cumulative >= LFE_EDGE_SHARE * total
+            })
+        })
+        .flatten()
+        .map(|bin| (bin + 1) as f32 * bin_hz);
+
+    Some(LfeUsage {
+        share_percent: energies[index].share_percent,
+        above_crossover_percent: percent(above, total),
+        upper_edge_hz,
+    })
+}
@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use id3::frame::ExtendedText;
+use id3::{Tag, TagLike};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::CachedMetrics;
+
+// TXXX descriptions `write_metric_tags` stores values under
+const CENTROID_FRAME: &str = "SPECTRAL_CENTROID";
+const SPREAD_FRAME: &str = "SPECTRAL_SPREAD";
+const ZCR_FRAME: &str = "ZERO_CROSSING_RATE";
+const BPM_FRAME: &str = "ANALYZED_BPM";
+
+/// The ID3v2 (or ID3v1) fields worth showing next to the metrics.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Tags {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+}
+
+impl Tags {
+    /// "Artist – Title", or just the title when there's no artist.
+    pub fn display_name(&self) -> Option<String> {
+        match (&self.artist, &self.title) {
+            (Some(artist), Some(title)) => Some(format!("{} – {}", artist, title)),
+            (None, Some(title)) => Some(title.clone()),
+            _ => None,
+        }
+    }
+}
+
+// Blank frames are as good as missing
+fn field(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+/// Tags from an MP3, or from the ID3 chunk of a WAV or AIFF file; `None` when the file
+/// has no tag or none of the fields are set.
+pub fn read_tags(path: &Path) -> Option<Tags> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let tag = match extension.as_str() {
+        "mp3" => id3::v1v2::read_from_path(path),
+        // Finds the ID3 chunk inside RIFF and AIFF containers
+        "wav" | "aif" | "aiff" | "aifc" => Tag::read_from_path(path),
+        _ => return None,
+    }
+    .ok()?;
+
+    let tags = Tags {
+        artist: field(tag.artist()),
+        title: field(tag.title()),
+        album: field(tag.album()),
+        // Resolves ID3v1 genre numbers like "(17)" to their names
+        genre: field(tag.genre_parsed().as_deref()),
+    };
+    (tags != Tags::default()).then_some(tags)
+}
+
+/// Store centroid, spread, ZCR, and BPM in TXXX frames of an MP3's ID3v2 tag, leaving
+/// its other frames alone. The standard BPM frame is only filled in when it's missing.
+pub fn write_metric_tags(
+    path: &Path,
+    entry: &CachedMetrics,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut tag = id3::no_tag_ok(Tag::read_from_path(path))?.unwrap_or_default();
+    let values = [
+        (CENTROID_FRAME, Some(entry.centroid)),
+        (SPREAD_FRAME, Some(entry.spread)),
+        (ZCR_FRAME, Some(entry.zero_crossing_rate)),
+        (BPM_FRAME, entry.tempo.as_ref().map(|t| t.bpm)),
+    ];
+    for (description, value) in values {
+        match value {
+            Some(value) => {
+                tag.add_frame(ExtendedText {
+                    description: description.to_string(),
+                    value: format!("{:.1}", value),
+                });
+            }
+            // Drop a stale reading, e.g. a tempo the latest analysis couldn't find
+            None => tag.remove_extended_text(Some(description), None),
+        }
+    }
+    if let Some(tempo) = &entry.tempo
+        && tag.get("TBPM").is_none()
+    {
+        tag.set_text("TBPM", format!("{:.0}", tempo.bpm));
+    }
+
+    // Existing tags keep their version; new ones are ID3v2.4
+    tag.write_to_path(path, tag.version())?;
+    Ok(())
+}
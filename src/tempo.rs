@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frequency_bands::{FRAME_SIZE, HOP_SIZE, for_each_spectrum};
+
+const MIN_BPM: f32 = 40.0;
+const MAX_BPM: f32 = 240.0;
+
+// Genre-agnostic log-normal prior: most music is felt near 120 BPM, give or take an octave
+const PRIOR_CENTER_BPM: f32 = 120.0;
+const PRIOR_WIDTH_OCTAVES: f32 = 1.0;
+
+// Kick drums and bass lines carry the pulse in most genres, so that band gets its own estimator
+const LOW_BAND_MAX_HZ: usize = 250;
+const LOW_BAND_WEIGHT: f32 = 0.5;
+
+// Candidates closer than this ratio are treated as the same tempo
+const MERGE_TOLERANCE: f32 = 0.03;
+const MAX_ALTERNATIVES: usize = 3;
+const MIN_ALTERNATIVE_CONFIDENCE: f32 = 0.05;
+
+// Average flux per frame below which a track is treated as static (silence, steady tones)
+const MIN_FLUX: f32 = 1.0;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TempoCandidate {
+    pub bpm: f32,
+    pub confidence: f32, // Share of the total hypothesis score (0-1)
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TempoEstimate {
+    pub bpm: f32,
+    pub confidence: f32,
+    pub alternatives: Vec<TempoCandidate>,
+}
+
+/// Estimate tempo from onset periodicity, resolving half/double-time ambiguity by scoring
+/// each octave-related hypothesis against the whole envelope and a tempo prior.
+pub fn estimate_tempo(samples: &[f32], sample_rate: usize) -> Option<TempoEstimate> {
+    let frame_rate = sample_rate as f32 / HOP_SIZE as f32;
+    let (full_envelope, low_envelope) = onset_envelopes(samples, low_band_bins(sample_rate));
+
+    let full_acf = autocorrelation(&full_envelope, frame_rate);
+    let low_acf = autocorrelation(&low_envelope, frame_rate);
+    if full_acf.is_empty() {
+        return None;
+    }
+
+    // Every strong periodicity and its octave neighbours becomes a hypothesis
+    let mut hypotheses: Vec<f32> = Vec::new();
+    for acf in [&full_acf, &low_acf] {
+        for bpm in periodicity_peaks(acf, frame_rate) {
+            for candidate in [bpm, bpm * 2.0, bpm / 2.0, bpm * 1.5, bpm / 1.5] {
+                if (MIN_BPM..=MAX_BPM).contains(&candidate)
+                    && !hypotheses
+                        .iter()
+                        .any(|&h| (h / candidate - 1.0).abs() < MERGE_TOLERANCE)
+                {
+                    hypotheses.push(candidate);
+                }
+            }
+        }
+    }
+
+    let mut scored: Vec<TempoCandidate> = hypotheses
+        .iter()
+        .map(|&bpm| {
+            let strength = pulse_strength(&full_acf, frame_rate, bpm)
+                + LOW_BAND_WEIGHT * pulse_strength(&low_acf, frame_rate, bpm);
+            TempoCandidate {
+                bpm,
+                confidence: strength.max(0.0) * tempo_prior(bpm),
+            }
+        })
+        .collect();
+
+    let total: f32 = scored.iter().map(|c| c.confidence).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    for candidate in &mut scored {
+        candidate.confidence /= total;
+    }
+    scored.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    let best = scored.remove(0);
+    let alternatives = scored
+        .into_iter()
+        .filter(|c| c.confidence >= MIN_ALTERNATIVE_CONFIDENCE)
+        .take(MAX_ALTERNATIVES)
+        .collect();
+
+    Some(TempoEstimate {
+        bpm: best.bpm,
+        confidence: best.confidence,
+        alternatives,
+    })
+}
+
+pub fn low_band_bins(sample_rate: usize) -> usize {
+    (LOW_BAND_MAX_HZ * FRAME_SIZE / sample_rate.max(1)).max(1)
+}
+
+/// Spectral flux envelopes for the full spectrum and for the low band only, one value per hop.
+pub fn onset_envelopes(samples: &[f32], low_bins: usize) -> (Vec<f32>, Vec<f32>) {
+    let (full, low) = spectral_flux(samples, low_bins);
+    (detrend(&full), detrend(&low))
+}
+
+/// How punchy a track is, 0-100: the share of its frame-to-frame spectral flux that
+/// arrives as sharp onsets well above the local average, rather than as slow swells. Drum-led
+/// mixes score high, pads and drones low. `None` when the spectrum never changes.
+pub fn onset_strength(samples: &[f32]) -> Option<f32> {
+    let (flux, _) = spectral_flux(samples, 0);
+    let total: f32 = flux.iter().sum();
+    if total <= MIN_FLUX * flux.len() as f32 {
+        return None;
+    }
+    // Only rises to at least twice the local average count, so steady jitter doesn't
+    let onsets: f32 = detrend(&flux)
+        .iter()
+        .zip(&flux)
+        .filter(|&(excess, value)| excess * 2.0 >= *value)
+        .map(|(excess, _)| excess)
+        .sum();
+    Some(onsets / total * 100.0)
+}
+
+// Rectified log-magnitude rises between frames, full spectrum and low band
+fn spectral_flux(samples: &[f32], low_bins: usize) -> (Vec<f32>, Vec<f32>) {
+    let mut full = Vec::new();
+    let mut low = Vec::new();
+    let mut previous: Vec<f32> = Vec::new();
+
+    for_each_spectrum(samples, FRAME_SIZE, HOP_SIZE, |magnitude| {
+        // Log compression keeps loud sustained notes from drowning out transients
+        let compressed: Vec<f32> = magnitude.iter().map(|&m| (1.0 + 100.0 * m).ln()).collect();
+
+        if previous.is_empty() {
+            previous = compressed;
+            return;
+        }
+
+        let rises: Vec<f32> = compressed
+            .iter()
+            .zip(&previous)
+            .map(|(now, before)| (now - before).max(0.0))
+            .collect();
+
+        full.push(rises.iter().sum());
+        low.push(rises[..low_bins.min(rises.len())].iter().sum());
+        previous = compressed;
+    });
+
+    (full, low)
+}
+
+/// Subtract a ~0.5s moving average and keep only the peaks above it.
+fn detrend(envelope: &[f32]) -> Vec<f32> {
+    let radius = 20;
+    envelope
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| {
+            let start = i.saturating_sub(radius);
+            let end = (i + radius + 1).min(envelope.len());
+            let mean = envelope[start..end].iter().sum::<f32>() / (end - start) as f32;
+            (value - mean).max(0.0)
+        })
+        .collect()
+}
+
+/// Normalized autocorrelation for lags up to four beats at the slowest tempo.
+fn autocorrelation(envelope: &[f32], frame_rate: f32) -> Vec<f32> {
+    let max_lag = (60.0 / MIN_BPM * frame_rate * 4.0) as usize;
+    if envelope.len() <= max_lag {
+        return Vec::new();
+    }
+
+    let energy: f32 = envelope.iter().map(|&x| x * x).sum();
+    if energy <= 0.0 {
+        return Vec::new();
+    }
+
+    (0..=max_lag)
+        .map(|lag| {
+            let sum: f32 = envelope[..envelope.len() - lag]
+                .iter()
+                .zip(&envelope[lag..])
+                .map(|(a, b)| a * b)
+                .sum();
+            // Compensate for fewer overlapping frames at long lags
+            sum / energy * envelope.len() as f32 / (envelope.len() - lag) as f32
+        })
+        .collect()
+}
+
+/// Tempos of the strongest local maxima within the plausible BPM range.
+fn periodicity_peaks(acf: &[f32], frame_rate: f32) -> Vec<f32> {
+    if acf.is_empty() {
+        return Vec::new();
+    }
+
+    let min_lag = (60.0 / MAX_BPM * frame_rate).floor().max(1.0) as usize;
+    let max_lag = ((60.0 / MIN_BPM * frame_rate).ceil() as usize).min(acf.len() - 2);
+
+    let mut peaks: Vec<(f32, f32)> = (min_lag.max(1)..=max_lag)
+        .filter(|&lag| acf[lag] > acf[lag - 1] && acf[lag] >= acf[lag + 1])
+        .map(|lag| {
+            // Parabolic interpolation for sub-frame lag precision
+            let (a, b, c) = (acf[lag - 1], acf[lag], acf[lag + 1]);
+            let denominator = a - 2.0 * b + c;
+            let offset = if denominator.abs() > f32::EPSILON {
+                0.5 * (a - c) / denominator
+            } else {
+                0.0
+            };
+            (60.0 * frame_rate / (lag as f32 + offset), b)
+        })
+        .collect();
+
+    peaks.sort_by(|a, b| b.1.total_cmp(&a.1));
+    peaks.into_iter().take(5).map(|(bpm, _)| bpm).collect()
+}
+
+/// How well a beat period explains the envelope: the periodicity at one beat, reinforced
+/// by its multiples so the metrical level with consistent support wins.
+fn pulse_strength(acf: &[f32], frame_rate: f32, bpm: f32) -> f32 {
+    let lag = 60.0 / bpm * frame_rate;
+
+    [1.0, 2.0, 3.0, 4.0]
+        .iter()
+        .map(|&multiple| interpolate(acf, lag * multiple) / multiple)
+        .sum()
+}
+
+fn interpolate(values: &[f32], position: f32) -> f32 {
+    let index = position.floor() as usize;
+    if index + 1 >= values.len() {
+        return 0.0;
+    }
+    let fraction = position - index as f32;
+    values[index] * (1.0 - fraction) + values[index + 1] * fraction
+}
+
+fn tempo_prior(bpm: f32) -> f32 {
+    let octaves = (bpm / PRIOR_CENTER_BPM).log2() / PRIOR_WIDTH_OCTAVES;
+    (-0.5 * octaves * octaves).exp()
+}
@@ -0,0 +1,171 @@
+use std::f32::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::warnings::WarningRule;
+
+const SAMPLE_RATE: u32 = 44_100;
+const LABELS_FILE: &str = "testset.json";
+#[cfg(feature = "encoder")]
+const EXTENSION: &str = "mp3";
+#[cfg(not(feature = "encoder"))]
+const EXTENSION: &str = "wav";
+
+/// One synthesized track and the readings the analyzer should give it.
+#[derive(Serialize)]
+pub struct Example {
+    pub file: String,
+    pub description: &'static str,
+    /// Same shape as config warning rules; every one should hold
+    pub expect: Vec<WarningRule>,
+}
+
+fn expect(metric: &str, above: Option<f32>, below: Option<f32>) -> WarningRule {
+    WarningRule {
+        metric: metric.to_string(),
+        above,
+        below,
+        message: None,
+    }
+}
+
+fn sines(seconds: f32, partials: &[(f32, f32)]) -> Vec<f32> {
+    (0..(seconds * SAMPLE_RATE as f32) as usize)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            partials
+                .iter()
+                .map(|&(hz, amplitude)| amplitude * (2.0 * PI * hz * t).sin())
+                .sum()
+        })
+        .collect()
+}
+
+// White noise from a fixed-seed xorshift, so every run writes the same files
+fn noise(seconds: f32, amplitude: f32) -> Vec<f32> {
+    let mut state = 0x9e37_79b9u32;
+    (0..(seconds * SAMPLE_RATE as f32) as usize)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            amplitude * (state as f32 / u32::MAX as f32 * 2.0 - 1.0)
+        })
+        .collect()
+}
+
+fn examples() -> Vec<(&'static str, &'static str, Vec<f32>, Vec<WarningRule>)> {
+    vec![
+        (
+            "bass-heavy",
+            "40 and 90 Hz sines, nothing above the bass",
+            sines(10.0, &[(40.0, 0.4), (90.0, 0.3)]),
+            vec![expect("centroid", None, Some(30.0))],
+        ),
+        (
+            "bright",
+            "8, 11, and 14 kHz sines, nothing below the highs",
+            sines(10.0, &[(8000.0, 0.2), (11000.0, 0.2), (14000.0, 0.2)]),
+            vec![
+                expect("centroid", Some(85.0), None),
+                expect("rolloff", Some(8000.0), None),
+            ],
+        ),
+        (
+            "noisy",
+            "Full-band white noise",
+            noise(10.0, 0.5),
+            vec![
+                expect("zcr", Some(50.0), None),
+                expect("tilt", Some(-1.0), Some(1.0)),
+            ],
+        ),
+        (
+            "silent",
+            "Digital silence",
+            vec![0.0; 5 * SAMPLE_RATE as usize],
+            vec![expect("loudness", None, Some(-59.0))],
+        ),
+        (
+            "short",
+            "Half a second of a 440 Hz sine",
+            sines(0.5, &[(440.0, 0.5)]),
+            vec![expect("duration", None, Some(1.0))],
+        ),
+    ]
+}
+
+#[cfg(not(feature = "encoder"))]
+fn write_audio(path: &Path, samples: &[f32]) -> Result<(), Box<dyn std::error::Error>> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for &sample in samples {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+#[cfg(feature = "encoder")]
+fn write_audio(path: &Path, samples: &[f32]) -> Result<(), Box<dyn std::error::Error>> {
+    use mp3lame_encoder::{Bitrate, Builder, FlushNoGap, MonoPcm, Quality};
+
+    let encoder_error = |e: &dyn std::fmt::Debug| format!("MP3 encoder: {:?}", e);
+    let mut builder = Builder::new().ok_or("MP3 encoder: could not allocate LAME")?;
+    builder.set_num_channels(1).map_err(|e| encoder_error(&e))?;
+    builder
+        .set_sample_rate(SAMPLE_RATE)
+        .map_err(|e| encoder_error(&e))?;
+    builder
+        .set_brate(Bitrate::Kbps192)
+        .map_err(|e| encoder_error(&e))?;
+    builder
+        .set_quality(Quality::Best)
+        .map_err(|e| encoder_error(&e))?;
+    let mut encoder = builder.build().map_err(|e| encoder_error(&e))?;
+
+    let pcm: Vec<i16> = samples
+        .iter()
+        .map(|&s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+        .collect();
+    let mut mp3 = Vec::with_capacity(mp3lame_encoder::max_required_buffer_size(pcm.len()));
+    encoder
+        .encode_to_vec(MonoPcm(&pcm), &mut mp3)
+        .map_err(|e| encoder_error(&e))?;
+    encoder
+        .flush_to_vec::<FlushNoGap>(&mut mp3)
+        .map_err(|e| encoder_error(&e))?;
+    fs::write(path, mp3)?;
+    Ok(())
+}
+
+/// Write the labeled corpus into `dir`, plus `testset.json` listing what each file should
+/// measure. MP3s need the `encoder` feature; without it the same signals are written as WAV.
+pub fn generate(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+    let mut labels = Vec::new();
+    for (name, description, samples, expectations) in examples() {
+        let file = format!("{}.{}", name, EXTENSION);
+        let path = dir.join(&file);
+        write_audio(&path, &samples)?;
+        written.push(path);
+        labels.push(Example {
+            file,
+            description,
+            expect: expectations,
+        });
+    }
+
+    let labels_path = dir.join(LABELS_FILE);
+    fs::write(&labels_path, serde_json::to_string_pretty(&labels)? + "\n")?;
+    written.push(labels_path);
+    Ok(written)
+}
@@ -0,0 +1,219 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::frequency_bands::FrequencyBand;
+
+const CURVES_DIR: &str = "curves";
+// Each band is integrated in this many log-spaced steps
+const INTEGRATION_STEPS: usize = 64;
+// Silent bands count as this far below the reference when scoring
+const MAX_DEVIATION_DB: f32 = 30.0;
+// Score points lost per dB of RMS deviation; 10 dB off across the board scores 0
+const SCORE_POINTS_PER_DB: f32 = 10.0;
+
+/// A long-term average spectrum shape, described relative to pink noise (equal energy per
+/// octave). Tilt is in dB per octave around 1 kHz; points add a piecewise-linear offset
+/// over log frequency and hold their end values beyond the first and last point.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct TonalCurve {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tilt_db_per_octave: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub points: Vec<(f32, f32)>,
+}
+
+pub const BUILT_IN_CURVES: &[&str] = &[
+    "pink",
+    "white",
+    "brown",
+    "pop",
+    "rock",
+    "hiphop",
+    "edm",
+    "classical",
+];
+
+fn curve(description: &str, tilt_db_per_octave: f32, points: &[(f32, f32)]) -> TonalCurve {
+    TonalCurve {
+        description: description.to_string(),
+        tilt_db_per_octave,
+        points: points.to_vec(),
+    }
+}
+
+// Genre shapes are rough averages of commercial releases, meant as a sanity check rather
+// than a mastering target
+fn built_in(name: &str) -> Option<TonalCurve> {
+    let curve = match name {
+        "pink" => curve("Equal energy per octave (-3 dB/octave)", 0.0, &[]),
+        "white" => curve("Equal energy per Hz (flat)", 3.0, &[]),
+        "brown" => curve("Brownian noise (-6 dB/octave)", -3.0, &[]),
+        "pop" => curve(
+            "Average modern pop master",
+            0.0,
+            &[
+                (20.0, -6.0),
+                (60.0, 3.0),
+                (120.0, 3.0),
+                (500.0, 0.0),
+                (2000.0, -1.0),
+                (6000.0, -2.0),
+                (12000.0, -5.0),
+                (20000.0, -15.0),
+            ],
+        ),
+        "rock" => curve(
+            "Average rock master",
+            0.0,
+            &[
+                (20.0, -8.0),
+                (60.0, 1.0),
+                (250.0, 1.0),
+                (1000.0, 0.0),
+                (3000.0, 1.0),
+                (8000.0, -2.0),
+                (16000.0, -8.0),
+            ],
+        ),
+        "hiphop" => curve(
+            "Average hip-hop master, heavy sub-bass",
+            0.0,
+            &[
+                (20.0, 0.0),
+                (50.0, 6.0),
+                (100.0, 5.0),
+                (300.0, 0.0),
+                (1000.0, -1.0),
+                (4000.0, -2.0),
+                (10000.0, -4.0),
+                (16000.0, -10.0),
+            ],
+        ),
+        "edm" => curve(
+            "Average electronic dance master",
+            0.0,
+            &[
+                (20.0, -2.0),
+                (50.0, 5.0),
+                (100.0, 4.0),
+                (500.0, 0.0),
+                (2000.0, 0.0),
+                (8000.0, 1.0),
+                (16000.0, -4.0),
+            ],
+        ),
+        "classical" => curve(
+            "Average orchestral recording",
+            0.0,
+            &[
+                (20.0, -12.0),
+                (60.0, -4.0),
+                (250.0, 0.0),
+                (1000.0, 0.0),
+                (4000.0, -3.0),
+                (10000.0, -8.0),
+                (16000.0, -14.0),
+            ],
+        ),
+        _ => return None,
+    };
+    Some(curve)
+}
+
+/// Look up a curve, preferring `<config dir>/curves/<name>.json` over the built-ins.
+pub fn load_curve(name: &str, config_dir: &Path) -> Result<TonalCurve, String> {
+    let path = config_dir.join(CURVES_DIR).join(format!("{}.json", name));
+    if let Ok(file) = File::open(&path) {
+        return serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("invalid curve file {}: {}", path.display(), e));
+    }
+
+    built_in(name).ok_or_else(|| {
+        format!(
+            "unknown curve '{}' (built-in: {}; custom curves go in {})",
+            name,
+            BUILT_IN_CURVES.join(", "),
+            config_dir.join(CURVES_DIR).display()
+        )
+    })
+}
+
+impl TonalCurve {
+    /// Offset from pink noise in dB at a frequency.
+    fn offset_db(&self, hz: f32) -> f32 {
+        let tilt = self.tilt_db_per_octave * (hz / 1000.0).log2();
+        let shape = match self.points.as_slice() {
+            [] => 0.0,
+            [(first_hz, first_db), ..] if hz <= *first_hz => *first_db,
+            [.., (last_hz, last_db)] if hz >= *last_hz => *last_db,
+            points => points
+                .windows(2)
+                .find(|pair| hz <= pair[1].0)
+                .map_or(0.0, |pair| {
+                    let ((lo_hz, lo_db), (hi_hz, hi_db)) = (pair[0], pair[1]);
+                    let t = (hz / lo_hz).ln() / (hi_hz / lo_hz).ln();
+                    lo_db + t * (hi_db - lo_db)
+                }),
+        };
+        tilt + shape
+    }
+
+    /// The share of energy (percent) this curve puts in each band.
+    pub fn band_shares(&self, bands: &[FrequencyBand]) -> Vec<f32> {
+        // Pink noise has constant energy per unit of log frequency, so integrating the
+        // offset over ln(f) gives each band's energy
+        let energies: Vec<f32> = bands
+            .iter()
+            .map(|band| {
+                let low = (band.low_hz.max(1) as f32).ln();
+                let high = (band.high_hz.max(1) as f32).ln();
+                let step = (high - low) / INTEGRATION_STEPS as f32;
+                (0..INTEGRATION_STEPS)
+                    .map(|i| {
+                        let hz = (low + (i as f32 + 0.5) * step).exp();
+                        10f32.powf(self.offset_db(hz) / 10.0) * step
+                    })
+                    .sum()
+            })
+            .collect();
+
+        let total: f32 = energies.iter().sum();
+        energies
+            .iter()
+            .map(|e| if total > 0.0 { e / total * 100.0 } else { 0.0 })
+            .collect()
+    }
+}
+
+/// How far each band sits from the reference share, in dB; `None` for silent bands.
+pub fn band_deviations(percentages: &[f32], reference: &[f32]) -> Vec<Option<f32>> {
+    percentages
+        .iter()
+        .zip(reference)
+        .map(|(&actual, &expected)| {
+            (actual > 0.0 && expected > 0.0).then(|| 10.0 * (actual / expected).log10())
+        })
+        .collect()
+}
+
+/// 0-100, from the RMS of the per-band deviations; 100 matches the curve exactly.
+pub fn balance_score(deviations: &[Option<f32>]) -> f32 {
+    if deviations.is_empty() {
+        return 0.0;
+    }
+    let mean_square = deviations
+        .iter()
+        .map(|d| {
+            d.unwrap_or(-MAX_DEVIATION_DB)
+                .clamp(-MAX_DEVIATION_DB, MAX_DEVIATION_DB)
+        })
+        .map(|d| d * d)
+        .sum::<f32>()
+        / deviations.len() as f32;
+    (100.0 - SCORE_POINTS_PER_DB * mean_square.sqrt()).max(0.0)
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frequency_bands::{hop_size, long_term_power};
+use crate::utils::StreamInfo;
+
+// Bins are pooled into groups this wide so single quiet bins don't read as a shelf
+const GROUP_HZ: f32 = 200.0;
+// Encoder lowpasses all sit above this
+const MIN_CUTOFF_HZ: f32 = 11000.0;
+// Level just under a candidate cutoff is read over this span
+const BELOW_SPAN_HZ: f32 = 1000.0;
+// Natural rolloff loses a few dB over the top octave; a brick-wall filter loses this much
+pub const SHELF_DB: f32 = 25.0;
+// Content under the cutoff this far below the 1-4 kHz level is noise, not music
+const CONTENT_RANGE_DB: f32 = 60.0;
+// At lower rates Nyquist is already under the encoder lowpasses
+const MIN_SAMPLE_RATE: usize = 32000;
+// Declared bitrates more than this factor above the source estimate get flagged
+const BITRATE_MARGIN: f32 = 1.25;
+
+// Where common MP3 and AAC encoders put their lowpass at each bitrate, lowest first
+const LOWPASS_KBPS: [(f32, u32); 7] = [
+    (11000.0, 64),
+    (15000.0, 96),
+    (16000.0, 128),
+    (17000.0, 160),
+    (18500.0, 192),
+    (19500.0, 256),
+    (20000.0, 320),
+];
+
+/// How far up the spectrum the content reaches.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Bandwidth {
+    pub cutoff_hz: f32, // Start of the shelf, or Nyquist when there is none
+    pub shelf_db: f32,  // Steepest drop found above `MIN_CUTOFF_HZ`
+}
+
+impl Bandwidth {
+    /// Whether the top end stops at a brick wall, as lossy encoders leave it.
+    pub fn is_lowpassed(&self) -> bool {
+        self.shelf_db >= SHELF_DB
+    }
+
+    /// The bitrate whose usual encoder lowpass matches the cutoff, or `None` when the
+    /// content runs up to Nyquist.
+    pub fn source_kbps(&self) -> Option<u32> {
+        if !self.is_lowpassed() {
+            return None;
+        }
+        LOWPASS_KBPS
+            .iter()
+            .rev()
+            .find(|&&(hz, _)| hz <= self.cutoff_hz + GROUP_HZ)
+            .map(|&(_, kbps)| kbps)
+    }
+}
+
+/// Find the steepest drop in the long-term spectrum between `MIN_CUTOFF_HZ` and Nyquist,
+/// comparing the level just under each candidate with everything above it. `None` for
+/// silence and sample rates too low to tell.
+pub fn measure_bandwidth(
+    samples: &[f32],
+    sample_rate: usize,
+    frame_size: usize,
+) -> Option<Bandwidth> {
+    if sample_rate < MIN_SAMPLE_RATE {
+        return None;
+    }
+    let power = long_term_power(samples, frame_size, hop_size(frame_size));
+    let bin_hz = sample_rate as f32 / frame_size as f32;
+    let group_bins = ((GROUP_HZ / bin_hz).round() as usize).max(1);
+    let groups: Vec<f64> = power
+        .chunks(group_bins)
+        .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+        .collect();
+    let group_hz = group_bins as f32 * bin_hz;
+    let group = |hz: f32| ((hz / group_hz) as usize).min(groups.len());
+    let db = |groups: &[f64]| {
+        let mean = groups.iter().sum::<f64>() / groups.len().max(1) as f64;
+        10.0 * mean.max(f64::MIN_POSITIVE).log10() as f32
+    };
+
+    let reference = &groups[group(1000.0)..group(4000.0)];
+    if reference.iter().all(|&p| p <= 0.0) {
+        return None;
+    }
+    let content_floor = db(reference) - CONTENT_RANGE_DB;
+
+    let nyquist = sample_rate as f32 / 2.0;
+    let span = ((BELOW_SPAN_HZ / group_hz).round() as usize).max(1);
+    // Leave at least two groups above the last candidate
+    let (shelf_db, boundary) = (group(MIN_CUTOFF_HZ)..groups.len().saturating_sub(2))
+        .filter_map(|boundary| {
+            let below = db(&groups[boundary.saturating_sub(span)..boundary]);
+            (below >= content_floor).then(|| (below - db(&groups[boundary..]), boundary))
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))?;
+
+    let cutoff_hz = if shelf_db >= SHELF_DB {
+        boundary as f32 * group_hz
+    } else {
+        nyquist
+    };
+    Some(Bandwidth {
+        cutoff_hz,
+        shelf_db,
+    })
+}
+
+/// A warning when the file claims more bitrate than its spectrum shows, as when a 128
+/// kbps MP3 is re-encoded at 320 or decoded to FLAC.
+pub fn transcode_problem(bandwidth: &Bandwidth, stream: &StreamInfo) -> Option<String> {
+    let source = bandwidth.source_kbps()?;
+    (stream.bitrate_kbps > source as f32 * BITRATE_MARGIN).then(|| {
+        format!(
+            "likely transcode: content stops at {:.1} kHz, typical of {} kbps, but the file is {:.0} kbps",
+            bandwidth.cutoff_hz / 1000.0,
+            source,
+            stream.bitrate_kbps
+        )
+    })
+}
@@ -0,0 +1,43 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+// Each wraps a plain f32 and serializes as one, so caches and JSON output don't change.
+// Display forwards to the number, so `{:.1}` formats them as before.
+macro_rules! unit {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+        #[serde(transparent)]
+        pub struct $name(pub f32);
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        impl From<$name> for f32 {
+            fn from(value: $name) -> f32 {
+                value.0
+            }
+        }
+    };
+}
+
+unit!(
+    /// A frequency in hertz.
+    Hz
+);
+unit!(
+    /// A 0-100 score or share, such as a position on the spectrum axis.
+    Percent
+);
+unit!(
+    /// A level or level difference in decibels (dBFS, dBTP, LU, or DR).
+    Db
+);
+unit!(
+    /// Loudness units relative to full scale, per EBU R128.
+    Lufs
+);
@@ -1,54 +1,1452 @@
+use std::fmt;
 use std::fs;
-use std::io::{BufReader, BufWriter};
-use std::path::Path;
-use std::{collections::HashMap, fs::File};
+use std::io::{BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{File, OpenOptions},
+};
 
+use claxon::FlacReader;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use hound::{SampleFormat, WavReader};
 use minimp3::{Decoder, Frame};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CODEC_TYPE_NULL, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, Track};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone)]
+use crate::analysis::AnalysisErrorKind;
+use crate::beats::Groove;
+use crate::clipping::Clipping;
+use crate::dsd::{DsdStream, read_dff, read_dsf, to_pcm};
+use crate::dynamics::Dynamics;
+use crate::frequency_bands::{SpectrumMetrics, Transform, is_reproducible};
+use crate::i18n::{Msg, tr};
+use crate::key::KeyEstimate;
+use crate::ratios::EnergyRatios;
+use crate::silence::Silence;
+use crate::stereo::Stereo;
+use crate::structure::{EdgeProfile, Section};
+use crate::surround::{ChannelEnergy, LfeUsage};
+use crate::tags::Tags;
+use crate::tempo::TempoEstimate;
+use crate::transcode::Bandwidth;
+use crate::units::{Db, Hz, Lufs, Percent};
+
+const CACHE_FILE_NAME: &str = "file_calc_cache.json";
+
+/// File extensions the decoder understands, lowercase.
+pub const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "wav", "flac", "aif", "aiff", "aifc", "dsf", "dff", "m4a", "aac", "mp4", "m4v", "mov",
+    "mkv", "webm",
+];
+
+/// Formats with no built-in decoder, picked up when the ffmpeg fallback is enabled.
+pub const FFMPEG_EXTENSIONS: &[&str] = &[
+    "ogg", "opus", "wma", "ape", "wv", "mka", "ac3", "caf", "mpc", "tta",
+];
+
+// Set from the command line or config before any decoding runs
+static FFMPEG: OnceLock<PathBuf> = OnceLock::new();
+static AUDIO_STREAM: OnceLock<usize> = OnceLock::new();
+static SKIP_BAD_FRAMES: AtomicBool = AtomicBool::new(false);
+
+// Formats that can hold several audio tracks, decoded with symphonia
+const CONTAINER_EXTENSIONS: &[&str] = &["m4a", "aac", "mp4", "m4v", "mov", "mkv", "webm"];
+// Enough decoded audio to read a single-stream file's format
+const STREAM_PROBE_BYTES: u64 = 1 << 20;
+
+// Bump whenever analysis gains or changes a metric so older cache entries get recomputed
+pub const ANALYSIS_VERSION: u32 = 27;
+pub const MAX_RATING: u8 = 5; // Stars available to `rate`
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// Only refresh access times once a day so plain re-runs don't rewrite the cache
+const ACCESS_REFRESH_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Default, Clone)]
+pub struct CacheOptions {
+    pub compact: bool,
+    pub gzip: bool,
+    pub max_size: Option<u64>,
+    /// Central cache folder instead of one cache file per analyzed directory
+    pub cache_dir: Option<PathBuf>,
+    /// Named profiles keep separate caches so band layouts don't collide
+    pub profile: Option<String>,
+}
+
+/// One file's cache entry. Keys missing from older caches take their defaults, and keys
+/// from newer versions are kept in `unknown_fields` so they survive being loaded and
+/// saved again.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
 pub struct CachedMetrics {
-    pub(crate) filename: String,
-    pub(crate) centroid: f32,
-    pub(crate) spread: f32,
-    pub(crate) zero_crossing_rate: f32,
-    pub(crate) loudness: f32,
-    pub(crate) duration_seconds: f32,
-    pub(crate) band_percentages: Vec<f32>,
+    pub filename: String,
+    pub centroid: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub centroid_hz: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exact_centroid: Option<f32>,
+    pub spread: f32,
+    pub zero_crossing_rate: f32,
+    pub loudness: f32,
+    pub duration_seconds: f32,
+    pub band_percentages: Vec<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ratios: Option<EnergyRatios>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_time: Option<u64>,
+    /// Identifies the file across renames; see `adopt_renamed_files`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Artist, title, album, and genre from the file's ID3 tag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Tags>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrated_lufs: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loudness_range: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub true_peak_db: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dr_score: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dynamics: Option<Dynamics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clipping: Option<Clipping>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub silence: Option<Silence>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imported_from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_accessed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tempo: Option<TempoEstimate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<KeyEstimate>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub(crate) file_size: Option<u64>,
+    pub groove: Option<Groove>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rhythm_pattern: Vec<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub(crate) modified_time: Option<u64>,
+    pub chorus: Option<Section>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intro: Option<EdgeProfile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outro: Option<EdgeProfile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tilt: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rolloff: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bandwidth: Option<Bandwidth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub onset_strength: Option<f32>,
+    /// Which audio stream was analyzed, when not the first (`--stream`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_stream: Option<usize>,
+    /// Read in windows under `--max-memory`, so some readings cover only the first
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub windowed: bool,
+    /// Measured with `--reproducible`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reproducible: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sharpness: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roughness: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stereo: Option<Stereo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub surround: Option<Vec<ChannelEnergy>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lfe: Option<LfeUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<StreamInfo>,
+    #[serde(default, skip_serializing_if = "Transform::is_stft")]
+    pub transform: Transform,
+    #[serde(default)]
+    pub analysis_version: u32,
+    /// Free-text annotation from `note`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Stars from `rate`, 1 to `MAX_RATING`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    /// Keys this version doesn't know, written back unchanged
+    #[serde(flatten)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+// Whether a fresh reading is close enough to the cached one to be float jitter: within
+// `tolerance` of the larger magnitude, or of 1 for values near zero
+fn within_tolerance(value: f32, previous: f32, tolerance: f32) -> bool {
+    (value - previous).abs() <= tolerance * value.abs().max(previous.abs()).max(1.0)
+}
+
+fn settle(value: &mut f32, previous: f32, tolerance: f32) {
+    if within_tolerance(*value, previous, tolerance) {
+        *value = previous;
+    }
+}
+
+fn settle_optional(value: &mut Option<f32>, previous: Option<f32>, tolerance: f32) {
+    if let (Some(value), Some(previous)) = (value.as_mut(), previous) {
+        settle(value, previous, tolerance);
+    }
+}
+
+impl CachedMetrics {
+    /// Keep the cached readings that a re-analysis (after a version bump or parameter
+    /// tweak) only moved by less than `tolerance`, relative, so exports and version-
+    /// controlled caches don't churn on float noise.
+    pub fn settle_jitter(&mut self, previous: &CachedMetrics, tolerance: f32) {
+        settle(&mut self.centroid, previous.centroid, tolerance);
+        settle(&mut self.spread, previous.spread, tolerance);
+        settle(
+            &mut self.zero_crossing_rate,
+            previous.zero_crossing_rate,
+            tolerance,
+        );
+        settle(&mut self.loudness, previous.loudness, tolerance);
+        settle(
+            &mut self.duration_seconds,
+            previous.duration_seconds,
+            tolerance,
+        );
+        for (field, previous_value) in [
+            (&mut self.centroid_hz, previous.centroid_hz),
+            (&mut self.exact_centroid, previous.exact_centroid),
+            (&mut self.tilt, previous.tilt),
+            (&mut self.rolloff, previous.rolloff),
+            (&mut self.onset_strength, previous.onset_strength),
+            (&mut self.sharpness, previous.sharpness),
+            (&mut self.roughness, previous.roughness),
+            (&mut self.integrated_lufs, previous.integrated_lufs),
+            (&mut self.loudness_range, previous.loudness_range),
+            (&mut self.true_peak_db, previous.true_peak_db),
+            (&mut self.dr_score, previous.dr_score),
+        ] {
+            settle_optional(field, previous_value, tolerance);
+        }
+        // Bands only compare when the layout is the same
+        if self.band_percentages.len() == previous.band_percentages.len() {
+            for (pct, &previous_pct) in self
+                .band_percentages
+                .iter_mut()
+                .zip(&previous.band_percentages)
+            {
+                settle(pct, previous_pct, tolerance);
+            }
+        }
+    }
+
+    pub fn to_metrics(&self) -> SpectrumMetrics {
+        SpectrumMetrics {
+            centroid: Percent(self.centroid),
+            centroid_hz: self.centroid_hz.map(Hz),
+            exact_centroid: self.exact_centroid.map(Percent),
+            spread: Percent(self.spread),
+            zero_crossing_rate: self.zero_crossing_rate,
+            loudness: Db(self.loudness),
+            integrated_lufs: self.integrated_lufs.map(Lufs),
+            loudness_range: self.loudness_range.map(Db),
+            true_peak_db: self.true_peak_db.map(Db),
+            dr_score: self.dr_score.map(Db),
+            dynamics: self.dynamics.clone(),
+            clipping: self.clipping.clone(),
+            silence: self.silence.clone(),
+            duration_seconds: self.duration_seconds,
+            band_percentages: self.band_percentages.clone(),
+            ratios: self.ratios.clone(),
+            tempo: self.tempo.clone(),
+            key: self.key.clone(),
+            groove: self.groove.clone(),
+            rhythm_pattern: self.rhythm_pattern.clone(),
+            chorus: self.chorus.clone(),
+            intro: self.intro.clone(),
+            outro: self.outro.clone(),
+            tilt: self.tilt,
+            rolloff: self.rolloff.map(Hz),
+            bandwidth: self.bandwidth.clone(),
+            onset_strength: self.onset_strength.map(Percent),
+            windowed: self.windowed,
+            sharpness: self.sharpness,
+            roughness: self.roughness,
+            stereo: self.stereo.clone(),
+            surround: self.surround.clone(),
+            lfe: self.lfe.clone(),
+            stream: self.stream.clone(),
+        }
+    }
+
+    /// Stamp the entry as seen, returning true if the cache needs saving.
+    pub fn touch(&mut self) -> bool {
+        let now = unix_now();
+        match self.last_accessed {
+            Some(last) if now.saturating_sub(last) < ACCESS_REFRESH_SECS => false,
+            _ => {
+                self.last_accessed = Some(now);
+                true
+            }
+        }
+    }
+}
+
+/// Decode a file and fold it to mono, with its sample rate.
+pub fn get_samples(path: &Path) -> Result<(Vec<f32>, usize), AnalysisErrorKind> {
+    let audio = decode_audio(path)?;
+    if audio.sample_rate == 0 {
+        return Err(AnalysisErrorKind::UnsupportedSampleRate(audio.sample_rate));
+    }
+    let samples = downmix(&audio.channels);
+    if samples.is_empty() {
+        return Err(AnalysisErrorKind::EmptyAudio);
+    }
+    Ok((samples, audio.sample_rate))
+}
+
+/// Format details read from the stream while decoding.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StreamInfo {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub codec: String, // "mp3", "wav", "flac", "aac", or ffmpeg's codec name
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bitrate_kbps: f32, // Mean over frames, so VBR files get their average
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitrate_mode: Option<BitrateMode>, // MP3 only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoder: Option<String>, // From the LAME tag or FLAC vendor string, e.g. "LAME3.100"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undecodable_percent: Option<f32>, // MP3 frames lost to corruption, when any were
+}
+
+/// Whether every MP3 frame has the same bitrate.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BitrateMode {
+    Cbr,
+    Vbr,
+}
+
+impl fmt::Display for BitrateMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            BitrateMode::Cbr => "CBR",
+            BitrateMode::Vbr => "VBR",
+        })
+    }
+}
+
+impl StreamInfo {
+    /// One line for display, e.g. "MP3, 44100 Hz, 2 ch, 320 kbps CBR, LAME3.100".
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.codec.is_empty() {
+            parts.push(self.codec.to_uppercase());
+        }
+        parts.push(format!("{} Hz", self.sample_rate));
+        parts.push(format!("{} ch", self.channels));
+        parts.push(match self.bitrate_mode {
+            Some(mode) => format!("{:.0} kbps {}", self.bitrate_kbps, mode),
+            None => format!("{:.0} kbps", self.bitrate_kbps),
+        });
+        parts.extend(self.encoder.clone());
+        parts.join(", ")
+    }
+}
+
+pub struct DecodedAudio {
+    pub channels: Vec<Vec<f32>>, // One vector per channel, normalized to -1.0 to 1.0
+    pub sample_rate: usize,
+    pub stream: StreamInfo,
+    pub truncated: bool, // More audio follows what fit within the memory budget
+}
+
+/// Decode through the ffmpeg binary at `path` whenever the built-in decoders can't, and
+/// include the formats only ffmpeg reads when scanning directories.
+pub fn set_ffmpeg(path: PathBuf) {
+    let _ = FFMPEG.set(path);
+}
+
+/// Analyze the audio stream at `index` (0-based, counting audio streams only) in files
+/// that have several, such as alternate languages or mixes in a video.
+pub fn set_audio_stream(index: usize) {
+    let _ = AUDIO_STREAM.set(index);
+}
+
+/// Keep decoding MP3s past read errors instead of failing the file, so a damaged stretch
+/// only costs the frames in it. How much was lost shows in `StreamInfo::undecodable_percent`.
+pub fn set_skip_bad_frames(enabled: bool) {
+    SKIP_BAD_FRAMES.store(enabled, Ordering::Relaxed);
+}
+
+/// The ffmpeg binary from `set_ffmpeg`, if the fallback is enabled.
+pub fn ffmpeg_binary() -> Option<&'static Path> {
+    FFMPEG.get().map(PathBuf::as_path)
+}
+
+/// The chosen audio stream, or `None` for the first.
+pub fn selected_audio_stream() -> Option<usize> {
+    AUDIO_STREAM.get().copied().filter(|&index| index > 0)
+}
+
+/// One audio stream of a file, as listed by `streams`.
+pub struct AudioStream {
+    pub codec: String,
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub language: Option<String>,
+}
+
+/// The audio streams in a file, in the order `--stream` counts them.
+pub fn list_audio_streams(path: &Path) -> Result<Vec<AudioStream>, Box<dyn std::error::Error>> {
+    if let Some(ffmpeg) = FFMPEG
+        .get()
+        .filter(|_| has_extension(path, FFMPEG_EXTENSIONS))
+    {
+        return probe_ffmpeg_streams(path, ffmpeg, None);
+    }
+    if has_extension(path, CONTAINER_EXTENSIONS) {
+        let reader = open_container(path)?;
+        return Ok(reader
+            .tracks()
+            .iter()
+            .filter(|t| is_audio_track(t))
+            .map(|t| AudioStream {
+                codec: codec_name(t),
+                sample_rate: t.codec_params.sample_rate.unwrap_or(0),
+                channels: t.codec_params.channels.map_or(0, |c| c.count()),
+                language: t.language.clone(),
+            })
+            .collect());
+    }
+
+    // Everything else holds a single stream; a short decode is enough to describe it
+    let audio = decode_audio_limited(path, Some(STREAM_PROBE_BYTES))?;
+    Ok(vec![AudioStream {
+        codec: audio.stream.codec,
+        sample_rate: audio.stream.sample_rate,
+        channels: audio.stream.channels as usize,
+        language: None,
+    }])
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            extensions
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+}
+
+/// Whether a path has one of the supported audio extensions, in any case.
+pub fn is_audio_file(path: &Path) -> bool {
+    has_extension(path, AUDIO_EXTENSIONS)
+        || (FFMPEG.get().is_some() && has_extension(path, FFMPEG_EXTENSIONS))
+}
+
+/// Supported audio files directly inside `dir`, sorted by path.
+pub fn audio_files_in(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| is_audio_file(path))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Decode an MP3, WAV, AIFF, FLAC, DSD (DSF/DFF), or AAC/M4A file, or the audio of an
+/// MP4/MKV video, picking the decoder by extension, or any format ffmpeg reads once `set_ffmpeg` is called.
+/// Errors from every decoder come back in the same "can't decode <format> file" form,
+/// except failures to read the file, which stay an `std::io::Error`.
+pub fn decode_audio(path: &Path) -> Result<DecodedAudio, Box<dyn std::error::Error>> {
+    decode_audio_limited(path, None)
+}
+
+/// Bytes analysis holds per sample frame at its peak: the interleaved and per-channel
+/// copies while decoding, plus the mono downmix.
+pub fn bytes_per_frame(channel_count: usize) -> u64 {
+    (std::mem::size_of::<f32>() * (2 * channel_count + 1)) as u64
+}
+
+// How many sample frames fit in `max_bytes`, or no limit at all
+fn frame_limit(max_bytes: Option<u64>, channel_count: usize) -> usize {
+    max_bytes.map_or(usize::MAX, |bytes| {
+        (bytes / bytes_per_frame(channel_count)) as usize
+    })
+}
+
+/// Like `decode_audio`, but stops once the decoded audio would need more than `max_bytes`
+/// (see `bytes_per_frame`), keeping only the start of the track and marking it truncated.
+pub fn decode_audio_limited(
+    path: &Path,
+    max_bytes: Option<u64>,
+) -> Result<DecodedAudio, Box<dyn std::error::Error>> {
+    decode_audio_window(path, 0, max_bytes)
+}
+
+/// Like `decode_audio_limited`, but starting `start` sample frames into the track, so a
+/// long file can be measured one bounded window after another.
+pub fn decode_audio_window(
+    path: &Path,
+    start: usize,
+    max_bytes: Option<u64>,
+) -> Result<DecodedAudio, Box<dyn std::error::Error>> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    let ffmpeg = FFMPEG.get();
+    // Formats with no built-in decoder go straight to ffmpeg
+    if let Some(ffmpeg) = ffmpeg.filter(|_| has_extension(path, FFMPEG_EXTENSIONS)) {
+        return decode_ffmpeg(path, ffmpeg, start, max_bytes)
+            .map_err(|e| decode_error(&extension.unwrap_or_default().to_ascii_uppercase(), e));
+    }
+
+    // Single-stream formats have nothing to choose from
+    if let Some(index) =
+        selected_audio_stream().filter(|_| !has_extension(path, CONTAINER_EXTENSIONS))
+    {
+        return Err(format!("no audio stream {} (the file has only one)", index).into());
+    }
+
+    let (format, result) = match extension.as_deref() {
+        // hound rejects 64-bit float and some extensible layouts, which symphonia reads
+        Some("wav") => (
+            "WAV",
+            decode_wav(path, start, max_bytes)
+                .or_else(|e| decode_pcm_container(path, start, max_bytes, "wav").map_err(|_| e)),
+        ),
+        Some("aif") | Some("aiff") | Some("aifc") => {
+            ("AIFF", decode_pcm_container(path, start, max_bytes, "aiff"))
+        }
+        Some("flac") => ("FLAC", decode_flac(path, start, max_bytes)),
+        Some("dsf") => ("DSF", read_dsf(path, start, max_bytes).map(decode_dsd)),
+        Some("dff") => ("DSDIFF", read_dff(path, start, max_bytes).map(decode_dsd)),
+        Some("m4a") | Some("aac") => ("AAC", decode_container(path, start, max_bytes)),
+        Some("mp4") | Some("m4v") | Some("mov") => {
+            ("MP4", decode_container(path, start, max_bytes))
+        }
+        Some("mkv") | Some("webm") => ("Matroska", decode_container(path, start, max_bytes)),
+        _ => ("MP3", decode_mp3(path, start, max_bytes)),
+    };
+
+    // Codecs the built-in decoders reject, like ALAC in an .m4a, get a second try; a file
+    // that can't be read at all doesn't
+    match (result.map_err(read_error), ffmpeg) {
+        (Ok(audio), _) => Ok(audio),
+        (Err(Ok(e)), _) => Err(Box::new(e)),
+        (Err(Err(native)), Some(ffmpeg)) => {
+            decode_ffmpeg(path, ffmpeg, start, max_bytes).map_err(|e| {
+                format!("can't decode {} file: {} (ffmpeg: {})", format, native, e).into()
+            })
+        }
+        (Err(Err(e)), None) => Err(format!("can't decode {} file: {}", format, e).into()),
+    }
+}
+
+// The "can't decode <format> file" form, or the `std::io::Error` if reading the file failed
+fn decode_error(format: &str, error: Box<dyn std::error::Error>) -> Box<dyn std::error::Error> {
+    match read_error(error) {
+        Ok(e) => Box::new(e),
+        Err(e) => format!("can't decode {} file: {}", format, e).into(),
+    }
+}
+
+// The I/O error under a decoder's own error type, when reading the file is what failed.
+// Decoders also report short or malformed data as I/O errors, so only ones from the OS
+// count; the rest stay decoding errors.
+fn read_error(
+    error: Box<dyn std::error::Error>,
+) -> Result<std::io::Error, Box<dyn std::error::Error>> {
+    let unreadable = |e: std::io::Error| match e.raw_os_error() {
+        Some(_) => Ok(e),
+        None => Err(Box::new(e) as Box<dyn std::error::Error>),
+    };
+    let error = match error.downcast::<std::io::Error>() {
+        Ok(e) => return unreadable(*e),
+        Err(error) => error,
+    };
+    let error = match error.downcast::<hound::Error>() {
+        Ok(e) => {
+            return match *e {
+                hound::Error::IoError(e) => unreadable(e),
+                e => Err(Box::new(e)),
+            };
+        }
+        Err(error) => error,
+    };
+    let error = match error.downcast::<claxon::Error>() {
+        Ok(e) => {
+            return match *e {
+                claxon::Error::IoError(e) => unreadable(e),
+                e => Err(Box::new(e)),
+            };
+        }
+        Err(error) => error,
+    };
+    let error = match error.downcast::<SymphoniaError>() {
+        Ok(e) => {
+            return match *e {
+                SymphoniaError::IoError(e) => unreadable(e),
+                e => Err(Box::new(e)),
+            };
+        }
+        Err(error) => error,
+    };
+    match error.downcast::<minimp3::Error>() {
+        Ok(e) => match *e {
+            minimp3::Error::Io(e) => unreadable(e),
+            e => Err(Box::new(e)),
+        },
+        Err(error) => Err(error),
+    }
+}
+
+// ffprobe ships alongside ffmpeg, so it's looked up the same way
+fn ffprobe_path(ffmpeg: &Path) -> PathBuf {
+    let name = match ffmpeg.extension() {
+        Some(ext) => format!("ffprobe.{}", ext.to_string_lossy()),
+        None => "ffprobe".to_string(),
+    };
+    ffmpeg.with_file_name(name)
+}
+
+/// Ask ffprobe for the codec, sample rate, channel count, and language of the file's audio
+/// streams, or only of stream `index`.
+fn probe_ffmpeg_streams(
+    path: &Path,
+    ffmpeg: &Path,
+    index: Option<usize>,
+) -> Result<Vec<AudioStream>, Box<dyn std::error::Error>> {
+    let selector = match index {
+        Some(index) => format!("a:{}", index),
+        None => "a".to_string(),
+    };
+    let probe = Command::new(ffprobe_path(ffmpeg))
+        .args(["-v", "error", "-select_streams", &selector])
+        .args([
+            "-show_entries",
+            "stream=codec_name,sample_rate,channels:stream_tags=language",
+        ])
+        .args(["-of", "compact=p=0"])
+        .arg(path)
+        .output()
+        .map_err(|e| format!("can't run ffprobe: {}", e))?;
+    if !probe.status.success() {
+        return Err(String::from_utf8_lossy(&probe.stderr)
+            .trim()
+            .to_string()
+            .into());
+    }
+
+    // One line per stream: codec_name=aac|sample_rate=48000|channels=2|tag:language=eng
+    Ok(std::str::from_utf8(&probe.stdout)?
+        .lines()
+        .map(|line| {
+            let fields: HashMap<&str, &str> =
+                line.split('|').filter_map(|f| f.split_once('=')).collect();
+            AudioStream {
+                codec: fields.get("codec_name").unwrap_or(&"").to_string(),
+                sample_rate: fields
+                    .get("sample_rate")
+                    .and_then(|rate| rate.parse().ok())
+                    .unwrap_or(0),
+                channels: fields
+                    .get("channels")
+                    .and_then(|channels| channels.parse().ok())
+                    .unwrap_or(0),
+                language: fields.get("tag:language").map(|l| l.to_string()),
+            }
+        })
+        .collect())
+}
+
+/// Decode the selected audio stream through ffmpeg as 32-bit float PCM.
+fn decode_ffmpeg(
+    path: &Path,
+    ffmpeg: &Path,
+    start: usize,
+    max_bytes: Option<u64>,
+) -> Result<DecodedAudio, Box<dyn std::error::Error>> {
+    let index = selected_audio_stream().unwrap_or(0);
+    let probed = probe_ffmpeg_streams(path, ffmpeg, Some(index))?
+        .into_iter()
+        .next()
+        .filter(|stream| stream.sample_rate > 0 && stream.channels > 0)
+        .ok_or_else(|| format!("no audio stream {}", index))?;
+    let sample_rate = probed.sample_rate;
+    let channel_count = probed.channels;
+
+    let mut child = Command::new(ffmpeg)
+        .args(["-v", "error", "-nostdin", "-i"])
+        .arg(path)
+        .args(["-map", &format!("0:a:{}", index)])
+        .args(["-f", "f32le", "-acodec", "pcm_f32le", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("can't run ffmpeg: {}", e))?;
+    let mut reader = BufReader::new(child.stdout.take().ok_or("no output from ffmpeg")?);
+
+    let max_samples = frame_limit(max_bytes, channel_count).saturating_mul(channel_count);
+    let mut to_skip = start.saturating_mul(channel_count);
+    let mut samples = Vec::new();
+    let mut bytes = [0u8; 4];
+    let mut truncated = false;
+    loop {
+        match reader.read_exact(&mut bytes) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(Box::new(e)),
+        }
+        if to_skip > 0 {
+            to_skip -= 1;
+            continue;
+        }
+        if samples.len() >= max_samples {
+            truncated = true;
+            break;
+        }
+        samples.push(f32::from_le_bytes(bytes));
+    }
+
+    if truncated {
+        let _ = child.kill();
+        let _ = child.wait();
+    } else {
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr)
+                .trim()
+                .to_string()
+                .into());
+        }
+    }
+
+    let channels = deinterleave(&samples, channel_count);
+    let stream = StreamInfo {
+        codec: probed.codec,
+        sample_rate,
+        channels: channel_count as u16,
+        bitrate_kbps: average_bitrate(path, &channels, sample_rate),
+        bitrate_mode: None,
+        encoder: None,
+        undecodable_percent: None,
+    };
+    Ok(DecodedAudio {
+        channels,
+        sample_rate: sample_rate as usize,
+        stream,
+        truncated,
+    })
+}
+
+/// Split interleaved samples into one vector per channel.
+fn deinterleave(samples: &[f32], channel_count: usize) -> Vec<Vec<f32>> {
+    let mut channels = vec![Vec::with_capacity(samples.len() / channel_count); channel_count];
+    for chunk in samples.chunks(channel_count) {
+        for (channel, &x) in channels.iter_mut().zip(chunk) {
+            channel.push(x);
+        }
+    }
+    channels
+}
+
+fn decode_flac(
+    path: &Path,
+    start: usize,
+    max_bytes: Option<u64>,
+) -> Result<DecodedAudio, Box<dyn std::error::Error>> {
+    let mut reader = FlacReader::open(path)?;
+    let info = reader.streaminfo();
+    let vendor = reader.vendor().map(str::to_string);
+    let channel_count = info.channels.max(1) as usize;
+    let max_samples = frame_limit(max_bytes, channel_count).saturating_mul(channel_count);
+    let skipped = start.saturating_mul(channel_count);
+    let truncated = info.samples.is_some_and(|frames| {
+        frames.saturating_mul(channel_count as u64)
+            > (skipped as u64).saturating_add(max_samples as u64)
+    });
+
+    let scale = (1u64 << (info.bits_per_sample.max(1) - 1)) as f32;
+    let samples: Vec<f32> = reader
+        .samples()
+        .skip(skipped)
+        .take(max_samples)
+        .map(|s| s.map(|s| s as f32 / scale))
+        .collect::<Result<_, _>>()?;
+    let channels = deinterleave(&samples, channel_count);
+
+    // Lossless bitrate varies with content, so it's taken from the file size
+    let stream = StreamInfo {
+        codec: "flac".to_string(),
+        sample_rate: info.sample_rate,
+        channels: channel_count as u16,
+        bitrate_kbps: average_bitrate(path, &channels, info.sample_rate),
+        bitrate_mode: None,
+        encoder: vendor,
+        undecodable_percent: None,
+    };
+    Ok(DecodedAudio {
+        channels,
+        sample_rate: info.sample_rate as usize,
+        stream,
+        truncated,
+    })
+}
+
+/// 1-bit DSD downconverted to PCM at a 64th of its rate, so DSD64 analyzes as 44.1 kHz.
+fn decode_dsd(dsd: DsdStream) -> DecodedAudio {
+    let channels: Vec<Vec<f32>> = dsd.channels.iter().map(|bits| to_pcm(bits)).collect();
+
+    // The analyzed rate goes in the stream info; the codec keeps the DSD multiple
+    let stream = StreamInfo {
+        codec: format!("dsd{}", dsd.dsd_rate / 44100),
+        sample_rate: dsd.pcm_rate(),
+        channels: channels.len() as u16,
+        bitrate_kbps: dsd.dsd_rate as f32 * channels.len() as f32 / 1000.0,
+        bitrate_mode: None,
+        encoder: None,
+        undecodable_percent: None,
+    };
+    DecodedAudio {
+        channels,
+        sample_rate: dsd.pcm_rate() as usize,
+        stream,
+        truncated: dsd.truncated,
+    }
+}
+
+/// Mean bitrate from the file size and decoded length, for formats without a fixed rate.
+fn average_bitrate(path: &Path, channels: &[Vec<f32>], sample_rate: u32) -> f32 {
+    let duration_seconds = channels.first().map_or(0, |c| c.len()) as f32 / sample_rate as f32;
+    let file_bits = fs::metadata(path).map_or(0, |m| m.len()) as f32 * 8.0;
+    if duration_seconds > 0.0 {
+        file_bits / duration_seconds / 1000.0
+    } else {
+        0.0
+    }
+}
+
+fn open_container(path: &Path) -> Result<Box<dyn FormatReader>, Box<dyn std::error::Error>> {
+    let source = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+    Ok(symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format)
+}
+
+// Video and subtitle tracks carry no sample rate
+fn is_audio_track(track: &Track) -> bool {
+    track.codec_params.codec != CODEC_TYPE_NULL && track.codec_params.sample_rate.is_some()
+}
+
+fn codec_name(track: &Track) -> String {
+    symphonia::default::get_codecs()
+        .get_codec(track.codec_params.codec)
+        .map_or("", |descriptor| descriptor.short_name)
+        .to_string()
+}
+
+// The audio track `set_audio_stream` picked, or the first one
+fn selected_track(reader: &dyn FormatReader) -> Result<&Track, Box<dyn std::error::Error>> {
+    let index = selected_audio_stream().unwrap_or(0);
+    let audio_tracks = reader.tracks().iter().filter(|t| is_audio_track(t));
+    match audio_tracks.clone().nth(index) {
+        Some(track) => Ok(track),
+        None if index == 0 => Err("no audio track".into()),
+        None => Err(format!(
+            "no audio stream {} (the file has {})",
+            index,
+            audio_tracks.count()
+        )
+        .into()),
+    }
+}
+
+/// The selected (by default the first) audio track of an MP4 or Matroska container
+/// (including music videos, where the video track is skipped), or a raw ADTS stream.
+fn decode_container(
+    path: &Path,
+    start: usize,
+    max_bytes: Option<u64>,
+) -> Result<DecodedAudio, Box<dyn std::error::Error>> {
+    let mut reader = open_container(path)?;
+    let track = selected_track(reader.as_ref())?;
+    let track_id = track.id;
+    let codec = codec_name(track);
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+    let mut sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+    let mut channel_count = track.codec_params.channels.map_or(1, |c| c.count());
+
+    let mut to_skip = start; // Sample frames still to drop before the window
+    let mut samples = Vec::new();
+    let mut truncated = false;
+    loop {
+        if samples.len() / channel_count.max(1) >= frame_limit(max_bytes, channel_count) {
+            truncated = true;
+            break;
+        }
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break;
+            }
+            Err(e) => return Err(Box::new(e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        match decoder.decode(&packet) {
+            Ok(buffer) => {
+                let spec = *buffer.spec();
+                sample_rate = spec.rate;
+                channel_count = spec.channels.count();
+                let mut interleaved = SampleBuffer::<f32>::new(buffer.capacity() as u64, spec);
+                interleaved.copy_interleaved_ref(buffer);
+                samples.extend_from_slice(interleaved.samples());
+                if to_skip > 0 {
+                    let skipped = to_skip.saturating_mul(channel_count).min(samples.len());
+                    samples.drain(..skipped);
+                    to_skip -= skipped / channel_count.max(1);
+                }
+            }
+            // A damaged packet only costs its own samples
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(Box::new(e)),
+        }
+    }
+
+    let channel_count = channel_count.max(1);
+    let channels = deinterleave(&samples, channel_count);
+    let stream = StreamInfo {
+        codec,
+        sample_rate,
+        channels: channel_count as u16,
+        bitrate_kbps: average_bitrate(path, &channels, sample_rate),
+        bitrate_mode: None,
+        encoder: None,
+        undecodable_percent: None,
+    };
+    Ok(DecodedAudio {
+        channels,
+        sample_rate: sample_rate as usize,
+        stream,
+        truncated,
+    })
+}
+
+/// Uncompressed PCM through symphonia, which scales every integer and float sample
+/// format to -1.0 to 1.0, labeled with the container's name.
+fn decode_pcm_container(
+    path: &Path,
+    start: usize,
+    max_bytes: Option<u64>,
+    codec: &str,
+) -> Result<DecodedAudio, Box<dyn std::error::Error>> {
+    let mut audio = decode_container(path, start, max_bytes)?;
+    audio.stream.codec = codec.to_string();
+    Ok(audio)
+}
+
+fn decode_wav(
+    path: &Path,
+    start: usize,
+    max_bytes: Option<u64>,
+) -> Result<DecodedAudio, Box<dyn std::error::Error>> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
+    let channel_count = spec.channels.max(1) as usize;
+    let max_samples = frame_limit(max_bytes, channel_count).saturating_mul(channel_count);
+    let frames = reader.duration() as usize;
+    let start = start.min(frames);
+    if start > 0 {
+        reader.seek(start as u32)?;
+    }
+    let truncated = frames - start > frame_limit(max_bytes, channel_count);
+
+    // Integer samples are scaled by their bit depth so every format lands in -1.0 to 1.0
+    let samples: Vec<f32> = match spec.sample_format {
+        SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .take(max_samples)
+            .collect::<Result<_, _>>()?,
+        SampleFormat::Int => {
+            let scale = (1u64 << (spec.bits_per_sample.max(1) - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .take(max_samples)
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+
+    let channels = deinterleave(&samples, channel_count);
+
+    let stream = StreamInfo {
+        codec: "wav".to_string(),
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+        bitrate_kbps: spec.sample_rate as f32 * spec.channels as f32 * spec.bits_per_sample as f32
+            / 1000.0,
+        bitrate_mode: None,
+        encoder: None,
+        undecodable_percent: None,
+    };
+    Ok(DecodedAudio {
+        channels,
+        sample_rate: spec.sample_rate as usize,
+        stream,
+        truncated,
+    })
 }
 
-pub fn get_samples(path: &Path) -> Result<(Vec<f32>, usize), Box<dyn std::error::Error>> {
+// Encoder tags in the first frame: LAME's own, and the same layout as written by ffmpeg
+const MP3_ENCODER_TAGS: [&[u8]; 3] = [b"LAME", b"Lavc", b"Lavf"];
+// The tag field is 9 bytes, but LAME also writes its full version into the padding
+const MP3_ENCODER_VERSION_LEN: usize = 12;
+// Xing and Info headers fill a frame of their own that decodes to no audio
+const MP3_INFO_TAGS: [&[u8]; 2] = [b"Xing", b"Info"];
+// With --skip-bad-frames, decoding gives up after this many read errors in a row
+const MP3_ERROR_LIMIT: usize = 8;
+
+// Offset of the first MPEG frame, past any ID3v2 tag
+fn mp3_audio_start(file: &mut File) -> Option<u64> {
+    let mut header = [0u8; 10];
+    file.read_exact(&mut header).ok()?;
+    if !header.starts_with(b"ID3") {
+        return Some(0);
+    }
+    // The tag size is synchsafe: seven bits per byte
+    let size = header[6..10]
+        .iter()
+        .fold(0u64, |size, &b| (size << 7) | (b & 0x7f) as u64);
+    Some(10 + size)
+}
+
+// The first kilobyte of MPEG audio, which holds the Xing/Info and LAME tags
+fn mp3_first_frame(file: &mut File) -> Option<Vec<u8>> {
+    let start = mp3_audio_start(file)?;
+    file.seek(SeekFrom::Start(start)).ok()?;
+    let mut frame = Vec::new();
+    file.take(1024).read_to_end(&mut frame).ok()?;
+    Some(frame)
+}
+
+/// The encoder and version from an MP3's LAME tag, which sits in the first frame after
+/// any ID3v2 tag.
+fn mp3_encoder(path: &Path) -> Option<String> {
+    let frame = mp3_first_frame(&mut File::open(path).ok()?)?;
+    let start = frame
+        .windows(4)
+        .position(|window| MP3_ENCODER_TAGS.contains(&window))?;
+    // Name, then a version of digits and dots, e.g. "LAME3.100" or "Lavc58.54"
+    let version: String = frame[start + 4..]
+        .iter()
+        .take(MP3_ENCODER_VERSION_LEN)
+        .take_while(|b| b.is_ascii_digit() || **b == b'.')
+        .map(|&b| b as char)
+        .collect();
+    Some(format!(
+        "{}{}",
+        String::from_utf8_lossy(&frame[start..start + 4]),
+        version.trim_end_matches('.')
+    ))
+}
+
+fn decode_mp3(
+    path: &Path,
+    start: usize,
+    max_bytes: Option<u64>,
+) -> Result<DecodedAudio, Box<dyn std::error::Error>> {
     let file = File::open(Path::new(path))?;
     let mut decoder = Decoder::new(file);
 
-    let mut all_samples = Vec::new();
+    let mut channels: Vec<Vec<f32>> = Vec::new();
     let mut sample_rate = 0;
+    let mut bitrate_sum = 0u64;
+    let mut bitrate_range: Option<(i32, i32)> = None;
+    let mut frame_count = 0u64;
+    let mut decoded_bytes = 0.0f64; // Compressed size of the frames decoded, from their bitrates
+    let mut errors_in_a_row = 0;
+    let mut truncated = false;
+    let mut to_skip = start; // Sample frames still to drop before the window
 
     loop {
+        let length = channels.first().map_or(0, |c| c.len());
+        if length >= frame_limit(max_bytes, channels.len().max(1)) {
+            truncated = true;
+            break;
+        }
         match decoder.next_frame() {
             Ok(Frame {
                 data,
                 sample_rate: sr,
+                channels: frame_channels,
+                bitrate,
                 ..
             }) => {
+                errors_in_a_row = 0;
                 sample_rate = sr as usize;
-                // Convert to mono by averaging channels and normalize to -1.0 to 1.0 (bits to float)
-                for chunk in data.chunks(2) {
-                    let mono =
-                        chunk.iter().map(|&x| x as f32 / 32768.0).sum::<f32>() / chunk.len() as f32;
-                    all_samples.push(mono);
+                bitrate_sum += bitrate.max(0) as u64;
+                bitrate_range = Some(match bitrate_range {
+                    Some((low, high)) => (low.min(bitrate), high.max(bitrate)),
+                    None => (bitrate, bitrate),
+                });
+                frame_count += 1;
+
+                let frame_channels = frame_channels.max(1);
+                let frame_samples = data.len() / frame_channels;
+                decoded_bytes +=
+                    frame_samples as f64 * bitrate.max(0) as f64 * 125.0 / sr.max(1) as f64;
+                if channels.len() < frame_channels {
+                    // Pad a channel that appears mid-stream so all channels stay aligned
+                    let length = channels.first().map_or(0, |c| c.len());
+                    channels.resize(frame_channels, vec![0.0; length]);
+                }
+                let skipped = to_skip.min(frame_samples);
+                to_skip -= skipped;
+                // Deinterleave and convert bits to float
+                for chunk in data.chunks(frame_channels).skip(skipped) {
+                    for (channel, &x) in channels.iter_mut().zip(chunk) {
+                        channel.push(x as f32 / 32768.0);
+                    }
                 }
             }
             Err(minimp3::Error::Eof) => break,
-            Err(e) => return Err(Box::new(e)),
+            Err(e) if !SKIP_BAD_FRAMES.load(Ordering::Relaxed) => return Err(Box::new(e)),
+            Err(e) => {
+                errors_in_a_row += 1;
+                if errors_in_a_row == MP3_ERROR_LIMIT {
+                    // Nothing decoded at all is still a failure
+                    if frame_count == 0 {
+                        return Err(Box::new(e));
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    let stream = StreamInfo {
+        codec: "mp3".to_string(),
+        sample_rate: sample_rate as u32,
+        channels: channels.len() as u16,
+        bitrate_kbps: bitrate_sum as f32 / frame_count.max(1) as f32,
+        bitrate_mode: bitrate_range.map(|(low, high)| {
+            if low == high {
+                BitrateMode::Cbr
+            } else {
+                BitrateMode::Vbr
+            }
+        }),
+        encoder: mp3_encoder(path),
+        // A partial decode leaves the rest of the file unread, not lost
+        undecodable_percent: (!truncated && start == 0)
+            .then(|| mp3_undecodable_percent(path, decoded_bytes, frame_count))
+            .flatten(),
+    };
+    Ok(DecodedAudio {
+        channels,
+        sample_rate,
+        stream,
+        truncated,
+    })
+}
+
+// Frames the decoder skipped, as a percentage of those in the file. The count comes from
+// the bytes of MPEG audio left over once the decoded frames' sizes are taken out.
+fn mp3_undecodable_percent(path: &Path, decoded_bytes: f64, frame_count: u64) -> Option<f32> {
+    let mut file = File::open(path).ok()?;
+    let start = mp3_audio_start(&mut file)?;
+    let mut end = file.metadata().ok()?.len();
+    // ID3v1 tags are the last 128 bytes
+    let mut tail = [0u8; 3];
+    if end >= start + 128
+        && file.seek(SeekFrom::Start(end - 128)).is_ok()
+        && file.read_exact(&mut tail).is_ok()
+        && &tail == b"TAG"
+    {
+        end -= 128;
+    }
+    let info_frame = mp3_first_frame(&mut file).is_some_and(|frame| {
+        frame
+            .windows(4)
+            .any(|window| MP3_INFO_TAGS.contains(&window))
+    });
+
+    let frame_bytes = decoded_bytes / frame_count.max(1) as f64;
+    if frame_bytes <= 0.0 {
+        return None;
+    }
+    let expected = end.saturating_sub(start) as f64 / frame_bytes - info_frame as u8 as f64;
+    let lost = (expected - frame_count as f64).round();
+    (lost >= 1.0).then(|| (lost / expected * 100.0) as f32)
+}
+
+type Block = Result<Vec<f32>, Box<dyn std::error::Error>>;
+
+/// Mono audio decoded a block at a time, for callers that walk a track once and never
+/// need all of it in memory.
+pub struct SampleStream {
+    pub sample_rate: usize,
+    blocks: Box<dyn Iterator<Item = Block>>,
+}
+
+impl Iterator for SampleStream {
+    type Item = Block;
+
+    fn next(&mut self) -> Option<Block> {
+        self.blocks.next()
+    }
+}
+
+/// Open a file for `SampleStream` reading. MP3, WAV, FLAC, AIFF, and the MP4/Matroska
+/// containers decode incrementally; DSD and anything left to ffmpeg are decoded whole
+/// first and then handed out in blocks.
+pub fn open_sample_stream(path: &Path) -> Result<SampleStream, Box<dyn std::error::Error>> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    if (FFMPEG.get().is_some() && has_extension(path, FFMPEG_EXTENSIONS))
+        || (selected_audio_stream().is_some() && !has_extension(path, CONTAINER_EXTENSIONS))
+    {
+        return stream_decoded(path);
+    }
+
+    let (format, result) = match extension.as_deref() {
+        Some("wav") => (
+            "WAV",
+            stream_wav(path).or_else(|e| stream_container(path).map_err(|_| e)),
+        ),
+        Some("aif") | Some("aiff") | Some("aifc") => ("AIFF", stream_container(path)),
+        Some("flac") => ("FLAC", stream_flac(path)),
+        Some("dsf") | Some("dff") => return stream_decoded(path),
+        Some("m4a") | Some("aac") => ("AAC", stream_container(path)),
+        Some("mp4") | Some("m4v") | Some("mov") => ("MP4", stream_container(path)),
+        Some("mkv") | Some("webm") => ("Matroska", stream_container(path)),
+        _ => ("MP3", stream_mp3(path)),
+    };
+    // Let `decode_audio` retry through ffmpeg, as it would for a full decode
+    match result {
+        Ok(stream) => Ok(stream),
+        Err(_) if FFMPEG.get().is_some() => stream_decoded(path),
+        Err(e) => Err(decode_error(format, e)),
+    }
+}
+
+// Samples per block handed out by `SampleStream`
+const STREAM_BLOCK_FRAMES: usize = 8192;
+
+/// Average interleaved frames down to mono blocks.
+fn mono_blocks<E: Into<Box<dyn std::error::Error>>>(
+    mut samples: impl Iterator<Item = Result<f32, E>> + 'static,
+    channel_count: usize,
+) -> impl Iterator<Item = Block> {
+    std::iter::from_fn(move || {
+        let mut block = Vec::with_capacity(STREAM_BLOCK_FRAMES);
+        let (mut frame_sum, mut filled) = (0.0f32, 0);
+        for sample in samples.by_ref() {
+            match sample {
+                Ok(x) => frame_sum += x,
+                Err(e) => return Some(Err(e.into())),
+            }
+            filled += 1;
+            if filled == channel_count {
+                block.push(frame_sum / channel_count as f32);
+                (frame_sum, filled) = (0.0, 0);
+                if block.len() == STREAM_BLOCK_FRAMES {
+                    break;
+                }
+            }
+        }
+        (!block.is_empty()).then_some(Ok(block))
+    })
+}
+
+fn stream_wav(path: &Path) -> Result<SampleStream, Box<dyn std::error::Error>> {
+    let reader = WavReader::open(path)?;
+    let spec = reader.spec();
+    let channel_count = spec.channels.max(1) as usize;
+    let blocks: Box<dyn Iterator<Item = Block>> = match spec.sample_format {
+        SampleFormat::Float => Box::new(mono_blocks(reader.into_samples::<f32>(), channel_count)),
+        SampleFormat::Int => {
+            let scale = (1u64 << (spec.bits_per_sample.max(1) - 1)) as f32;
+            Box::new(mono_blocks(
+                reader
+                    .into_samples::<i32>()
+                    .map(move |s| s.map(|s| s as f32 / scale)),
+                channel_count,
+            ))
+        }
+    };
+    Ok(SampleStream {
+        sample_rate: spec.sample_rate as usize,
+        blocks,
+    })
+}
+
+fn stream_flac(path: &Path) -> Result<SampleStream, Box<dyn std::error::Error>> {
+    let mut reader = FlacReader::open(path)?;
+    let info = reader.streaminfo();
+    let scale = (1u64 << (info.bits_per_sample.max(1) - 1)) as f32;
+    // Each FLAC block decodes into the buffer the previous one gave back
+    let mut buffer = Vec::new();
+    let blocks = std::iter::from_fn(move || {
+        match reader
+            .blocks()
+            .read_next_or_eof(std::mem::take(&mut buffer))
+        {
+            Ok(Some(block)) => {
+                let channel_count = block.channels().max(1);
+                let mono = (0..block.duration())
+                    .map(|i| {
+                        (0..channel_count)
+                            .map(|ch| block.sample(ch, i) as f32)
+                            .sum::<f32>()
+                            / (channel_count as f32 * scale)
+                    })
+                    .collect();
+                buffer = block.into_buffer();
+                Some(Ok(mono))
+            }
+            Ok(None) => None,
+            Err(e) => Some(Err(e.into())),
+        }
+    });
+    Ok(SampleStream {
+        sample_rate: info.sample_rate as usize,
+        blocks: Box::new(blocks),
+    })
+}
+
+fn stream_mp3(path: &Path) -> Result<SampleStream, Box<dyn std::error::Error>> {
+    let mut decoder = Decoder::new(File::open(path)?);
+    // The rate is only known once the first frame is decoded, so that frame is held back
+    let first = decoder.next_frame()?;
+    let sample_rate = first.sample_rate as usize;
+    let mut pending = Some(first);
+    let blocks = std::iter::from_fn(move || {
+        let frame = match pending.take().map_or_else(|| decoder.next_frame(), Ok) {
+            Ok(frame) => frame,
+            Err(minimp3::Error::Eof) => return None,
+            Err(e) => return Some(Err(e.into())),
+        };
+        let channel_count = frame.channels.max(1);
+        Some(Ok(frame
+            .data
+            .chunks(channel_count)
+            .map(|chunk| {
+                chunk.iter().map(|&x| x as f32).sum::<f32>() / (channel_count as f32 * 32768.0)
+            })
+            .collect()))
+    });
+    Ok(SampleStream {
+        sample_rate,
+        blocks: Box::new(blocks),
+    })
+}
+
+fn stream_container(path: &Path) -> Result<SampleStream, Box<dyn std::error::Error>> {
+    let mut reader = open_container(path)?;
+    let track = selected_track(reader.as_ref())?;
+    let track_id = track.id;
+    let sample_rate = track.codec_params.sample_rate.unwrap_or(0) as usize;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+    let blocks = std::iter::from_fn(move || {
+        loop {
+            let packet = match reader.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                    return None;
+                }
+                Err(e) => return Some(Err(e.into())),
+            };
+            if packet.track_id() != track_id {
+                continue;
+            }
+            match decoder.decode(&packet) {
+                Ok(buffer) => {
+                    let spec = *buffer.spec();
+                    let mut interleaved = SampleBuffer::<f32>::new(buffer.capacity() as u64, spec);
+                    interleaved.copy_interleaved_ref(buffer);
+                    let channel_count = spec.channels.count().max(1);
+                    return Some(Ok(interleaved
+                        .samples()
+                        .chunks(channel_count)
+                        .map(|chunk| chunk.iter().sum::<f32>() / channel_count as f32)
+                        .collect()));
+                }
+                // A damaged packet only costs its own samples
+                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    });
+    Ok(SampleStream {
+        sample_rate,
+        blocks: Box::new(blocks),
+    })
+}
+
+// Formats without an incremental decoder here are decoded whole, then split up
+fn stream_decoded(path: &Path) -> Result<SampleStream, Box<dyn std::error::Error>> {
+    let audio = decode_audio(path)?;
+    let mono = downmix(&audio.channels);
+    let blocks: Vec<Vec<f32>> = mono
+        .chunks(STREAM_BLOCK_FRAMES)
+        .map(<[f32]>::to_vec)
+        .collect();
+    Ok(SampleStream {
+        sample_rate: audio.sample_rate,
+        blocks: Box::new(blocks.into_iter().map(Ok)),
+    })
+}
+
+/// Average all channels into one mono signal.
+pub fn downmix(channels: &[Vec<f32>]) -> Vec<f32> {
+    match channels {
+        [] => Vec::new(),
+        [mono] => mono.clone(),
+        _ => {
+            let length = channels.iter().map(|c| c.len()).min().unwrap_or(0);
+            (0..length)
+                .map(|i| channels.iter().map(|c| c[i]).sum::<f32>() / channels.len() as f32)
+                .collect()
         }
     }
-    return Ok((all_samples, sample_rate));
 }
 
 pub fn truncate_filename(name: &str, max_len: usize) -> String {
@@ -59,45 +1457,363 @@ pub fn truncate_filename(name: &str, max_len: usize) -> String {
     }
 }
 
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn cache_file_path(dir: &Path, options: &CacheOptions) -> PathBuf {
+    let file_name = match &options.profile {
+        Some(name) => CACHE_FILE_NAME.replace(".json", &format!(".{}.json", name)),
+        None => CACHE_FILE_NAME.to_string(),
+    };
+
+    let plain = match &options.cache_dir {
+        Some(cache_dir) => {
+            // Flatten the analyzed directory's path into a unique file name prefix
+            let absolute = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+            let key: String = absolute
+                .to_string_lossy()
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            cache_dir.join(format!("{}_{}", key, file_name))
+        }
+        None => dir.join(file_name),
+    };
+    if options.gzip {
+        plain.with_extension("json.gz")
+    } else {
+        plain
+    }
+}
+
+// The same cache in the other storage format, so toggling --gzip-cache keeps entries
+fn alternate_cache_path(cache_file: &Path) -> PathBuf {
+    let name = cache_file.file_name().unwrap_or_default().to_string_lossy();
+    match name.strip_suffix(".gz") {
+        Some(plain) => cache_file.with_file_name(plain),
+        None => cache_file.with_file_name(format!("{}.gz", name)),
+    }
+}
+
+/// Hold an advisory lock on a cache until the returned file is dropped, so overlapping
+/// runs (say, cron and a manual one) take turns instead of overwriting each other's
+/// entries. Take it before `load_cache` and keep it until after `save_cache`.
+pub fn lock_cache(cache_file: &Path) -> Option<File> {
+    // One lock for both storage formats, since either may be read
+    let name = cache_file.file_name().unwrap_or_default().to_string_lossy();
+    let lock_path = cache_file.with_file_name(format!(
+        ".{}.lock",
+        name.strip_suffix(".gz").unwrap_or(&name)
+    ));
+    if let Some(parent) = lock_path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    // Without a writable lock file we carry on unlocked, as before
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .ok()?;
+
+    if file.try_lock().is_err() {
+        eprintln!("{}", tr(Msg::WaitingForLock, &[&cache_file.display()]));
+        file.lock().ok()?;
+    }
+    Some(file)
+}
+
 pub fn load_cache(cache_file: &Path) -> HashMap<String, CachedMetrics> {
-    if let Ok(file) = File::open(cache_file) {
-        let reader = BufReader::new(file);
-        serde_json::from_reader(reader).unwrap_or_default()
+    let path = if cache_file.exists() {
+        cache_file.to_path_buf()
+    } else {
+        alternate_cache_path(cache_file)
+    };
+
+    let Ok(bytes) = fs::read(&path) else {
+        return HashMap::new();
+    };
+
+    // Detect compression from the content rather than trusting the extension
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut json = Vec::new();
+        if GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut json)
+            .is_err()
+        {
+            return HashMap::new();
+        }
+        decode_entries(&json)
+    } else {
+        decode_entries(&bytes)
+    }
+}
+
+/// Entries are read one at a time, so one this version can't make sense of is reported
+/// and skipped instead of emptying the whole cache.
+fn decode_entries(json: &[u8]) -> HashMap<String, CachedMetrics> {
+    let Ok(raw) = serde_json::from_slice::<HashMap<String, serde_json::Value>>(json) else {
+        return HashMap::new();
+    };
+    raw.into_iter()
+        .filter_map(|(name, value)| match serde_json::from_value(value) {
+            Ok(entry) => Some((name, entry)),
+            Err(e) => {
+                eprintln!("{}", tr(Msg::SkippingCacheEntry, &[&name, &e]));
+                None
+            }
+        })
+        .collect()
+}
+
+/// `files_dir` is the directory cache keys are relative to, used to spot deleted files.
+pub fn save_cache(
+    cache_file: &Path,
+    files_dir: &Path,
+    cache: &mut HashMap<String, CachedMetrics>,
+    options: &CacheOptions,
+) {
+    let Ok(mut bytes) = encode_cache(cache, options) else {
+        return;
+    };
+
+    if let Some(max_size) = options.max_size {
+        evict_missing_entries(files_dir, cache, options, max_size, &mut bytes);
+    }
+
+    if let Some(parent) = cache_file.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    // Write beside the cache and rename over it, so readers never see a half-written file
+    let name = cache_file.file_name().unwrap_or_default().to_string_lossy();
+    let temp_file = cache_file.with_file_name(format!(".{}.tmp", name));
+    if let Ok(mut file) = File::create(&temp_file) {
+        if file.write_all(&bytes).is_ok() {
+            let _ = fs::rename(&temp_file, cache_file);
+        } else {
+            let _ = fs::remove_file(&temp_file);
+        }
+    }
+
+    // Drop the stale copy left behind by a format switch
+    let alternate = alternate_cache_path(cache_file);
+    if alternate.exists() {
+        let _ = fs::remove_file(alternate);
+    }
+}
+
+pub fn cache_exceeds_limit(cache_file: &Path, options: &CacheOptions) -> bool {
+    let Some(max_size) = options.max_size else {
+        return false;
+    };
+    fs::metadata(cache_file)
+        .map(|m| m.len() > max_size)
+        .unwrap_or(false)
+}
+
+fn encode_cache(
+    cache: &HashMap<String, CachedMetrics>,
+    options: &CacheOptions,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let json = if options.compact {
+        serde_json::to_vec(cache)?
     } else {
-        HashMap::new()
+        serde_json::to_vec_pretty(cache)?
+    };
+
+    if !options.gzip {
+        return Ok(json);
     }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&json)?;
+    Ok(encoder.finish()?)
 }
 
-pub fn save_cache(cache_file: &Path, cache: &HashMap<String, CachedMetrics>) {
-    if let Ok(file) = File::create(cache_file) {
-        let writer = BufWriter::new(file);
-        let _ = serde_json::to_writer_pretty(writer, cache);
+fn evict_missing_entries(
+    dir: &Path,
+    cache: &mut HashMap<String, CachedMetrics>,
+    options: &CacheOptions,
+    max_size: u64,
+    bytes: &mut Vec<u8>,
+) {
+    if bytes.len() as u64 <= max_size {
+        return;
+    }
+
+    // Only entries whose files are gone are eligible, least recently seen first
+    let mut candidates: Vec<(Option<u64>, String)> = cache
+        .iter()
+        .filter(|(filename, _)| !dir.join(filename).exists())
+        .map(|(filename, entry)| (entry.last_accessed, filename.clone()))
+        .collect();
+    candidates.sort();
+    candidates.reverse();
+
+    while bytes.len() as u64 > max_size && !candidates.is_empty() {
+        // Estimate how many entries must go from the average entry size
+        let average = (bytes.len() / cache.len().max(1)).max(1) as u64;
+        let excess = bytes.len() as u64 - max_size;
+        let batch = excess.div_ceil(average).max(1) as usize;
+
+        for _ in 0..batch.min(candidates.len()) {
+            if let Some((_, filename)) = candidates.pop() {
+                cache.remove(&filename);
+            }
+        }
+
+        match encode_cache(cache, options) {
+            Ok(encoded) => *bytes = encoded,
+            Err(_) => return,
+        }
+    }
+
+    if bytes.len() as u64 > max_size {
+        eprintln!("{}", tr(Msg::CacheOverLimit, &[&bytes.len(), &max_size]));
+    }
+}
+
+/// Seconds since the epoch of a file's last modification.
+pub fn modified_secs(metadata: &fs::Metadata) -> Option<u64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+/// BLAKE3 of the file contents in hex, which stays the same across runs and renames.
+pub fn content_hash(path: &Path) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let mut hasher = blake3::Hasher::new();
+    std::io::copy(&mut file, &mut hasher).ok()?;
+    Some(hasher.finalize().to_hex().to_string())
+}
+
+/// Move the entries of files that were renamed since the last run onto their new names,
+/// matching by content hash, so they aren't analyzed again. Returns (old, new) name pairs.
+pub fn adopt_renamed_files(
+    cache: &mut HashMap<String, CachedMetrics>,
+    files: &[PathBuf],
+) -> Vec<(String, String)> {
+    let names: Vec<String> = files
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    let present: HashSet<&String> = names.iter().collect();
+
+    // Entries whose file is gone, by hash
+    let mut orphans: HashMap<String, String> = cache
+        .values()
+        .filter(|entry| !present.contains(&entry.filename))
+        .filter_map(|entry| Some((entry.content_hash.clone()?, entry.filename.clone())))
+        .collect();
+    let orphan_sizes: HashSet<u64> = orphans
+        .values()
+        .filter_map(|name| cache[name].file_size)
+        .collect();
+
+    let mut renames = Vec::new();
+    for (path, filename) in files.iter().zip(names) {
+        if orphans.is_empty() {
+            break;
+        }
+        if cache.contains_key(&filename) {
+            continue;
+        }
+        // Only files with a matching size get hashed, so new additions cost no extra read
+        let Ok(metadata) = fs::metadata(path) else {
+            continue;
+        };
+        if !orphan_sizes.contains(&metadata.len()) {
+            continue;
+        }
+        let Some(old_name) = content_hash(path).and_then(|hash| orphans.remove(&hash)) else {
+            continue;
+        };
+
+        let mut entry = cache.remove(&old_name).unwrap();
+        entry.filename = filename.clone();
+        // Copies get a new timestamp even though the audio is unchanged
+        entry.modified_time = modified_secs(&metadata);
+        cache.insert(filename.clone(), entry);
+        renames.push((old_name, filename));
     }
+    renames
 }
 
 pub fn should_analyze(
     file_path: &Path,
     cache: &HashMap<String, CachedMetrics>,
     filename: &str,
+    band_count: usize,
+    transform: Transform,
 ) -> bool {
     // If not in cache, analyze
     let Some(cached) = cache.get(filename) else {
         return true;
     };
+    entry_is_stale(cached, band_count, transform)
+        || !file_unchanged(file_path, cached.file_size, cached.modified_time)
+}
+
+/// Whether an entry was measured differently from how this run would measure, judged
+/// without touching the file itself.
+pub fn entry_is_stale(cached: &CachedMetrics, band_count: usize, transform: Transform) -> bool {
+    // Partial imports carry no band data, and a changed band layout makes old data stale
+    if cached.band_percentages.len() != band_count {
+        return true;
+    }
+
+    // Bands measured with a different transform aren't comparable
+    if cached.transform != transform {
+        return true;
+    }
 
+    // Entries from before the latest metrics were added
+    if cached.analysis_version < ANALYSIS_VERSION {
+        return true;
+    }
+
+    // Reproducible and regular runs round differently, so neither reuses the other's results
+    if cached.reproducible != is_reproducible() {
+        return true;
+    }
+
+    // Another audio stream was chosen since
+    if cached.audio_stream != selected_audio_stream() {
+        return true;
+    }
+
+    // Give partial results another go, in case this run has more memory to work with
+    cached.windowed
+}
+
+/// Whether the file's size and modification time still match the cached ones. Readings
+/// the entry doesn't have, or the file system can't give, count as matching.
+pub fn file_unchanged(
+    file_path: &Path,
+    cached_size: Option<u64>,
+    cached_time: Option<u64>,
+) -> bool {
     // If file metadata changed, re-analyze
     if let Ok(metadata) = fs::metadata(file_path) {
-        if let Some(cached_size) = cached.file_size {
+        if let Some(cached_size) = cached_size {
             if metadata.len() != cached_size {
-                return true;
+                return false;
             }
         }
 
-        if let Some(cached_time) = cached.modified_time {
+        if let Some(cached_time) = cached_time {
             if let Ok(modified) = metadata.modified() {
                 if let Ok(duration) = modified.duration_since(std::time::UNIX_EPOCH) {
                     if duration.as_secs() != cached_time {
-                        return true;
+                        return false;
                     }
                 }
             }
@@ -105,5 +1821,5 @@ pub fn should_analyze(
     }
 
     // File hasn't changed, use cache
-    false
+    true
 }
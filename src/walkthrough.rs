@@ -0,0 +1,214 @@
+use std::path::Path;
+
+use rustfft::num_complex::Complex;
+
+use crate::frequency_bands::{
+    FRAME_SIZE, FrequencyBand, HOP_SIZE, LOW_ZOOM_MAX_HZ, ZOOM_FRAME_SIZE, calculate_band_energies,
+    calculate_band_positions, get_bands, hann_window, plan_fft,
+};
+use crate::utils::{decode_audio, downmix};
+
+const PEAK_BINS: usize = 10; // Strongest bins listed from the example spectrum
+
+fn step(number: usize, title: &str) {
+    let heading = format!("{}. {}", number, title);
+    println!("\n{}", heading);
+    println!("{}", "-".repeat(heading.chars().count()));
+}
+
+// FFT bins a band covers for a given frame size, as `calculate_band_energies` slices them
+fn band_bins(band: &FrequencyBand, frame_size: usize, sample_rate: usize) -> (usize, usize) {
+    let low_bin = band.low_hz * frame_size / sample_rate;
+    let high_bin = (band.high_hz * frame_size / sample_rate).min(frame_size / 2);
+    (low_bin, high_bin)
+}
+
+/// Walk through the default STFT analysis of one file, printing the intermediate values
+/// of each step so the DSP can be checked by hand.
+pub fn explain_run(
+    path: &Path,
+    band_layout: Option<&[FrequencyBand]>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let audio = decode_audio(path)?;
+    let sample_rate = audio.sample_rate;
+    let length = audio.channels.iter().map(|c| c.len()).min().unwrap_or(0);
+
+    println!("Analysis walkthrough: {}", path.display());
+    println!("{}", "=".repeat(80));
+
+    step(1, "Decode");
+    println!(
+        "  {} channel(s) at {} Hz, {} samples per channel ({:.2} s)",
+        audio.channels.len(),
+        sample_rate,
+        length,
+        length as f32 / sample_rate as f32
+    );
+    println!("  Channels are averaged sample by sample into one mono signal.");
+    let samples = downmix(&audio.channels);
+    if samples.len() <= FRAME_SIZE {
+        return Err(format!("need more than {} samples for one frame", FRAME_SIZE).into());
+    }
+
+    step(2, "Framing");
+    let frame_count = (samples.len() - FRAME_SIZE).div_ceil(HOP_SIZE);
+    println!(
+        "  {} frames of {} samples, starting every {} samples ({:.1} ms window, {:.1} ms hop)",
+        frame_count,
+        FRAME_SIZE,
+        HOP_SIZE,
+        FRAME_SIZE as f32 * 1000.0 / sample_rate as f32,
+        HOP_SIZE as f32 * 1000.0 / sample_rate as f32
+    );
+    // The loudest frame makes the clearest example
+    let example = (0..frame_count)
+        .max_by(|&a, &b| {
+            let energy = |i: usize| {
+                samples[i * HOP_SIZE..i * HOP_SIZE + FRAME_SIZE]
+                    .iter()
+                    .map(|s| s * s)
+                    .sum::<f32>()
+            };
+            energy(a).total_cmp(&energy(b))
+        })
+        .unwrap_or(0);
+    let start = example * HOP_SIZE;
+    let frame = &samples[start..start + FRAME_SIZE];
+    println!(
+        "  Example frame: #{} at {:.3} s (the loudest)",
+        example,
+        start as f32 / sample_rate as f32
+    );
+
+    step(3, "Hann window");
+    let window = hann_window(FRAME_SIZE);
+    println!(
+        "  w[j] = 0.5 * (1 - cos(2πj / (N - 1))), N = {}",
+        FRAME_SIZE
+    );
+    println!(
+        "  {:>6}  {:>8}  {:>10}  {:>10}",
+        "j", "w[j]", "sample", "windowed"
+    );
+    let last = FRAME_SIZE - 1;
+    for j in [
+        0,
+        last / 8,
+        last / 4,
+        last / 2,
+        last * 3 / 4,
+        last * 7 / 8,
+        last,
+    ] {
+        println!(
+            "  {:>6}  {:>8.5}  {:>10.6}  {:>10.6}",
+            j,
+            window[j],
+            frame[j],
+            frame[j] * window[j]
+        );
+    }
+
+    step(4, "Example frame spectrum");
+    let mut buffer: Vec<Complex<f32>> = frame
+        .iter()
+        .zip(&window)
+        .map(|(&s, &w)| Complex::new(s * w, 0.0))
+        .collect();
+    plan_fft(FRAME_SIZE).process(&mut buffer);
+    let magnitude: Vec<f32> = buffer[..FRAME_SIZE / 2].iter().map(|c| c.norm()).collect();
+    let bin_hz = sample_rate as f32 / FRAME_SIZE as f32;
+    println!(
+        "  {} bins of {:.2} Hz; magnitude = |FFT|. Strongest bins:",
+        magnitude.len(),
+        bin_hz
+    );
+    let mut strongest: Vec<usize> = (0..magnitude.len()).collect();
+    strongest.sort_by(|&a, &b| magnitude[b].total_cmp(&magnitude[a]));
+    let top = magnitude[strongest[0]];
+    println!(
+        "  {:>6}  {:>9}  {:>12}  {:>8}",
+        "bin", "Hz", "magnitude", "dB"
+    );
+    for &bin in strongest.iter().take(PEAK_BINS) {
+        let relative = if top > 0.0 && magnitude[bin] > 0.0 {
+            format!("{:>8.1}", 20.0 * (magnitude[bin] / top).log10())
+        } else {
+            format!("{:>8}", "--")
+        };
+        println!(
+            "  {:>6}  {:>9.1}  {:>12.4}  {}",
+            bin,
+            bin as f32 * bin_hz,
+            magnitude[bin],
+            relative
+        );
+    }
+
+    step(5, "Bin-to-band mapping");
+    let bands = get_bands(sample_rate, band_layout);
+    println!("  bin = Hz * N / sample rate, upper edge exclusive; energy is the sum of magnitude²");
+    for band in &bands {
+        let (low_bin, high_bin) = band_bins(band, FRAME_SIZE, sample_rate);
+        let energy: f32 = magnitude[low_bin..high_bin.max(low_bin)]
+            .iter()
+            .map(|m| m * m)
+            .sum();
+        print!(
+            "  {:>5}-{:<5} Hz  bins {:>4}..{:<4} ({:>4} bins)  example frame {:>12.4}",
+            band.low_hz,
+            band.high_hz,
+            low_bin,
+            high_bin,
+            high_bin.saturating_sub(low_bin),
+            energy
+        );
+        if band.high_hz <= LOW_ZOOM_MAX_HZ {
+            let (low_zoom, high_zoom) = band_bins(band, ZOOM_FRAME_SIZE, sample_rate);
+            print!(
+                "  (re-measured: bins {}..{} of {})",
+                low_zoom, high_zoom, ZOOM_FRAME_SIZE
+            );
+        }
+        println!();
+    }
+    println!(
+        "  Bands below {} Hz are re-measured with {}-sample frames for finer bins, then",
+        LOW_ZOOM_MAX_HZ, ZOOM_FRAME_SIZE
+    );
+    println!(
+        "  divided by {} so both window lengths read on the same scale.",
+        (ZOOM_FRAME_SIZE / FRAME_SIZE).pow(2)
+    );
+
+    step(6, "Band energies and normalization");
+    let energies = calculate_band_energies(&samples, sample_rate, &bands)?;
+    let total: f64 = energies.iter().sum();
+    let positions = calculate_band_positions(&bands, sample_rate);
+    println!("  Raw energy is the per-frame band sum averaged over all frames.");
+    println!(
+        "  {:>11}  {:>14}  {:>8}  {:>9}",
+        "band (Hz)", "raw energy", "share %", "position"
+    );
+    let mut centroid = 0.0;
+    for ((band, &energy), &position) in bands.iter().zip(&energies).zip(&positions) {
+        let share = if total > 0.0 {
+            (energy / total * 100.0) as f32
+        } else {
+            0.0
+        };
+        centroid += share * position / 100.0;
+        println!(
+            "  {:>5}-{:<5}  {:>14.6e}  {:>8.2}  {:>9.1}",
+            band.low_hz, band.high_hz, energy, share, position
+        );
+    }
+    println!("  Total raw energy: {:.6e}", total);
+    println!(
+        "  Position = geometric band center on a log axis, 20 Hz = 0 to {} Hz = 100",
+        sample_rate / 2
+    );
+    println!("  Centroid = Σ share × position / 100 = {:.1}", centroid);
+
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frequency_bands::SpectrumMetrics;
+use crate::silence::Silence;
+use crate::surround::LFE_CROSSOVER_HZ;
+use crate::transcode::transcode_problem;
+
+/// A user-defined threshold from the config, e.g. `{"metric": "loudness", "above": -6}`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WarningRule {
+    pub metric: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub above: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub below: Option<f32>,
+    /// Shown instead of the generated description when the rule fires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Metric keys that rules can test.
+pub const RULE_METRICS: &[&str] = &[
+    "centroid",
+    "centroid-hz",
+    "tilt",
+    "rolloff",
+    "cutoff",
+    "spread",
+    "low-share",
+    "mid-share",
+    "high-share",
+    "low-high",
+    "mid-prominence",
+    "zcr",
+    "onset",
+    "loudness",
+    "lufs",
+    "lra",
+    "true-peak",
+    "dr",
+    "rms",
+    "crest",
+    "clipping",
+    "silence",
+    "lead-silence",
+    "tail-silence",
+    "duration",
+    "bpm",
+    "swing",
+    "sharpness",
+    "roughness",
+    "balance",
+    "mono-below",
+    "lfe",
+    "lfe-leak",
+];
+
+/// The value a rule compares against, or `None` when the track has no reading.
+pub fn metric_value(metrics: &SpectrumMetrics, key: &str) -> Option<f32> {
+    match key {
+        "centroid" => Some(metrics.centroid.0),
+        "centroid-hz" => metrics.centroid_hz.map(f32::from),
+        "tilt" => metrics.tilt,
+        "rolloff" => metrics.rolloff.map(f32::from),
+        "cutoff" => metrics.bandwidth.as_ref().map(|b| b.cutoff_hz),
+        "spread" => Some(metrics.spread.0),
+        "low-share" => metrics.ratios.as_ref().map(|r| r.low_percent),
+        "mid-share" => metrics.ratios.as_ref().map(|r| r.mid_percent),
+        "high-share" => metrics.ratios.as_ref().map(|r| r.high_percent),
+        "low-high" => metrics.ratios.as_ref().and_then(|r| r.low_high_db),
+        "mid-prominence" => metrics.ratios.as_ref().and_then(|r| r.mid_prominence_db),
+        "zcr" => Some(metrics.zero_crossing_rate),
+        "onset" => metrics.onset_strength.map(f32::from),
+        "loudness" => Some(metrics.loudness.0),
+        "lufs" => metrics.integrated_lufs.map(f32::from),
+        "lra" => metrics.loudness_range.map(f32::from),
+        "true-peak" => metrics.true_peak_db.map(f32::from),
+        "dr" => metrics.dr_score.map(f32::from),
+        "rms" => metrics.dynamics.as_ref().map(|d| d.rms_db),
+        "crest" => metrics.dynamics.as_ref().map(|d| d.crest_factor_db),
+        "clipping" => metrics.clipping.as_ref().map(|c| c.clipped_percent),
+        "silence" => metrics.silence.as_ref().map(Silence::total_seconds),
+        "lead-silence" => metrics.silence.as_ref().map(|s| s.leading_seconds),
+        "tail-silence" => metrics.silence.as_ref().map(|s| s.trailing_seconds),
+        "duration" => Some(metrics.duration_seconds),
+        "bpm" => metrics.tempo.as_ref().map(|t| t.bpm),
+        "swing" => metrics.groove.as_ref().map(|g| g.swing_percent),
+        "sharpness" => metrics.sharpness,
+        "roughness" => metrics.roughness,
+        "balance" => metrics.stereo.as_ref().map(|s| s.balance_db),
+        "mono-below" => metrics.stereo.as_ref().and_then(|s| s.mono_below_hz),
+        "lfe" => metrics.lfe.as_ref().map(|l| l.share_percent),
+        "lfe-leak" => metrics.lfe.as_ref().map(|l| l.above_crossover_percent),
+        _ => None,
+    }
+}
+
+pub struct RuleBreach {
+    pub value: f32,
+    pub limit: f32,
+    pub above: bool, // True when the value exceeded an upper limit
+}
+
+/// The measured value and crossed limit when a rule fires.
+pub fn evaluate_rule(rule: &WarningRule, metrics: &SpectrumMetrics) -> Option<RuleBreach> {
+    let value = metric_value(metrics, &rule.metric)?;
+    match (rule.above, rule.below) {
+        (Some(limit), _) if value > limit => Some(RuleBreach {
+            value,
+            limit,
+            above: true,
+        }),
+        (_, Some(limit)) if value < limit => Some(RuleBreach {
+            value,
+            limit,
+            above: false,
+        }),
+        _ => None,
+    }
+}
+
+/// Rules naming metrics we don't know, so a typo doesn't silently disable a check.
+pub fn unknown_rules(rules: &[WarningRule]) -> Vec<&str> {
+    rules
+        .iter()
+        .map(|rule| rule.metric.as_str())
+        .filter(|metric| !RULE_METRICS.contains(metric))
+        .collect()
+}
+
+/// Problems worth flagging next to a file's metrics: built-in checks, then configured rules.
+pub fn file_warnings(metrics: &SpectrumMetrics, rules: &[WarningRule]) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if metrics.windowed {
+        warnings.push(
+            "read in windows (--max-memory); tempo, key, structure, and R128 cover the first"
+                .to_string(),
+        );
+    }
+
+    if let Some(percent) = metrics.stream.as_ref().and_then(|s| s.undecodable_percent) {
+        warnings.push(format!("{:.2}% of frames undecodable (skipped)", percent));
+    }
+
+    if let Some(stereo) = &metrics.stereo {
+        if stereo.is_dual_mono() {
+            warnings.push("dual-mono (identical channels)".to_string());
+        }
+        if let Some(side) = stereo.heavy_side() {
+            warnings.push(format!("lopsided ({} heavy)", side));
+        }
+    }
+
+    if let Some(clipping) = metrics.clipping.as_ref().filter(|c| c.is_distorted()) {
+        warnings.push(format!(
+            "clipping ({:.2}% of samples in {} runs)",
+            clipping.clipped_percent, clipping.runs
+        ));
+    }
+
+    if let Some(silence) = &metrics.silence {
+        warnings.extend(silence.problems());
+    }
+
+    if let (Some(bandwidth), Some(stream)) = (&metrics.bandwidth, &metrics.stream) {
+        warnings.extend(transcode_problem(bandwidth, stream));
+    }
+
+    if let Some(lfe) = metrics.lfe.as_ref().filter(|l| l.is_full_range()) {
+        warnings.push(format!(
+            "full-range content in LFE ({:.0}% above {:.0} Hz)",
+            lfe.above_crossover_percent, LFE_CROSSOVER_HZ
+        ));
+    }
+
+    for rule in rules {
+        let Some(breach) = evaluate_rule(rule, metrics) else {
+            continue;
+        };
+        warnings.push(match &rule.message {
+            Some(message) => message.clone(),
+            None => format!(
+                "{} {:.1} {} {}",
+                rule.metric,
+                breach.value,
+                if breach.above { ">" } else { "<" },
+                breach.limit
+            ),
+        });
+    }
+
+    warnings
+}